@@ -1,12 +1,14 @@
 //! G9.5 — Criterion benchmarks for the hot paths: JSON parse → analysis →
-//! pretty render. Two payload sizes (small canonical fixture, deep synthetic
-//! tree) to detect regressions in either steady-state or worst-case work.
+//! pretty render. Small canonical fixture, deep synthetic tree, and (for
+//! parsing) a large synthetic tree, read both recursively and streaming, to
+//! detect regressions in steady-state, worst-case, and per-node work.
+//! Analysis also runs over an 8191-node tree.
 
 use std::fmt::Write as _;
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
 use rustcmdpev_core::display::colors::Theme;
-use rustcmdpev_core::parser::parse_explain_document;
+use rustcmdpev_core::parser::{parse_explain_document, parse_explain_document_with, ParseOptions};
 use rustcmdpev_core::render::{render_explain, RenderOptions};
 use rustcmdpev_core::{analyze_explain, render_visualization_with};
 
@@ -44,6 +46,7 @@ fn synthesize_deep_payload(depth: usize, fanout: usize) -> String {
 
 fn bench_parse(c: &mut Criterion) {
     let deep = synthesize_deep_payload(6, 2); // 2^7 - 1 = 127 nodes
+    let large = synthesize_deep_payload(11, 2); // 2^12 - 1 = 4095 nodes
     let mut group = c.benchmark_group("parse_explain_document");
     group.throughput(Throughput::Bytes(SMALL_PAYLOAD.len() as u64));
     group.bench_function("small_fixture", |b| {
@@ -59,6 +62,41 @@ fn bench_parse(c: &mut Criterion) {
             black_box(parsed);
         });
     });
+    group.throughput(Throughput::Bytes(large.len() as u64));
+    group.bench_function("large_synthetic_4095_nodes", |b| {
+        b.iter(|| {
+            let parsed = parse_explain_document(black_box(&large)).expect("parse");
+            black_box(parsed);
+        });
+    });
+    let streaming = ParseOptions::new().with_streaming(true);
+    group.bench_function("large_synthetic_4095_nodes_streaming", |b| {
+        b.iter(|| {
+            let parsed = parse_explain_document_with(black_box(&large), &streaming).expect("parse");
+            black_box(parsed);
+        });
+    });
+    group.finish();
+}
+
+fn bench_analyze(c: &mut Criterion) {
+    let deep = parse_explain_document(&synthesize_deep_payload(6, 2)).expect("parse deep");
+    let huge = parse_explain_document(&synthesize_deep_payload(12, 2)).expect("parse huge");
+    let mut group = c.benchmark_group("analyze_explain");
+    group.bench_function("deep_synthetic_127_nodes", |b| {
+        b.iter_batched(
+            || deep.clone(),
+            |explain| black_box(analyze_explain(explain)),
+            BatchSize::SmallInput,
+        );
+    });
+    group.bench_function("huge_synthetic_8191_nodes", |b| {
+        b.iter_batched(
+            || huge.clone(),
+            |explain| black_box(analyze_explain(explain)),
+            BatchSize::LargeInput,
+        );
+    });
     group.finish();
 }
 
@@ -98,5 +136,11 @@ fn bench_end_to_end(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_parse, bench_render, bench_end_to_end);
+criterion_group!(
+    benches,
+    bench_parse,
+    bench_analyze,
+    bench_render,
+    bench_end_to_end
+);
 criterion_main!(benches);
@@ -1,6 +1,16 @@
-use crate::constants::{CTE_SCAN_NODE, DELTA_ERROR_THRESHOLD, OVER_LABEL, UNDER_LABEL};
+use crate::constants::{
+    DELTA_ERROR_THRESHOLD, ESTIMATE_ERROR_FACTOR_THRESHOLD, OVER_LABEL, UNDER_LABEL,
+};
+use crate::fingerprint::Fingerprint;
+use crate::pipeline::{AnalysisPass, Pipeline};
+use crate::structure::data::actuals::PlanActuals;
+use crate::structure::data::analysis_flags::EstimateErrorSource;
 use crate::structure::data::explain::Explain;
+use crate::structure::data::node_type::NodeType;
 use crate::structure::data::plan::Plan;
+use crate::structure::data::predicates::ParsedPredicates;
+use crate::structure::graph::ExplainGraph;
+use crate::structure::visit::{walk_mut, NodeContext, PlanVisitorMut, Walk};
 
 pub fn calculate_planner_estimate(plan: &mut Plan) {
     plan.analysis_flags.planner_row_estimate_factor = 0.0;
@@ -26,22 +36,32 @@ pub fn calculate_planner_estimate(plan: &mut Plan) {
 }
 
 pub fn calculate_actuals(explain: &mut Explain, plan: &mut Plan) {
-    plan.actuals.actual_duration = plan.actuals.actual_total_time;
-    plan.actuals.actual_cost = plan.estimates.total_cost;
+    explain.total_cost += derive_actuals(plan.estimates.total_cost, &mut plan.actuals, &plan.plans);
+}
 
-    for child_plan in &plan.plans {
-        if child_plan.identity.node_type != CTE_SCAN_NODE {
-            plan.actuals.actual_duration -= child_plan.actuals.actual_total_time;
-            plan.actuals.actual_cost -= child_plan.estimates.total_cost;
+/// Set a node's exclusive duration and cost from its inclusive totals and
+/// its children's, returning the exclusive cost.
+fn derive_actuals<'a>(
+    total_cost: f64,
+    actuals: &mut PlanActuals,
+    children: impl IntoIterator<Item = &'a Plan>,
+) -> f64 {
+    actuals.actual_duration = actuals.actual_total_time;
+    actuals.actual_cost = total_cost;
+
+    for child_plan in children {
+        if child_plan.identity.node_type != NodeType::CteScan {
+            actuals.actual_duration -= child_plan.actuals.actual_total_time;
+            actuals.actual_cost -= child_plan.estimates.total_cost;
         }
     }
 
-    if plan.actuals.actual_cost < 0.0 {
-        plan.actuals.actual_cost = 0.0;
+    if actuals.actual_cost < 0.0 {
+        actuals.actual_cost = 0.0;
     }
 
-    explain.total_cost += plan.actuals.actual_cost;
-    plan.actuals.actual_duration *= plan.actuals.actual_loops as f64;
+    actuals.actual_duration *= actuals.actual_loops as f64;
+    actuals.actual_cost
 }
 
 pub fn calculate_maximums(explain: &mut Explain, plan: &Plan) {
@@ -57,7 +77,7 @@ pub fn calculate_maximums(explain: &mut Explain, plan: &Plan) {
 }
 
 /// Outlier comparison thresholds extracted from the analyzed explain so the
-/// recursion does not need to re-borrow `Explain` while the plan tree is held
+/// outlier pass does not need to re-borrow `Explain` while the plan tree is held
 /// mutably.
 #[derive(Debug, Clone, Copy)]
 struct OutlierMaxima {
@@ -82,51 +102,239 @@ pub fn calculate_outlier_nodes(explain: &Explain, plan: &mut Plan) {
 }
 
 fn flag_outliers(maxima: OutlierMaxima, plan: &mut Plan) {
+    walk_mut(plan, &mut OutlierVisitor(maxima));
+}
+
+/// Pre-order walk marking the costliest, largest, and slowest nodes.
+struct OutlierVisitor(OutlierMaxima);
+
+impl PlanVisitorMut for OutlierVisitor {
+    fn pre(&mut self, plan: &mut Plan, _ctx: &NodeContext<'_>) -> Walk {
+        flag_outlier(self.0, plan);
+        Walk::Continue
+    }
+}
+
+fn is_misestimated(plan: &Plan) -> bool {
+    plan.analysis_flags.planner_row_estimate_factor >= ESTIMATE_ERROR_FACTOR_THRESHOLD
+}
+
+/// Walk the tree bottom-up and mark where planner row misestimates originate.
+///
+/// A misestimated node is `Inherited` when a child misestimated in the same
+/// direction accounts for its error (the node's factor is within
+/// [`ESTIMATE_ERROR_FACTOR_THRESHOLD`] of the child's), and `Origin` otherwise.
+/// Requires [`calculate_planner_estimate`] to have run on every node.
+pub fn classify_estimate_errors(plan: &mut Plan) {
+    walk_mut(plan, &mut EstimateErrorVisitor);
+}
+
+/// Post-order walk so each node sees its children already classified.
+struct EstimateErrorVisitor;
+
+impl PlanVisitorMut for EstimateErrorVisitor {
+    fn post(&mut self, plan: &mut Plan, _ctx: &NodeContext<'_>) -> Walk {
+        plan.analysis_flags.estimate_error_source = estimate_error_source(plan, &plan.plans);
+        Walk::Continue
+    }
+}
+
+fn estimate_error_source<'a>(
+    plan: &Plan,
+    children: impl IntoIterator<Item = &'a Plan>,
+) -> EstimateErrorSource {
+    if !is_misestimated(plan) {
+        return EstimateErrorSource::None;
+    }
+
+    let inherited_factor = children
+        .into_iter()
+        .filter(|child| {
+            is_misestimated(child)
+                && child.analysis_flags.planner_row_estimate_direction
+                    == plan.analysis_flags.planner_row_estimate_direction
+        })
+        .map(|child| child.analysis_flags.planner_row_estimate_factor)
+        .fold(0.0, f64::max);
+
+    if inherited_factor > 0.0
+        && plan.analysis_flags.planner_row_estimate_factor / inherited_factor
+            < ESTIMATE_ERROR_FACTOR_THRESHOLD
+    {
+        EstimateErrorSource::Inherited
+    } else {
+        EstimateErrorSource::Origin
+    }
+}
+
+/// Flags each outlier node against the explain-level maxima.
+fn flag_outlier(maxima: OutlierMaxima, plan: &mut Plan) {
     plan.analysis_flags.costliest =
         (plan.actuals.actual_cost - maxima.max_cost).abs() < DELTA_ERROR_THRESHOLD;
     plan.analysis_flags.largest = plan.actuals.actual_rows == maxima.max_rows;
     plan.analysis_flags.slowest =
         (plan.actuals.actual_duration - maxima.max_duration).abs() < DELTA_ERROR_THRESHOLD;
+}
+
+/// Sets each node's planner row estimate factor and direction.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlannerEstimatePass;
+
+impl AnalysisPass for PlannerEstimatePass {
+    fn name(&self) -> &'static str {
+        "planner_estimate"
+    }
+
+    fn run(&self, explain: &mut Explain) {
+        ExplainGraph::apply(explain, |explain| self.run_graph(explain));
+    }
+
+    fn run_graph(&self, explain: &mut ExplainGraph) {
+        explain
+            .graph
+            .plans_mut()
+            .for_each(calculate_planner_estimate);
+    }
+}
+
+/// Derives exclusive per-node duration and cost and sums the explain's
+/// total cost.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ActualsPass;
+
+impl AnalysisPass for ActualsPass {
+    fn name(&self) -> &'static str {
+        "actuals"
+    }
+
+    fn run(&self, explain: &mut Explain) {
+        ExplainGraph::apply(explain, |explain| self.run_graph(explain));
+    }
+
+    fn run_graph(&self, explain: &mut ExplainGraph) {
+        let ExplainGraph { explain, graph } = explain;
+        for node in graph.nodes() {
+            let (plan, children) = graph.with_children_mut(node);
+            explain.total_cost +=
+                derive_actuals(plan.estimates.total_cost, &mut plan.actuals, children);
+        }
+    }
+}
+
+/// Records the largest per-node rows, cost, and duration on the explain.
+/// Runs after [`ActualsPass`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaximumsPass;
+
+impl AnalysisPass for MaximumsPass {
+    fn name(&self) -> &'static str {
+        "maximums"
+    }
+
+    fn run(&self, explain: &mut Explain) {
+        ExplainGraph::apply(explain, |explain| self.run_graph(explain));
+    }
+
+    fn run_graph(&self, explain: &mut ExplainGraph) {
+        for plan in explain.graph.plans() {
+            calculate_maximums(&mut explain.explain, plan);
+        }
+    }
+}
+
+/// See [`classify_estimate_errors`]. Runs after [`PlannerEstimatePass`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EstimateErrorPass;
+
+impl AnalysisPass for EstimateErrorPass {
+    fn name(&self) -> &'static str {
+        "estimate_errors"
+    }
+
+    fn run(&self, explain: &mut Explain) {
+        ExplainGraph::apply(explain, |explain| self.run_graph(explain));
+    }
 
-    for child_plan in &mut plan.plans {
-        flag_outliers(maxima, child_plan);
+    fn run_graph(&self, explain: &mut ExplainGraph) {
+        let graph = &mut explain.graph;
+        for node in graph.nodes().rev() {
+            let (plan, children) = graph.with_children_mut(node);
+            plan.analysis_flags.estimate_error_source = estimate_error_source(plan, children);
+        }
     }
 }
 
-fn process_root(explain: &mut Explain) {
-    calculate_planner_estimate(&mut explain.plan);
-    let mut plan = std::mem::take(&mut explain.plan);
-    calculate_actuals(explain, &mut plan);
-    calculate_maximums(explain, &plan);
-    explain.plan = plan;
+/// Tags the costliest, largest, and slowest nodes. Runs after
+/// [`MaximumsPass`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutlierPass;
+
+impl AnalysisPass for OutlierPass {
+    fn name(&self) -> &'static str {
+        "outliers"
+    }
+
+    fn run(&self, explain: &mut Explain) {
+        ExplainGraph::apply(explain, |explain| self.run_graph(explain));
+    }
+
+    fn run_graph(&self, explain: &mut ExplainGraph) {
+        let maxima = OutlierMaxima::from(&explain.explain);
+        for plan in explain.graph.plans_mut() {
+            flag_outlier(maxima, plan);
+        }
+    }
 }
 
-fn process_child_plans(explain: &mut Explain, plans: &mut [Plan]) {
-    for child_plan in plans.iter_mut() {
-        calculate_planner_estimate(child_plan);
-        calculate_actuals(explain, child_plan);
-        calculate_maximums(explain, child_plan);
+/// Parses each node's predicate and output text into
+/// `predicates.parsed`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExpressionPass;
 
-        if !child_plan.plans.is_empty() {
-            let mut nested = std::mem::take(&mut child_plan.plans);
-            process_child_plans(explain, &mut nested);
-            child_plan.plans = nested;
+impl AnalysisPass for ExpressionPass {
+    fn name(&self) -> &'static str {
+        "expressions"
+    }
+
+    fn run(&self, explain: &mut Explain) {
+        ExplainGraph::apply(explain, |explain| self.run_graph(explain));
+    }
+
+    fn run_graph(&self, explain: &mut ExplainGraph) {
+        for plan in explain.graph.plans_mut() {
+            plan.predicates.parsed = ParsedPredicates::of(&plan.predicates);
         }
     }
 }
 
-pub fn process_all(mut explain: Explain) -> Explain {
-    process_root(&mut explain);
+/// Stores each node's subtree [`Fingerprint`] in `identity.fingerprint`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FingerprintPass;
+
+impl AnalysisPass for FingerprintPass {
+    fn name(&self) -> &'static str {
+        "fingerprint"
+    }
 
-    if !explain.plan.plans.is_empty() {
-        let mut child_plans = std::mem::take(&mut explain.plan.plans);
-        process_child_plans(&mut explain, &mut child_plans);
-        explain.plan.plans = child_plans;
+    fn run(&self, explain: &mut Explain) {
+        ExplainGraph::apply(explain, |explain| self.run_graph(explain));
     }
 
-    let maxima = OutlierMaxima::from(&explain);
-    flag_outliers(maxima, &mut explain.plan);
-    explain
+    fn run_graph(&self, explain: &mut ExplainGraph) {
+        let graph = &mut explain.graph;
+        for node in graph.nodes().rev() {
+            let (plan, children) = graph.with_children_mut(node);
+            plan.identity.fingerprint = Fingerprint::of_node(
+                plan,
+                children.iter().map(|child| child.identity.fingerprint),
+            );
+        }
+    }
+}
+
+/// Run the default [`Pipeline`].
+pub fn process_all(explain: Explain) -> Explain {
+    Pipeline::default().run(explain)
 }
 
 #[cfg(test)]
@@ -134,10 +342,11 @@ mod tests {
     use super::*;
     use crate::structure::data::actuals::PlanActuals;
     use crate::structure::data::estimates::PlanEstimates;
+    use crate::testing::explain_for;
 
     fn leaf(node_type: &str, total_cost: f64, total_time: f64, rows: u64) -> Plan {
         let mut plan = Plan::default();
-        plan.identity.node_type = node_type.to_string();
+        plan.identity.node_type = node_type.into();
         plan.estimates = PlanEstimates {
             total_cost,
             ..PlanEstimates::default()
@@ -200,6 +409,64 @@ mod tests {
         assert!(!plan.plans[0].analysis_flags.slowest);
     }
 
+    fn misestimated(node_type: &str, plan_rows: u64, actual_rows: u64) -> Plan {
+        let mut plan = leaf(node_type, 1.0, 1.0, actual_rows);
+        plan.estimates.plan_rows = plan_rows;
+        calculate_planner_estimate(&mut plan);
+        plan
+    }
+
+    #[test]
+    fn classify_estimate_errors_marks_lowest_misestimate_as_origin() {
+        let scan = misestimated("Seq Scan", 10, 1_000);
+        let mut join = misestimated("Hash Join", 20, 2_000);
+        join.plans.push(scan);
+        let mut root = misestimated("Sort", 20, 2_000);
+        root.plans.push(join);
+
+        classify_estimate_errors(&mut root);
+
+        assert_eq!(
+            root.plans[0].plans[0].analysis_flags.estimate_error_source,
+            EstimateErrorSource::Origin
+        );
+        assert_eq!(
+            root.plans[0].analysis_flags.estimate_error_source,
+            EstimateErrorSource::Inherited
+        );
+        assert_eq!(
+            root.analysis_flags.estimate_error_source,
+            EstimateErrorSource::Inherited
+        );
+    }
+
+    #[test]
+    fn classify_estimate_errors_treats_amplified_or_opposite_errors_as_origin() {
+        let mut amplifying = misestimated("Nested Loop", 10, 100_000);
+        amplifying.plans.push(misestimated("Seq Scan", 10, 100));
+        let mut opposite = misestimated("Hash Join", 1_000, 10);
+        opposite.plans.push(misestimated("Seq Scan", 10, 1_000));
+        let mut accurate = misestimated("Limit", 10, 10);
+        accurate.plans.push(misestimated("Seq Scan", 10, 1_000));
+
+        classify_estimate_errors(&mut amplifying);
+        classify_estimate_errors(&mut opposite);
+        classify_estimate_errors(&mut accurate);
+
+        assert_eq!(
+            amplifying.analysis_flags.estimate_error_source,
+            EstimateErrorSource::Origin
+        );
+        assert_eq!(
+            opposite.analysis_flags.estimate_error_source,
+            EstimateErrorSource::Origin
+        );
+        assert_eq!(
+            accurate.analysis_flags.estimate_error_source,
+            EstimateErrorSource::None
+        );
+    }
+
     #[test]
     fn process_all_threads_through_without_per_node_clones() {
         let mut root = leaf("Hash Join", 10.0, 5.0, 100);
@@ -217,4 +484,49 @@ mod tests {
         assert!(processed.max_cost > 0.0);
         assert!(processed.plan.analysis_flags.costliest || processed.plan.analysis_flags.slowest);
     }
+
+    #[test]
+    fn expression_pass_parses_predicates_and_outputs() {
+        let mut root = leaf("Hash Join", 10.0, 5.0, 100);
+        root.predicates.hash_condition = "(o.customer_id = c.id)".into();
+        let mut scan = leaf("Seq Scan", 4.0, 2.0, 50);
+        scan.predicates.filter = "((status)::text = 'open'::text)".into();
+        scan.predicates.output = vec!["o.id".into(), "count(DISTINCT o.id)".into()];
+        root.plans.push(scan);
+
+        let processed = process_all(explain_for(root, 5.0, 10.0));
+
+        let parsed = &processed.plan.predicates.parsed;
+        let columns: Vec<String> = parsed
+            .hash_condition
+            .as_ref()
+            .expect("hash condition")
+            .columns()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(columns, ["o.customer_id", "c.id"]);
+        assert_eq!(parsed.filter, None);
+
+        let parsed = &processed.plan.plans[0].predicates.parsed;
+        assert_eq!(
+            parsed.filter.as_ref().map(ToString::to_string).as_deref(),
+            Some("((status)::text = 'open'::text)")
+        );
+        assert!(parsed.output[0].is_some());
+        assert!(parsed.output[1].is_none());
+    }
+
+    #[test]
+    fn expression_pass_leaves_overly_nested_expressions_unparsed() {
+        let mut output = "c0".to_string();
+        for index in 1..10_000 {
+            output = format!("({output} + c{index})");
+        }
+        let mut scan = leaf("Seq Scan", 4.0, 2.0, 50);
+        scan.predicates.output = vec![output];
+
+        let processed = process_all(explain_for(scan, 5.0, 10.0));
+
+        assert_eq!(processed.plan.predicates.parsed.output, [None]);
+    }
 }
@@ -0,0 +1,480 @@
+//! Aggregate report over many analyzed plans, e.g. every plan captured for a
+//! release.
+//!
+//! Each successfully analyzed file becomes a [`FileRecord`] around its
+//! [`PlanSummary`]; files that could not be read or parsed are kept as
+//! [`FileFailure`]s so one bad capture does not hide the rest. The
+//! [`BatchReport`] ranks queries by execution time, counts which node types
+//! and relations are the hotspot most often, groups plans by shape
+//! [`Fingerprint`] so plan flips between captures stand out, and totals
+//! advisory [`Findings`]. It renders as text, JSON, or CSV.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write as _};
+
+use serde::Serialize;
+
+use crate::cast_check::find_cast_issues;
+use crate::constants::ESTIMATE_ERROR_FACTOR_THRESHOLD;
+use crate::delta::Hotspot;
+use crate::fingerprint::Fingerprint;
+use crate::index_advice::suggest_indexes;
+use crate::rollup::RelationReport;
+use crate::structure::data::analysis_flags::EstimateErrorSource;
+use crate::structure::data::explain::Explain;
+use crate::structure::visit::pre_order;
+use crate::summary::PlanSummary;
+use crate::work_mem::recommend_work_mem;
+
+/// Queries listed in the text report's ranking unless told otherwise.
+pub const DEFAULT_RANKED_QUERIES: usize = 10;
+
+/// Paths the text report names per plan shape; the rest are counted.
+const LISTED_SHAPE_PATHS: usize = 3;
+
+const CSV_HEADER: &str = "path,status,execution_time_ms,planning_time_ms,total_cost,node_count,fingerprint,hotspot,findings,error";
+
+/// Counts of the advisories the single-plan report can show.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Findings {
+    /// `--suggest-indexes` candidates.
+    pub index_suggestions: u64,
+    /// Sorts, hashes, and aggregates that spilled past `work_mem`.
+    pub work_mem_spills: u64,
+    /// Nodes where a row misestimate originates rather than being inherited.
+    pub misestimates: u64,
+    /// Indexes that never executed or discarded more rows than they returned.
+    pub unproductive_indexes: u64,
+    /// `--check-casts` comparisons that defeat an index.
+    pub cast_issues: u64,
+}
+
+impl Findings {
+    pub fn of(explain: &Explain, block_size: u64) -> Self {
+        Self {
+            index_suggestions: suggest_indexes(explain).len() as u64,
+            work_mem_spills: recommend_work_mem(explain, block_size)
+                .map_or(0, |advice| advice.operations.len() as u64),
+            misestimates: pre_order(&explain.plan)
+                .filter(|node| {
+                    let flags = &node.plan.analysis_flags;
+                    flags.estimate_error_source == EstimateErrorSource::Origin
+                        && flags.planner_row_estimate_factor >= ESTIMATE_ERROR_FACTOR_THRESHOLD
+                })
+                .count() as u64,
+            unproductive_indexes: RelationReport::from_explain(explain)
+                .indexes
+                .iter()
+                .filter(|index| !index.payoff.pays_off())
+                .count() as u64,
+            cast_issues: find_cast_issues(explain).len() as u64,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.index_suggestions
+            + self.work_mem_spills
+            + self.misestimates
+            + self.unproductive_indexes
+            + self.cast_issues
+    }
+
+    fn add(&mut self, other: &Findings) {
+        self.index_suggestions += other.index_suggestions;
+        self.work_mem_spills += other.work_mem_spills;
+        self.misestimates += other.misestimates;
+        self.unproductive_indexes += other.unproductive_indexes;
+        self.cast_issues += other.cast_issues;
+    }
+}
+
+impl fmt::Display for Findings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (index suggestions {}, work_mem spills {}, misestimates {}, unproductive indexes {}, index-defeating casts {})",
+            self.total(),
+            self.index_suggestions,
+            self.work_mem_spills,
+            self.misestimates,
+            self.unproductive_indexes,
+            self.cast_issues
+        )
+    }
+}
+
+/// One analyzed plan.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileRecord {
+    pub path: String,
+    pub execution_time: f64,
+    pub planning_time: f64,
+    pub total_cost: f64,
+    pub node_count: u64,
+    pub fingerprint: Fingerprint,
+    pub hotspot: Option<Hotspot>,
+    pub findings: Findings,
+    #[serde(skip)]
+    pub summary: PlanSummary,
+}
+
+impl FileRecord {
+    pub fn new(path: impl Into<String>, explain: &Explain, block_size: u64) -> Self {
+        let summary = PlanSummary::from_explain_with_block_size(explain, block_size);
+        Self {
+            path: path.into(),
+            execution_time: summary.execution_time,
+            planning_time: summary.planning_time,
+            total_cost: summary.total_cost,
+            node_count: summary.node_count,
+            fingerprint: Fingerprint::of(&explain.plan),
+            hotspot: Hotspot::of(explain),
+            findings: Findings::of(explain, block_size),
+            summary,
+        }
+    }
+}
+
+/// A file that could not be analyzed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileFailure {
+    pub path: String,
+    pub message: String,
+}
+
+/// How often a node type or relation was a plan's hotspot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HotspotCount {
+    pub name: String,
+    pub plans: usize,
+}
+
+/// Plans that share a shape [`Fingerprint`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlanShape {
+    pub fingerprint: Fingerprint,
+    /// In path order.
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    /// Slowest first; ties keep path order.
+    pub files: Vec<FileRecord>,
+    /// In path order.
+    pub failures: Vec<FileFailure>,
+    /// Most frequent first.
+    pub hotspot_node_types: Vec<HotspotCount>,
+    pub hotspot_relations: Vec<HotspotCount>,
+    /// Most common first.
+    pub shapes: Vec<PlanShape>,
+    pub findings: Findings,
+    /// Queries the text form ranks; the JSON form always lists every file.
+    #[serde(skip)]
+    pub ranked: usize,
+}
+
+impl BatchReport {
+    pub fn new(mut files: Vec<FileRecord>, mut failures: Vec<FileFailure>) -> Self {
+        files.sort_by(|a, b| {
+            b.execution_time
+                .total_cmp(&a.execution_time)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        failures.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut findings = Findings::default();
+        for file in &files {
+            findings.add(&file.findings);
+        }
+        let hotspots = || files.iter().filter_map(|file| file.hotspot.as_ref());
+        let hotspot_node_types = count(hotspots().map(|hotspot| hotspot.node_type.as_str()));
+        let hotspot_relations = count(
+            hotspots()
+                .map(|hotspot| hotspot.relation_name.as_str())
+                .filter(|relation| !relation.is_empty()),
+        );
+        let shapes = shapes(&files);
+
+        Self {
+            files,
+            failures,
+            hotspot_node_types,
+            hotspot_relations,
+            shapes,
+            findings,
+            ranked: DEFAULT_RANKED_QUERIES,
+        }
+    }
+
+    /// Rank at most `ranked` queries in the text form.
+    #[must_use]
+    pub fn with_ranked(mut self, ranked: usize) -> Self {
+        self.ranked = ranked;
+        self
+    }
+
+    /// One row per file, analyzed files slowest first and then failures,
+    /// under a header row.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        csv.push_str(CSV_HEADER);
+        csv.push('\n');
+        for file in &self.files {
+            let hotspot = file
+                .hotspot
+                .as_ref()
+                .map(Hotspot::to_string)
+                .unwrap_or_default();
+            let _ = writeln!(
+                csv,
+                "{},ok,{},{},{},{},{},{},{},",
+                csv_field(&file.path),
+                file.execution_time,
+                file.planning_time,
+                file.total_cost,
+                file.node_count,
+                file.fingerprint,
+                csv_field(&hotspot),
+                file.findings.total()
+            );
+        }
+        for failure in &self.failures {
+            let _ = writeln!(
+                csv,
+                "{},failed,,,,,,,,{}",
+                csv_field(&failure.path),
+                csv_field(&failure.message)
+            );
+        }
+        csv
+    }
+}
+
+/// `value` quoted when it holds a comma, quote, or line break (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn shapes(files: &[FileRecord]) -> Vec<PlanShape> {
+    let mut groups: BTreeMap<Fingerprint, Vec<String>> = BTreeMap::new();
+    for file in files {
+        groups
+            .entry(file.fingerprint)
+            .or_default()
+            .push(file.path.clone());
+    }
+    let mut shapes: Vec<PlanShape> = groups
+        .into_iter()
+        .map(|(fingerprint, mut paths)| {
+            paths.sort();
+            PlanShape { fingerprint, paths }
+        })
+        .collect();
+    shapes.sort_by_key(|shape| Reverse(shape.paths.len()));
+    shapes
+}
+
+fn count<'a>(names: impl Iterator<Item = &'a str>) -> Vec<HotspotCount> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for name in names {
+        *counts.entry(name).or_default() += 1;
+    }
+    let mut counts: Vec<HotspotCount> = counts
+        .into_iter()
+        .map(|(name, plans)| HotspotCount {
+            name: name.to_string(),
+            plans,
+        })
+        .collect();
+    counts.sort_by(|a, b| b.plans.cmp(&a.plans).then_with(|| a.name.cmp(&b.name)));
+    counts
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.files.len() + self.failures.len();
+        writeln!(
+            f,
+            "Batch: {total} {}, {} analyzed, {} failed",
+            if total == 1 { "file" } else { "files" },
+            self.files.len(),
+            self.failures.len()
+        )?;
+
+        if !self.files.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Slowest queries (execution time):")?;
+            for (rank, file) in self.files.iter().take(self.ranked).enumerate() {
+                write!(
+                    f,
+                    "{:>4}. {:>10.2} ms  {}",
+                    rank + 1,
+                    file.execution_time,
+                    file.path
+                )?;
+                if let Some(hotspot) = &file.hotspot {
+                    write!(f, "  hotspot {hotspot}")?;
+                }
+                writeln!(f)?;
+            }
+            let unranked = self.files.len().saturating_sub(self.ranked);
+            if unranked > 0 {
+                writeln!(f, "      … {unranked} more")?;
+            }
+        }
+
+        for (title, counts) in [
+            ("Hotspot node types:", &self.hotspot_node_types),
+            ("Hotspot relations:", &self.hotspot_relations),
+        ] {
+            if counts.is_empty() {
+                continue;
+            }
+            writeln!(f)?;
+            writeln!(f, "{title}")?;
+            for entry in counts {
+                writeln!(f, "  {:>4}  {}", entry.plans, entry.name)?;
+            }
+        }
+
+        if !self.shapes.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Plan shapes: {} distinct", self.shapes.len())?;
+            for shape in &self.shapes {
+                let listed: Vec<&str> = shape
+                    .paths
+                    .iter()
+                    .take(LISTED_SHAPE_PATHS)
+                    .map(String::as_str)
+                    .collect();
+                write!(
+                    f,
+                    "  {}  {:>4}  {}",
+                    shape.fingerprint,
+                    shape.paths.len(),
+                    listed.join(", ")
+                )?;
+                let unlisted = shape.paths.len().saturating_sub(LISTED_SHAPE_PATHS);
+                if unlisted > 0 {
+                    write!(f, ", … {unlisted} more")?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Findings: {}", self.findings)?;
+
+        if !self.failures.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Failures:")?;
+            for failure in &self.failures {
+                writeln!(f, "  {}: {}", failure.path, failure.message)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{explain_for, with_children, LeafBuilder};
+
+    fn record(path: &str, execution_time: f64, hotspot: (&str, &str)) -> FileRecord {
+        let mut scan = LeafBuilder::new(hotspot.0)
+            .relation("public", hotspot.1)
+            .build();
+        scan.actuals.actual_duration = 5.0;
+        let mut root = with_children(LeafBuilder::new("Hash Join").build(), [scan]);
+        root.actuals.actual_duration = 1.0;
+        let explain = explain_for(root, execution_time, 0.0);
+        FileRecord::new(path, &explain, 8192)
+    }
+
+    #[test]
+    fn ranks_by_execution_time_and_counts_hotspots() {
+        let report = BatchReport::new(
+            vec![
+                record("a.json", 3.0, ("Seq Scan", "orders")),
+                record("b.json", 40.0, ("Seq Scan", "customers")),
+                record("c.json", 12.0, ("Index Scan", "orders")),
+                record("d.json", 1.0, ("Seq Scan", "orders")),
+            ],
+            vec![FileFailure {
+                path: "broken.json".to_string(),
+                message: "invalid JSON".to_string(),
+            }],
+        );
+
+        let ranked: Vec<&str> = report.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(ranked, ["b.json", "c.json", "a.json", "d.json"]);
+        assert_eq!(
+            report.hotspot_node_types,
+            [
+                HotspotCount {
+                    name: "Seq Scan".to_string(),
+                    plans: 3
+                },
+                HotspotCount {
+                    name: "Index Scan".to_string(),
+                    plans: 1
+                },
+            ]
+        );
+        assert_eq!(report.hotspot_relations[0].name, "orders");
+        assert_eq!(report.shapes.len(), 3);
+        assert_eq!(report.shapes[0].paths, ["a.json", "d.json"]);
+
+        let text = report.with_ranked(2).to_string();
+        assert!(text.starts_with("Batch: 5 files, 4 analyzed, 1 failed\n"));
+        assert!(text.contains("   1.      40.00 ms  b.json  hotspot #0 Seq Scan on customers"));
+        assert!(text.contains("… 2 more"));
+        assert!(text.contains("  broken.json: invalid JSON"));
+        assert!(text.contains("Plan shapes: 3 distinct\n"));
+    }
+
+    #[test]
+    fn csv_lists_files_then_failures_and_quotes_fields() {
+        let report = BatchReport::new(
+            vec![record("a.json", 3.0, ("Seq Scan", "orders"))],
+            vec![FileFailure {
+                path: "b,c.json".to_string(),
+                message: "expected \"Plan\"".to_string(),
+            }],
+        );
+        let fingerprint = report.files[0].fingerprint;
+
+        let csv = report.to_csv();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], CSV_HEADER);
+        assert_eq!(
+            rows[1],
+            format!("a.json,ok,3,0,0,2,{fingerprint},#0 Seq Scan on orders,0,")
+        );
+        assert_eq!(
+            rows[2],
+            "\"b,c.json\",failed,,,,,,,,\"expected \"\"Plan\"\"\""
+        );
+    }
+
+    #[test]
+    fn findings_total_every_category() {
+        let findings = Findings {
+            index_suggestions: 2,
+            work_mem_spills: 1,
+            misestimates: 3,
+            unproductive_indexes: 1,
+            cast_issues: 2,
+        };
+        assert_eq!(findings.total(), 9);
+        assert!(findings.to_string().starts_with("9 (index suggestions 2"));
+        assert!(findings.to_string().ends_with("index-defeating casts 2)"));
+    }
+}
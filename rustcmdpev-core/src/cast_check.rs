@@ -0,0 +1,374 @@
+//! Predicates that keep an index on a column from being used.
+//!
+//! A b-tree index on `account_id` serves `account_id = 123` but not
+//! `(account_id)::text = '123'::text`: once a column is cast or wrapped in a
+//! function, only an index on that same expression can help, so the planner
+//! falls back to scanning. [`find_cast_issues`] inspects the `Filter` of every
+//! relation scan and the `Hash Cond`, `Merge Cond`, and `Join Filter` of
+//! every join, and reports comparisons where
+//!
+//! - a column is cast, e.g. `((account_id)::text = '123'::text)`;
+//! - a column is wrapped in a function, e.g. `(lower((email)::text) = $1)`;
+//! - a join casts one column to the other's type, e.g.
+//!   `((o.account_id)::bigint = a.id)`.
+//!
+//! EXPLAIN does not print column types, and `varchar` columns are always
+//! compared as `text`, so a cast to a text type is reported only when the
+//! other side is a numeric constant like the `'123'` above; a text cast
+//! compared with text is usually a `varchar` column and harmless.
+//!
+//! Reads `predicates.parsed`, which the `expressions` analysis pass fills.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::expression::{ColumnRef, Const, Expr};
+use crate::structure::data::explain::Explain;
+use crate::structure::data::node_type::NodeType;
+use crate::structure::data::plan::Plan;
+use crate::structure::visit::{pre_order, pre_order_at};
+
+/// Operators whose operands an index can match.
+const COMPARISON_OPERATORS: &[&str] = &[
+    "=",
+    "<>",
+    "!=",
+    "<",
+    ">",
+    "<=",
+    ">=",
+    "~~",
+    "~~*",
+    "IS DISTINCT FROM",
+    "IS NOT DISTINCT FROM",
+];
+
+/// Types `varchar` and `char` columns are compared as.
+const TEXT_TYPES: &[&str] = &["text", "character varying", "varchar", "bpchar", "name"];
+
+/// How a predicate keeps an index on its column from being used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CastIssueKind {
+    /// The column is cast to `type_name` before the comparison.
+    ColumnCast { type_name: String },
+    /// The column is an argument of `function`.
+    FunctionCall { function: String },
+    /// A join condition casts the column to `type_name`, the type of the
+    /// column it is compared with.
+    JoinTypeMismatch { type_name: String },
+}
+
+/// One comparison that defeats an index on a column.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct CastIssue {
+    /// Path of the node whose predicate holds the comparison.
+    pub node_path: String,
+    pub node_type: NodeType,
+    /// EXPLAIN field holding the comparison, e.g. `Filter` or `Hash Cond`.
+    pub field: String,
+    pub schema: String,
+    /// Relation of the column, or its qualifier when no scan below the node
+    /// reads a relation by that name.
+    pub relation: String,
+    pub column: String,
+    #[serde(flatten)]
+    pub kind: CastIssueKind,
+    /// The comparison, as EXPLAIN prints it.
+    pub expression: String,
+}
+
+impl CastIssue {
+    /// What the comparison does to the column, e.g. `cast to text`.
+    pub fn description(&self) -> String {
+        match &self.kind {
+            CastIssueKind::ColumnCast { type_name } => format!("cast to {type_name}"),
+            CastIssueKind::FunctionCall { function } => format!("wrapped in {function}()"),
+            CastIssueKind::JoinTypeMismatch { type_name } => {
+                format!("cast to {type_name} to match the joined column")
+            }
+        }
+    }
+}
+
+/// Every index-defeating comparison in the plan, in pre-order.
+pub fn find_cast_issues(explain: &Explain) -> Vec<CastIssue> {
+    let mut issues = Vec::new();
+    for node in pre_order_at(&explain.plan, explain.root_path()) {
+        let plan = node.plan;
+        let parsed = &plan.predicates.parsed;
+        if !plan.identity.relation_name.is_empty() {
+            if let Some(filter) = &parsed.filter {
+                check(plan, &node.path, "Filter", filter, false, &mut issues);
+            }
+        }
+        for (field, condition) in [
+            ("Hash Cond", &parsed.hash_condition),
+            ("Merge Cond", &parsed.merge_condition),
+            ("Join Filter", &parsed.join_filter),
+        ] {
+            if let Some(condition) = condition {
+                check(plan, &node.path, field, condition, true, &mut issues);
+            }
+        }
+    }
+    issues
+}
+
+fn check(
+    plan: &Plan,
+    path: &str,
+    field: &str,
+    predicate: &Expr,
+    join: bool,
+    issues: &mut Vec<CastIssue>,
+) {
+    for comparison in predicate.walk() {
+        let (left, right) = match comparison {
+            Expr::Binary { op, left, right } if COMPARISON_OPERATORS.contains(&op.as_str()) => {
+                (left, right)
+            }
+            Expr::ArrayOp { left, right, .. } => (left, right),
+            _ => continue,
+        };
+        for (side, other) in [(left, right), (right, left)] {
+            let Some((column, kind)) = side_issue(side, other, join) else {
+                continue;
+            };
+            let scan = if join {
+                column
+                    .qualifier
+                    .as_deref()
+                    .and_then(|qualifier| scan_named(plan, qualifier))
+            } else {
+                Some(plan)
+            };
+            let (schema, relation) = match scan {
+                Some(scan) => (
+                    scan.identity.schema.clone(),
+                    scan.identity.relation_name.clone(),
+                ),
+                None => (String::new(), column.qualifier.clone().unwrap_or_default()),
+            };
+            issues.push(CastIssue {
+                node_path: path.to_string(),
+                node_type: plan.identity.node_type.clone(),
+                field: field.to_string(),
+                schema,
+                relation,
+                column: column.name.clone(),
+                kind,
+                expression: comparison.to_string(),
+            });
+        }
+    }
+}
+
+/// The column on `side` of a comparison with `other` that an index on it
+/// could serve: a bare column, or one cast to a text type that
+/// [`find_cast_issues`] does not report. Index advice uses this so the two
+/// analyses agree on every predicate.
+pub(crate) fn indexable_column<'a>(
+    side: &'a Expr,
+    other: &Expr,
+    join: bool,
+) -> Option<&'a ColumnRef> {
+    match side {
+        Expr::Column(column) => Some(column),
+        Expr::Cast { type_name, .. }
+            if TEXT_TYPES.contains(&type_name.trim_end_matches("[]"))
+                && side_issue(side, other, join).is_none() =>
+        {
+            match side.uncast() {
+                Expr::Column(column) => Some(column),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The column `side` hides from an index, and how, given the `other` side of
+/// the comparison.
+fn side_issue<'a>(
+    side: &'a Expr,
+    other: &Expr,
+    join: bool,
+) -> Option<(&'a ColumnRef, CastIssueKind)> {
+    if let Expr::Cast { expr, type_name } = side {
+        let Expr::Column(column) = expr.uncast() else {
+            return side_issue(expr, other, join);
+        };
+        let element_type = type_name.trim_end_matches("[]");
+        if TEXT_TYPES.contains(&element_type) && (join || !is_numeric_constant(other)) {
+            return None;
+        }
+        let type_name = type_name.clone();
+        return match other {
+            // Both sides cast to one type: neither column is favored.
+            Expr::Cast {
+                type_name: other_type,
+                ..
+            } if join && *other_type == type_name => None,
+            _ if join && matches!(other.uncast(), Expr::Column(_)) => {
+                Some((column, CastIssueKind::JoinTypeMismatch { type_name }))
+            }
+            _ => Some((column, CastIssueKind::ColumnCast { type_name })),
+        };
+    }
+    let Expr::Call { name, args } = side else {
+        return None;
+    };
+    args.iter().find_map(|arg| match arg.uncast() {
+        Expr::Column(column) => Some((
+            column,
+            CastIssueKind::FunctionCall {
+                function: name.clone(),
+            },
+        )),
+        _ => None,
+    })
+}
+
+/// Whether `expr` is a number, or a string or cast holding one, e.g. `42`
+/// or `'123'::text`.
+fn is_numeric_constant(expr: &Expr) -> bool {
+    match expr.uncast() {
+        Expr::Const(Const::Number(_)) => true,
+        Expr::Const(Const::String(text)) => {
+            let text = text.trim();
+            !text.is_empty() && text.parse::<f64>().is_ok_and(f64::is_finite)
+        }
+        Expr::Unary { op, expr } if op == "-" => is_numeric_constant(expr),
+        _ => false,
+    }
+}
+
+/// The scan under `plan` reading the relation aliased or named `qualifier`.
+fn scan_named<'a>(plan: &'a Plan, qualifier: &str) -> Option<&'a Plan> {
+    pre_order(plan).map(|node| node.plan).find(|scan| {
+        let identity = &scan.identity;
+        !identity.relation_name.is_empty()
+            && (identity.alias == qualifier || identity.relation_name == qualifier)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::process_all;
+    use crate::testing::{explain_for, with_children, LeafBuilder};
+
+    fn scan(relation: &str, alias: &str, filter: &str) -> Plan {
+        let mut scan = LeafBuilder::new("Seq Scan")
+            .relation("public", relation)
+            .build();
+        scan.identity.alias = alias.into();
+        scan.predicates.filter = filter.into();
+        scan
+    }
+
+    fn issues(plan: Plan) -> Vec<CastIssue> {
+        find_cast_issues(&process_all(explain_for(plan, 1.0, 1.0)))
+    }
+
+    #[test]
+    fn flags_a_numeric_column_compared_as_text() {
+        let found = issues(scan(
+            "orders",
+            "orders",
+            "((account_id)::text = '123'::text)",
+        ));
+
+        assert_eq!(
+            found,
+            [CastIssue {
+                node_path: "Plan".into(),
+                node_type: NodeType::SeqScan,
+                field: "Filter".into(),
+                schema: "public".into(),
+                relation: "orders".into(),
+                column: "account_id".into(),
+                kind: CastIssueKind::ColumnCast {
+                    type_name: "text".into()
+                },
+                expression: "((account_id)::text = '123'::text)".into(),
+            }]
+        );
+        assert_eq!(found[0].description(), "cast to text");
+    }
+
+    #[test]
+    fn flags_non_text_casts_and_function_wrapped_columns() {
+        let found = issues(scan(
+            "users",
+            "u",
+            "(((created_at)::date = '2024-01-01'::date) AND (lower((email)::text) = $1) AND (id = ANY ('{1,2}'::integer[])))",
+        ));
+
+        let kinds: Vec<(&str, &CastIssueKind)> = found
+            .iter()
+            .map(|issue| (issue.column.as_str(), &issue.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (
+                    "created_at",
+                    &CastIssueKind::ColumnCast {
+                        type_name: "date".into()
+                    }
+                ),
+                (
+                    "email",
+                    &CastIssueKind::FunctionCall {
+                        function: "lower".into()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(found[1].expression, "(lower((email)::text) = $1)");
+    }
+
+    #[test]
+    fn ignores_text_casts_of_varchar_columns() {
+        let found = issues(scan(
+            "users",
+            "u",
+            "(((status)::text = 'open'::text) AND ((name)::text ~~ 'A%'::text) AND ((code)::text = $1))",
+        ));
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn flags_join_conditions_casting_one_side() {
+        let mut join = with_children(
+            LeafBuilder::new("Hash Join").build(),
+            [
+                scan("orders", "o", ""),
+                with_children(
+                    LeafBuilder::new("Hash").build(),
+                    [scan("accounts", "a", "")],
+                ),
+            ],
+        );
+        join.predicates.hash_condition = "((o.account_id)::bigint = a.id)".into();
+        let mut varchar_join = join.clone();
+        varchar_join.predicates.hash_condition = "((o.code)::text = (a.code)::text)".into();
+
+        let found = issues(join);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].field, "Hash Cond");
+        assert_eq!(found[0].node_type, NodeType::HashJoin);
+        assert_eq!(found[0].relation, "orders");
+        assert_eq!(found[0].column, "account_id");
+        assert_eq!(
+            found[0].kind,
+            CastIssueKind::JoinTypeMismatch {
+                type_name: "bigint".into()
+            }
+        );
+
+        assert!(issues(varchar_join).is_empty());
+    }
+}
@@ -0,0 +1,307 @@
+//! Compact comparison between two analyzed versions of the same query's plan.
+//!
+//! Used by the CLI's watch mode to say what a re-run changed: execution time,
+//! which node is the hotspot, which nodes changed type, and which planner
+//! settings changed. Nodes are matched by their path in the tree, so an
+//! inserted or removed node shows up as a change at that path and the ones
+//! after it.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::planner_settings::{setting_changes, SettingChange};
+use crate::structure::data::explain::Explain;
+use crate::structure::data::plan::Plan;
+use crate::structure::visit::pre_order_at;
+
+/// At most this many node type changes are listed; the rest are counted.
+pub const MAX_LISTED_NODE_CHANGES: usize = 5;
+
+/// The node with the largest exclusive duration.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hotspot {
+    pub node_id: u64,
+    pub path: String,
+    pub node_type: String,
+    pub relation_name: String,
+    pub duration: f64,
+}
+
+impl Hotspot {
+    /// `None` for a plan with no timed node.
+    pub fn of(explain: &Explain) -> Option<Self> {
+        let mut hotspot: Option<Self> = None;
+        for node in pre_order_at(&explain.plan, explain.root_path()) {
+            let duration = node.plan.actuals.actual_duration;
+            if duration > hotspot.as_ref().map_or(0.0, |hotspot| hotspot.duration) {
+                hotspot = Some(Self {
+                    node_id: node.plan.identity.node_id,
+                    path: node.path,
+                    node_type: node.plan.identity.node_type.as_str().to_string(),
+                    relation_name: node.plan.identity.relation_name.clone(),
+                    duration,
+                });
+            }
+        }
+        hotspot
+    }
+
+    /// Whether `other` is the same node doing the same kind of work.
+    fn same_node(&self, other: &Self) -> bool {
+        self.path == other.path
+            && self.node_type == other.node_type
+            && self.relation_name == other.relation_name
+    }
+}
+
+impl fmt::Display for Hotspot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {}", self.node_id, self.node_type)?;
+        if !self.relation_name.is_empty() {
+            write!(f, " on {}", self.relation_name)?;
+        }
+        Ok(())
+    }
+}
+
+/// A path whose node type differs between the two plans. `None` means the
+/// path does not exist on that side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeTypeChange {
+    pub path: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl fmt::Display for NodeTypeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.before, &self.after) {
+            (Some(before), Some(after)) => write!(f, "{}: {before} → {after}", self.path),
+            (None, Some(after)) => write!(f, "{}: + {after}", self.path),
+            (Some(before), None) => write!(f, "{}: - {before}", self.path),
+            (None, None) => write!(f, "{}", self.path),
+        }
+    }
+}
+
+/// What changed from `previous` to `current`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanDelta {
+    pub previous_execution_time: f64,
+    pub execution_time: f64,
+    pub previous_hotspot: Option<Hotspot>,
+    pub hotspot: Option<Hotspot>,
+    /// In the current plan's pre-order, then removed paths.
+    pub node_type_changes: Vec<NodeTypeChange>,
+    /// `EXPLAIN (SETTINGS)` values that differ, by name.
+    pub setting_changes: Vec<SettingChange>,
+}
+
+impl PlanDelta {
+    pub fn between(previous: &Explain, current: &Explain) -> Self {
+        Self {
+            previous_execution_time: previous.execution_time,
+            execution_time: current.execution_time,
+            previous_hotspot: Hotspot::of(previous),
+            hotspot: Hotspot::of(current),
+            node_type_changes: node_type_changes(previous, current),
+            setting_changes: setting_changes(&previous.settings, &current.settings),
+        }
+    }
+
+    pub fn hotspot_changed(&self) -> bool {
+        match (&self.previous_hotspot, &self.hotspot) {
+            (Some(previous), Some(current)) => !previous.same_node(current),
+            (previous, current) => previous.is_some() != current.is_some(),
+        }
+    }
+}
+
+fn node_type_changes(previous: &Explain, current: &Explain) -> Vec<NodeTypeChange> {
+    let node_type = |plan: &Plan| plan.identity.node_type.as_str().to_string();
+    let previous: Vec<(String, String)> = pre_order_at(&previous.plan, previous.root_path())
+        .map(|node| (node.path, node_type(node.plan)))
+        .collect();
+    let mut unmatched: HashMap<&str, &str> = previous
+        .iter()
+        .map(|(path, node_type)| (path.as_str(), node_type.as_str()))
+        .collect();
+
+    let mut changes = Vec::new();
+    for node in pre_order_at(&current.plan, current.root_path()) {
+        let after = node_type(node.plan);
+        let before = unmatched.remove(node.path.as_str());
+        if before != Some(after.as_str()) {
+            changes.push(NodeTypeChange {
+                before: before.map(str::to_string),
+                after: Some(after),
+                path: node.path,
+            });
+        }
+    }
+    changes.extend(
+        previous
+            .iter()
+            .filter(|(path, _)| unmatched.contains_key(path.as_str()))
+            .map(|(path, node_type)| NodeTypeChange {
+                path: path.clone(),
+                before: Some(node_type.clone()),
+                after: None,
+            }),
+    );
+    changes
+}
+
+impl fmt::Display for PlanDelta {
+    /// Four lines: execution time, hotspot, node types, settings.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Δ execution: {:.2} ms → {:.2} ms",
+            self.previous_execution_time, self.execution_time
+        )?;
+        if self.previous_execution_time > 0.0 {
+            let change = (self.execution_time - self.previous_execution_time)
+                / self.previous_execution_time
+                * 100.0;
+            write!(f, " ({change:+.1}%)")?;
+        }
+        writeln!(f)?;
+
+        let describe = |hotspot: &Option<Hotspot>| {
+            hotspot
+                .as_ref()
+                .map_or_else(|| "none".to_string(), Hotspot::to_string)
+        };
+        if self.hotspot_changed() {
+            writeln!(
+                f,
+                "Δ hotspot: {} → {}",
+                describe(&self.previous_hotspot),
+                describe(&self.hotspot)
+            )?;
+        } else {
+            writeln!(f, "Δ hotspot: unchanged ({})", describe(&self.hotspot))?;
+        }
+
+        if self.node_type_changes.is_empty() {
+            writeln!(f, "Δ node types: unchanged")?;
+        } else {
+            let listed: Vec<String> = self
+                .node_type_changes
+                .iter()
+                .take(MAX_LISTED_NODE_CHANGES)
+                .map(NodeTypeChange::to_string)
+                .collect();
+            write!(f, "Δ node types: {}", listed.join("; "))?;
+            let unlisted = self
+                .node_type_changes
+                .len()
+                .saturating_sub(MAX_LISTED_NODE_CHANGES);
+            if unlisted > 0 {
+                write!(f, "; … {unlisted} more")?;
+            }
+            writeln!(f)?;
+        }
+
+        if self.setting_changes.is_empty() {
+            return writeln!(f, "Δ settings: unchanged");
+        }
+        let listed: Vec<String> = self
+            .setting_changes
+            .iter()
+            .map(SettingChange::to_string)
+            .collect();
+        writeln!(f, "Δ settings: {}", listed.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{explain_for, with_children, LeafBuilder};
+
+    fn timed(node_type: &str, relation: &str, time: f64) -> Plan {
+        let mut builder = LeafBuilder::new(node_type).time(time);
+        if !relation.is_empty() {
+            builder = builder.relation("public", relation);
+        }
+        let mut plan = builder.build();
+        plan.actuals.actual_duration = time;
+        plan
+    }
+
+    fn join(scan: Plan) -> Explain {
+        let plan = with_children(timed("Hash Join", "", 1.0), [scan, timed("Hash", "", 0.5)]);
+        explain_for(plan, 0.0, 0.0)
+    }
+
+    #[test]
+    fn reports_execution_time_hotspot_and_node_type_changes() {
+        let mut before = join(timed("Seq Scan", "orders", 9.0));
+        before.execution_time = 12.0;
+        let mut after = join(timed("Index Scan", "orders", 0.2));
+        after.execution_time = 3.0;
+
+        let delta = PlanDelta::between(&before, &after);
+
+        assert!(delta.hotspot_changed());
+        assert_eq!(
+            delta.node_type_changes,
+            [NodeTypeChange {
+                path: "Plan.Plans[0]".to_string(),
+                before: Some("Seq Scan".to_string()),
+                after: Some("Index Scan".to_string()),
+            }]
+        );
+        let text = delta.to_string();
+        assert!(text.contains("Δ execution: 12.00 ms → 3.00 ms (-75.0%)"));
+        assert!(text.contains("Δ hotspot: #0 Seq Scan on orders → #0 Hash Join"));
+        assert!(text.contains("Δ node types: Plan.Plans[0]: Seq Scan → Index Scan"));
+    }
+
+    #[test]
+    fn identical_plans_report_no_structural_change() {
+        let plan = join(timed("Seq Scan", "orders", 9.0));
+        let delta = PlanDelta::between(&plan, &plan);
+
+        assert!(!delta.hotspot_changed());
+        assert!(delta.node_type_changes.is_empty());
+        let text = delta.to_string();
+        assert!(text.contains("Δ hotspot: unchanged (#0 Seq Scan on orders)"));
+        assert!(text.contains("Δ node types: unchanged"));
+        assert!(text.contains("Δ settings: unchanged"));
+    }
+
+    #[test]
+    fn changed_settings_are_listed_by_name() {
+        let before = join(timed("Seq Scan", "orders", 9.0));
+        let mut after = before.clone();
+        after.settings.insert("enable_seqscan".into(), "off".into());
+
+        let delta = PlanDelta::between(&before, &after);
+
+        assert_eq!(delta.setting_changes.len(), 1);
+        assert!(delta
+            .to_string()
+            .ends_with("Δ settings: enable_seqscan: default → off\n"));
+    }
+
+    #[test]
+    fn added_and_removed_nodes_are_listed_by_path() {
+        let before = explain_for(timed("Seq Scan", "orders", 1.0), 0.0, 0.0);
+        let after = explain_for(
+            with_children(timed("Sort", "", 0.1), [timed("Seq Scan", "orders", 1.0)]),
+            0.0,
+            0.0,
+        );
+
+        let text = PlanDelta::between(&before, &after).to_string();
+        assert!(text.contains("Plan: Seq Scan → Sort; Plan.Plans[0]: + Seq Scan"));
+
+        let text = PlanDelta::between(&after, &before).to_string();
+        assert!(text.contains("Plan: Sort → Seq Scan; Plan.Plans[0]: - Seq Scan"));
+    }
+}
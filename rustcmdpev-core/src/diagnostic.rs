@@ -0,0 +1,421 @@
+//! Structured errors: what went wrong, where in the input, and how to fix it.
+//!
+//! A [`Diagnostic`] names the failure `kind`, the JSON path of the offending
+//! value (`$[0].Plan.Plans[1].Total Cost`), its byte/line/column position
+//! when known, and an optional hint. [`Diagnostic::render`] formats it with
+//! the offending input line and a caret; the struct serializes as-is for
+//! machine consumers.
+
+use std::fmt;
+use std::fmt::Write as _;
+
+use serde::Serialize;
+
+/// Root of every diagnostic path; the first explain object is `$[0]`.
+pub const JSON_PATH_ROOT: &str = "$";
+
+/// Lines longer than this many characters are trimmed around the caret.
+const EXCERPT_MAX_WIDTH: usize = 100;
+/// Characters of context kept either side of the caret on trimmed lines.
+const EXCERPT_CONTEXT: usize = 40;
+
+/// Where in the input an error was detected. `line` and `column` are
+/// 1-based; `column` counts characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SourcePosition {
+    pub byte: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl SourcePosition {
+    /// Position of `byte` within `input`, clamped to the input length.
+    pub fn from_byte(input: &str, byte: usize) -> Self {
+        let mut byte = byte.min(input.len());
+        while !input.is_char_boundary(byte) {
+            byte -= 1;
+        }
+        let line_start = input[..byte].rfind('\n').map_or(0, |index| index + 1);
+        Self {
+            byte,
+            line: input[..byte].matches('\n').count() + 1,
+            column: input[line_start..byte].chars().count() + 1,
+        }
+    }
+
+    /// Position from a 1-based line and byte column, as reported by
+    /// `serde_json`. A column of 0 points at the start of the line.
+    pub fn from_line_column(input: &str, line: usize, column: usize) -> Self {
+        let line_start = input
+            .split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(str::len)
+            .sum::<usize>();
+        Self::from_byte(input, line_start + column.saturating_sub(1))
+    }
+}
+
+/// A structured, renderable error.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// Stable snake_case identifier, e.g. `invalid_json` or `invalid_plan`.
+    pub kind: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<SourcePosition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(kind: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            path: None,
+            position: None,
+            hint: None,
+        }
+    }
+
+    /// Diagnostic for input that is not valid JSON, positioned where
+    /// `serde_json` gave up.
+    pub fn invalid_json(input: &str, err: &serde_json::Error) -> Self {
+        let mut diagnostic = Self::new("invalid_json", format!("invalid JSON input: {err}"))
+            .with_hint("Ensure input is a PostgreSQL EXPLAIN FORMAT JSON array.");
+        if err.line() > 0 {
+            diagnostic.position = Some(SourcePosition::from_line_column(
+                input,
+                err.line(),
+                err.column(),
+            ));
+        }
+        diagnostic
+    }
+
+    #[must_use]
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_position(mut self, position: SourcePosition) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    #[must_use]
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Resolve `path` against `input` when no position is known yet. A path
+    /// to a missing key resolves to the object that should contain it.
+    #[must_use]
+    pub fn located_in(mut self, input: &str) -> Self {
+        if self.position.is_none() {
+            if let Some(byte) = self.path.as_deref().and_then(|path| locate(input, path)) {
+                self.position = Some(SourcePosition::from_byte(input, byte));
+            }
+        }
+        self
+    }
+
+    /// Human-readable report. With `input`, the offending line is quoted
+    /// and a caret marks the column; `source` names the input in the
+    /// location line (a file path or `<stdin>`).
+    pub fn render(&self, source: &str, input: Option<&str>) -> String {
+        let mut out = format!("error: {}\n", self.message);
+        let excerpt = self.position.zip(input).and_then(|(position, input)| {
+            let line = input.lines().nth(position.line - 1)?;
+            Some((position, line))
+        });
+        if let Some((position, line)) = excerpt {
+            let number = position.line.to_string();
+            let gutter = " ".repeat(number.len());
+            let (text, caret) = excerpt_around(line, position.column - 1);
+            let _ = writeln!(
+                out,
+                "{gutter}--> {source}:{}:{}",
+                position.line, position.column
+            );
+            let _ = writeln!(out, "{gutter} |");
+            let _ = writeln!(out, "{number} | {text}");
+            let _ = writeln!(out, "{gutter} | {}^", " ".repeat(caret));
+        } else if let Some(position) = self.position {
+            let _ = writeln!(out, " --> {source}:{}:{}", position.line, position.column);
+        }
+        if let Some(path) = self.path.as_deref() {
+            if !self.message.contains(path) {
+                let _ = writeln!(out, "  = path: {path}");
+            }
+        }
+        if let Some(hint) = self.hint.as_deref() {
+            let _ = writeln!(out, "  = hint: {hint}");
+        }
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Trim `line` to a window around character `column`, returning the text
+/// and the caret offset within it. Minified EXPLAIN output is one long line.
+fn excerpt_around(line: &str, column: usize) -> (String, usize) {
+    let chars: Vec<char> = line
+        .chars()
+        .map(|c| if c == '\t' { ' ' } else { c })
+        .collect();
+    let column = column.min(chars.len());
+    if chars.len() <= EXCERPT_MAX_WIDTH {
+        return (chars.into_iter().collect(), column);
+    }
+    let start = column.saturating_sub(EXCERPT_CONTEXT);
+    let end = (column + EXCERPT_CONTEXT).min(chars.len());
+    let mut text = String::new();
+    let mut caret = column - start;
+    if start > 0 {
+        text.push('…');
+        caret += 1;
+    }
+    text.extend(&chars[start..end]);
+    if end < chars.len() {
+        text.push('…');
+    }
+    (text, caret)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Segment<'p> {
+    Index(usize),
+    Key(&'p str),
+}
+
+/// Split `$[0].Plan.Plans[1].Total Cost` into segments. Keys run to the next
+/// `.` or `[`, which PostgreSQL key names never contain.
+fn segments(path: &str) -> Option<Vec<Segment<'_>>> {
+    let mut rest = path.strip_prefix(JSON_PATH_ROOT)?;
+    let mut out = Vec::new();
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix('[') {
+            let close = tail.find(']')?;
+            out.push(Segment::Index(tail[..close].parse().ok()?));
+            rest = &tail[close + 1..];
+        } else if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            out.push(Segment::Key(&tail[..end]));
+            rest = &tail[end..];
+        } else {
+            return None;
+        }
+    }
+    Some(out)
+}
+
+/// Byte offset of the value at `path` in `input`, or of the deepest
+/// enclosing value that exists.
+pub fn locate(input: &str, path: &str) -> Option<usize> {
+    let segments = segments(path)?;
+    let mut scanner = Scanner {
+        bytes: input.as_bytes(),
+        pos: 0,
+    };
+    scanner.skip_whitespace();
+    if scanner.pos >= scanner.bytes.len() {
+        return None;
+    }
+    for segment in segments {
+        let container = scanner.pos;
+        let found = match segment {
+            Segment::Index(index) => scanner.enter_index(index),
+            Segment::Key(key) => scanner.enter_key(key),
+        };
+        if found.is_none() {
+            return Some(container);
+        }
+    }
+    Some(scanner.pos)
+}
+
+/// Just enough of a JSON reader to walk to a path without building values.
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.skip_whitespace();
+        (self.peek()? == byte).then(|| self.pos += 1)
+    }
+
+    /// Move to the start of element `index` of the array at the cursor.
+    fn enter_index(&mut self, index: usize) -> Option<()> {
+        self.expect(b'[')?;
+        for _ in 0..index {
+            self.skip_whitespace();
+            if self.peek()? == b']' {
+                return None;
+            }
+            self.skip_value()?;
+            self.expect(b',')?;
+        }
+        self.skip_whitespace();
+        (self.peek()? != b']').then_some(())
+    }
+
+    /// Move to the value of `key` in the object at the cursor.
+    fn enter_key(&mut self, key: &str) -> Option<()> {
+        self.expect(b'{')?;
+        loop {
+            self.skip_whitespace();
+            if self.peek()? != b'"' {
+                return None;
+            }
+            let name = self.read_string()?;
+            self.expect(b':')?;
+            self.skip_whitespace();
+            if name == key {
+                return Some(());
+            }
+            self.skip_value()?;
+            self.expect(b',')?;
+        }
+    }
+
+    fn read_string(&mut self) -> Option<String> {
+        let start = self.pos + 1;
+        self.skip_string()?;
+        let raw = std::str::from_utf8(&self.bytes[start..self.pos - 1]).ok()?;
+        if raw.contains('\\') {
+            serde_json::from_str(&format!("\"{raw}\"")).ok()
+        } else {
+            Some(raw.to_string())
+        }
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        self.pos += 1;
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        self.skip_whitespace();
+        let mut depth = 0usize;
+        loop {
+            match self.peek()? {
+                b'"' => self.skip_string()?,
+                b'{' | b'[' => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                b'}' | b']' if depth > 0 => {
+                    depth -= 1;
+                    self.pos += 1;
+                }
+                b',' | b'}' | b']' if depth == 0 => return Some(()),
+                _ => self.pos += 1,
+            }
+            if depth == 0 && matches!(self.peek(), Some(b',' | b'}' | b']') | None) {
+                return Some(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "[\n  {\n    \"Plan\": {\n      \"Node Type\": \"Hash\",\n      \"Plans\": [\n        {\"Node Type\": \"Seq Scan\", \"Total Cost\": -1}\n      ]\n    }\n  }\n]\n";
+
+    #[test]
+    fn locate_walks_keys_and_indexes() {
+        let byte = locate(INPUT, "$[0].Plan.Plans[0].Total Cost").expect("found");
+        assert!(INPUT[byte..].starts_with("-1}"));
+        let position = SourcePosition::from_byte(INPUT, byte);
+        assert_eq!((position.line, position.column), (6, 49));
+    }
+
+    #[test]
+    fn locate_falls_back_to_the_enclosing_value() {
+        let byte = locate(INPUT, "$[0].Plan.Plans[0].Startup Cost").expect("found");
+        assert!(INPUT[byte..].starts_with("{\"Node Type\": \"Seq Scan\""));
+        let byte = locate(INPUT, "$[0].Plan.Plans[3]").expect("found");
+        assert!(INPUT[byte..].starts_with("[\n        {"));
+    }
+
+    #[test]
+    fn render_quotes_the_line_with_a_caret() {
+        let rendered = Diagnostic::new("contract_violation", "Total Cost must be non-negative")
+            .with_path("$[0].Plan.Plans[0].Total Cost")
+            .with_hint("check the input")
+            .located_in(INPUT)
+            .render("plan.json", Some(INPUT));
+        let expected = format!(
+            "error: Total Cost must be non-negative\n --> plan.json:6:49\n  |\n6 |         {{\"Node Type\": \"Seq Scan\", \"Total Cost\": -1}}\n  | {}^\n  = path: $[0].Plan.Plans[0].Total Cost\n  = hint: check the input\n",
+            " ".repeat(48)
+        );
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn long_lines_are_trimmed_around_the_caret() {
+        let input = format!(
+            "[{{\"Plan\":{{{}\"Total Cost\":-1}}}}]",
+            "\"Filter\":\"x\",".repeat(20)
+        );
+        let rendered = Diagnostic::new("contract_violation", "bad")
+            .with_path("$[0].Plan.Total Cost")
+            .located_in(&input)
+            .render("<stdin>", Some(&input));
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert!(lines[3].starts_with("1 | …"));
+        let caret = lines[4].find('^').expect("caret");
+        let quoted: Vec<char> = lines[3].chars().collect();
+        let prefix = lines[4][..caret].chars().count();
+        assert_eq!(quoted[prefix], '-');
+    }
+
+    #[test]
+    fn invalid_json_uses_serde_position_and_serializes() {
+        let input = "[\n  {\"Plan\": }\n]";
+        let err = serde_json::from_str::<serde_json::Value>(input).expect_err("invalid");
+        let diagnostic = Diagnostic::invalid_json(input, &err);
+        let position = diagnostic.position.expect("position");
+        assert_eq!(position.line, 2);
+        assert_eq!(&input[position.byte..position.byte + 1], "}");
+
+        let json = serde_json::to_value(&diagnostic).expect("serialize");
+        assert_eq!(json["kind"], "invalid_json");
+        assert_eq!(json["position"]["line"], 2);
+        assert!(json.get("path").is_none());
+    }
+}
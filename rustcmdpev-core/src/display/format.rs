@@ -1,8 +1,11 @@
 use crate::constants::{
-    BAD_ESTIMATE_FACTOR_THRESHOLD, TAG_BAD_ESTIMATE, TAG_COSTLIEST, TAG_LARGEST, TAG_SLOWEST,
+    BAD_ESTIMATE_FACTOR_THRESHOLD, TAG_BAD_ESTIMATE, TAG_COSTLIEST, TAG_ESTIMATE_INHERITED,
+    TAG_ESTIMATE_ORIGIN, TAG_LARGEST, TAG_SLOWEST,
 };
 use crate::display::colors::{themed_format, Theme};
 use crate::display::tree;
+use crate::io_profile::IoProfile;
+use crate::structure::data::analysis_flags::EstimateErrorSource;
 use crate::structure::data::plan;
 
 pub fn duration_to_string(value: f64) -> colored::ColoredString {
@@ -55,6 +58,11 @@ pub fn format_tags(plan: &plan::Plan) -> String {
     if plan.analysis_flags.planner_row_estimate_factor >= BAD_ESTIMATE_FACTOR_THRESHOLD {
         tags.push(TAG_BAD_ESTIMATE);
     }
+    match plan.analysis_flags.estimate_error_source {
+        EstimateErrorSource::Origin => tags.push(TAG_ESTIMATE_ORIGIN),
+        EstimateErrorSource::Inherited => tags.push(TAG_ESTIMATE_INHERITED),
+        EstimateErrorSource::None => {}
+    }
     tags.join(" ")
 }
 
@@ -68,6 +76,50 @@ pub fn output_terminator(index: usize, plan: &plan::Plan) -> &'static str {
     tree::output_terminator(index, plan)
 }
 
+/// Format a byte rate with binary units, e.g. `12.50 MiB/s`.
+pub fn format_throughput(bytes_per_sec: f64) -> String {
+    const UNITS: [&str; 4] = ["B/s", "KiB/s", "MiB/s", "GiB/s"];
+    let mut value = bytes_per_sec;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.2} {}", UNITS[unit])
+}
+
+/// Format a byte count with binary units, e.g. `3.20 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.2} {}", UNITS[unit])
+}
+
+/// Format an I/O profile as `hit=… read=… io=… (bound)`, skipping metrics
+/// the plan did not report.
+pub fn format_io_profile(profile: &IoProfile) -> String {
+    let mut parts = Vec::new();
+    if let Some(ratio) = profile.cache_hit_ratio {
+        parts.push(format!("hit={}", format_percent(ratio * 100.0, 1)));
+    }
+    if let Some(throughput) = profile.read_throughput {
+        parts.push(format!("read={}", format_throughput(throughput)));
+    }
+    if let Some(share) = profile.io_time_share {
+        parts.push(format!("io={}", format_percent(share * 100.0, 1)));
+    }
+    parts.push(format!("({})", profile.bound.label()));
+    parts.join(" ")
+}
+
 pub fn format_percent(number: f64, precision: usize) -> String {
     format!("{:.1$}%", number, precision)
 }
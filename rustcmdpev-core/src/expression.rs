@@ -0,0 +1,1150 @@
+//! Parser for the expressions EXPLAIN prints.
+//!
+//! `Filter`, `Index Cond`, `Hash Cond`, `Output`, and the other expression
+//! fields hold text deparsed by PostgreSQL's `ruleutils.c`: operators fully
+//! parenthesized, casts as `(expr)::type`, `IN` lists as `= ANY (...)`, `$n`
+//! parameters, and `(SubPlan n)` references. [`parse`] turns that text into
+//! an [`Expr`] tree, so analyses can ask which columns a condition compares
+//! and which casts it applies instead of matching strings, and [`Expr`]'s
+//! `Display` writes the tree back in the same style.
+//!
+//! The grammar covers what EXPLAIN prints for ordinary queries. Aggregate
+//! modifiers (`count(DISTINCT x)`, `FILTER`, `OVER`), array slices, and
+//! `alternatives:` lists are reported as errors rather than guessed at, and so
+//! is nesting deeper than [`MAX_EXPRESSION_DEPTH`], which keeps the recursive
+//! parser, `Display`, and `Drop` within the stack.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::constants::MAX_EXPRESSION_DEPTH;
+
+/// A parsed expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Column reference, e.g. `o.customer_id`.
+    Column(ColumnRef),
+    Const(Const),
+    /// Query parameter `$n`.
+    Param(u32),
+    /// `expr::type`; `type_name` is as printed, e.g. `character varying[]`.
+    Cast {
+        expr: Box<Expr>,
+        type_name: String,
+    },
+    /// Function call; `name` keeps any schema qualification.
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    /// `*` as the argument of `count(*)`.
+    Star,
+    /// SQL value function printed without parentheses, e.g. `CURRENT_DATE`.
+    SqlValue(String),
+    /// Infix operator, including `IS [NOT] DISTINCT FROM`.
+    Binary {
+        op: String,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    /// Prefix operator, e.g. `- x`.
+    Unary {
+        op: String,
+        expr: Box<Expr>,
+    },
+    /// `left op ANY (right)`, or `ALL` when `any` is false.
+    ArrayOp {
+        op: String,
+        any: bool,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    /// `expr IS test`, where `test` is e.g. `NULL`, `NOT NULL`, or `TRUE`.
+    Is {
+        expr: Box<Expr>,
+        test: String,
+    },
+    /// `ARRAY[...]`.
+    Array(Vec<Expr>),
+    /// `ROW(...)`, or a parenthesized list.
+    Row(Vec<Expr>),
+    /// `(SubPlan n)`, or `(hashed SubPlan n)`.
+    SubPlan {
+        id: u32,
+        hashed: bool,
+    },
+    /// `InitPlan n`, whose output columns are selected with [`Expr::Field`].
+    InitPlan(u32),
+    /// `(expr).name`.
+    Field {
+        expr: Box<Expr>,
+        name: String,
+    },
+    /// `(expr)[index]`.
+    Subscript {
+        expr: Box<Expr>,
+        index: Box<Expr>,
+    },
+    /// `expr COLLATE collation`.
+    Collate {
+        expr: Box<Expr>,
+        collation: String,
+    },
+    /// `CASE [operand] WHEN ... THEN ... [ELSE ...] END`.
+    Case {
+        operand: Option<Box<Expr>>,
+        whens: Vec<(Expr, Expr)>,
+        otherwise: Option<Box<Expr>>,
+    },
+}
+
+/// A column, optionally qualified by a relation alias. Names are unquoted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnRef {
+    pub qualifier: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Const {
+    /// String constant, with doubled quotes unescaped.
+    String(String),
+    /// Numeric constant, as written.
+    Number(String),
+    Bool(bool),
+    Null,
+}
+
+impl Expr {
+    /// Direct subexpressions, in source order.
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Column(_)
+            | Expr::Const(_)
+            | Expr::Param(_)
+            | Expr::Star
+            | Expr::SqlValue(_)
+            | Expr::SubPlan { .. }
+            | Expr::InitPlan(_) => Vec::new(),
+            Expr::Cast { expr, .. }
+            | Expr::Unary { expr, .. }
+            | Expr::Not(expr)
+            | Expr::Is { expr, .. }
+            | Expr::Field { expr, .. }
+            | Expr::Collate { expr, .. } => vec![expr],
+            Expr::Binary { left, right, .. } | Expr::ArrayOp { left, right, .. } => {
+                vec![left, right]
+            }
+            Expr::Subscript { expr, index } => vec![expr, index],
+            Expr::Call { args: items, .. }
+            | Expr::And(items)
+            | Expr::Or(items)
+            | Expr::Array(items)
+            | Expr::Row(items) => items.iter().collect(),
+            Expr::Case {
+                operand,
+                whens,
+                otherwise,
+            } => operand
+                .iter()
+                .map(Box::as_ref)
+                .chain(whens.iter().flat_map(|(when, then)| [when, then]))
+                .chain(otherwise.iter().map(Box::as_ref))
+                .collect(),
+        }
+    }
+
+    /// This expression and every subexpression, in pre-order.
+    pub fn walk(&self) -> impl Iterator<Item = &Expr> {
+        let mut pending = vec![self];
+        std::iter::from_fn(move || {
+            let expr = pending.pop()?;
+            pending.extend(expr.children().into_iter().rev());
+            Some(expr)
+        })
+    }
+
+    /// Every column referenced, in source order.
+    pub fn columns(&self) -> impl Iterator<Item = &ColumnRef> {
+        self.walk().filter_map(|expr| match expr {
+            Expr::Column(column) => Some(column),
+            _ => None,
+        })
+    }
+
+    /// The terms of a top-level `AND`, or the expression itself.
+    pub fn conjuncts(&self) -> &[Expr] {
+        match self {
+            Expr::And(terms) => terms,
+            _ => std::slice::from_ref(self),
+        }
+    }
+
+    /// The expression under any casts, e.g. the column of `(status)::text`.
+    pub fn uncast(&self) -> &Expr {
+        let mut expr = self;
+        while let Expr::Cast { expr: inner, .. } = expr {
+            expr = inner;
+        }
+        expr
+    }
+}
+
+/// Why an expression could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExprParseError {
+    /// Byte offset in the input where parsing stopped.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ExprParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.position)
+    }
+}
+
+impl Error for ExprParseError {}
+
+/// Parse one deparsed expression.
+pub fn parse(text: &str) -> Result<Expr, ExprParseError> {
+    let mut parser = Parser {
+        tokens: lex(text)?,
+        pos: 0,
+        end: text.len(),
+        depth: 0,
+    };
+    let expr = parser.expr(0)?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(parser.error(format!("unexpected {token}"))),
+    }
+}
+
+/// Words that continue a multi-word type name, e.g. `character varying`.
+const TYPE_NAME_WORDS: &[&str] = &["varying", "precision", "with", "without", "time", "zone"];
+
+/// SQL value functions, printed without parentheses.
+const SQL_VALUES: &[&str] = &[
+    "CURRENT_DATE",
+    "CURRENT_TIME",
+    "CURRENT_TIMESTAMP",
+    "LOCALTIME",
+    "LOCALTIMESTAMP",
+    "CURRENT_ROLE",
+    "CURRENT_USER",
+    "SESSION_USER",
+    "USER",
+    "CURRENT_CATALOG",
+    "CURRENT_SCHEMA",
+];
+
+/// Binding power of `::`; `[...]`, `.name`, and `COLLATE` bind just as tight.
+const POSTFIX_BP: u8 = 23;
+const NOT_BP: u8 = 5;
+const PREFIX_OP_BP: u8 = 19;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Unquoted identifier or keyword, as written.
+    Word(String),
+    /// Double-quoted identifier, unescaped.
+    Quoted(String),
+    Number(String),
+    Str(String),
+    Param(u32),
+    Op(String),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Dot,
+    Cast,
+}
+
+impl Token {
+    fn is_word(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) | Token::Number(word) | Token::Op(word) => write!(f, "`{word}`"),
+            Token::Quoted(name) => write!(f, "`\"{name}\"`"),
+            Token::Str(_) => f.write_str("string constant"),
+            Token::Param(n) => write!(f, "`${n}`"),
+            Token::LParen => f.write_str("`(`"),
+            Token::RParen => f.write_str("`)`"),
+            Token::LBracket => f.write_str("`[`"),
+            Token::RBracket => f.write_str("`]`"),
+            Token::Comma => f.write_str("`,`"),
+            Token::Dot => f.write_str("`.`"),
+            Token::Cast => f.write_str("`::`"),
+        }
+    }
+}
+
+fn lex(text: &str) -> Result<Vec<(Token, usize)>, ExprParseError> {
+    let bytes = text.as_bytes();
+    let error = |position: usize, message: &str| ExprParseError {
+        position,
+        message: message.to_string(),
+    };
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let byte = bytes[i];
+        let token = if byte.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        } else if byte == b'\'' {
+            let (end, value) =
+                quoted(text, i, b'\'').ok_or_else(|| error(i, "unterminated string constant"))?;
+            i = end;
+            Token::Str(value)
+        } else if byte == b'"' {
+            let (end, name) =
+                quoted(text, i, b'"').ok_or_else(|| error(i, "unterminated quoted identifier"))?;
+            i = end;
+            Token::Quoted(name)
+        } else if byte == b'$' {
+            let end = digits_end(bytes, i + 1);
+            let n = text[i + 1..end]
+                .parse()
+                .map_err(|_| error(i, "expected a parameter number after `$`"))?;
+            i = end;
+            Token::Param(n)
+        } else if byte.is_ascii_digit() {
+            i = number_end(bytes, i);
+            Token::Number(text[start..i].to_string())
+        } else if is_word_byte(byte) {
+            i += bytes[i..]
+                .iter()
+                .take_while(|&&byte| is_word_byte(byte) || byte.is_ascii_digit() || byte == b'$')
+                .count();
+            let word = &text[start..i];
+            if bytes.get(i) == Some(&b'\'') && matches!(word, "E" | "e" | "B" | "b" | "X" | "x") {
+                let (end, value) = quoted(text, i, b'\'')
+                    .ok_or_else(|| error(i, "unterminated string constant"))?;
+                i = end;
+                Token::Str(value)
+            } else {
+                Token::Word(word.to_string())
+            }
+        } else if bytes[i..].starts_with(b"::") {
+            i += 2;
+            Token::Cast
+        } else if is_operator_byte(byte) {
+            i += bytes[i..]
+                .iter()
+                .take_while(|&&byte| is_operator_byte(byte))
+                .count();
+            // As in PostgreSQL's lexer, a multi-character operator only ends
+            // in `+` or `-` when it also holds one of `~!@#%^&|`?`, so
+            // `>-1` reads as `>` followed by `-1`.
+            let special = text[start..i].bytes().any(|b| b"~!@#%^&|`?".contains(&b));
+            while !special && i - start > 1 && matches!(bytes[i - 1], b'+' | b'-') {
+                i -= 1;
+            }
+            Token::Op(text[start..i].to_string())
+        } else {
+            i += 1;
+            match byte {
+                b'(' => Token::LParen,
+                b')' => Token::RParen,
+                b'[' => Token::LBracket,
+                b']' => Token::RBracket,
+                b',' => Token::Comma,
+                b'.' => Token::Dot,
+                _ => return Err(error(start, "unexpected character")),
+            }
+        };
+        tokens.push((token, start));
+    }
+    Ok(tokens)
+}
+
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || byte == b'_' || !byte.is_ascii()
+}
+
+fn is_operator_byte(byte: u8) -> bool {
+    b"+-*/<>=~!@#%^&|`?".contains(&byte)
+}
+
+fn digits_end(bytes: &[u8], start: usize) -> usize {
+    start
+        + bytes[start..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+}
+
+/// End of a numeric constant such as `42`, `1.5`, or `2e-3`.
+fn number_end(bytes: &[u8], start: usize) -> usize {
+    let mut end = digits_end(bytes, start);
+    if bytes.get(end) == Some(&b'.') {
+        end = digits_end(bytes, end + 1);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+        let exponent = digits_end(bytes, end + 1 + sign);
+        if exponent > end + 1 + sign {
+            end = exponent;
+        }
+    }
+    end
+}
+
+/// The end of the quoted run opened at `start` and its unescaped content,
+/// or `None` when it is not closed.
+fn quoted(text: &str, start: usize, quote: u8) -> Option<(usize, String)> {
+    let bytes = text.as_bytes();
+    let mut value = String::new();
+    let mut run = start + 1;
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == quote {
+            value.push_str(&text[run..i]);
+            if bytes.get(i + 1) == Some(&quote) {
+                value.push(char::from(quote));
+                i += 2;
+                run = i;
+                continue;
+            }
+            return Some((i + 1, value));
+        }
+        i += 1;
+    }
+    None
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+    /// Nesting of the node being built: one per `expr` call and one per
+    /// operator applied to its left operand.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(token, _)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn error(&self, message: String) -> ExprParseError {
+        let position = self
+            .tokens
+            .get(self.pos)
+            .map_or(self.end, |(_, position)| *position);
+        ExprParseError { position, message }
+    }
+
+    fn nest(&mut self) -> Result<(), ExprParseError> {
+        self.depth += 1;
+        if self.depth > MAX_EXPRESSION_DEPTH {
+            return Err(self.error(format!(
+                "expression nests deeper than {MAX_EXPRESSION_DEPTH} levels"
+            )));
+        }
+        Ok(())
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), ExprParseError> {
+        match self.peek() {
+            Some(token) if token == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(token) => Err(self.error(format!("expected {expected}, found {token}"))),
+            None => Err(self.error(format!("expected {expected}, found end of input"))),
+        }
+    }
+
+    fn expect_word(&mut self, keyword: &str) -> Result<(), ExprParseError> {
+        if self.peek().is_some_and(|token| token.is_word(keyword)) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{keyword}`")))
+        }
+    }
+
+    /// Parse operators binding tighter than `min_bp`.
+    fn expr(&mut self, min_bp: u8) -> Result<Expr, ExprParseError> {
+        let depth = self.depth;
+        let expr = self.nested_expr(min_bp);
+        self.depth = depth;
+        expr
+    }
+
+    fn nested_expr(&mut self, min_bp: u8) -> Result<Expr, ExprParseError> {
+        self.nest()?;
+        let mut left = self.prefix()?;
+        while let Some(token) = self.peek().cloned() {
+            if POSTFIX_BP < min_bp {
+                break;
+            }
+            self.nest()?;
+            left = match token {
+                Token::Cast => {
+                    self.pos += 1;
+                    Expr::Cast {
+                        expr: Box::new(left),
+                        type_name: self.type_name()?,
+                    }
+                }
+                Token::LBracket => {
+                    self.pos += 1;
+                    let index = self.expr(0)?;
+                    self.expect(&Token::RBracket)?;
+                    Expr::Subscript {
+                        expr: Box::new(left),
+                        index: Box::new(index),
+                    }
+                }
+                Token::Dot => {
+                    self.pos += 1;
+                    Expr::Field {
+                        expr: Box::new(left),
+                        name: self.name()?,
+                    }
+                }
+                Token::Word(word) if word.eq_ignore_ascii_case("COLLATE") => {
+                    self.pos += 1;
+                    Expr::Collate {
+                        expr: Box::new(left),
+                        collation: self.qualified_name()?,
+                    }
+                }
+                _ => match self.infix(left, min_bp)? {
+                    Ok(expr) => expr,
+                    Err(left) => return Ok(left),
+                },
+            };
+        }
+        Ok(left)
+    }
+
+    /// Apply the infix operator at the cursor to `left`, or hand `left`
+    /// back when there is none binding tighter than `min_bp`.
+    fn infix(&mut self, left: Expr, min_bp: u8) -> Result<Result<Expr, Expr>, ExprParseError> {
+        let Some(token) = self.peek().cloned() else {
+            return Ok(Err(left));
+        };
+        let (left_bp, right_bp) = match &token {
+            Token::Word(word) if word.eq_ignore_ascii_case("OR") => (1, 2),
+            Token::Word(word) if word.eq_ignore_ascii_case("AND") => (3, 4),
+            Token::Word(word) if word.eq_ignore_ascii_case("IS") => (7, 8),
+            Token::Op(op) => operator_bp(op),
+            _ => return Ok(Err(left)),
+        };
+        if left_bp < min_bp {
+            return Ok(Err(left));
+        }
+        self.pos += 1;
+
+        let expr = match token {
+            Token::Word(word) if word.eq_ignore_ascii_case("IS") => self.is(left, right_bp)?,
+            Token::Word(word) => {
+                let right = self.expr(right_bp)?;
+                let and = word.eq_ignore_ascii_case("AND");
+                let mut terms = match left {
+                    Expr::And(terms) if and => terms,
+                    Expr::Or(terms) if !and => terms,
+                    left => vec![left],
+                };
+                terms.push(right);
+                if and {
+                    Expr::And(terms)
+                } else {
+                    Expr::Or(terms)
+                }
+            }
+            Token::Op(op) => {
+                let quantifier = self
+                    .peek()
+                    .filter(|_| self.peek_at(1) == Some(&Token::LParen))
+                    .and_then(|token| match token {
+                        token if token.is_word("ANY") => Some(true),
+                        token if token.is_word("ALL") => Some(false),
+                        _ => None,
+                    });
+                if let Some(any) = quantifier {
+                    self.pos += 2;
+                    let right = self.expr(0)?;
+                    self.expect(&Token::RParen)?;
+                    Expr::ArrayOp {
+                        op,
+                        any,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    }
+                } else {
+                    Expr::Binary {
+                        op,
+                        left: Box::new(left),
+                        right: Box::new(self.expr(right_bp)?),
+                    }
+                }
+            }
+            _ => unreachable!("only words and operators have binding powers"),
+        };
+        Ok(Ok(expr))
+    }
+
+    /// The rest of `left IS ...`.
+    fn is(&mut self, left: Expr, right_bp: u8) -> Result<Expr, ExprParseError> {
+        let negated = self.peek().is_some_and(|token| token.is_word("NOT"));
+        if negated {
+            self.pos += 1;
+        }
+        let test = match self.next() {
+            Some(Token::Word(word))
+                if ["NULL", "TRUE", "FALSE", "UNKNOWN"]
+                    .iter()
+                    .any(|test| word.eq_ignore_ascii_case(test)) =>
+            {
+                word.to_ascii_uppercase()
+            }
+            Some(token) if token.is_word("DISTINCT") => {
+                self.expect_word("FROM")?;
+                let op = if negated {
+                    "IS NOT DISTINCT FROM"
+                } else {
+                    "IS DISTINCT FROM"
+                };
+                return Ok(Expr::Binary {
+                    op: op.to_string(),
+                    left: Box::new(left),
+                    right: Box::new(self.expr(right_bp)?),
+                });
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error(
+                    "expected NULL, TRUE, FALSE, UNKNOWN, or DISTINCT FROM after IS".to_string(),
+                ));
+            }
+        };
+        Ok(Expr::Is {
+            expr: Box::new(left),
+            test: if negated { format!("NOT {test}") } else { test },
+        })
+    }
+
+    fn prefix(&mut self) -> Result<Expr, ExprParseError> {
+        let Some(token) = self.next() else {
+            return Err(self.error("expected an expression, found end of input".to_string()));
+        };
+        let expr = match token {
+            Token::Number(number) => Expr::Const(Const::Number(number)),
+            Token::Str(value) => Expr::Const(Const::String(value)),
+            Token::Param(n) => Expr::Param(n),
+            Token::Op(op) if op == "*" && self.peek() == Some(&Token::RParen) => Expr::Star,
+            Token::Op(op) => Expr::Unary {
+                op,
+                expr: Box::new(self.expr(PREFIX_OP_BP)?),
+            },
+            Token::LParen => {
+                let first = self.expr(0)?;
+                if self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                    let mut items = vec![first];
+                    items.extend(self.list(&Token::RParen)?);
+                    Expr::Row(items)
+                } else {
+                    self.expect(&Token::RParen)?;
+                    first
+                }
+            }
+            Token::Quoted(name) => self.column_or_call(name)?,
+            Token::Word(word) => self.word(word)?,
+            token => {
+                self.pos -= 1;
+                return Err(self.error(format!("expected an expression, found {token}")));
+            }
+        };
+        Ok(expr)
+    }
+
+    /// An expression starting with the unquoted word `word`.
+    fn word(&mut self, word: String) -> Result<Expr, ExprParseError> {
+        let upper = word.to_ascii_uppercase();
+        let next = self.peek();
+        let expr = match upper.as_str() {
+            "NOT" => Expr::Not(Box::new(self.expr(NOT_BP)?)),
+            "NULL" => Expr::Const(Const::Null),
+            "TRUE" => Expr::Const(Const::Bool(true)),
+            "FALSE" => Expr::Const(Const::Bool(false)),
+            "CASE" => self.case()?,
+            "ARRAY" if next == Some(&Token::LBracket) => {
+                self.pos += 1;
+                Expr::Array(self.list(&Token::RBracket)?)
+            }
+            "ROW" if next == Some(&Token::LParen) => {
+                self.pos += 1;
+                Expr::Row(self.list(&Token::RParen)?)
+            }
+            "SUBPLAN" | "INITPLAN" if matches!(next, Some(Token::Number(_))) => {
+                let id = self.plan_number()?;
+                if upper == "SUBPLAN" {
+                    Expr::SubPlan { id, hashed: false }
+                } else {
+                    Expr::InitPlan(id)
+                }
+            }
+            "HASHED" if next.is_some_and(|token| token.is_word("SubPlan")) => {
+                self.pos += 1;
+                Expr::SubPlan {
+                    id: self.plan_number()?,
+                    hashed: true,
+                }
+            }
+            _ if SQL_VALUES.contains(&upper.as_str()) && next != Some(&Token::LParen) => {
+                Expr::SqlValue(upper)
+            }
+            _ => self.column_or_call(word)?,
+        };
+        Ok(expr)
+    }
+
+    fn plan_number(&mut self) -> Result<u32, ExprParseError> {
+        match self.next() {
+            Some(Token::Number(number)) => number.parse().map_err(|_| {
+                self.pos -= 1;
+                self.error(format!("`{number}` is not a plan number"))
+            }),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected a plan number".to_string()))
+            }
+        }
+    }
+
+    /// A possibly qualified column reference or function call whose first
+    /// name has been read.
+    fn column_or_call(&mut self, first: String) -> Result<Expr, ExprParseError> {
+        let mut names = vec![first];
+        while self.peek() == Some(&Token::Dot) {
+            match self.peek_at(1) {
+                Some(Token::Word(name) | Token::Quoted(name)) => names.push(name.clone()),
+                Some(Token::Op(op)) if op == "*" => names.push(op.clone()),
+                _ => break,
+            }
+            self.pos += 2;
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let args = if self.peek() == Some(&Token::RParen) {
+                self.pos += 1;
+                Vec::new()
+            } else {
+                self.list(&Token::RParen)?
+            };
+            return Ok(Expr::Call {
+                name: names.join("."),
+                args,
+            });
+        }
+        let name = names.pop().expect("at least one name");
+        let qualifier = (!names.is_empty()).then(|| names.join("."));
+        Ok(Expr::Column(ColumnRef { qualifier, name }))
+    }
+
+    /// Comma-separated expressions up to and including `close`.
+    fn list(&mut self, close: &Token) -> Result<Vec<Expr>, ExprParseError> {
+        let mut items = Vec::new();
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(self.expr(0)?);
+            match self.peek() {
+                Some(Token::Comma) => self.pos += 1,
+                Some(token) if token == close => {
+                    self.pos += 1;
+                    return Ok(items);
+                }
+                _ => return Err(self.error(format!("expected `,` or {close}"))),
+            }
+        }
+    }
+
+    fn case(&mut self) -> Result<Expr, ExprParseError> {
+        let operand = if self.peek().is_some_and(|token| token.is_word("WHEN")) {
+            None
+        } else {
+            Some(Box::new(self.expr(0)?))
+        };
+        let mut whens = Vec::new();
+        while self.peek().is_some_and(|token| token.is_word("WHEN")) {
+            self.pos += 1;
+            let when = self.expr(0)?;
+            self.expect_word("THEN")?;
+            whens.push((when, self.expr(0)?));
+        }
+        if whens.is_empty() {
+            return Err(self.error("expected `WHEN`".to_string()));
+        }
+        let otherwise = if self.peek().is_some_and(|token| token.is_word("ELSE")) {
+            self.pos += 1;
+            Some(Box::new(self.expr(0)?))
+        } else {
+            None
+        };
+        self.expect_word("END")?;
+        Ok(Expr::Case {
+            operand,
+            whens,
+            otherwise,
+        })
+    }
+
+    fn name(&mut self) -> Result<String, ExprParseError> {
+        match self.next() {
+            Some(Token::Word(name) | Token::Quoted(name)) => Ok(name),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected a name".to_string()))
+            }
+        }
+    }
+
+    /// A name as printed, keeping quotes, e.g. `pg_catalog."default"`.
+    fn qualified_name(&mut self) -> Result<String, ExprParseError> {
+        let mut name = String::new();
+        loop {
+            match self.next() {
+                Some(Token::Word(word)) => name.push_str(&word),
+                Some(Token::Quoted(quoted)) => name.push_str(&quote_identifier(&quoted)),
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.error("expected a name".to_string()));
+                }
+            }
+            if self.peek() != Some(&Token::Dot) {
+                return Ok(name);
+            }
+            self.pos += 1;
+            name.push('.');
+        }
+    }
+
+    /// A type name after `::`, e.g. `timestamp without time zone`,
+    /// `numeric(10,2)`, or `text[]`.
+    fn type_name(&mut self) -> Result<String, ExprParseError> {
+        let mut name = self.qualified_name()?;
+        while let Some(Token::Word(word)) = self.peek() {
+            if !TYPE_NAME_WORDS.contains(&word.to_ascii_lowercase().as_str()) {
+                break;
+            }
+            name.push(' ');
+            name.push_str(word);
+            self.pos += 1;
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let mut modifiers = Vec::new();
+            loop {
+                match self.next() {
+                    Some(Token::Number(number)) => modifiers.push(number),
+                    _ => {
+                        self.pos -= 1;
+                        return Err(self.error("expected a type modifier".to_string()));
+                    }
+                }
+                match self.next() {
+                    Some(Token::Comma) => {}
+                    Some(Token::RParen) => break,
+                    _ => {
+                        self.pos -= 1;
+                        return Err(self.error("expected `,` or `)`".to_string()));
+                    }
+                }
+            }
+            name.push('(');
+            name.push_str(&modifiers.join(","));
+            name.push(')');
+        }
+        while self.peek() == Some(&Token::LBracket) && self.peek_at(1) == Some(&Token::RBracket) {
+            self.pos += 2;
+            name.push_str("[]");
+        }
+        Ok(name)
+    }
+}
+
+/// Left and right binding powers of an infix operator, loosely following
+/// PostgreSQL's precedence table.
+fn operator_bp(op: &str) -> (u8, u8) {
+    match op {
+        "=" | "<>" | "!=" | "<" | ">" | "<=" | ">=" => (9, 10),
+        "+" | "-" => (13, 14),
+        "*" | "/" | "%" => (15, 16),
+        "^" => (17, 18),
+        _ => (11, 12),
+    }
+}
+
+/// `name`, double-quoted unless PostgreSQL would print it bare.
+pub(crate) fn quote_identifier(name: &str) -> String {
+    let bare = name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '$');
+    if bare || name == "*" {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, items: &[Expr], separator: &str) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            f.write_str(separator)?;
+        }
+        write!(f, "{item}")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for ColumnRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(qualifier) = &self.qualifier {
+            for part in qualifier.split('.') {
+                write!(f, "{}.", quote_identifier(part))?;
+            }
+        }
+        f.write_str(&quote_identifier(&self.name))
+    }
+}
+
+impl fmt::Display for Const {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Const::String(value) => write!(f, "'{}'", value.replace('\'', "''")),
+            Const::Number(number) => f.write_str(number),
+            Const::Bool(value) => write!(f, "{value}"),
+            Const::Null => f.write_str("NULL"),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    /// Writes the expression as EXPLAIN prints it: operators parenthesized
+    /// and casts of anything but a constant as `(expr)::type`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Column(column) => write!(f, "{column}"),
+            Expr::Const(constant) => write!(f, "{constant}"),
+            Expr::Param(n) => write!(f, "${n}"),
+            Expr::Cast { expr, type_name } => match expr.as_ref() {
+                Expr::Const(_) => write!(f, "{expr}::{type_name}"),
+                _ => write!(f, "({expr})::{type_name}"),
+            },
+            Expr::Call { name, args } => {
+                write!(f, "{name}(")?;
+                write_list(f, args, ", ")?;
+                f.write_str(")")
+            }
+            Expr::Star => f.write_str("*"),
+            Expr::SqlValue(name) => f.write_str(name),
+            Expr::Binary { op, left, right } => write!(f, "({left} {op} {right})"),
+            Expr::Unary { op, expr } => write!(f, "({op} {expr})"),
+            Expr::ArrayOp {
+                op,
+                any,
+                left,
+                right,
+            } => {
+                let quantifier = if *any { "ANY" } else { "ALL" };
+                write!(f, "({left} {op} {quantifier} ({right}))")
+            }
+            Expr::And(terms) | Expr::Or(terms) => {
+                let separator = if matches!(self, Expr::And(_)) {
+                    " AND "
+                } else {
+                    " OR "
+                };
+                f.write_str("(")?;
+                write_list(f, terms, separator)?;
+                f.write_str(")")
+            }
+            Expr::Not(expr) => write!(f, "(NOT {expr})"),
+            Expr::Is { expr, test } => write!(f, "({expr} IS {test})"),
+            Expr::Array(items) => {
+                f.write_str("ARRAY[")?;
+                write_list(f, items, ", ")?;
+                f.write_str("]")
+            }
+            Expr::Row(items) => {
+                f.write_str("ROW(")?;
+                write_list(f, items, ", ")?;
+                f.write_str(")")
+            }
+            Expr::SubPlan { id, hashed: true } => write!(f, "(hashed SubPlan {id})"),
+            Expr::SubPlan { id, hashed: false } => write!(f, "(SubPlan {id})"),
+            Expr::InitPlan(id) => write!(f, "InitPlan {id}"),
+            Expr::Field { expr, name } => write!(f, "({expr}).{}", quote_identifier(name)),
+            Expr::Subscript { expr, index } => write!(f, "({expr})[{index}]"),
+            Expr::Collate { expr, collation } => write!(f, "({expr} COLLATE {collation})"),
+            Expr::Case {
+                operand,
+                whens,
+                otherwise,
+            } => {
+                f.write_str("CASE")?;
+                if let Some(operand) = operand {
+                    write!(f, " {operand}")?;
+                }
+                for (when, then) in whens {
+                    write!(f, " WHEN {when} THEN {then}")?;
+                }
+                if let Some(otherwise) = otherwise {
+                    write!(f, " ELSE {otherwise}")?;
+                }
+                f.write_str(" END")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(qualifier: Option<&str>, name: &str) -> Expr {
+        Expr::Column(ColumnRef {
+            qualifier: qualifier.map(str::to_string),
+            name: name.to_string(),
+        })
+    }
+
+    #[test]
+    fn round_trips_what_explain_prints() {
+        for text in [
+            "((status = 'shipped'::text) AND (total > 100.50))",
+            "((o.customer_id = c.id) AND (o.region = ANY ('{eu,us}'::text[])))",
+            "(lower((email)::text) = 'ann@example.com'::text)",
+            "(NOT (hashed SubPlan 1))",
+            "((created_at >= '2024-01-01 00:00:00'::timestamp without time zone) AND (deleted_at IS NULL))",
+            "(id = ANY (ARRAY[$1, $2, $3]))",
+            "(x.amount > (InitPlan 1).col1)",
+            "((name)::text ~~ 'O''Brien%'::text)",
+            "CASE WHEN (a > 0) THEN 'pos'::text ELSE 'neg'::text END",
+            "count(*)",
+            "((price)::numeric(10,2) <> (t.\"Unit Price\" * '1.2'::numeric))",
+            "((a IS NOT DISTINCT FROM b) OR (flag IS NOT TRUE))",
+            "(created_at < CURRENT_DATE)",
+            "((tags)[1] = 'x'::text)",
+            "((label)::text COLLATE \"C\")",
+        ] {
+            let expr = parse(text).unwrap_or_else(|err| panic!("{text}: {err}"));
+            assert_eq!(expr.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn builds_the_expected_tree() {
+        let expr = parse("((status)::text = 'open'::text)").expect("parse");
+        assert_eq!(
+            expr,
+            Expr::Binary {
+                op: "=".into(),
+                left: Box::new(Expr::Cast {
+                    expr: Box::new(column(None, "status")),
+                    type_name: "text".into()
+                }),
+                right: Box::new(Expr::Cast {
+                    expr: Box::new(Expr::Const(Const::String("open".into()))),
+                    type_name: "text".into()
+                }),
+            }
+        );
+
+        let expr = parse("((a = 1) AND (b = 2) AND ((c = 3) OR (d = 4)))").expect("parse");
+        assert_eq!(expr.conjuncts().len(), 3);
+        assert!(matches!(&expr.conjuncts()[2], Expr::Or(terms) if terms.len() == 2));
+    }
+
+    #[test]
+    fn follows_precedence_without_parentheses() {
+        let expr = parse("a = 1 AND NOT b OR c + 2 * d > -3").expect("parse");
+        assert_eq!(
+            expr.to_string(),
+            "(((a = 1) AND (NOT b)) OR ((c + (2 * d)) > (- 3)))"
+        );
+    }
+
+    #[test]
+    fn lists_columns_and_strips_casts() {
+        let expr = parse("((o.customer_id)::bigint = c.\"Id\")").expect("parse");
+        let columns: Vec<String> = expr.columns().map(ToString::to_string).collect();
+        assert_eq!(columns, ["o.customer_id", "c.\"Id\""]);
+
+        let Expr::Binary { left, .. } = &expr else {
+            panic!("expected a comparison");
+        };
+        assert_eq!(left.uncast(), &column(Some("o"), "customer_id"));
+    }
+
+    #[test]
+    fn reports_where_unsupported_syntax_starts() {
+        let err = parse("count(DISTINCT x)").expect_err("unsupported");
+        assert_eq!(err.position, 15);
+        assert!(err.message.contains("expected `,` or `)`"));
+
+        assert!(parse("(a = 'open").is_err());
+        assert!(parse("(a = )").is_err());
+        assert!(parse("a = 1 b").is_err());
+    }
+
+    #[test]
+    fn rejects_expressions_nested_past_the_limit() {
+        let terms = 10_000;
+        let mut parenthesized = "c0".to_string();
+        for index in 1..terms {
+            parenthesized = format!("({parenthesized} + c{index})");
+        }
+        let flat = (0..terms)
+            .map(|index| format!("c{index}"))
+            .collect::<Vec<_>>()
+            .join(" + ");
+        let prefixed = format!("{}x", "- ".repeat(terms));
+
+        for text in [parenthesized, flat, prefixed] {
+            let err = parse(&text).expect_err("too deep");
+            assert!(err.message.contains("nests deeper than"), "{err}");
+        }
+
+        let mut shallow = "c0".to_string();
+        for index in 1..100 {
+            shallow = format!("({shallow} + c{index})");
+        }
+        assert_eq!(
+            parse(&shallow).expect("within the limit").to_string(),
+            shallow
+        );
+    }
+}
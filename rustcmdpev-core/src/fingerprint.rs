@@ -0,0 +1,226 @@
+//! Plan-shape fingerprints for tracking the same query across captures.
+//!
+//! A [`Fingerprint`] hashes what the planner chose — node types, strategies,
+//! join types, relations, indexes, CTEs, and the order and nesting of
+//! children — and nothing that varies between runs of the same plan: costs,
+//! row counts, timings, buffers, filter and condition text (which carries
+//! literal values), and aliases. Two captures with equal root fingerprints
+//! ran the same plan; a different fingerprint for the same query means the
+//! plan flipped.
+//!
+//! The hash is 64-bit FNV-1a over a fixed encoding, so it is stable across
+//! platforms, processes, and releases. [`FingerprintPass`] stores each node's
+//! subtree fingerprint in `identity.fingerprint`.
+//!
+//! [`FingerprintPass`]: crate::analysis::FingerprintPass
+
+use std::fmt;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::structure::data::plan::Plan;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Hash of a plan subtree's shape. Displayed and serialized as 16 lowercase
+/// hex digits, since JSON numbers cannot hold every `u64` exactly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(pub u64);
+
+impl Fingerprint {
+    /// Fingerprint of `plan`'s subtree given its children's fingerprints, in
+    /// order. Used to fill a tree bottom-up without rehashing subtrees.
+    pub fn of_node(plan: &Plan, children: impl IntoIterator<Item = Fingerprint>) -> Self {
+        let identity = &plan.identity;
+        let mut hash = Fnv::new();
+        for field in [
+            identity.node_type.as_str(),
+            &identity.strategy,
+            &identity.join_type,
+            &identity.scan_direction,
+            &identity.schema,
+            &identity.relation_name,
+            &identity.index_name,
+            &identity.cte_name,
+        ] {
+            hash.write(field.as_bytes());
+            // PostgreSQL names never contain NUL, so it separates fields.
+            hash.write(&[0]);
+        }
+        let mut count: u64 = 0;
+        for child in children {
+            hash.write(&child.0.to_le_bytes());
+            count += 1;
+        }
+        hash.write(&count.to_le_bytes());
+        Self(hash.finish())
+    }
+
+    /// Fingerprint of `plan`'s whole subtree.
+    pub fn of(plan: &Plan) -> Self {
+        // Post-order over an explicit stack, so deep plans cannot overflow.
+        let mut pending = vec![(plan, false)];
+        let mut done: Vec<Fingerprint> = Vec::new();
+        while let Some((node, expanded)) = pending.pop() {
+            if expanded {
+                let children = done.split_off(done.len() - node.plans.len());
+                done.push(Self::of_node(node, children));
+            } else {
+                pending.push((node, true));
+                pending.extend(node.plans.iter().rev().map(|child| (child, false)));
+            }
+        }
+        done.pop().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl Serialize for Fingerprint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Fingerprint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        u64::from_str_radix(&hex, 16)
+            .map(Self)
+            .map_err(|_| D::Error::custom(format!("{hex:?} is not a plan fingerprint")))
+    }
+}
+
+/// 64-bit FNV-1a.
+pub(crate) struct Fnv(u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::process_all;
+    use crate::testing::{explain_for, with_children, LeafBuilder};
+
+    fn join(outer: Plan, inner: Plan) -> Plan {
+        let mut join = LeafBuilder::new("Hash Join").build();
+        join.identity.join_type = "Inner".to_string();
+        with_children(join, [outer, inner])
+    }
+
+    fn scan(node_type: &str, relation: &str) -> Plan {
+        LeafBuilder::new(node_type)
+            .relation("public", relation)
+            .build()
+    }
+
+    #[test]
+    fn fnv_matches_reference_values() {
+        let mut hash = Fnv::new();
+        assert_eq!(hash.finish(), 0xcbf2_9ce4_8422_2325);
+        hash.write(b"a");
+        assert_eq!(hash.finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn ignores_costs_timings_literals_and_aliases() {
+        let plan = join(scan("Seq Scan", "orders"), scan("Seq Scan", "customers"));
+        let mut rerun = plan.clone();
+        rerun.estimates.total_cost = 999.0;
+        rerun.actuals.actual_total_time = 12.5;
+        rerun.plans[0].predicates.filter = "(status = 'shipped'::text)".to_string();
+        rerun.plans[1].identity.alias = "c".to_string();
+
+        assert_eq!(Fingerprint::of(&plan), Fingerprint::of(&rerun));
+    }
+
+    #[test]
+    fn changes_with_node_types_indexes_join_types_and_child_order() {
+        let plan = join(scan("Seq Scan", "orders"), scan("Seq Scan", "customers"));
+        let base = Fingerprint::of(&plan);
+
+        let mut index = plan.clone();
+        index.plans[0] = scan("Index Scan", "orders");
+        index.plans[0].identity.index_name = "orders_status_idx".to_string();
+        let mut other_index = index.clone();
+        other_index.plans[0].identity.index_name = "orders_pkey".to_string();
+        let mut join_type = plan.clone();
+        join_type.identity.join_type = "Left".to_string();
+        let swapped = join(scan("Seq Scan", "customers"), scan("Seq Scan", "orders"));
+
+        let fingerprints = [
+            base,
+            Fingerprint::of(&index),
+            Fingerprint::of(&other_index),
+            Fingerprint::of(&join_type),
+            Fingerprint::of(&swapped),
+        ];
+        for (i, a) in fingerprints.iter().enumerate() {
+            for b in &fingerprints[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn nesting_is_part_of_the_shape() {
+        let flat = with_children(
+            LeafBuilder::new("Append").build(),
+            [scan("Seq Scan", "a"), scan("Seq Scan", "b")],
+        );
+        let nested = with_children(
+            LeafBuilder::new("Append").build(),
+            [with_children(
+                scan("Seq Scan", "a"),
+                [scan("Seq Scan", "b")],
+            )],
+        );
+        assert_ne!(Fingerprint::of(&flat), Fingerprint::of(&nested));
+    }
+
+    #[test]
+    fn pass_matches_standalone_fingerprints() {
+        let plan = join(scan("Seq Scan", "orders"), scan("Seq Scan", "customers"));
+        let explain = process_all(explain_for(plan.clone(), 0.0, 0.0));
+
+        assert_eq!(explain.plan.identity.fingerprint, Fingerprint::of(&plan));
+        assert_eq!(
+            explain.plan.plans[1].identity.fingerprint,
+            Fingerprint::of(&plan.plans[1])
+        );
+    }
+
+    #[test]
+    fn round_trips_as_fixed_width_hex() {
+        let fingerprint = Fingerprint(0xab);
+        assert_eq!(fingerprint.to_string(), "00000000000000ab");
+        assert_eq!(
+            serde_json::to_value(fingerprint).expect("serialize"),
+            "00000000000000ab"
+        );
+        let parsed: Fingerprint =
+            serde_json::from_str("\"00000000000000ab\"").expect("deserialize");
+        assert_eq!(parsed, fingerprint);
+    }
+}
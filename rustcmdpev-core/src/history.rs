@@ -0,0 +1,596 @@
+//! Per-query plan history and regression detection.
+//!
+//! Each recorded run is a [`HistoryEntry`]: the analyzed explain, its
+//! [`PlanSummary`], and its plan-shape [`Fingerprint`], filed under a query
+//! key that does not depend on the plan's shape (see [`query_key`]). A
+//! [`HistoryReport`] walks a query's runs in order and compares each one
+//! with a baseline of the runs before it: execution time and buffer accesses
+//! regress when they exceed the baseline median by more than
+//! [`REGRESSION_MAD_FACTOR`] scaled median absolute deviations (and at least
+//! [`REGRESSION_MIN_CHANGE`] of the median, so a perfectly steady baseline
+//! does not flag noise), and the plan shape regresses when it differs from
+//! the baseline's most common one.
+//!
+//! Storage is left to the caller; entries serialize to one JSON object each,
+//! which suits a JSON-lines file per query.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{
+    HISTORY_BASELINE_RUNS, HISTORY_MIN_BASELINE_RUNS, REGRESSION_MAD_FACTOR, REGRESSION_MIN_CHANGE,
+};
+use crate::fingerprint::{Fingerprint, Fnv};
+use crate::redact::{redact_literals, QUERY_TEXT_KEY};
+use crate::structure::data::explain::Explain;
+use crate::summary::PlanSummary;
+
+/// Top-level EXPLAIN key PostgreSQL 14+ emits with `compute_query_id` and
+/// `VERBOSE`.
+const QUERY_IDENTIFIER_KEY: &str = "Query Identifier";
+
+/// Scales a median absolute deviation to a standard deviation estimate for
+/// normally distributed values.
+const MAD_TO_STD_DEV: f64 = 1.4826;
+
+/// Key a run is filed under when the caller names none: the server's query
+/// identifier when the plan carries one, else a hash of its `Query Text`
+/// with literals and whitespace normalized. Neither changes when the plan
+/// flips, so the new shape is compared with the runs before it. `None` when
+/// the plan carries neither, e.g. after `--redact-identifiers`.
+pub fn query_key(explain: &Explain) -> Option<String> {
+    if let Some(serde_json::Value::Number(id)) = explain.extras.get(QUERY_IDENTIFIER_KEY) {
+        return Some(id.to_string());
+    }
+    let text = normalize_query_text(explain.extras.get(QUERY_TEXT_KEY)?.as_str()?);
+    if text.is_empty() {
+        return None;
+    }
+    let mut hash = Fnv::new();
+    hash.write(text.as_bytes());
+    Some(format!("{:016x}", hash.finish()))
+}
+
+/// `text` with literals replaced by `$`, parameter numbers dropped, and
+/// whitespace collapsed, so runs with different values share a key.
+fn normalize_query_text(text: &str) -> String {
+    let redacted = redact_literals(text);
+    let mut normalized = String::with_capacity(redacted.len());
+    let mut chars = redacted.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            normalized.push(' ');
+        } else {
+            normalized.push(c);
+            // Placeholder numbers depend on which literals happen to be
+            // equal, e.g. `LIMIT 10 OFFSET 10` against `OFFSET 20`.
+            if c == '$' {
+                while chars.next_if(char::is_ascii_digit).is_some() {}
+            }
+        }
+    }
+    normalized
+        .trim()
+        .trim_end_matches(';')
+        .trim_end()
+        .to_string()
+}
+
+/// One recorded run of a query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch.
+    pub recorded_at: u64,
+    pub query: String,
+    pub fingerprint: Fingerprint,
+    /// Where the plan came from, e.g. its file path.
+    #[serde(default)]
+    pub source: String,
+    pub summary: PlanSummary,
+    /// The analyzed explain as `--format json` serializes it; kept as a
+    /// value because that form is not read back into an [`Explain`].
+    pub explain: serde_json::Value,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        query: impl Into<String>,
+        source: impl Into<String>,
+        explain: &Explain,
+        block_size: u64,
+        recorded_at: u64,
+    ) -> Self {
+        Self {
+            recorded_at,
+            query: query.into(),
+            fingerprint: Fingerprint::of(&explain.plan),
+            source: source.into(),
+            summary: PlanSummary::from_explain_with_block_size(explain, block_size),
+            explain: serde_json::to_value(explain).unwrap_or_default(),
+        }
+    }
+}
+
+/// A tracked measurement of each run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    ExecutionTime,
+    /// Every shared, local, and temp block counter summed.
+    Buffers,
+}
+
+impl Metric {
+    fn of(self, entry: &HistoryEntry) -> f64 {
+        match self {
+            Metric::ExecutionTime => entry.summary.execution_time,
+            Metric::Buffers => entry.summary.buffers.total() as f64,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Metric::ExecutionTime => "time",
+            Metric::Buffers => "buffers",
+        }
+    }
+
+    fn format(self, value: f64) -> String {
+        match self {
+            Metric::ExecutionTime => format!("{value:.2} ms"),
+            Metric::Buffers => format!("{value:.0} blocks"),
+        }
+    }
+}
+
+/// Median and median absolute deviation of a baseline window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Baseline {
+    pub median: f64,
+    pub mad: f64,
+}
+
+impl Baseline {
+    /// `None` for an empty window.
+    pub fn of(values: &[f64]) -> Option<Self> {
+        let center = median(values.to_vec())?;
+        let mad = median(values.iter().map(|value| (value - center).abs()).collect())?;
+        Some(Self {
+            median: center,
+            mad,
+        })
+    }
+
+    /// Whether `value` sits above the baseline by more than the allowed
+    /// spread.
+    pub fn is_exceeded_by(&self, value: f64) -> bool {
+        let allowed = (REGRESSION_MAD_FACTOR * MAD_TO_STD_DEV * self.mad)
+            .max(REGRESSION_MIN_CHANGE * self.median);
+        value - self.median > allowed
+    }
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    Some(if values.len() % 2 == 0 {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    })
+}
+
+/// How a run compares badly with its baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Regression {
+    Metric {
+        metric: Metric,
+        value: f64,
+        baseline: Baseline,
+    },
+    PlanShape {
+        fingerprint: Fingerprint,
+        baseline: Fingerprint,
+    },
+}
+
+impl Regression {
+    fn marker(&self) -> String {
+        match self {
+            Regression::Metric { metric, .. } => format!("▲ {}", metric.label()),
+            Regression::PlanShape { .. } => "◆ shape".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Regression::Metric {
+                metric,
+                value,
+                baseline,
+            } => {
+                write!(
+                    f,
+                    "{} {} vs baseline median {} (MAD {}",
+                    match metric {
+                        Metric::ExecutionTime => "execution time",
+                        Metric::Buffers => "buffers",
+                    },
+                    metric.format(*value),
+                    metric.format(baseline.median),
+                    metric.format(baseline.mad)
+                )?;
+                if baseline.median > 0.0 {
+                    write!(
+                        f,
+                        ", {:+.1}%",
+                        (value - baseline.median) / baseline.median * 100.0
+                    )?;
+                }
+                write!(f, ")")
+            }
+            Regression::PlanShape {
+                fingerprint,
+                baseline,
+            } => write!(
+                f,
+                "plan shape {fingerprint} differs from baseline shape {baseline}"
+            ),
+        }
+    }
+}
+
+/// One run in a [`HistoryReport`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunTrend {
+    /// 1-based position in the history.
+    pub run: usize,
+    pub recorded_at: u64,
+    pub execution_time: f64,
+    pub buffers: u64,
+    pub fingerprint: Fingerprint,
+    /// Empty when the run is in line with its baseline or has none.
+    pub regressions: Vec<Regression>,
+}
+
+/// Trend of a query's runs, oldest first.
+#[derive(Debug, Clone)]
+pub struct HistoryReport {
+    pub query: String,
+    pub runs: Vec<RunTrend>,
+    /// Earlier runs each run is compared with.
+    pub baseline_runs: usize,
+    /// Most recent runs the text form lists; `None` lists every run.
+    pub shown: Option<usize>,
+}
+
+impl HistoryReport {
+    pub fn new(query: impl Into<String>, entries: &[HistoryEntry]) -> Self {
+        Self::with_baseline(query, entries, HISTORY_BASELINE_RUNS)
+    }
+
+    /// Compare each run with at most `baseline_runs` runs before it.
+    pub fn with_baseline(
+        query: impl Into<String>,
+        entries: &[HistoryEntry],
+        baseline_runs: usize,
+    ) -> Self {
+        let runs = entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let window = &entries[index.saturating_sub(baseline_runs)..index];
+                RunTrend {
+                    run: index + 1,
+                    recorded_at: entry.recorded_at,
+                    execution_time: entry.summary.execution_time,
+                    buffers: entry.summary.buffers.total(),
+                    fingerprint: entry.fingerprint,
+                    regressions: regressions(entry, window),
+                }
+            })
+            .collect();
+        Self {
+            query: query.into(),
+            runs,
+            baseline_runs,
+            shown: None,
+        }
+    }
+
+    /// List only the `shown` most recent runs in the text form.
+    #[must_use]
+    pub fn with_shown(mut self, shown: usize) -> Self {
+        self.shown = Some(shown);
+        self
+    }
+
+    pub fn latest(&self) -> Option<&RunTrend> {
+        self.runs.last()
+    }
+
+    /// Whether the most recent run regressed.
+    pub fn regressed(&self) -> bool {
+        self.latest()
+            .is_some_and(|latest| !latest.regressions.is_empty())
+    }
+}
+
+fn regressions(entry: &HistoryEntry, window: &[HistoryEntry]) -> Vec<Regression> {
+    let mut regressions = Vec::new();
+    if window.len() >= HISTORY_MIN_BASELINE_RUNS {
+        for metric in [Metric::ExecutionTime, Metric::Buffers] {
+            let values: Vec<f64> = window.iter().map(|earlier| metric.of(earlier)).collect();
+            let value = metric.of(entry);
+            if let Some(baseline) = Baseline::of(&values) {
+                if baseline.is_exceeded_by(value) {
+                    regressions.push(Regression::Metric {
+                        metric,
+                        value,
+                        baseline,
+                    });
+                }
+            }
+        }
+    }
+    if let Some(baseline) = most_common_shape(window) {
+        if baseline != entry.fingerprint {
+            regressions.push(Regression::PlanShape {
+                fingerprint: entry.fingerprint,
+                baseline,
+            });
+        }
+    }
+    regressions
+}
+
+/// The window's most common fingerprint; ties go to the most recent.
+fn most_common_shape(window: &[HistoryEntry]) -> Option<Fingerprint> {
+    let mut counts: HashMap<Fingerprint, (usize, usize)> = HashMap::new();
+    for (index, entry) in window.iter().enumerate() {
+        let count = counts.entry(entry.fingerprint).or_default();
+        count.0 += 1;
+        count.1 = index;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(_, (count, last))| (*count, *last))
+        .map(|(fingerprint, _)| fingerprint)
+}
+
+impl fmt::Display for HistoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Query {}: {} runs, each compared with up to {} earlier runs",
+            self.query,
+            self.runs.len(),
+            self.baseline_runs
+        )?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "  {:>4}  {:<19}  {:>12}  {:>10}  plan shape",
+            "run", "recorded (UTC)", "execution ms", "buffers"
+        )?;
+        let hidden = self
+            .shown
+            .map_or(0, |shown| self.runs.len().saturating_sub(shown));
+        if hidden > 0 {
+            writeln!(f, "  … {hidden} earlier runs")?;
+        }
+        for run in &self.runs[hidden..] {
+            write!(
+                f,
+                "  {:>4}  {:<19}  {:>12.2}  {:>10}  {}",
+                run.run,
+                format_utc(run.recorded_at),
+                run.execution_time,
+                run.buffers,
+                run.fingerprint
+            )?;
+            if !run.regressions.is_empty() {
+                let markers: Vec<String> = run.regressions.iter().map(Regression::marker).collect();
+                write!(f, "  {}", markers.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+
+        let Some(latest) = self.latest() else {
+            return Ok(());
+        };
+        writeln!(f)?;
+        if !latest.regressions.is_empty() {
+            writeln!(f, "Latest run regressed:")?;
+            for regression in &latest.regressions {
+                writeln!(f, "  {regression}")?;
+            }
+        } else if latest.run <= HISTORY_MIN_BASELINE_RUNS {
+            writeln!(
+                f,
+                "Latest run has {} earlier runs; timing and buffer checks need {HISTORY_MIN_BASELINE_RUNS}.",
+                latest.run - 1
+            )?;
+        } else {
+            writeln!(f, "Latest run is in line with its baseline.")?;
+        }
+        Ok(())
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS` for seconds since the Unix epoch, in UTC.
+pub fn format_utc(seconds: u64) -> String {
+    let days = seconds / 86_400;
+    let time = seconds % 86_400;
+    // Civil-from-days (H. Hinnant), for the proleptic Gregorian calendar.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::process_all;
+    use crate::structure::data::plan::Plan;
+    use crate::testing::{explain_for, LeafBuilder};
+
+    fn entry(node_type: &str, execution_time: f64, shared_hit_blocks: u64) -> HistoryEntry {
+        let mut plan = LeafBuilder::new(node_type)
+            .relation("public", "orders")
+            .build();
+        plan.buffers.shared_hit_blocks = shared_hit_blocks;
+        let explain = process_all(explain_for(plan, execution_time, 0.0));
+        HistoryEntry::new(
+            "orders_by_status",
+            "plan.json",
+            &explain,
+            8192,
+            1_700_000_000,
+        )
+    }
+
+    #[test]
+    fn baseline_uses_median_and_mad() {
+        let baseline = Baseline::of(&[10.0, 12.0, 11.0, 50.0, 11.0]).expect("baseline");
+        assert_eq!(baseline.median, 11.0);
+        assert_eq!(baseline.mad, 1.0);
+        // 3 × 1.4826 × 1.0 is under 20% of the median, so 20% applies.
+        assert!(!baseline.is_exceeded_by(13.2));
+        assert!(baseline.is_exceeded_by(15.5));
+        assert_eq!(Baseline::of(&[]), None);
+    }
+
+    #[test]
+    fn flags_slow_runs_buffer_growth_and_plan_flips() {
+        let mut entries: Vec<HistoryEntry> = [10.0, 11.0, 10.5, 10.8]
+            .into_iter()
+            .map(|time| entry("Index Scan", time, 100))
+            .collect();
+        entries.push(entry("Seq Scan", 42.0, 5_000));
+
+        let report = HistoryReport::new("orders_by_status", &entries);
+
+        assert!(report.runs[..4]
+            .iter()
+            .all(|run| run.regressions.is_empty()));
+        assert!(report.regressed());
+        let latest = report.latest().expect("latest run");
+        assert_eq!(latest.regressions.len(), 3);
+        let text = report.to_string();
+        assert!(text.contains("▲ time, ▲ buffers, ◆ shape"));
+        assert!(text.contains(
+            "execution time 42.00 ms vs baseline median 10.65 ms (MAD 0.25 ms, +294.4%)"
+        ));
+        assert!(text.contains("buffers 5000 blocks vs baseline median 100 blocks"));
+        assert!(text.contains(&format!(
+            "plan shape {} differs from baseline shape {}",
+            entries[4].fingerprint, entries[0].fingerprint
+        )));
+    }
+
+    #[test]
+    fn short_histories_only_check_the_plan_shape() {
+        let entries = [entry("Index Scan", 10.0, 0), entry("Index Scan", 90.0, 0)];
+        let report = HistoryReport::new("q", &entries).with_shown(1);
+
+        assert!(!report.regressed());
+        let text = report.to_string();
+        assert!(text.contains("… 1 earlier runs"));
+        assert!(text.contains("timing and buffer checks need 3"));
+    }
+
+    #[test]
+    fn entries_round_trip_through_json() {
+        let original = entry("Seq Scan", 4.0, 7);
+        let line = serde_json::to_string(&original).expect("serialize");
+        let parsed: HistoryEntry = serde_json::from_str(&line).expect("deserialize");
+
+        assert_eq!(parsed.fingerprint, original.fingerprint);
+        assert_eq!(parsed.summary.execution_time, 4.0);
+        assert_eq!(parsed.summary.buffers.shared_hit_blocks, 7);
+        assert_eq!(parsed.explain["plan"]["node_type"], "Seq Scan");
+    }
+
+    #[test]
+    fn query_key_prefers_the_query_identifier() {
+        let mut explain = process_all(explain_for(LeafBuilder::new("Result").build(), 0.0, 0.0));
+        assert_eq!(query_key(&explain), None);
+        explain
+            .extras
+            .insert(QUERY_TEXT_KEY.into(), "SELECT 1".into());
+        assert!(query_key(&explain).is_some());
+        explain.extras.insert(
+            QUERY_IDENTIFIER_KEY.into(),
+            (-4_221_830_918_412_290_000_i64).into(),
+        );
+        assert_eq!(query_key(&explain).as_deref(), Some("-4221830918412290000"));
+    }
+
+    #[test]
+    fn query_text_key_ignores_literals_whitespace_and_plan_shape() {
+        let keyed = |plan: Plan, text: &str| {
+            let mut explain = process_all(explain_for(plan, 0.0, 0.0));
+            explain.extras.insert(QUERY_TEXT_KEY.into(), text.into());
+            query_key(&explain).expect("key")
+        };
+        let index_scan = || {
+            LeafBuilder::new("Index Scan")
+                .relation("public", "orders")
+                .build()
+        };
+        let seq_scan = LeafBuilder::new("Seq Scan")
+            .relation("public", "orders")
+            .build();
+
+        let key = keyed(
+            index_scan(),
+            "SELECT * FROM orders WHERE status = 'open' LIMIT 10 OFFSET 10;",
+        );
+        assert_eq!(
+            keyed(
+                seq_scan,
+                "SELECT *\n  FROM orders\n WHERE status = 'closed'  LIMIT 10 OFFSET 20"
+            ),
+            key
+        );
+        assert_ne!(
+            keyed(
+                index_scan(),
+                "SELECT * FROM customers WHERE status = 'open'"
+            ),
+            key
+        );
+    }
+
+    #[test]
+    fn formats_utc_timestamps() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00:00");
+        assert_eq!(format_utc(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format_utc(1_792_389_723), "2026-10-19 06:02:03");
+    }
+}
@@ -0,0 +1,397 @@
+//! Heuristic `CREATE INDEX` suggestions derived from filter and join
+//! predicates.
+//!
+//! The pass looks for sequential scans that discard most of the rows they
+//! read, takes the simple column comparisons (`col = literal`, `(col)::text >
+//! $1`, `a.x = b.y`) out of the parsed form of the node's `Filter` or its
+//! parent's join condition (see [`crate::expression`]), and proposes an index
+//! on the scanned relation. Equality columns are listed before range
+//! columns. A column behind a cast the index could not serve, such as
+//! `(id)::text = '123'::text` on an integer column, is skipped by the same
+//! rule [`crate::cast_check`] reports it with.
+//!
+//! Every suggestion is a heuristic: it ignores existing indexes, write
+//! amplification, and whether the planner would actually pick the index.
+//! Callers should present them as candidates to verify, never as fixes.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::cast_check::indexable_column;
+use crate::constants::{INDEX_ADVICE_MAX_SELECTIVITY, INDEX_ADVICE_MIN_REMOVED_ROWS};
+use crate::expression::{quote_identifier, Expr};
+use crate::structure::data::explain::Explain;
+use crate::structure::data::node_type::NodeType;
+use crate::structure::data::plan::Plan;
+
+/// Which predicate a suggestion was derived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionSource {
+    Filter,
+    JoinCondition,
+}
+
+/// One candidate index for a scanned relation.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct IndexSuggestion {
+    /// Always `true`; carried in the JSON output so consumers cannot mistake
+    /// a suggestion for a verified recommendation.
+    pub heuristic: bool,
+    /// Path of the scan node, e.g. `Plan.Plans[0]`.
+    pub node_path: String,
+    pub node_type: NodeType,
+    pub schema: String,
+    pub relation: String,
+    pub columns: Vec<String>,
+    pub source: SuggestionSource,
+    /// The predicate text the columns were parsed from.
+    pub predicate: String,
+    /// Estimated fraction of scanned rows that survive the predicate.
+    pub selectivity: f64,
+    pub rows_removed: u64,
+    pub statement: String,
+}
+
+/// Collect index suggestions for every qualifying scan in the plan, sorted by
+/// removed rows (descending).
+pub fn suggest_indexes(explain: &Explain) -> Vec<IndexSuggestion> {
+    let mut suggestions = Vec::new();
+    collect(&explain.plan, explain.root_path(), &mut suggestions);
+    suggestions.sort_by_key(|suggestion| std::cmp::Reverse(suggestion.rows_removed));
+
+    let mut seen = Vec::new();
+    suggestions.retain(|suggestion| {
+        let key = (
+            suggestion.schema.clone(),
+            suggestion.relation.clone(),
+            suggestion.columns.clone(),
+        );
+        if seen.contains(&key) {
+            false
+        } else {
+            seen.push(key);
+            true
+        }
+    });
+    suggestions
+}
+
+fn collect(plan: &Plan, path: &str, out: &mut Vec<IndexSuggestion>) {
+    if let Some(suggestion) = filter_suggestion(plan, path) {
+        out.push(suggestion);
+    }
+    for (index, child) in plan.plans.iter().enumerate() {
+        let child_path = format!("{path}.Plans[{index}]");
+        if let Some(suggestion) = join_suggestion(plan, child, &child_path) {
+            out.push(suggestion);
+        }
+        collect(child, &child_path, out);
+    }
+}
+
+fn is_candidate_scan(plan: &Plan) -> bool {
+    plan.identity.node_type == NodeType::SeqScan && !plan.identity.relation_name.is_empty()
+}
+
+fn filter_suggestion(plan: &Plan, path: &str) -> Option<IndexSuggestion> {
+    if !is_candidate_scan(plan) {
+        return None;
+    }
+    let filter = plan.predicates.parsed.filter.as_ref()?;
+    let loops = plan.actuals.actual_loops.max(1);
+    let kept = plan.actuals.actual_rows.saturating_mul(loops);
+    let removed = plan.predicates.rows_removed_by_filter.saturating_mul(loops);
+    let columns = index_columns(filter, false, |qualifier| {
+        qualifier.is_none_or(|q| owns_qualifier(plan, q))
+    });
+    build(
+        plan,
+        path,
+        SuggestionSource::Filter,
+        &plan.predicates.filter,
+        columns,
+        kept,
+        removed,
+    )
+}
+
+fn join_suggestion(join: &Plan, child: &Plan, path: &str) -> Option<IndexSuggestion> {
+    if !is_candidate_scan(child) {
+        return None;
+    }
+    let predicates = &join.predicates;
+    let parsed = &predicates.parsed;
+    let (condition, parsed, kept, removed) = if !predicates.join_filter.is_empty() {
+        let loops = join.actuals.actual_loops.max(1);
+        (
+            &predicates.join_filter,
+            &parsed.join_filter,
+            join.actuals.actual_rows.saturating_mul(loops),
+            predicates.rows_removed_by_join_filter.saturating_mul(loops),
+        )
+    } else if !predicates.hash_condition.is_empty() || !predicates.merge_condition.is_empty() {
+        let (condition, parsed) = if predicates.hash_condition.is_empty() {
+            (&predicates.merge_condition, &parsed.merge_condition)
+        } else {
+            (&predicates.hash_condition, &parsed.hash_condition)
+        };
+        let scanned = child
+            .actuals
+            .actual_rows
+            .saturating_mul(child.actuals.actual_loops.max(1));
+        let joined = join
+            .actuals
+            .actual_rows
+            .saturating_mul(join.actuals.actual_loops.max(1))
+            .min(scanned);
+        (condition, parsed, joined, scanned - joined)
+    } else {
+        return None;
+    };
+    let columns = index_columns(parsed.as_ref()?, true, |qualifier| {
+        qualifier.is_some_and(|q| owns_qualifier(child, q))
+    });
+    build(
+        child,
+        path,
+        SuggestionSource::JoinCondition,
+        condition,
+        columns,
+        kept,
+        removed,
+    )
+}
+
+fn owns_qualifier(plan: &Plan, qualifier: &str) -> bool {
+    qualifier == plan.identity.alias || qualifier == plan.identity.relation_name
+}
+
+fn build(
+    scan: &Plan,
+    path: &str,
+    source: SuggestionSource,
+    predicate: &str,
+    columns: Vec<String>,
+    kept: u64,
+    removed: u64,
+) -> Option<IndexSuggestion> {
+    if columns.is_empty() || removed < INDEX_ADVICE_MIN_REMOVED_ROWS {
+        return None;
+    }
+    let selectivity = kept as f64 / (kept + removed) as f64;
+    if selectivity > INDEX_ADVICE_MAX_SELECTIVITY {
+        return None;
+    }
+    let identity = &scan.identity;
+    let target = if identity.schema.is_empty() {
+        identity.relation_name.clone()
+    } else {
+        format!("{}.{}", identity.schema, identity.relation_name)
+    };
+    Some(IndexSuggestion {
+        heuristic: true,
+        node_path: path.to_string(),
+        node_type: identity.node_type.clone(),
+        schema: identity.schema.clone(),
+        relation: identity.relation_name.clone(),
+        statement: format!("CREATE INDEX ON {target} ({});", columns.join(", ")),
+        columns,
+        source,
+        predicate: predicate.to_string(),
+        selectivity,
+        rows_removed: removed,
+    })
+}
+
+/// The index columns owned by the scan that `predicate` compares: equality
+/// columns first, then range columns, without duplicates. Returns nothing
+/// for a top-level `OR`, which a single b-tree cannot serve.
+fn index_columns(predicate: &Expr, join: bool, owns: impl Fn(Option<&str>) -> bool) -> Vec<String> {
+    let mut equality = Vec::new();
+    let mut range = Vec::new();
+    for conjunct in predicate.conjuncts() {
+        let (kind, left, right) = match conjunct {
+            Expr::Binary { op, left, right } => match comparison_kind(op) {
+                Some(kind) => (kind, left, right),
+                None => continue,
+            },
+            Expr::ArrayOp {
+                op,
+                any: true,
+                left,
+                right,
+            } if op == "=" => (ComparisonKind::Equality, left, right),
+            _ => continue,
+        };
+        let target = match kind {
+            ComparisonKind::Equality => &mut equality,
+            ComparisonKind::Range => &mut range,
+        };
+        for (side, other) in [(left, right), (right, left)] {
+            let Some(column) = indexable_column(side, other, join) else {
+                continue;
+            };
+            let name = quote_identifier(&column.name);
+            if owns(column.qualifier.as_deref()) && !target.contains(&name) {
+                target.push(name);
+            }
+        }
+    }
+    range.retain(|column| !equality.contains(column));
+    equality.extend(range);
+    equality
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ComparisonKind {
+    Equality,
+    Range,
+}
+
+/// How a b-tree serves `op`; `None` for operators it cannot, such as `<>`.
+fn comparison_kind(op: &str) -> Option<ComparisonKind> {
+    match op {
+        "=" => Some(ComparisonKind::Equality),
+        "<" | ">" | "<=" | ">=" | "~~" => Some(ComparisonKind::Range),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::process_all;
+    use crate::cast_check::find_cast_issues;
+    use crate::testing::{explain_for, with_children, LeafBuilder};
+
+    fn suggestions_for(plan: Plan) -> Vec<IndexSuggestion> {
+        suggest_indexes(&process_all(explain_for(plan, 1.0, 1.0)))
+    }
+
+    fn filtered_scan(filter: &str, kept: u64, removed: u64) -> Plan {
+        let mut scan = LeafBuilder::new("Seq Scan")
+            .relation("public", "orders")
+            .rows(kept, kept)
+            .build();
+        scan.identity.alias = "o".into();
+        scan.predicates.filter = filter.into();
+        scan.predicates.rows_removed_by_filter = removed;
+        scan
+    }
+
+    #[test]
+    fn suggests_equality_then_range_columns_from_selective_filter() {
+        let scan = filtered_scan(
+            "((created_at > '2024-01-01'::date) AND ((status)::text = 'open'::text))",
+            10,
+            99_990,
+        );
+
+        let suggestions = suggestions_for(scan);
+
+        assert_eq!(suggestions.len(), 1);
+        let suggestion = &suggestions[0];
+        assert!(suggestion.heuristic);
+        assert_eq!(suggestion.node_path, "Plan");
+        assert_eq!(suggestion.columns, vec!["status", "created_at"]);
+        assert_eq!(
+            suggestion.statement,
+            "CREATE INDEX ON public.orders (status, created_at);"
+        );
+        assert!((suggestion.selectivity - 0.0001).abs() < 1e-9);
+    }
+
+    #[test]
+    fn skips_unselective_or_disjunctive_or_function_wrapped_filters() {
+        let unselective = filtered_scan("(status = 'open'::text)", 5_000, 5_000);
+        let disjunctive = filtered_scan("((status = 'open'::text) OR (id = 1))", 1, 99_999);
+        let wrapped = filtered_scan("(lower(email) = 'x'::text)", 1, 99_999);
+        let negated = filtered_scan("(status <> 'open'::text)", 1, 99_999);
+
+        for scan in [unselective, disjunctive, wrapped, negated] {
+            assert!(suggestions_for(scan).is_empty());
+        }
+    }
+
+    #[test]
+    fn skips_columns_behind_casts_an_index_cannot_serve() {
+        let numeric_as_text = filtered_scan("((o.account_id)::text = '123'::text)", 1, 99_999);
+        let non_text = filtered_scan("((o.created_at)::date = '2024-01-01'::date)", 1, 99_999);
+        for scan in [numeric_as_text, non_text] {
+            assert!(suggestions_for(scan).is_empty());
+        }
+
+        let varchar = filtered_scan("((o.code)::text = 'A-1'::text)", 1, 99_999);
+        let suggestions = suggestions_for(varchar);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].columns, vec!["code"]);
+    }
+
+    #[test]
+    fn quotes_column_names_that_need_it() {
+        let scan = filtered_scan(
+            "((\"città\" = 1) AND (\"Created At\" >= 'é'::text))",
+            1,
+            99_999,
+        );
+        let suggestions = suggestions_for(scan);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].columns, vec!["\"città\"", "\"Created At\""]);
+        assert_eq!(
+            suggestions[0].statement,
+            "CREATE INDEX ON public.orders (\"città\", \"Created At\");"
+        );
+    }
+
+    #[test]
+    fn suggests_join_column_on_inner_scan_of_nested_loop() {
+        let outer = LeafBuilder::new("Index Scan")
+            .relation("public", "users")
+            .rows(10, 10)
+            .build();
+        let inner = LeafBuilder::new("Seq Scan")
+            .relation("public", "orders")
+            .rows(50_000, 50_000)
+            .loops(10)
+            .build();
+        let mut join = with_children(
+            LeafBuilder::new("Nested Loop").rows(20, 20).build(),
+            [outer, inner],
+        );
+        join.plans[1].identity.alias = "o".into();
+        join.predicates.join_filter = "(o.user_id = u.id)".into();
+        join.predicates.rows_removed_by_join_filter = 499_980;
+
+        let suggestions = suggestions_for(join);
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].source, SuggestionSource::JoinCondition);
+        assert_eq!(suggestions[0].node_path, "Plan.Plans[1]");
+        assert_eq!(suggestions[0].columns, vec!["user_id"]);
+    }
+
+    #[test]
+    fn agrees_with_cast_check_on_every_comparison() {
+        for (filter, indexable) in [
+            ("((account_id)::text = '123'::text)", false),
+            ("((account_id)::bigint = 123)", false),
+            ("(lower(code) = 'a'::text)", false),
+            ("((code)::text = 'A-1'::text)", true),
+            ("((code)::text = ANY ('{a,b}'::text[]))", true),
+            ("(code = 'A-1'::text)", true),
+        ] {
+            let explain = process_all(explain_for(filtered_scan(filter, 1, 99_999), 1.0, 1.0));
+            assert_eq!(
+                suggest_indexes(&explain).is_empty(),
+                !indexable,
+                "suggestion for {filter}"
+            );
+            assert_eq!(
+                find_cast_issues(&explain).is_empty(),
+                indexable,
+                "cast issue for {filter}"
+            );
+        }
+    }
+}
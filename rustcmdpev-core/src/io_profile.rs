@@ -0,0 +1,208 @@
+//! Buffer cache efficiency and I/O-bound classification.
+//!
+//! PostgreSQL reports buffer counters and I/O timings inclusive of a node's
+//! children. Per-node profiles therefore subtract the children's counters so a
+//! join above a disk-heavy scan is not classified by its input's I/O. The
+//! whole-plan profile uses the root node's inclusive counters directly.
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{CACHE_BOUND_BLOCKS_PER_MS, DISK_BOUND_HIT_RATIO, DISK_BOUND_IO_SHARE};
+use crate::structure::data::explain::Explain;
+use crate::structure::data::plan::Plan;
+
+/// Where a node (or plan) spends its time.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IoBound {
+    #[default]
+    Cpu,
+    Cache,
+    Disk,
+}
+
+impl IoBound {
+    pub fn label(self) -> &'static str {
+        match self {
+            IoBound::Cpu => "cpu-bound",
+            IoBound::Cache => "cache-bound",
+            IoBound::Disk => "disk-bound",
+        }
+    }
+}
+
+/// Buffer and I/O timing counters feeding a profile.
+#[derive(Debug, Clone, Copy, Default)]
+struct IoCounters {
+    hit_blocks: u64,
+    read_blocks: u64,
+    io_read_time: f64,
+    io_write_time: f64,
+    elapsed: f64,
+}
+
+impl IoCounters {
+    fn inclusive(plan: &Plan) -> Self {
+        Self {
+            hit_blocks: plan.buffers.shared_hit_blocks + plan.buffers.local_hit_blocks,
+            read_blocks: plan.buffers.shared_read_blocks + plan.buffers.local_read_blocks,
+            io_read_time: plan.io_timing.io_read_time,
+            io_write_time: plan.io_timing.io_write_time,
+            elapsed: plan.actuals.actual_total_time * plan.actuals.actual_loops as f64,
+        }
+    }
+
+    fn exclusive(plan: &Plan) -> Self {
+        let mut counters = Self::inclusive(plan);
+        for child in &plan.plans {
+            let child = Self::inclusive(child);
+            counters.hit_blocks = counters.hit_blocks.saturating_sub(child.hit_blocks);
+            counters.read_blocks = counters.read_blocks.saturating_sub(child.read_blocks);
+            counters.io_read_time = (counters.io_read_time - child.io_read_time).max(0.0);
+            counters.io_write_time = (counters.io_write_time - child.io_write_time).max(0.0);
+        }
+        counters.elapsed = plan.actuals.actual_duration;
+        counters
+    }
+}
+
+/// Cache efficiency and time-split metrics for a node or a whole plan.
+///
+/// Ratios are `None` when the inputs they need were not reported (e.g. no
+/// `BUFFERS` option, or `track_io_timing` disabled).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IoProfile {
+    /// Share of shared/local block accesses served from cache, in `0.0..=1.0`.
+    pub cache_hit_ratio: Option<f64>,
+    /// Read throughput in bytes per second (`read blocks × block size` over
+    /// `I/O Read Time`).
+    pub read_throughput: Option<f64>,
+    /// Share of elapsed time spent in I/O timing, in `0.0..=1.0`.
+    pub io_time_share: Option<f64>,
+    pub bound: IoBound,
+}
+
+impl IoProfile {
+    /// Profile a single node using its exclusive (self-only) counters.
+    pub fn for_plan(plan: &Plan, block_size: u64) -> Self {
+        Self::from_counters(IoCounters::exclusive(plan), block_size)
+    }
+
+    /// Profile the whole plan from the root node's inclusive counters.
+    pub fn for_explain(explain: &Explain, block_size: u64) -> Self {
+        let mut counters = IoCounters::inclusive(&explain.plan);
+        if explain.execution_time > 0.0 {
+            counters.elapsed = explain.execution_time;
+        }
+        Self::from_counters(counters, block_size)
+    }
+
+    fn from_counters(counters: IoCounters, block_size: u64) -> Self {
+        let accessed = counters.hit_blocks + counters.read_blocks;
+        let cache_hit_ratio = (accessed > 0).then(|| counters.hit_blocks as f64 / accessed as f64);
+        let read_throughput = (counters.io_read_time > 0.0)
+            .then(|| (counters.read_blocks * block_size) as f64 / (counters.io_read_time / 1000.0));
+        let io_time = counters.io_read_time + counters.io_write_time;
+        let io_time_share = (io_time > 0.0 && counters.elapsed > 0.0)
+            .then(|| (io_time / counters.elapsed).min(1.0));
+
+        let disk_bound = match io_time_share {
+            Some(share) => share >= DISK_BOUND_IO_SHARE,
+            None => counters.read_blocks > 0 && cache_hit_ratio < Some(DISK_BOUND_HIT_RATIO),
+        };
+        let cache_bound = counters.elapsed > 0.0
+            && counters.hit_blocks as f64 / counters.elapsed >= CACHE_BOUND_BLOCKS_PER_MS;
+        let bound = if disk_bound {
+            IoBound::Disk
+        } else if cache_bound {
+            IoBound::Cache
+        } else {
+            IoBound::Cpu
+        };
+
+        Self {
+            cache_hit_ratio,
+            read_throughput,
+            io_time_share,
+            bound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::DEFAULT_BLOCK_SIZE;
+    use crate::testing::{with_children, LeafBuilder};
+
+    fn scan(hit: u64, read: u64, io_read: f64, duration: f64) -> Plan {
+        let mut plan = LeafBuilder::new("Seq Scan").time(duration).build();
+        plan.actuals.actual_duration = duration;
+        plan.buffers.shared_hit_blocks = hit;
+        plan.buffers.shared_read_blocks = read;
+        plan.io_timing.io_read_time = io_read;
+        plan
+    }
+
+    #[test]
+    fn disk_bound_when_io_dominates_node_time() {
+        let profile = IoProfile::for_plan(&scan(100, 1_000, 8.0, 10.0), DEFAULT_BLOCK_SIZE);
+
+        assert_eq!(profile.bound, IoBound::Disk);
+        assert!((profile.cache_hit_ratio.unwrap() - 100.0 / 1_100.0).abs() < 1e-9);
+        assert!((profile.io_time_share.unwrap() - 0.8).abs() < 1e-9);
+        // 1000 blocks × 8 KiB over 8 ms.
+        assert!((profile.read_throughput.unwrap() - 1_024_000_000.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cache_bound_when_hits_are_dense_and_cpu_bound_otherwise() {
+        let cached = IoProfile::for_plan(&scan(50_000, 0, 0.0, 10.0), DEFAULT_BLOCK_SIZE);
+        let cpu = IoProfile::for_plan(&scan(10, 0, 0.0, 10.0), DEFAULT_BLOCK_SIZE);
+
+        assert_eq!(cached.bound, IoBound::Cache);
+        assert_eq!(cached.cache_hit_ratio, Some(1.0));
+        assert_eq!(cpu.bound, IoBound::Cpu);
+        assert!(cpu.read_throughput.is_none());
+    }
+
+    #[test]
+    fn falls_back_to_hit_ratio_without_io_timing() {
+        let profile = IoProfile::for_plan(&scan(10, 90, 0.0, 10.0), DEFAULT_BLOCK_SIZE);
+        assert!(profile.io_time_share.is_none());
+        assert_eq!(profile.bound, IoBound::Disk);
+    }
+
+    #[test]
+    fn node_profile_excludes_children_counters() {
+        let child = scan(0, 1_000, 8.0, 10.0);
+        let mut join = with_children(LeafBuilder::new("Hash Join").time(12.0).build(), [child]);
+        join.actuals.actual_duration = 2.0;
+        join.buffers.shared_read_blocks = 1_000;
+        join.io_timing.io_read_time = 8.0;
+
+        let node = IoProfile::for_plan(&join, DEFAULT_BLOCK_SIZE);
+        let explain = Explain {
+            execution_time: 12.0,
+            plan: join,
+            ..Explain::default()
+        };
+        let whole = IoProfile::for_explain(&explain, DEFAULT_BLOCK_SIZE);
+
+        assert_eq!(node.bound, IoBound::Cpu);
+        assert!(node.cache_hit_ratio.is_none());
+        assert_eq!(whole.bound, IoBound::Disk);
+        assert_eq!(whole.cache_hit_ratio, Some(0.0));
+    }
+
+    #[test]
+    fn block_size_scales_read_throughput() {
+        let plan = scan(0, 1_000, 1_000.0, 2_000.0);
+        let default = IoProfile::for_plan(&plan, DEFAULT_BLOCK_SIZE);
+        let large = IoProfile::for_plan(&plan, 32 * 1024);
+        assert!(
+            (large.read_throughput.unwrap() / default.read_throughput.unwrap() - 4.0).abs() < 1e-9
+        );
+    }
+}
@@ -4,14 +4,31 @@ use std::error::Error;
 use std::fmt;
 
 pub mod analysis;
+pub mod batch;
+pub mod cast_check;
 pub mod constants;
+pub mod delta;
+pub mod diagnostic;
 pub mod display;
+pub mod expression;
+pub mod fingerprint;
+pub mod history;
+pub mod index_advice;
+pub mod io_profile;
+pub mod lossless;
+pub mod output;
 pub mod parser;
+pub mod pipeline;
+pub mod planner_settings;
+pub mod redact;
 pub mod render;
+pub mod rollup;
 pub mod structure;
 pub mod summary;
 pub mod testing;
+pub mod work_mem;
 
+use diagnostic::{Diagnostic, JSON_PATH_ROOT};
 use parser::ParseOptions;
 use structure::data::explain::Explain;
 
@@ -20,7 +37,59 @@ pub enum VisualizeError {
     InvalidJson(serde_json::Error),
     EmptyExplainArray,
     MissingPlan,
-    InvalidPlan(String),
+    InvalidPlan(PlanViolation),
+}
+
+/// Which rule a [`PlanViolation`] breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The input breaks the EXPLAIN JSON contract (shape, types, negative
+    /// values, size limits); see [`structure::contract`].
+    Contract,
+    /// The input satisfies the contract but describes an unusable plan.
+    Invariant,
+}
+
+/// A plan that parsed as JSON but breaks the input contract or a structural
+/// invariant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanViolation {
+    pub kind: ViolationKind,
+    /// JSON path of the offending value, e.g. `$[0].Plan.Plans[0].Total Cost`.
+    pub path: String,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl PlanViolation {
+    pub fn contract(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(ViolationKind::Contract, path, message)
+    }
+
+    pub fn invariant(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(ViolationKind::Invariant, path, message)
+    }
+
+    fn new(kind: ViolationKind, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            path: path.into(),
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl fmt::Display for PlanViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
 }
 
 impl fmt::Display for VisualizeError {
@@ -36,7 +105,49 @@ impl fmt::Display for VisualizeError {
             VisualizeError::MissingPlan => {
                 write!(f, "first explain object must contain 'Plan' object")
             }
-            VisualizeError::InvalidPlan(message) => write!(f, "{message}"),
+            VisualizeError::InvalidPlan(violation) => write!(f, "{violation}"),
+        }
+    }
+}
+
+impl VisualizeError {
+    /// Whether the input itself is malformed (not JSON, wrong shape, or
+    /// breaking the contract) as opposed to well-formed but unusable.
+    pub fn is_contract_violation(&self) -> bool {
+        match self {
+            VisualizeError::InvalidJson(err) => err.is_syntax() || err.is_eof(),
+            VisualizeError::EmptyExplainArray | VisualizeError::MissingPlan => true,
+            VisualizeError::InvalidPlan(violation) => violation.kind == ViolationKind::Contract,
+        }
+    }
+
+    /// Structured form of the error, positioned within `input` where the
+    /// offending value can be found.
+    pub fn diagnostic(&self, input: &str) -> Diagnostic {
+        match self {
+            VisualizeError::InvalidJson(err) => Diagnostic::invalid_json(input, err),
+            VisualizeError::EmptyExplainArray => {
+                Diagnostic::new("empty_explain_array", self.to_string())
+                    .with_path(JSON_PATH_ROOT)
+                    .with_hint("Pass the complete output of EXPLAIN (ANALYZE, FORMAT JSON).")
+                    .located_in(input)
+            }
+            VisualizeError::MissingPlan => Diagnostic::new("missing_plan", self.to_string())
+                .with_path(format!("{JSON_PATH_ROOT}[0].Plan"))
+                .with_hint("Pass the complete output of EXPLAIN (ANALYZE, FORMAT JSON).")
+                .located_in(input),
+            VisualizeError::InvalidPlan(violation) => {
+                let kind = match violation.kind {
+                    ViolationKind::Contract => "contract_violation",
+                    ViolationKind::Invariant => "invalid_plan",
+                };
+                let mut diagnostic = Diagnostic::new(kind, violation.message.clone())
+                    .with_path(violation.path.clone());
+                if let Some(hint) = &violation.hint {
+                    diagnostic = diagnostic.with_hint(hint.clone());
+                }
+                diagnostic.located_in(input)
+            }
         }
     }
 }
@@ -48,6 +159,11 @@ pub fn analyze_explain(explain: Explain) -> Explain {
     analysis::process_all(explain)
 }
 
+/// Apply a custom [`pipeline::Pipeline`] to a validated explain document.
+pub fn analyze_explain_with(explain: Explain, pipeline: &pipeline::Pipeline) -> Explain {
+    pipeline.run(explain)
+}
+
 /// Parse raw input and run the full validation and analysis pipeline.
 pub fn parse_and_process(input: &str) -> Result<Explain, VisualizeError> {
     let explain = parser::parse_explain_document(input)?;
@@ -0,0 +1,375 @@
+//! Re-serialize an analyzed plan under PostgreSQL's original key names.
+//!
+//! The typed model fills absent fields with defaults and does not remember
+//! which keys were present, so known fields are written only when they hold a
+//! non-default value: a source key such as `"Startup Cost": 0.0` or
+//! `"Actual Loops": 0` is dropped, and the output is lossless only up to
+//! [`normalize`]. Computed fields (`Actual Duration`, `Actual Cost`,
+//! outlier flags, maxima) are never written. Unrecognized keys come back from
+//! the `extras` maps in their original order.
+//!
+//! The result compares equal to the source document after [`normalize`],
+//! which applies the same rules to both sides: alias keys are renamed to their
+//! canonical spelling, known keys holding a default value (including a
+//! `Planning` block whose counters are all zero) and computed keys are
+//! dropped, numbers compare as `f64`, and object keys are sorted.
+
+use serde_json::{Map, Number, Value};
+
+use crate::structure::data::buffers::PlanBuffers;
+use crate::structure::data::explain::Explain;
+use crate::structure::data::plan::Plan;
+
+/// Keys written by [`to_postgres_json`] whose default value is omitted.
+const KNOWN_KEYS: &[&str] = &[
+    "Node Type",
+    "Strategy",
+    "Parent Relationship",
+    "Scan Direction",
+    "Index Name",
+    "Relation Name",
+    "Schema",
+    "Alias",
+    "CTE Name",
+    "Join Type",
+    "Startup Cost",
+    "Total Cost",
+    "Plan Rows",
+    "Plan Width",
+    "Workers Planned",
+    "Actual Startup Time",
+    "Actual Total Time",
+    "Actual Rows",
+    "Actual Loops",
+    "Workers Launched",
+    "Output",
+    "Group Key",
+    "Hash Cond",
+    "Merge Cond",
+    "Index Cond",
+    "Join Filter",
+    "Rows Removed by Join Filter",
+    "Filter",
+    "Rows Removed by Filter",
+    "Rows Removed by Index Recheck",
+    "Heap Fetches",
+    "Sort Method",
+    "Sort Space Used",
+    "Sort Space Type",
+    "Peak Memory Usage",
+    "Disk Usage",
+    "HashAgg Batches",
+    "Hash Buckets",
+    "Original Hash Buckets",
+    "Hash Batches",
+    "Original Hash Batches",
+    "Shared Hit Blocks",
+    "Shared Read Blocks",
+    "Shared Dirtied Blocks",
+    "Shared Written Blocks",
+    "Local Hit Blocks",
+    "Local Read Blocks",
+    "Local Dirtied Blocks",
+    "Local Written Blocks",
+    "Temp Read Blocks",
+    "Temp Written Blocks",
+    "I/O Read Time",
+    "I/O Write Time",
+    "WAL Records",
+    "WAL FPI",
+    "WAL Bytes",
+    "Plans",
+    "Settings",
+    "Planning",
+    "Planning Time",
+    "Execution Time",
+];
+
+/// Alternate spellings accepted on input, mapped to the spelling written.
+const KEY_ALIASES: &[(&str, &str)] = &[
+    ("Rows Removed By Filter", "Rows Removed by Filter"),
+    (
+        "Rows Removed By Index Recheck",
+        "Rows Removed by Index Recheck",
+    ),
+    ("Rows Removed By Join Filter", "Rows Removed by Join Filter"),
+    ("IO Read Time", "I/O Read Time"),
+    ("IO Write Time", "I/O Write Time"),
+    ("Postgres Version", "PostgreSQL Version"),
+];
+
+/// Keys the parser consumes but this crate computes itself.
+const DERIVED_KEYS: &[&str] = &["Actual Cost", "Actual Duration"];
+
+/// Serialize `explain` as a PostgreSQL `EXPLAIN (FORMAT JSON)` document: a
+/// one-element array holding the top-level object.
+pub fn to_postgres_json(explain: &Explain) -> Value {
+    let mut document = Map::new();
+    document.insert("Plan".into(), plan_to_postgres_json(&explain.plan));
+    if let Some(version) = &explain.postgres_version {
+        document.insert("PostgreSQL Version".into(), Value::from(version.as_str()));
+    }
+    if !explain.settings.is_empty() {
+        let settings = explain
+            .settings
+            .iter()
+            .map(|(name, value)| (name.clone(), Value::from(value.as_str())))
+            .collect();
+        document.insert("Settings".into(), Value::Object(settings));
+    }
+    if !explain.planning.is_empty() {
+        let mut planning = Map::new();
+        put_buffers(&mut planning, &explain.planning.buffers);
+        for (key, value) in &explain.planning.extras {
+            planning.insert(key.clone(), value.clone());
+        }
+        document.insert("Planning".into(), Value::Object(planning));
+    }
+    put_f64(&mut document, "Planning Time", explain.planning_time);
+    for (key, value) in &explain.extras {
+        document.insert(key.clone(), value.clone());
+    }
+    put_f64(&mut document, "Execution Time", explain.execution_time);
+    Value::Array(vec![Value::Object(document)])
+}
+
+fn plan_to_postgres_json(plan: &Plan) -> Value {
+    let mut node = Map::new();
+    let identity = &plan.identity;
+    node.insert("Node Type".into(), Value::from(identity.node_type.as_str()));
+    put_str(&mut node, "Strategy", &identity.strategy);
+    put_str(
+        &mut node,
+        "Parent Relationship",
+        &identity.parent_relationship,
+    );
+    put_str(&mut node, "Scan Direction", &identity.scan_direction);
+    put_str(&mut node, "Index Name", &identity.index_name);
+    put_str(&mut node, "Relation Name", &identity.relation_name);
+    put_str(&mut node, "Schema", &identity.schema);
+    put_str(&mut node, "Alias", &identity.alias);
+    put_str(&mut node, "CTE Name", &identity.cte_name);
+    put_str(&mut node, "Join Type", &identity.join_type);
+
+    let estimates = &plan.estimates;
+    put_f64(&mut node, "Startup Cost", estimates.startup_cost);
+    put_f64(&mut node, "Total Cost", estimates.total_cost);
+    put_u64(&mut node, "Plan Rows", estimates.plan_rows);
+    put_u64(&mut node, "Plan Width", estimates.plan_width);
+    put_u64(&mut node, "Workers Planned", estimates.workers_planned);
+
+    let actuals = &plan.actuals;
+    put_f64(
+        &mut node,
+        "Actual Startup Time",
+        actuals.actual_startup_time,
+    );
+    put_f64(&mut node, "Actual Total Time", actuals.actual_total_time);
+    put_u64(&mut node, "Actual Rows", actuals.actual_rows);
+    put_u64(&mut node, "Actual Loops", actuals.actual_loops);
+    put_u64(&mut node, "Workers Launched", actuals.workers_launched);
+
+    let predicates = &plan.predicates;
+    put_list(&mut node, "Output", &predicates.output);
+    put_list(&mut node, "Group Key", &predicates.group_key);
+    put_str(&mut node, "Hash Cond", &predicates.hash_condition);
+    put_str(&mut node, "Merge Cond", &predicates.merge_condition);
+    put_str(&mut node, "Index Cond", &predicates.index_condition);
+    put_str(&mut node, "Join Filter", &predicates.join_filter);
+    put_u64(
+        &mut node,
+        "Rows Removed by Join Filter",
+        predicates.rows_removed_by_join_filter,
+    );
+    put_str(&mut node, "Filter", &predicates.filter);
+    put_u64(
+        &mut node,
+        "Rows Removed by Filter",
+        predicates.rows_removed_by_filter,
+    );
+    put_u64(
+        &mut node,
+        "Rows Removed by Index Recheck",
+        predicates.rows_removed_by_index_recheck,
+    );
+
+    let memory = &plan.memory;
+    put_str(&mut node, "Sort Method", &memory.sort_method);
+    put_u64(&mut node, "Sort Space Used", memory.sort_space_used);
+    put_str(&mut node, "Sort Space Type", &memory.sort_space_type);
+    put_u64(&mut node, "Peak Memory Usage", memory.peak_memory_usage);
+    put_u64(&mut node, "Disk Usage", memory.disk_usage);
+    put_u64(&mut node, "HashAgg Batches", memory.hashagg_batches);
+    put_u64(&mut node, "Hash Buckets", memory.hash_buckets);
+    put_u64(
+        &mut node,
+        "Original Hash Buckets",
+        memory.original_hash_buckets,
+    );
+    put_u64(&mut node, "Hash Batches", memory.hash_batches);
+    put_u64(
+        &mut node,
+        "Original Hash Batches",
+        memory.original_hash_batches,
+    );
+
+    put_buffers(&mut node, &plan.buffers);
+    put_f64(&mut node, "I/O Read Time", plan.io_timing.io_read_time);
+    put_f64(&mut node, "I/O Write Time", plan.io_timing.io_write_time);
+    put_u64(&mut node, "WAL Records", plan.wal.wal_records);
+    put_u64(&mut node, "WAL FPI", plan.wal.wal_fpi);
+    put_u64(&mut node, "WAL Bytes", plan.wal.wal_bytes);
+
+    for (key, value) in &plan.extras {
+        node.insert(key.clone(), value.clone());
+    }
+    if !plan.plans.is_empty() {
+        let children = plan.plans.iter().map(plan_to_postgres_json).collect();
+        node.insert("Plans".into(), Value::Array(children));
+    }
+    Value::Object(node)
+}
+
+fn put_buffers(node: &mut Map<String, Value>, buffers: &PlanBuffers) {
+    put_u64(node, "Heap Fetches", buffers.heap_fetches);
+    put_u64(node, "Shared Hit Blocks", buffers.shared_hit_blocks);
+    put_u64(node, "Shared Read Blocks", buffers.shared_read_blocks);
+    put_u64(node, "Shared Dirtied Blocks", buffers.shared_dirtied_blocks);
+    put_u64(node, "Shared Written Blocks", buffers.shared_written_blocks);
+    put_u64(node, "Local Hit Blocks", buffers.local_hit_blocks);
+    put_u64(node, "Local Read Blocks", buffers.local_read_blocks);
+    put_u64(node, "Local Dirtied Blocks", buffers.local_dirtied_blocks);
+    put_u64(node, "Local Written Blocks", buffers.local_written_blocks);
+    put_u64(node, "Temp Read Blocks", buffers.temp_read_blocks);
+    put_u64(node, "Temp Written Blocks", buffers.temp_written_blocks);
+}
+
+fn put_str(node: &mut Map<String, Value>, key: &str, value: &str) {
+    if !value.is_empty() {
+        node.insert(key.into(), Value::from(value));
+    }
+}
+
+fn put_u64(node: &mut Map<String, Value>, key: &str, value: u64) {
+    if value != 0 {
+        node.insert(key.into(), Value::from(value));
+    }
+}
+
+fn put_f64(node: &mut Map<String, Value>, key: &str, value: f64) {
+    if value != 0.0 {
+        node.insert(key.into(), Value::from(value));
+    }
+}
+
+fn put_list(node: &mut Map<String, Value>, key: &str, values: &[String]) {
+    if !values.is_empty() {
+        node.insert(key.into(), Value::from(values.to_vec()));
+    }
+}
+
+/// Canonicalize a PostgreSQL JSON document for comparison with the output of
+/// [`to_postgres_json`]; see the module docs for the rules.
+pub fn normalize(document: &Value) -> Value {
+    match document {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map
+                .iter()
+                .filter(|(key, _)| !DERIVED_KEYS.contains(&key.as_str()))
+                .map(|(key, value)| (canonical_key(key).to_string(), normalize(value)))
+                .filter(|(key, value)| !(KNOWN_KEYS.contains(&key.as_str()) && is_default(value)))
+                .collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(entries.into_iter().collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(normalize).collect()),
+        Value::Number(number) => number
+            .as_f64()
+            .and_then(Number::from_f64)
+            .map_or_else(|| document.clone(), Value::Number),
+        Value::Null | Value::Bool(_) | Value::String(_) => document.clone(),
+    }
+}
+
+fn canonical_key(key: &str) -> &str {
+    KEY_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == key)
+        .map_or(key, |(_, canonical)| canonical)
+}
+
+fn is_default(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Number(number) => number.as_f64() == Some(0.0),
+        Value::String(text) => text.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        Value::Bool(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_explain_document;
+
+    const PAYLOAD: &str = r#"[{"Plan":{"Node Type":"Hash Join","Parallel Aware":false,"Join Type":"Inner","Startup Cost":1.00,"Total Cost":12.50,"Plan Rows":10,"Plan Width":8,"Actual Startup Time":0.1,"Actual Total Time":0.9,"Actual Rows":10,"Actual Loops":1,"Inner Unique":true,"Hash Cond":"(a.id = b.id)","Plans":[{"Node Type":"Seq Scan","Parent Relationship":"Outer","Relation Name":"a","Alias":"a","Startup Cost":0.00,"Total Cost":5.00,"Plan Rows":100,"Plan Width":4,"Actual Startup Time":0.0,"Actual Total Time":0.3,"Actual Rows":100,"Actual Loops":1,"Filter":"(x > 1)","Rows Removed By Filter":5,"WAL Records":2,"WAL FPI":1,"WAL Bytes":8300,"Async Capable":false}]},"Settings":{"enable_seqscan":"off","work_mem":"64MB"},"Planning":{"Shared Hit Blocks":12,"Shared Read Blocks":1,"Memory Used":21},"Planning Time":0.2,"Triggers":[],"Execution Time":1.1}]"#;
+
+    #[test]
+    fn round_trip_matches_source_after_normalization() {
+        let source: Value = serde_json::from_str(PAYLOAD).unwrap();
+        let explain = parse_explain_document(PAYLOAD).unwrap();
+
+        let written = to_postgres_json(&explain);
+
+        assert_eq!(normalize(&written), normalize(&source));
+        let node = &written[0]["Plan"];
+        assert_eq!(node["Inner Unique"], true);
+        assert_eq!(node["Plans"][0]["Rows Removed by Filter"], 5);
+        assert_eq!(written[0]["Triggers"], Value::Array(Vec::new()));
+        assert_eq!(written[0]["Settings"]["work_mem"], "64MB");
+        assert_eq!(written[0]["Planning"]["Shared Hit Blocks"], 12);
+        assert_eq!(written[0]["Planning"]["Memory Used"], 21);
+        assert_eq!(node["Plans"][0]["WAL Bytes"], 8300);
+    }
+
+    #[test]
+    fn zero_wal_counters_and_planning_buffers_round_trip() {
+        let payload = r#"[{"Plan":{"Node Type":"Seq Scan","Relation Name":"a","Total Cost":5.0,"Actual Rows":1,"Actual Loops":1,"Shared Hit Blocks":0,"WAL Records":0,"WAL FPI":0,"WAL Bytes":0},"Planning":{"Shared Hit Blocks":0,"Shared Read Blocks":0,"Temp Written Blocks":0},"Planning Time":0.1,"Execution Time":0.4}]"#;
+        let source: Value = serde_json::from_str(payload).unwrap();
+        let explain = parse_explain_document(payload).unwrap();
+
+        let written = to_postgres_json(&explain);
+
+        assert_eq!(normalize(&written), normalize(&source));
+        assert!(written[0].get("Planning").is_none());
+        assert!(written[0]["Plan"].get("WAL Bytes").is_none());
+    }
+
+    #[test]
+    fn computed_fields_are_not_written() {
+        let explain = crate::analysis::process_all(parse_explain_document(PAYLOAD).unwrap());
+
+        let written = to_postgres_json(&explain);
+
+        assert!(written[0]["Plan"].get("Actual Duration").is_none());
+        assert!(written[0].get("Max Duration").is_none());
+        assert_eq!(
+            normalize(&written),
+            normalize(&serde_json::from_str(PAYLOAD).unwrap())
+        );
+    }
+
+    #[test]
+    fn normalize_renames_aliases_and_drops_defaults() {
+        let value: Value =
+            serde_json::from_str(r#"{"IO Read Time":0,"Rows Removed By Filter":2,"Extra":0}"#)
+                .unwrap();
+        let normalized = normalize(&value);
+        assert_eq!(
+            serde_json::to_string(&normalized).unwrap(),
+            r#"{"Extra":0.0,"Rows Removed by Filter":2.0}"#
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! JSON output document emitted by `--format json`.
+//!
+//! The analyzed [`Explain`] is flattened at the top level so existing
+//! consumers keep reading `plan`, `execution_time`, and friends unchanged;
+//! optional report blocks are appended only when requested. Each plan node
+//! also carries its `node_path`, which the model does not store and is
+//! computed as the document is written.
+//!
+//! The shape is versioned by [`JSON_OUTPUT_SCHEMA_VERSION`], emitted as
+//! `schema_version`. The first change to the serialized shape after a
+//! release bumps the version, and later changes before the next release
+//! amend it; [`json_schema`] describes the current version and a committed
+//! copy under `schema/` guards against drift.
+
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde::ser::Error as _;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::cast_check::{find_cast_issues, CastIssue};
+use crate::constants::JSON_OUTPUT_SCHEMA_VERSION;
+use crate::index_advice::{suggest_indexes, IndexSuggestion};
+use crate::planner_settings::{distorting_settings, SettingWarning};
+use crate::rollup::RelationReport;
+use crate::structure::data::explain::Explain;
+use crate::structure::visit::child_path;
+use crate::work_mem::{recommend_work_mem, WorkMemAdvice};
+
+/// Serializable wrapper around an analyzed explain and its optional reports.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct JsonOutput<'a> {
+    /// Version of this document's shape; see the module docs for when it
+    /// changes.
+    pub schema_version: u32,
+    #[serde(flatten, serialize_with = "serialize_with_node_paths")]
+    pub explain: &'a Explain,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relations: Option<RelationReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_suggestions: Option<Vec<IndexSuggestion>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cast_issues: Option<Vec<CastIssue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_mem: Option<WorkMemAdvice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub setting_warnings: Option<Vec<SettingWarning>>,
+}
+
+impl<'a> JsonOutput<'a> {
+    pub fn new(explain: &'a Explain) -> Self {
+        Self {
+            schema_version: JSON_OUTPUT_SCHEMA_VERSION,
+            explain,
+            relations: None,
+            index_suggestions: None,
+            cast_issues: None,
+            work_mem: None,
+            setting_warnings: None,
+        }
+    }
+
+    /// Attach the per-relation and per-index rollup block.
+    pub fn with_relations(mut self) -> Self {
+        self.relations = Some(RelationReport::from_explain(self.explain));
+        self
+    }
+
+    /// Attach heuristic index suggestions (an empty list when none qualify).
+    pub fn with_index_suggestions(mut self) -> Self {
+        self.index_suggestions = Some(suggest_indexes(self.explain));
+        self
+    }
+
+    /// Attach casts and function calls on columns that defeat indexes (an
+    /// empty list when none are found).
+    pub fn with_cast_issues(mut self) -> Self {
+        self.cast_issues = Some(find_cast_issues(self.explain));
+        self
+    }
+
+    /// Attach the `work_mem` recommendation when any operation spilled.
+    pub fn with_work_mem(mut self, block_size: u64) -> Self {
+        self.work_mem = recommend_work_mem(self.explain, block_size);
+        self
+    }
+
+    /// Attach the planner settings that commonly skew plans, when any are set.
+    pub fn with_setting_warnings(mut self) -> Self {
+        let warnings = distorting_settings(&self.explain.settings);
+        self.setting_warnings = (!warnings.is_empty()).then_some(warnings);
+        self
+    }
+}
+
+/// Serialize `explain` with a `node_path` after each node's `node_id`, the
+/// path in the source document (see [`Explain::root_path`]).
+fn serialize_with_node_paths<S: Serializer>(
+    explain: &&Explain,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut document = serde_json::to_value(explain).map_err(S::Error::custom)?;
+    if let Some(root) = document.get_mut("plan") {
+        add_node_paths(root, explain.root_path());
+    }
+    document.serialize(serializer)
+}
+
+fn add_node_paths(root: &mut Value, root_path: &str) {
+    let mut stack = vec![(root, root_path.to_string())];
+    while let Some((node, path)) = stack.pop() {
+        let Value::Object(fields) = node else {
+            continue;
+        };
+        let mut with_path = Map::with_capacity(fields.len() + 1);
+        for (key, value) in std::mem::take(fields) {
+            let is_id = key == "node_id";
+            with_path.insert(key, value);
+            if is_id {
+                with_path.insert(NODE_PATH_KEY.into(), Value::from(path.as_str()));
+            }
+        }
+        *fields = with_path;
+        if let Some(Value::Array(children)) = fields.get_mut("plans") {
+            for (index, child) in children.iter_mut().enumerate() {
+                stack.push((child, child_path(&path, index)));
+            }
+        }
+    }
+}
+
+/// Serialized name of the per-node path written by [`JsonOutput`].
+const NODE_PATH_KEY: &str = "node_path";
+
+/// JSON Schema (draft 2020-12) for [`JsonOutput`] at the current
+/// [`JSON_OUTPUT_SCHEMA_VERSION`], using serialized field names.
+pub fn json_schema() -> serde_json::Value {
+    let generator = SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator();
+    let mut schema = generator.into_root_schema_for::<JsonOutput>();
+    schema.insert(
+        "title".into(),
+        format!("rustcmdpev JSON output v{JSON_OUTPUT_SCHEMA_VERSION}").into(),
+    );
+    if let Some(version) = schema
+        .get_mut("properties")
+        .and_then(|properties| properties.get_mut("schema_version"))
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        version.insert("const".into(), JSON_OUTPUT_SCHEMA_VERSION.into());
+    }
+    if let Some(plan) = schema
+        .get_mut("$defs")
+        .and_then(|definitions| definitions.get_mut("Plan"))
+        .and_then(Value::as_object_mut)
+    {
+        if let Some(properties) = plan.get_mut("properties").and_then(Value::as_object_mut) {
+            properties.insert(
+                NODE_PATH_KEY.into(),
+                serde_json::json!({
+                    "type": "string",
+                    "description": "Path of the node in the source document, e.g. \
+                        `Plan.Plans[0].Plans[1]`. Computed as the document is written.",
+                }),
+            );
+        }
+        if let Some(required) = plan.get_mut("required").and_then(Value::as_array_mut) {
+            required.push(NODE_PATH_KEY.into());
+        }
+    }
+    schema.to_value()
+}
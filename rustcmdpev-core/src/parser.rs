@@ -1,18 +1,31 @@
+use crate::structure::contract::{build_validated_explain, explain_parse_failure, PlanLimits};
 use crate::structure::data::explain::Explain;
 use crate::structure::raw::{schema_profile_from_hint, PostgresSchemaProfile, RawExplain, RawPlan};
+use crate::structure::streaming;
 use crate::structure::tree::PlanTree;
 use crate::VisualizeError;
 
+/// Deepest plan the recursive parser accepts. `serde_json` stops at 128
+/// nesting levels, and each plan level costs two (the node object and its
+/// `Plans` array) on top of the node's own nested values; deeper limits
+/// switch to [`streaming`].
+const RECURSIVE_PARSE_MAX_DEPTH: usize = 48;
+
 /// Parser-level configuration.
 ///
-/// Currently the only knob is the optional `postgres_version_hint`, which lets
-/// callers steer schema-profile selection when the JSON payload itself does
-/// not carry a `PostgreSQL Version` field. The hint is parsed for a leading
-/// integer (e.g. `"12"`, `"PostgreSQL 13.4"`) and ignored if no major version
-/// can be extracted.
+/// The optional `postgres_version_hint` lets callers steer schema-profile
+/// selection when the JSON payload itself does not carry a `PostgreSQL
+/// Version` field. The hint is parsed for a leading integer (e.g. `"12"`,
+/// `"PostgreSQL 13.4"`) and ignored if no major version can be extracted.
+///
+/// `limits` bounds the plan's depth and node count. `streaming` reads the
+/// plan tree iteratively rather than with recursive deserialization; it is
+/// used regardless when `limits.max_depth` is too deep for recursion.
 #[derive(Debug, Clone, Default)]
 pub struct ParseOptions {
     pub postgres_version_hint: Option<String>,
+    pub limits: PlanLimits,
+    pub streaming: bool,
 }
 
 impl ParseOptions {
@@ -25,6 +38,26 @@ impl ParseOptions {
         self
     }
 
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.limits.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.limits.max_nodes = max_nodes;
+        self
+    }
+
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+
+    /// Whether documents are read by [`streaming`] rather than recursively.
+    pub fn uses_streaming(&self) -> bool {
+        self.streaming || self.limits.depth_limit() > RECURSIVE_PARSE_MAX_DEPTH
+    }
+
     pub fn schema_profile_hint(&self) -> Option<PostgresSchemaProfile> {
         self.postgres_version_hint
             .as_deref()
@@ -33,8 +66,12 @@ impl ParseOptions {
 }
 
 /// Parse the top-level PostgreSQL EXPLAIN JSON array into raw serde models.
+///
+/// Input that fails typed deserialization is reported as the first
+/// [contract](crate::structure::contract) rule it breaks, when there is one.
 pub fn parse_raw_explains(input: &str) -> Result<Vec<RawExplain>, VisualizeError> {
-    serde_json::from_str(input).map_err(VisualizeError::InvalidJson)
+    serde_json::from_str(input)
+        .map_err(|err| explain_parse_failure(input, err, PlanLimits::default()))
 }
 
 /// Convert a raw explain document into the strongly typed domain model.
@@ -48,14 +85,12 @@ pub fn build_domain_explain_with(
     raw: RawExplain,
     options: &ParseOptions,
 ) -> Result<Explain, VisualizeError> {
-    let mut effective_profile = raw.schema_profile();
-    if effective_profile == PostgresSchemaProfile::Unknown {
-        if let Some(hinted) = options.schema_profile_hint() {
-            effective_profile = hinted;
-        }
-    }
-
-    let RawExplain { metadata, plan } = raw;
+    let effective_profile = effective_schema_profile(&raw, options);
+    let RawExplain {
+        metadata,
+        plan,
+        extras,
+    } = raw;
     let plan = plan
         .ok_or(VisualizeError::MissingPlan)?
         .into_domain(effective_profile)?;
@@ -70,20 +105,33 @@ pub fn build_domain_explain_with(
         postgres_version,
         planning_time: metadata.planning_time,
         execution_time: metadata.execution_time,
+        settings: metadata.settings,
+        planning: metadata.planning,
+        extras,
         ..Default::default()
     })
 }
 
+fn effective_schema_profile(raw: &RawExplain, options: &ParseOptions) -> PostgresSchemaProfile {
+    match raw.schema_profile() {
+        PostgresSchemaProfile::Unknown => options
+            .schema_profile_hint()
+            .unwrap_or(PostgresSchemaProfile::Unknown),
+        profile => profile,
+    }
+}
+
 /// Validate the domain plan as an explicit tree with structural invariants.
-pub fn validate_plan_tree(explain: Explain) -> Result<Explain, VisualizeError> {
-    let tree = PlanTree::new(explain.plan.clone())?;
-    Ok(Explain {
-        plan: tree.into_root(),
-        ..explain
-    })
+pub fn validate_plan_tree(mut explain: Explain) -> Result<Explain, VisualizeError> {
+    let tree = PlanTree::new(std::mem::take(&mut explain.plan))?;
+    explain.plan = tree.into_root();
+    Ok(explain)
 }
 
 /// Run the full parser pipeline from raw JSON through validated domain explain.
+///
+/// The document is deserialized once; contract and tree checks, node
+/// addressing, and domain conversion share a single pre-order walk.
 pub fn parse_explain_document(input: &str) -> Result<Explain, VisualizeError> {
     parse_explain_document_with(input, &ParseOptions::default())
 }
@@ -93,19 +141,41 @@ pub fn parse_explain_document_with(
     input: &str,
     options: &ParseOptions,
 ) -> Result<Explain, VisualizeError> {
-    let raw_explain = parse_raw_explains(input)?
+    if options.uses_streaming() {
+        let streamed = streaming::read_explain(input, options.limits)?;
+        let (profile, postgres_version) = resolve_version(&streamed.raw, options);
+        let mut explain = streamed.into_explain(profile)?;
+        explain.postgres_version = postgres_version;
+        return Ok(explain);
+    }
+
+    let raw_explain = serde_json::from_str::<Vec<RawExplain>>(input)
+        .map_err(|err| explain_parse_failure(input, err, options.limits))?
         .into_iter()
         .next()
         .ok_or(VisualizeError::EmptyExplainArray)?;
-    let mut explain = build_domain_explain_with(raw_explain.clone(), options)?;
-
-    if raw_explain.schema_profile() == PostgresSchemaProfile::Unknown
-        && options.schema_profile_hint().is_none()
-    {
-        explain.postgres_version = None;
-    }
+    let (profile, postgres_version) = resolve_version(&raw_explain, options);
+    let mut explain = build_validated_explain(raw_explain, profile, options.limits)?;
+    explain.postgres_version = postgres_version;
+    Ok(explain)
+}
 
-    validate_plan_tree(explain)
+/// Schema profile to read `raw` with, and the version to report: the
+/// payload's own, else the hint, and neither when no profile could be
+/// determined.
+fn resolve_version(
+    raw: &RawExplain,
+    options: &ParseOptions,
+) -> (PostgresSchemaProfile, Option<String>) {
+    let version_known = raw.schema_profile() != PostgresSchemaProfile::Unknown
+        || options.schema_profile_hint().is_some();
+    let postgres_version = raw
+        .metadata
+        .postgres_version
+        .clone()
+        .or_else(|| options.postgres_version_hint.clone())
+        .filter(|_| version_known);
+    (effective_schema_profile(raw, options), postgres_version)
 }
 
 // Avoid an "unused" warning if no caller of `RawPlan` needs it from here yet.
@@ -158,4 +228,25 @@ mod tests {
         let explain = parse_explain_document(PG12_PAYLOAD_NO_VERSION).expect("parse no hint");
         assert!(explain.postgres_version.is_none());
     }
+
+    #[test]
+    fn unrecognized_keys_are_kept_in_source_order() {
+        let payload = r#"[{"Plan":{"Node Type":"Custom Scan","Parallel Aware":false,"Custom Plan Provider":"columnar","Total Cost":1.0,"Rows Removed by Filter":4,"IO Read Time":1.5,"Columnar Chunk Groups Removed by Filter":3},"Triggers":[],"JIT":{"Functions":2},"Execution Time":0.5}]"#;
+
+        let explain = parse_explain_document(payload).expect("parse");
+
+        let keys: Vec<&str> = explain.plan.extras.keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            [
+                "Parallel Aware",
+                "Custom Plan Provider",
+                "Columnar Chunk Groups Removed by Filter"
+            ]
+        );
+        assert_eq!(explain.plan.predicates.rows_removed_by_filter, 4);
+        assert!((explain.plan.io_timing.io_read_time - 1.5).abs() < 1e-9);
+        let top: Vec<&str> = explain.extras.keys().map(String::as_str).collect();
+        assert_eq!(top, ["Triggers", "JIT"]);
+    }
 }
@@ -0,0 +1,235 @@
+//! Ordered analysis passes run over a parsed explain.
+//!
+//! [`Pipeline::default`] runs the built-in passes from [`crate::analysis`];
+//! custom passes are added with the `with_pass*` builders and can attach
+//! [`Annotation`](crate::structure::data::annotations::Annotation)s to nodes,
+//! which JSON output and the pretty renderer then display.
+
+use std::fmt;
+
+use crate::analysis::{
+    ActualsPass, EstimateErrorPass, ExpressionPass, FingerprintPass, MaximumsPass, OutlierPass,
+    PlannerEstimatePass,
+};
+use crate::structure::data::explain::Explain;
+use crate::structure::graph::ExplainGraph;
+
+/// One step of analysis over a whole explain document.
+pub trait AnalysisPass: Send + Sync {
+    /// Identifier used to position or remove the pass within a [`Pipeline`].
+    fn name(&self) -> &'static str;
+
+    fn run(&self, explain: &mut Explain);
+
+    /// Run over the arena form of the plan. [`Pipeline::run`] calls this,
+    /// converting the tree once for all passes; the default converts back
+    /// to the owned tree for [`AnalysisPass::run`], so passes only need to
+    /// implement it when they traverse large plans.
+    fn run_graph(&self, explain: &mut ExplainGraph) {
+        explain.with_tree(|explain| self.run(explain));
+    }
+}
+
+/// Analysis passes run in order by [`Pipeline::run`].
+pub struct Pipeline {
+    passes: Vec<Box<dyn AnalysisPass>>,
+}
+
+impl Pipeline {
+    /// A pipeline with no passes.
+    pub fn empty() -> Self {
+        Self { passes: Vec::new() }
+    }
+
+    /// Append `pass` to the end of the pipeline.
+    #[must_use]
+    pub fn with_pass(mut self, pass: impl AnalysisPass + 'static) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// Insert `pass` before the pass named `before`, or append it when no
+    /// pass has that name.
+    #[must_use]
+    pub fn with_pass_before(mut self, before: &str, pass: impl AnalysisPass + 'static) -> Self {
+        let index = self.position(before).unwrap_or(self.passes.len());
+        self.passes.insert(index, Box::new(pass));
+        self
+    }
+
+    /// Insert `pass` after the pass named `after`, or append it when no pass
+    /// has that name.
+    #[must_use]
+    pub fn with_pass_after(mut self, after: &str, pass: impl AnalysisPass + 'static) -> Self {
+        let index = self
+            .position(after)
+            .map_or(self.passes.len(), |index| index + 1);
+        self.passes.insert(index, Box::new(pass));
+        self
+    }
+
+    /// Remove every pass named `name`.
+    #[must_use]
+    pub fn without_pass(mut self, name: &str) -> Self {
+        self.passes.retain(|pass| pass.name() != name);
+        self
+    }
+
+    pub fn pass_names(&self) -> Vec<&str> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    pub fn run(&self, explain: Explain) -> Explain {
+        let mut explain = ExplainGraph::from(explain);
+        for pass in &self.passes {
+            pass.run_graph(&mut explain);
+        }
+        explain.into_explain()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.passes.iter().position(|pass| pass.name() == name)
+    }
+}
+
+impl Default for Pipeline {
+    /// The built-in passes in dependency order.
+    fn default() -> Self {
+        Self::empty()
+            .with_pass(ExpressionPass)
+            .with_pass(PlannerEstimatePass)
+            .with_pass(ActualsPass)
+            .with_pass(MaximumsPass)
+            .with_pass(EstimateErrorPass)
+            .with_pass(OutlierPass)
+            .with_pass(FingerprintPass)
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("passes", &self.pass_names())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::parser::parse_explain_document;
+    use crate::render::{render_explain, RenderOptions};
+    use crate::structure::data::annotations::Annotation;
+    use crate::structure::data::plan::Plan;
+    use crate::structure::visit::{walk_mut, NodeContext, PlanVisitorMut, Walk};
+
+    const PAYLOAD: &str = r#"[{"Plan":{"Node Type":"Hash Join","Total Cost":10.0,"Plan Rows":10,"Actual Total Time":5.0,"Actual Rows":10,"Actual Loops":1,"Plans":[{"Node Type":"Seq Scan","Relation Name":"t","Total Cost":4.0,"Plan Rows":5,"Actual Total Time":4.0,"Actual Rows":5,"Actual Loops":1}]},"Execution Time":5.0}]"#;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct SharedScan {
+        relation: String,
+    }
+
+    impl Annotation for SharedScan {
+        const KEY: &'static str = "acme.shared_scan";
+    }
+
+    /// Flags scans that read a named relation.
+    struct SharedScanPass;
+
+    impl PlanVisitorMut for SharedScanPass {
+        fn pre(&mut self, plan: &mut Plan, _ctx: &NodeContext<'_>) -> Walk {
+            if !plan.identity.relation_name.is_empty() {
+                let annotation = SharedScan {
+                    relation: plan.identity.relation_name.clone(),
+                };
+                plan.annotations.insert(&annotation).expect("annotate");
+            }
+            Walk::Continue
+        }
+    }
+
+    impl AnalysisPass for SharedScanPass {
+        fn name(&self) -> &'static str {
+            "acme.shared_scan"
+        }
+
+        fn run(&self, explain: &mut Explain) {
+            walk_mut(&mut explain.plan, &mut SharedScanPass);
+        }
+    }
+
+    fn explain() -> Explain {
+        parse_explain_document(PAYLOAD).expect("parse")
+    }
+
+    #[test]
+    fn default_pipeline_lists_built_in_passes_in_order() {
+        assert_eq!(
+            Pipeline::default().pass_names(),
+            [
+                "expressions",
+                "planner_estimate",
+                "actuals",
+                "maximums",
+                "estimate_errors",
+                "outliers",
+                "fingerprint"
+            ]
+        );
+    }
+
+    #[test]
+    fn builders_position_and_remove_passes() {
+        let pipeline = Pipeline::default()
+            .without_pass("outliers")
+            .with_pass_before("actuals", SharedScanPass)
+            .with_pass_after("missing", SharedScanPass);
+        assert_eq!(
+            pipeline.pass_names(),
+            [
+                "expressions",
+                "planner_estimate",
+                "acme.shared_scan",
+                "actuals",
+                "maximums",
+                "estimate_errors",
+                "fingerprint",
+                "acme.shared_scan"
+            ]
+        );
+    }
+
+    #[test]
+    fn removing_a_pass_skips_its_effects() {
+        let processed = Pipeline::default().without_pass("outliers").run(explain());
+        assert!(processed.max_cost > 0.0);
+        assert!(!processed.plan.analysis_flags.costliest);
+        assert!(!processed.plan.analysis_flags.slowest);
+    }
+
+    #[test]
+    fn custom_annotations_reach_json_and_pretty_output() {
+        let processed = Pipeline::default().with_pass(SharedScanPass).run(explain());
+        let scan = &processed.plan.plans[0];
+        assert_eq!(
+            scan.annotations.get::<SharedScan>(),
+            Some(SharedScan {
+                relation: "t".into()
+            })
+        );
+        assert!(processed.plan.annotations.is_empty());
+
+        let json = serde_json::to_value(&processed).expect("serialize");
+        assert_eq!(
+            json["plan"]["plans"][0]["annotations"]["acme.shared_scan"]["relation"],
+            "t"
+        );
+        assert!(json["plan"].get("annotations").is_none());
+
+        let rendered = render_explain(&processed, RenderOptions::new(80));
+        assert!(rendered.contains("○ acme.shared_scan: {\"relation\":\"t\"}"));
+    }
+}
@@ -0,0 +1,189 @@
+//! Planner settings reported by `EXPLAIN (SETTINGS)`.
+//!
+//! PostgreSQL lists only the settings that differ from the server defaults,
+//! which makes the block the first thing to compare when one environment
+//! plans a query differently from another. [`distorting_settings`] flags the
+//! ones that commonly skew plans, such as an `enable_seqscan = off` left over
+//! from a debugging session; [`setting_changes`] lists what differs between
+//! two plans.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// Value the planner uses for `seq_page_cost` when the plan does not list it.
+const DEFAULT_SEQ_PAGE_COST: f64 = 1.0;
+
+/// A non-default setting that commonly skews plans.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct SettingWarning {
+    pub name: String,
+    pub value: String,
+    /// How the setting skews plans.
+    pub reason: String,
+}
+
+impl fmt::Display for SettingWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}: {}", self.name, self.value, self.reason)
+    }
+}
+
+/// Settings in `settings` that commonly skew plans, by name.
+pub fn distorting_settings(settings: &BTreeMap<String, String>) -> Vec<SettingWarning> {
+    settings
+        .iter()
+        .filter_map(|(name, value)| {
+            let reason = distortion(name, value, settings)?;
+            Some(SettingWarning {
+                name: name.clone(),
+                value: value.clone(),
+                reason,
+            })
+        })
+        .collect()
+}
+
+fn distortion(name: &str, value: &str, settings: &BTreeMap<String, String>) -> Option<String> {
+    let reason = match name {
+        _ if name.starts_with("enable_") && value == "off" => {
+            "the planner avoids this plan type even where it is cheapest; often left over from debugging"
+        }
+        "join_collapse_limit" | "from_collapse_limit" if value == "1" => {
+            "joins run in the order the query lists them"
+        }
+        "max_parallel_workers_per_gather" if value == "0" => "parallel plans are disabled",
+        "plan_cache_mode" if value == "force_generic_plan" => {
+            "prepared statements are planned without their parameter values"
+        }
+        "random_page_cost" => {
+            let seq_page_cost = settings
+                .get("seq_page_cost")
+                .map_or(Some(DEFAULT_SEQ_PAGE_COST), |cost| cost.parse().ok())?;
+            if value.parse::<f64>().ok()? >= seq_page_cost {
+                return None;
+            }
+            return Some(format!(
+                "random reads cost less than sequential ones (seq_page_cost = {seq_page_cost}), so index scans win too easily"
+            ));
+        }
+        _ => return None,
+    };
+    Some(reason.to_string())
+}
+
+/// A setting whose value differs between two plans. `None` means the plan
+/// does not list it, so the server default applied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SettingChange {
+    pub name: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl fmt::Display for SettingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = |value: &Option<String>| value.as_deref().unwrap_or("default").to_string();
+        write!(
+            f,
+            "{}: {} → {}",
+            self.name,
+            side(&self.before),
+            side(&self.after)
+        )
+    }
+}
+
+/// Settings that differ from `previous` to `current`, by name.
+pub fn setting_changes(
+    previous: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+) -> Vec<SettingChange> {
+    let mut names: Vec<&String> = previous.keys().chain(current.keys()).collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let before = previous.get(name);
+            let after = current.get(name);
+            (before != after).then(|| SettingChange {
+                name: name.clone(),
+                before: before.cloned(),
+                after: after.cloned(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn flags_settings_that_skew_plans() {
+        let warnings = distorting_settings(&settings(&[
+            ("enable_seqscan", "off"),
+            ("enable_partitionwise_join", "on"),
+            ("join_collapse_limit", "1"),
+            ("max_parallel_workers_per_gather", "0"),
+            ("random_page_cost", "0.5"),
+            ("work_mem", "64MB"),
+        ]));
+
+        let names: Vec<&str> = warnings.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "enable_seqscan",
+                "join_collapse_limit",
+                "max_parallel_workers_per_gather",
+                "random_page_cost"
+            ]
+        );
+        assert!(warnings[0]
+            .to_string()
+            .starts_with("enable_seqscan = off: the planner avoids"));
+    }
+
+    #[test]
+    fn random_page_cost_is_judged_against_seq_page_cost() {
+        assert!(distorting_settings(&settings(&[("random_page_cost", "1.1")])).is_empty());
+        assert_eq!(
+            distorting_settings(&settings(&[
+                ("random_page_cost", "1.1"),
+                ("seq_page_cost", "2"),
+            ]))
+            .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn lists_changed_added_and_removed_settings() {
+        let changes = setting_changes(
+            &settings(&[("enable_seqscan", "off"), ("work_mem", "4MB")]),
+            &settings(&[("random_page_cost", "1.1"), ("work_mem", "64MB")]),
+        );
+
+        let text: Vec<String> = changes.iter().map(SettingChange::to_string).collect();
+        assert_eq!(
+            text,
+            [
+                "enable_seqscan: off → default",
+                "random_page_cost: default → 1.1",
+                "work_mem: 4MB → 64MB"
+            ]
+        );
+        assert!(setting_changes(&settings(&[("a", "1")]), &settings(&[("a", "1")])).is_empty());
+    }
+}
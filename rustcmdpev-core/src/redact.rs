@@ -0,0 +1,801 @@
+//! Remove customer data from a plan before sharing it.
+//!
+//! [`RedactPass`] replaces the literals in every expression a node carries
+//! (`Filter`, `Index Cond`, `Hash Cond`, `Output`, sort keys, and the like)
+//! with `$n` placeholders, numbered after any parameters the plan already
+//! uses. Node fields outside the typed model are treated as expressions
+//! unless they are known to hold only PostgreSQL's own vocabulary, such as
+//! `Operation` or `Sort Space Type`, so keys added by newer servers are
+//! redacted rather than leaked. Equal literals share a placeholder, so a
+//! constant that appears in both an index condition and a filter still reads
+//! as one value, and casts are kept: `'shipped'::text` becomes `$1::text`.
+//!
+//! With [`RedactOptions::with_identifiers`], schema, relation, index
+//! (including conflict arbiter indexes), alias, CTE, and column names are
+//! also replaced by `n_` and eight hex digits of a keyed hash, as are the
+//! schema and tablespace names in `search_path` and similar settings. A name
+//! maps to the same token everywhere, and in every plan redacted with the
+//! same salt, so join conditions still line up with the scans they join. SQL
+//! keywords, function names, and type names are kept.
+//! `Query Text` cannot be tokenized reliably without a SQL parser, so it is
+//! dropped in that mode; otherwise only its literals are replaced.
+//!
+//! The result is still a plan: [`crate::lossless::to_postgres_json`] writes
+//! it as EXPLAIN JSON that parses and renders like the original.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use serde_json::Value;
+
+use crate::fingerprint::Fnv;
+use crate::pipeline::AnalysisPass;
+use crate::structure::data::explain::Explain;
+use crate::structure::data::plan::{Extras, Plan};
+use crate::structure::graph::ExplainGraph;
+
+/// Node keys outside the typed model whose string values are PostgreSQL's
+/// own vocabulary and are kept. Every other string is an expression.
+const VOCABULARY_KEYS: &[&str] = &[
+    "Cache Mode",
+    "Command",
+    "Conflict Resolution",
+    "Custom Plan Provider",
+    "Function Name",
+    "Operation",
+    "Partial Mode",
+    "Sampling Method",
+    "Sort Method",
+    "Sort Space Type",
+    "Storage",
+    "Table Function Name",
+];
+
+/// Node keys outside the typed model whose values are names, hashed only
+/// with identifiers. The scan keys appear in `Target Tables` entries.
+const NAME_KEYS: &[&str] = &[
+    "Conflict Arbiter Indexes",
+    "Tuplestore Name",
+    "Schema",
+    "Relation Name",
+    "Alias",
+    "Index Name",
+    "CTE Name",
+];
+
+/// Keys of `Triggers` entries that name database objects.
+const TRIGGER_NAME_KEYS: &[&str] = &["Trigger Name", "Constraint Name", "Relation"];
+
+/// `EXPLAIN (SETTINGS)` entries whose values list schema or tablespace
+/// names, hashed with identifiers.
+const NAME_LIST_SETTINGS: &[&str] = &["search_path", "temp_tablespaces", "default_tablespace"];
+
+/// The `search_path` entry for the current user's schema, kept as is.
+const CURRENT_USER_SCHEMA: &str = "$user";
+
+pub(crate) const QUERY_TEXT_KEY: &str = "Query Text";
+
+/// Words PostgreSQL prints unquoted in expressions that are not names: SQL
+/// keywords and the trailing words of multi-word type names.
+const KEYWORDS: &[&str] = &[
+    "all",
+    "and",
+    "any",
+    "array",
+    "as",
+    "asc",
+    "at",
+    "between",
+    "case",
+    "collate",
+    "current_date",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "cte",
+    "default",
+    "desc",
+    "distinct",
+    "else",
+    "end",
+    "exists",
+    "false",
+    "first",
+    "from",
+    "hashed",
+    "ilike",
+    "in",
+    "initplan",
+    "is",
+    "last",
+    "like",
+    "localtime",
+    "localtimestamp",
+    "not",
+    "null",
+    "nulls",
+    "or",
+    "precision",
+    "returns",
+    "row",
+    "similar",
+    "some",
+    "subplan",
+    "then",
+    "time",
+    "to",
+    "true",
+    "using",
+    "varying",
+    "when",
+    "with",
+    "without",
+    "zone",
+];
+
+/// What [`RedactPass`] replaces besides literals.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedactOptions {
+    identifiers: bool,
+    salt: String,
+}
+
+impl RedactOptions {
+    /// Replace literals only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also replace names with keyed hashes.
+    #[must_use]
+    pub fn with_identifiers(mut self, identifiers: bool) -> Self {
+        self.identifiers = identifiers;
+        self
+    }
+
+    /// Key mixed into identifier hashes. Without one, a reader can confirm a
+    /// guessed name by hashing it.
+    #[must_use]
+    pub fn with_salt(mut self, salt: impl Into<String>) -> Self {
+        self.salt = salt.into();
+        self
+    }
+}
+
+/// Replaces literals, and optionally names, throughout a plan; see the
+/// module docs. Runs before the built-in passes so everything derived from
+/// the plan (index suggestions, fingerprints) uses the redacted text.
+#[derive(Debug, Clone, Default)]
+pub struct RedactPass {
+    options: RedactOptions,
+}
+
+impl RedactPass {
+    pub fn new(options: RedactOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl AnalysisPass for RedactPass {
+    fn name(&self) -> &'static str {
+        "redact"
+    }
+
+    fn run(&self, explain: &mut Explain) {
+        ExplainGraph::apply(explain, |explain| self.run_graph(explain));
+    }
+
+    fn run_graph(&self, explain: &mut ExplainGraph) {
+        let query_text = explain.explain.extras.get(QUERY_TEXT_KEY);
+        let last_param = explain
+            .graph
+            .plans()
+            .flat_map(plan_texts)
+            .chain(query_text.into_iter().flat_map(value_texts))
+            .map(max_param)
+            .max()
+            .unwrap_or(0);
+
+        let mut redactor = Redactor::new(&self.options, last_param + 1);
+        for plan in explain.graph.plans_mut() {
+            redactor.plan(plan);
+        }
+        redactor.document(&mut explain.explain.extras);
+        redactor.settings(&mut explain.explain.settings);
+    }
+}
+
+/// Redact `explain` in place; see the module docs.
+pub fn redact(explain: &mut Explain, options: &RedactOptions) {
+    RedactPass::new(options.clone()).run(explain);
+}
+
+/// `text` with its literals replaced by `$n` placeholders, as `--redact`
+/// rewrites `Query Text`.
+pub(crate) fn redact_literals(text: &str) -> String {
+    Redactor::new(&RedactOptions::new(), max_param(text) + 1).expression(text)
+}
+
+struct Redactor<'a> {
+    options: &'a RedactOptions,
+    next_param: usize,
+    placeholders: HashMap<String, usize>,
+}
+
+impl<'a> Redactor<'a> {
+    fn new(options: &'a RedactOptions, first_param: usize) -> Self {
+        Self {
+            options,
+            next_param: first_param,
+            placeholders: HashMap::new(),
+        }
+    }
+
+    fn plan(&mut self, plan: &mut Plan) {
+        let predicates = &mut plan.predicates;
+        let texts = [
+            &mut predicates.filter,
+            &mut predicates.index_condition,
+            &mut predicates.hash_condition,
+            &mut predicates.merge_condition,
+            &mut predicates.join_filter,
+        ];
+        for text in texts
+            .into_iter()
+            .chain(&mut predicates.group_key)
+            .chain(&mut predicates.output)
+        {
+            *text = self.expression(text);
+        }
+        for (key, value) in &mut plan.extras {
+            self.extra(key, value);
+        }
+
+        if self.options.identifiers {
+            let identity = &mut plan.identity;
+            for name in [
+                &mut identity.schema,
+                &mut identity.relation_name,
+                &mut identity.index_name,
+                &mut identity.alias,
+                &mut identity.cte_name,
+            ] {
+                if !name.is_empty() {
+                    *name = self.identifier(name);
+                }
+            }
+        }
+    }
+
+    /// Document-level fields: `Query Text` and trigger names.
+    fn document(&mut self, extras: &mut Extras) {
+        if !self.options.identifiers {
+            if let Some(query) = extras.get_mut(QUERY_TEXT_KEY) {
+                self.expression_value(query);
+            }
+            return;
+        }
+        extras.shift_remove(QUERY_TEXT_KEY);
+        let Some(Value::Array(triggers)) = extras.get_mut("Triggers") else {
+            return;
+        };
+        for trigger in triggers.iter_mut().filter_map(Value::as_object_mut) {
+            for key in TRIGGER_NAME_KEYS {
+                if let Some(Value::String(name)) = trigger.get_mut(*key) {
+                    *name = self.identifier(name);
+                }
+            }
+        }
+    }
+
+    /// Schema and tablespace names in `EXPLAIN (SETTINGS)` values, such as
+    /// `search_path = '"acme", public'`. Other settings hold numbers, sizes,
+    /// and PostgreSQL's own vocabulary, and are kept.
+    fn settings(&self, settings: &mut BTreeMap<String, String>) {
+        if !self.options.identifiers {
+            return;
+        }
+        for (name, value) in settings.iter_mut() {
+            if !NAME_LIST_SETTINGS.contains(&name.as_str()) {
+                continue;
+            }
+            let names: Vec<String> = value
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| {
+                    let unquoted = entry
+                        .strip_prefix('"')
+                        .and_then(|inner| inner.strip_suffix('"'))
+                        .map(|inner| inner.replace("\"\"", "\""));
+                    match unquoted.as_deref().unwrap_or(entry) {
+                        CURRENT_USER_SCHEMA => entry.to_string(),
+                        name => self.identifier(name),
+                    }
+                })
+                .collect();
+            *value = names.join(", ");
+        }
+    }
+
+    /// Rewrite the node field `key` outside the typed model, descending into
+    /// arrays and into objects such as `Target Tables` entries.
+    fn extra(&mut self, key: &str, value: &mut Value) {
+        let name = NAME_KEYS.contains(&key);
+        if VOCABULARY_KEYS.contains(&key) || (name && !self.options.identifiers) {
+            return;
+        }
+        match value {
+            Value::String(text) if name => *text = self.identifier(text),
+            Value::String(text) => *text = self.expression(text),
+            Value::Array(items) => {
+                for item in items {
+                    self.extra(key, item);
+                }
+            }
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    self.extra(key, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn expression_value(&mut self, value: &mut Value) {
+        match value {
+            Value::String(text) => *text = self.expression(text),
+            Value::Array(items) => {
+                for item in items {
+                    self.expression_value(item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Rewrite one expression as PostgreSQL deparses it.
+    fn expression(&mut self, text: &str) -> String {
+        let bytes = text.as_bytes();
+        let mut out = String::with_capacity(text.len());
+        // A word after `::` names a type; a number after `SubPlan` or
+        // `InitPlan` refers to it.
+        let mut after_cast = false;
+        let mut after_subplan = false;
+        let mut i = 0;
+        while i < bytes.len() {
+            let start = i;
+            let byte = bytes[i];
+            if byte == b'\'' {
+                i = quoted_end(bytes, i, b'\'').0;
+                self.placeholder(&text[start..i], &mut out);
+            } else if byte == b'"' {
+                let (end, closed) = quoted_end(bytes, i, b'"');
+                i = end;
+                if self.options.identifiers && !after_cast {
+                    let inner = &text[start + 1..if closed { end - 1 } else { end }];
+                    out.push_str(&self.identifier(&inner.replace("\"\"", "\"")));
+                } else {
+                    out.push_str(&text[start..end]);
+                }
+            } else if byte == b'$' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit) {
+                i = digits_end(bytes, i + 1);
+                out.push_str(&text[start..i]);
+            } else if byte.is_ascii_digit() {
+                i = number_end(bytes, i);
+                if after_subplan {
+                    out.push_str(&text[start..i]);
+                } else {
+                    self.placeholder(&text[start..i], &mut out);
+                }
+            } else if is_word_start(byte) {
+                i = word_end(bytes, i);
+                let word = &text[start..i];
+                if bytes.get(i) == Some(&b'\'') && matches!(word, "E" | "e" | "B" | "b" | "X" | "x")
+                {
+                    i = quoted_end(bytes, i, b'\'').0;
+                    self.placeholder(&text[start..i], &mut out);
+                } else {
+                    let lower = word.to_ascii_lowercase();
+                    let is_call =
+                        bytes[i..].iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'(');
+                    if self.options.identifiers
+                        && !after_cast
+                        && !is_call
+                        && !KEYWORDS.contains(&lower.as_str())
+                    {
+                        out.push_str(&self.identifier(word));
+                    } else {
+                        out.push_str(word);
+                    }
+                    after_cast = false;
+                    after_subplan = lower == "subplan" || lower == "initplan";
+                    continue;
+                }
+            } else if byte.is_ascii_whitespace() {
+                i += 1;
+                out.push(char::from(byte));
+                continue;
+            } else if bytes[i..].starts_with(b"::") {
+                i += 2;
+                out.push_str("::");
+                after_cast = true;
+                after_subplan = false;
+                continue;
+            } else {
+                i += 1;
+                out.push(char::from(byte));
+            }
+            after_cast = false;
+            after_subplan = false;
+        }
+        out
+    }
+
+    fn placeholder(&mut self, literal: &str, out: &mut String) {
+        let next_param = &mut self.next_param;
+        let param = *self
+            .placeholders
+            .entry(literal.to_string())
+            .or_insert_with(|| {
+                *next_param += 1;
+                *next_param - 1
+            });
+        let _ = write!(out, "${param}");
+    }
+
+    fn identifier(&self, name: &str) -> String {
+        let mut hash = Fnv::new();
+        hash.write(self.options.salt.as_bytes());
+        hash.write(&[0]);
+        hash.write(name.as_bytes());
+        // FNV barely moves the high bits for short names, so mix them with
+        // MurmurHash3's finalizer before keeping 32 of them.
+        let mut hash = hash.finish();
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        hash ^= hash >> 33;
+        format!("n_{:08x}", hash >> 32)
+    }
+}
+
+/// Every text field of `plan` that [`Redactor::plan`] rewrites.
+fn plan_texts(plan: &Plan) -> impl Iterator<Item = &str> {
+    let predicates = &plan.predicates;
+    [
+        &predicates.filter,
+        &predicates.index_condition,
+        &predicates.hash_condition,
+        &predicates.merge_condition,
+        &predicates.join_filter,
+    ]
+    .into_iter()
+    .chain(&predicates.group_key)
+    .chain(&predicates.output)
+    .map(String::as_str)
+    .chain(
+        plan.extras
+            .iter()
+            .flat_map(|(key, value)| extra_texts(key, value)),
+    )
+}
+
+/// The strings under the node field `key` that [`Redactor::extra`] rewrites
+/// as expressions.
+fn extra_texts<'a>(key: &str, value: &'a Value) -> Vec<&'a str> {
+    if VOCABULARY_KEYS.contains(&key) || NAME_KEYS.contains(&key) {
+        return Vec::new();
+    }
+    match value {
+        Value::String(text) => vec![text.as_str()],
+        Value::Array(items) => items
+            .iter()
+            .flat_map(|item| extra_texts(key, item))
+            .collect(),
+        Value::Object(fields) => fields
+            .iter()
+            .flat_map(|(key, value)| extra_texts(key, value))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn value_texts(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(text) => vec![text.as_str()],
+        Value::Array(items) => items.iter().flat_map(value_texts).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Highest `$n` parameter referenced in `text`, or 0.
+fn max_param(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut max = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_word_start(bytes[i]) {
+            i = word_end(bytes, i);
+        } else if bytes[i] == b'$' {
+            let end = digits_end(bytes, i + 1);
+            max = max.max(text[i + 1..end].parse().unwrap_or(0));
+            i = end.max(i + 1);
+        } else {
+            i += 1;
+        }
+    }
+    max
+}
+
+fn is_word_start(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || byte == b'_' || !byte.is_ascii()
+}
+
+fn word_end(bytes: &[u8], start: usize) -> usize {
+    start
+        + bytes[start..]
+            .iter()
+            .take_while(|&&byte| is_word_start(byte) || byte.is_ascii_digit() || byte == b'$')
+            .count()
+}
+
+fn digits_end(bytes: &[u8], start: usize) -> usize {
+    start
+        + bytes[start..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+}
+
+/// End of a numeric constant such as `42`, `1.5`, or `2e-3`.
+fn number_end(bytes: &[u8], start: usize) -> usize {
+    let mut end = digits_end(bytes, start);
+    if bytes.get(end) == Some(&b'.') {
+        end = digits_end(bytes, end + 1);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+        let exponent = digits_end(bytes, end + 1 + sign);
+        if exponent > end + 1 + sign {
+            end = exponent;
+        }
+    }
+    end
+}
+
+/// End of the string or quoted identifier opened at `start`, where a doubled
+/// quote is an escaped one, and whether it was closed.
+fn quoted_end(bytes: &[u8], start: usize, quote: u8) -> (usize, bool) {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == quote {
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+                continue;
+            }
+            return (i + 1, true);
+        }
+        i += 1;
+    }
+    (bytes.len(), false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lossless::to_postgres_json;
+    use crate::parser::parse_explain_document;
+    use crate::pipeline::Pipeline;
+    use crate::render::{render_explain, RenderOptions};
+
+    const PAYLOAD: &str = r#"[{"Plan":{"Node Type":"Hash Join","Join Type":"Inner","Hash Cond":"(o.customer_id = c.id)","Total Cost":10.0,"Actual Total Time":5.0,"Actual Rows":1,"Actual Loops":1,"Plans":[{"Node Type":"Seq Scan","Relation Name":"orders","Schema":"public","Alias":"o","Filter":"((status = 'shipped'::text) AND (total > 100.50))","Rows Removed by Filter":90,"Output":["o.id","o.customer_id"],"Total Cost":4.0,"Actual Total Time":4.0,"Actual Rows":10,"Actual Loops":1},{"Node Type":"Index Scan","Relation Name":"customers","Schema":"public","Alias":"c","Index Name":"customers_email_idx","Index Cond":"(lower((c.email)::text) = 'ann@example.com'::character varying)","Sort Key":["c.\"Signup Date\" DESC"],"Total Cost":1.0,"Actual Total Time":0.5,"Actual Rows":1,"Actual Loops":1}]},"Query Text":"SELECT * FROM orders o JOIN customers c ON o.customer_id = c.id WHERE status = 'shipped' AND total > 100.50","Triggers":[{"Trigger Name":"orders_audit","Relation":"orders","Time":0.1,"Calls":1}],"Execution Time":5.0}]"#;
+
+    fn redacted(options: RedactOptions) -> Explain {
+        let explain = parse_explain_document(PAYLOAD).expect("parse");
+        Pipeline::default()
+            .with_pass_before("expressions", RedactPass::new(options))
+            .run(explain)
+    }
+
+    fn expression(text: &str, options: &RedactOptions) -> String {
+        Redactor::new(options, max_param(text) + 1).expression(text)
+    }
+
+    #[test]
+    fn literals_become_shared_placeholders_after_existing_params() {
+        let options = RedactOptions::new();
+        assert_eq!(
+            expression(
+                "((status = 'shipped'::text) AND (total > 100.50) AND (id = $2) AND (kind = 'shipped'::text))",
+                &options
+            ),
+            "((status = $3::text) AND (total > $4) AND (id = $2) AND (kind = $3::text))"
+        );
+        assert_eq!(
+            expression(
+                "((note ~~ E'it''s\\%'::text) AND (t1.col2 = ANY ('{1,2}'::integer[])) AND (score > 1.5e-3))",
+                &options
+            ),
+            "((note ~~ $1::text) AND (t1.col2 = ANY ($2::integer[])) AND (score > $3))"
+        );
+        assert_eq!(
+            expression("(NOT (hashed SubPlan 1))", &options),
+            "(NOT (hashed SubPlan 1))"
+        );
+    }
+
+    #[test]
+    fn identifiers_hash_consistently_and_keep_keywords_functions_and_types() {
+        let options = RedactOptions::new().with_identifiers(true);
+        let rewritten = expression(
+            "((lower((c.email)::text) = 'a'::character varying) AND (c.\"Signup Date\" IS NOT NULL))",
+            &options,
+        );
+        let redactor = Redactor::new(&options, 1);
+        let c = redactor.identifier("c");
+        let email = redactor.identifier("email");
+        let signup = redactor.identifier("Signup Date");
+        assert_eq!(
+            rewritten,
+            format!("((lower(({c}.{email})::text) = $1::character varying) AND ({c}.{signup} IS NOT NULL))")
+        );
+
+        let salted = RedactOptions::new().with_identifiers(true).with_salt("k");
+        assert_ne!(Redactor::new(&salted, 1).identifier("c"), c);
+        assert_eq!(Redactor::new(&options, 1).identifier("c"), c);
+    }
+
+    #[test]
+    fn pass_redacts_predicates_extras_and_identity() {
+        let explain = redacted(RedactOptions::new());
+        let orders = &explain.plan.plans[0];
+        let customers = &explain.plan.plans[1];
+        assert_eq!(
+            orders.predicates.filter,
+            "((status = $1::text) AND (total > $2))"
+        );
+        assert_eq!(orders.identity.relation_name, "orders");
+        assert_eq!(
+            customers.predicates.index_condition,
+            "(lower((c.email)::text) = $3::character varying)"
+        );
+        assert_eq!(
+            explain.extras[QUERY_TEXT_KEY],
+            "SELECT * FROM orders o JOIN customers c ON o.customer_id = c.id WHERE status = $1 AND total > $2"
+        );
+
+        let explain = redacted(RedactOptions::new().with_identifiers(true));
+        let orders = &explain.plan.plans[0];
+        let alias = orders.identity.alias.clone();
+        assert!(alias.starts_with("n_"));
+        assert!(explain
+            .plan
+            .predicates
+            .hash_condition
+            .starts_with(&format!("({alias}.")));
+        assert_ne!(orders.identity.relation_name, "orders");
+        assert_ne!(orders.identity.schema, "public");
+        assert!(!explain.plan.plans[1].identity.index_name.contains("email"));
+        assert!(!explain.plan.plans[1].extras["Sort Key"][0]
+            .as_str()
+            .expect("sort key")
+            .contains("Signup"));
+        assert!(!explain.extras.contains_key(QUERY_TEXT_KEY));
+        assert_eq!(
+            explain.extras["Triggers"][0]["Relation"],
+            orders.identity.relation_name.as_str()
+        );
+    }
+
+    const UPSERT: &str = r#"[{"Plan":{"Node Type":"ModifyTable","Operation":"Insert","Relation Name":"shops","Alias":"shops","Conflict Resolution":"UPDATE","Conflict Arbiter Indexes":["shops_owner_key"],"Conflict Filter":"(shops.owner <> 'acme'::text)","Target Tables":[{"Relation Name":"shops_east","Schema":"retail","Alias":"shops_1"}],"Plans":[{"Node Type":"Index Scan","Relation Name":"shops","Alias":"shops","Index Name":"shops_location_idx","Order By":"(shops.location <-> '(40.7128,-74.0060)'::point)","Subplan Name":"CTE nearby","Partial Mode":"Simple"}]}}]"#;
+
+    fn redacted_upsert(options: RedactOptions) -> Explain {
+        let explain = parse_explain_document(UPSERT).expect("parse");
+        Pipeline::default()
+            .with_pass_before("expressions", RedactPass::new(options))
+            .run(explain)
+    }
+
+    #[test]
+    fn conflict_filter_is_redacted() {
+        let explain = redacted_upsert(RedactOptions::new());
+        assert_eq!(
+            explain.plan.extras["Conflict Filter"],
+            "(shops.owner <> $1::text)"
+        );
+    }
+
+    #[test]
+    fn knn_order_by_is_redacted() {
+        let explain = redacted_upsert(RedactOptions::new());
+        assert_eq!(
+            explain.plan.plans[0].extras["Order By"],
+            "(shops.location <-> $2::point)"
+        );
+    }
+
+    #[test]
+    fn conflict_arbiter_indexes_are_hashed_with_identifiers() {
+        let explain = redacted_upsert(RedactOptions::new());
+        assert_eq!(
+            explain.plan.extras["Conflict Arbiter Indexes"][0],
+            "shops_owner_key"
+        );
+
+        let options = RedactOptions::new().with_identifiers(true);
+        let explain = redacted_upsert(options.clone());
+        assert_eq!(
+            explain.plan.extras["Conflict Arbiter Indexes"][0],
+            Redactor::new(&options, 1)
+                .identifier("shops_owner_key")
+                .as_str()
+        );
+    }
+
+    #[test]
+    fn nested_names_are_hashed_and_vocabulary_is_kept() {
+        let options = RedactOptions::new().with_identifiers(true);
+        let explain = redacted_upsert(options.clone());
+        let redactor = Redactor::new(&options, 1);
+        let target = &explain.plan.extras["Target Tables"][0];
+        assert_eq!(
+            target["Relation Name"],
+            redactor.identifier("shops_east").as_str()
+        );
+        assert_eq!(target["Schema"], redactor.identifier("retail").as_str());
+        let scan = &explain.plan.plans[0];
+        assert_eq!(
+            scan.extras["Subplan Name"],
+            format!("CTE {}", redactor.identifier("nearby")).as_str()
+        );
+        assert_eq!(scan.extras["Partial Mode"], "Simple");
+        assert_eq!(explain.plan.extras["Operation"], "Insert");
+        assert_eq!(explain.plan.extras["Conflict Resolution"], "UPDATE");
+    }
+
+    #[test]
+    fn search_path_names_are_hashed_with_identifiers() {
+        let payload = r#"[{"Plan":{"Node Type":"Seq Scan","Relation Name":"orders","Schema":"acme_corp","Alias":"orders"},"Settings":{"search_path":"\"$user\", \"acme_corp\", public","work_mem":"64MB","enable_seqscan":"off"}}]"#;
+        let run = |options: RedactOptions| {
+            Pipeline::default()
+                .with_pass_before("expressions", RedactPass::new(options))
+                .run(parse_explain_document(payload).expect("parse"))
+        };
+
+        let kept = run(RedactOptions::new());
+        assert_eq!(
+            kept.settings["search_path"],
+            "\"$user\", \"acme_corp\", public"
+        );
+
+        let options = RedactOptions::new().with_identifiers(true);
+        let explain = run(options.clone());
+        let redactor = Redactor::new(&options, 1);
+        let schema = redactor.identifier("acme_corp");
+        assert_eq!(explain.plan.identity.schema, schema);
+        assert_eq!(
+            explain.settings["search_path"],
+            format!("\"$user\", {schema}, {}", redactor.identifier("public"))
+        );
+        assert_eq!(explain.settings["work_mem"], "64MB");
+        assert_eq!(explain.settings["enable_seqscan"], "off");
+    }
+
+    #[test]
+    fn redacted_document_parses_and_renders() {
+        let explain = redacted(RedactOptions::new().with_identifiers(true));
+        let document = to_postgres_json(&explain).to_string();
+        assert!(!document.contains("shipped"));
+        assert!(!document.contains("customers"));
+
+        let reparsed = crate::parse_and_process(&document).expect("reparse");
+        assert_eq!(
+            reparsed.plan.plans[0].predicates.filter,
+            explain.plan.plans[0].predicates.filter
+        );
+        let rendered = render_explain(&reparsed, RenderOptions::new(80));
+        assert!(rendered.contains(&explain.plan.plans[0].identity.relation_name));
+    }
+}
@@ -1,16 +1,25 @@
+use std::collections::BTreeMap;
 use std::fmt::Write;
 
-use crate::constants::DESCRIPTIONS;
+use crate::cast_check::{find_cast_issues, CastIssue};
+use crate::constants::DEFAULT_BLOCK_SIZE;
 use crate::display::colors::{themed_format, Theme};
 use crate::display::format::{
-    duration_to_string_themed, format_details, format_percent, format_tags,
+    duration_to_string_themed, format_bytes, format_details, format_io_profile, format_percent,
+    format_tags,
 };
 use crate::display::tree::{
     styled_node_joint, styled_output_terminator, styled_prefix_continuation, TreeStyle,
 };
+use crate::index_advice::{suggest_indexes, IndexSuggestion};
+use crate::io_profile::IoProfile;
+use crate::planner_settings::distorting_settings;
+use crate::rollup::RelationReport;
 use crate::structure::data::explain::Explain;
 use crate::structure::data::plan::Plan;
-use crate::summary::PlanSummary;
+use crate::structure::visit::child_path;
+use crate::summary::{BufferTotals, PlanSummary};
+use crate::work_mem::{format_kb, recommend_work_mem, WorkMemAdvice};
 
 /// Render verbosity for a plan.
 ///
@@ -68,6 +77,16 @@ pub struct RenderOptions {
     pub mode: RenderMode,
     pub summary: SummaryStyle,
     pub tree_style: TreeStyle,
+    /// Server block size in bytes, used to turn block counts into throughput.
+    pub block_size: u64,
+    /// Append the per-relation and per-index rollup after the tree.
+    pub by_relation: bool,
+    /// Append heuristic index suggestions after the tree.
+    pub suggest_indexes: bool,
+    /// Append casts and function calls on columns that defeat indexes.
+    pub check_casts: bool,
+    /// Prefix each node with its `#id`; verbose mode also prints its path.
+    pub node_ids: bool,
 }
 
 impl RenderOptions {
@@ -78,6 +97,11 @@ impl RenderOptions {
             mode: RenderMode::default(),
             summary: SummaryStyle::default(),
             tree_style: TreeStyle::default(),
+            block_size: DEFAULT_BLOCK_SIZE,
+            by_relation: false,
+            suggest_indexes: false,
+            check_casts: false,
+            node_ids: false,
         }
     }
 
@@ -100,6 +124,31 @@ impl RenderOptions {
         self.tree_style = tree_style;
         self
     }
+
+    pub fn with_block_size(mut self, block_size: u64) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    pub fn with_by_relation(mut self, by_relation: bool) -> Self {
+        self.by_relation = by_relation;
+        self
+    }
+
+    pub fn with_suggest_indexes(mut self, suggest_indexes: bool) -> Self {
+        self.suggest_indexes = suggest_indexes;
+        self
+    }
+
+    pub fn with_check_casts(mut self, check_casts: bool) -> Self {
+        self.check_casts = check_casts;
+        self
+    }
+
+    pub fn with_node_ids(mut self, node_ids: bool) -> Self {
+        self.node_ids = node_ids;
+        self
+    }
 }
 
 impl Default for RenderOptions {
@@ -127,9 +176,17 @@ impl RenderContext<'_> {
 struct NodePosition {
     prefix: String,
     last_child: bool,
+    /// Address of the node, e.g. `Plan.Plans[0]`.
+    path: String,
 }
 
-fn write_summary_block(buffer: &mut String, summary: &PlanSummary, options: RenderOptions) {
+fn write_summary_block(
+    buffer: &mut String,
+    summary: &PlanSummary,
+    work_mem: Option<&WorkMemAdvice>,
+    settings: &BTreeMap<String, String>,
+    options: RenderOptions,
+) {
     let theme = options.theme;
     writeln!(buffer, "○ Total Cost {}", summary.total_cost).expect("write to string");
     writeln!(
@@ -153,60 +210,267 @@ fn write_summary_block(buffer: &mut String, summary: &PlanSummary, options: Rend
     writeln!(buffer, "○ Total Loops: {}", summary.total_loops).expect("write to string");
     writeln!(buffer, "○ Total Nodes: {}", summary.node_count).expect("write to string");
 
-    let buffers = &summary.buffers;
-    if !buffers.is_empty() {
+    write_buffer_totals(buffer, "Buffers", &summary.buffers);
+    write_buffer_totals(buffer, "Planning Buffers", &summary.planning_buffers);
+    if !summary.wal.is_empty() {
         writeln!(
             buffer,
-            "○ Buffers: shared hit={} read={} written={} dirtied={}",
-            buffers.shared_hit_blocks,
-            buffers.shared_read_blocks,
-            buffers.shared_written_blocks,
-            buffers.shared_dirtied_blocks,
+            "○ WAL: records={} fpi={} bytes={}",
+            summary.wal.records,
+            summary.wal.fpi,
+            format_bytes(summary.wal.bytes),
         )
         .expect("write to string");
-        if buffers.local_hit_blocks
-            + buffers.local_read_blocks
-            + buffers.local_written_blocks
-            + buffers.local_dirtied_blocks
-            > 0
-        {
+    }
+
+    if summary.total_io_read_time > 0.0 || summary.total_io_write_time > 0.0 {
+        writeln!(
+            buffer,
+            "○ I/O Time: read={} write={}",
+            duration_to_string_themed(summary.total_io_read_time, theme),
+            duration_to_string_themed(summary.total_io_write_time, theme),
+        )
+        .expect("write to string");
+    }
+
+    writeln!(buffer, "○ Cache: {}", format_io_profile(&summary.io)).expect("write to string");
+
+    if let Some(advice) = work_mem {
+        writeln!(
+            buffer,
+            "○ work_mem: ≥ {} per operation ({} spilling, up to {} across processes)",
+            themed_format(format_kb(advice.per_operation_kb), "warning", theme),
+            advice.operations.len(),
+            format_kb(advice.query_total_kb),
+        )
+        .expect("write to string");
+        for operation in &advice.operations {
             writeln!(
                 buffer,
-                "○ Buffers: local hit={} read={} written={} dirtied={}",
-                buffers.local_hit_blocks,
-                buffers.local_read_blocks,
-                buffers.local_written_blocks,
-                buffers.local_dirtied_blocks,
+                "  {} at {}: {} needs {} × {} process(es)",
+                operation.node_type,
+                operation.node_path,
+                operation.kind.label(),
+                format_kb(operation.required_kb),
+                operation.processes,
             )
             .expect("write to string");
         }
-        if buffers.temp_read_blocks + buffers.temp_written_blocks > 0 {
+    }
+
+    if !settings.is_empty() {
+        let listed: Vec<String> = settings
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+        writeln!(buffer, "○ Settings: {}", listed.join(", ")).expect("write to string");
+        for warning in distorting_settings(settings) {
             writeln!(
                 buffer,
-                "○ Buffers: temp read={} written={}",
-                buffers.temp_read_blocks, buffers.temp_written_blocks,
+                "  {} {}",
+                themed_format(
+                    format!("{} = {}:", warning.name, warning.value),
+                    "warning",
+                    theme
+                ),
+                warning.reason,
             )
             .expect("write to string");
         }
     }
+}
 
-    if summary.total_io_read_time > 0.0 || summary.total_io_write_time > 0.0 {
+/// `○ <label>: …` lines for the shared, local, and temp counters in use.
+fn write_buffer_totals(buffer: &mut String, label: &str, buffers: &BufferTotals) {
+    if buffers.is_empty() {
+        return;
+    }
+    writeln!(
+        buffer,
+        "○ {label}: shared hit={} read={} written={} dirtied={}",
+        buffers.shared_hit_blocks,
+        buffers.shared_read_blocks,
+        buffers.shared_written_blocks,
+        buffers.shared_dirtied_blocks,
+    )
+    .expect("write to string");
+    if buffers.local_hit_blocks
+        + buffers.local_read_blocks
+        + buffers.local_written_blocks
+        + buffers.local_dirtied_blocks
+        > 0
+    {
         writeln!(
             buffer,
-            "○ I/O Time: read={} write={}",
-            duration_to_string_themed(summary.total_io_read_time, theme),
-            duration_to_string_themed(summary.total_io_write_time, theme),
+            "○ {label}: local hit={} read={} written={} dirtied={}",
+            buffers.local_hit_blocks,
+            buffers.local_read_blocks,
+            buffers.local_written_blocks,
+            buffers.local_dirtied_blocks,
+        )
+        .expect("write to string");
+    }
+    if buffers.temp_read_blocks + buffers.temp_written_blocks > 0 {
+        writeln!(
+            buffer,
+            "○ {label}: temp read={} written={}",
+            buffers.temp_read_blocks, buffers.temp_written_blocks,
+        )
+        .expect("write to string");
+    }
+}
+
+fn qualified_name(schema: &str, name: &str) -> String {
+    if schema.is_empty() {
+        name.to_string()
+    } else {
+        format!("{schema}.{name}")
+    }
+}
+
+fn write_relation_report(buffer: &mut String, report: &RelationReport, options: RenderOptions) {
+    let theme = options.theme;
+    writeln!(buffer).expect("write to string");
+    writeln!(buffer, "○ Relations:").expect("write to string");
+    for rollup in &report.relations {
+        writeln!(
+            buffer,
+            "  {} scans={} methods={} rows={} buffers={} time={}",
+            themed_format(
+                qualified_name(&rollup.schema, &rollup.relation),
+                "bold",
+                theme
+            ),
+            rollup.scans,
+            rollup.access_methods.join(","),
+            rollup.rows,
+            rollup.buffers.total(),
+            duration_to_string_themed(rollup.exclusive_time, theme),
+        )
+        .expect("write to string");
+    }
+
+    if report.indexes.is_empty() {
+        return;
+    }
+    writeln!(buffer, "○ Indexes:").expect("write to string");
+    for rollup in &report.indexes {
+        let payoff = if rollup.payoff.pays_off() {
+            String::new()
+        } else {
+            format!(
+                " {}",
+                themed_format(format!(" {} ", rollup.payoff.label()), "tag", theme)
+            )
+        };
+        writeln!(
+            buffer,
+            "  {} on {} scans={} rows={} removed={} buffers={} time={}{}",
+            themed_format(&rollup.index, "bold", theme),
+            qualified_name(&rollup.schema, &rollup.relation),
+            rollup.scans,
+            rollup.rows,
+            rollup.rows_removed,
+            rollup.buffers.total(),
+            duration_to_string_themed(rollup.exclusive_time, theme),
+            payoff,
+        )
+        .expect("write to string");
+    }
+}
+
+fn write_index_suggestions(
+    buffer: &mut String,
+    suggestions: &[IndexSuggestion],
+    options: RenderOptions,
+) {
+    let theme = options.theme;
+    writeln!(buffer).expect("write to string");
+    writeln!(
+        buffer,
+        "○ Index suggestions (heuristic; verify before creating):"
+    )
+    .expect("write to string");
+    if suggestions.is_empty() {
+        writeln!(buffer, "  {}", themed_format("none", "muted", theme)).expect("write to string");
+        return;
+    }
+    for suggestion in suggestions {
+        writeln!(
+            buffer,
+            "  {} {}",
+            themed_format("heuristic", "warning", theme),
+            themed_format(&suggestion.statement, "bold", theme),
+        )
+        .expect("write to string");
+        writeln!(
+            buffer,
+            "    {} {} at {}: {} keeps {} of rows [-{} rows]",
+            themed_format("from", "muted", theme),
+            suggestion.node_type,
+            suggestion.node_path,
+            suggestion.predicate,
+            format_percent(suggestion.selectivity * 100.0, 2),
+            suggestion.rows_removed,
+        )
+        .expect("write to string");
+    }
+}
+
+fn write_cast_issues(buffer: &mut String, issues: &[CastIssue], options: RenderOptions) {
+    let theme = options.theme;
+    writeln!(buffer).expect("write to string");
+    writeln!(buffer, "○ Predicates that defeat indexes:").expect("write to string");
+    if issues.is_empty() {
+        writeln!(buffer, "  {}", themed_format("none", "muted", theme)).expect("write to string");
+        return;
+    }
+    for issue in issues {
+        let column = format!(
+            "{}.{}",
+            qualified_name(&issue.schema, &issue.relation),
+            issue.column
+        );
+        writeln!(
+            buffer,
+            "  {} {} in {} of {} at {}",
+            themed_format(&column, "bold", theme),
+            themed_format(&issue.description(), "warning", theme),
+            issue.field,
+            issue.node_type,
+            issue.node_path,
+        )
+        .expect("write to string");
+        writeln!(
+            buffer,
+            "    {}",
+            themed_format(&issue.expression, "muted", theme)
         )
         .expect("write to string");
     }
 }
 
 /// Render a processed explain tree into terminal-friendly text.
+/// Strings print bare; anything else as compact JSON.
+fn json_value_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
 pub fn render_explain(explain: &Explain, options: RenderOptions) -> String {
     let mut buffer = String::new();
     let theme = options.theme;
-    let summary = PlanSummary::from_explain(explain);
-    write_summary_block(&mut buffer, &summary, options);
+    let summary = PlanSummary::from_explain_with_block_size(explain, options.block_size);
+    let work_mem = recommend_work_mem(explain, options.block_size);
+    write_summary_block(
+        &mut buffer,
+        &summary,
+        work_mem.as_ref(),
+        &explain.settings,
+        options,
+    );
     writeln!(
         &mut buffer,
         "{}",
@@ -225,17 +489,53 @@ pub fn render_explain(explain: &Explain, options: RenderOptions) -> String {
         NodePosition {
             prefix: String::new(),
             last_child,
+            path: explain.root_path().to_string(),
         },
     );
+    if options.by_relation {
+        write_relation_report(&mut buffer, &RelationReport::from_explain(explain), options);
+    }
+    if options.suggest_indexes {
+        write_index_suggestions(&mut buffer, &suggest_indexes(explain), options);
+    }
+    if options.check_casts {
+        write_cast_issues(&mut buffer, &find_cast_issues(explain), options);
+    }
     buffer
 }
 
-fn write_plan(ctx: &mut RenderContext<'_>, plan: &Plan, position: NodePosition) {
+/// Render `root` and its descendants in pre-order. Uses an explicit stack so
+/// plans deeper than the native stack allows still render.
+fn write_plan(ctx: &mut RenderContext<'_>, root: &Plan, position: NodePosition) {
+    let mut pending = vec![(root, position)];
+    while let Some((plan, position)) = pending.pop() {
+        let path = position.path.clone();
+        let source_prefix = write_node(ctx, plan, position);
+        let last_index = plan.plans.len().saturating_sub(1);
+        for (index, child_plan) in plan.plans.iter().enumerate().rev() {
+            pending.push((
+                child_plan,
+                NodePosition {
+                    prefix: source_prefix.clone(),
+                    last_child: index == last_index,
+                    path: child_path(&path, index),
+                },
+            ));
+        }
+    }
+}
+
+/// Render one node and return the prefix its children's lines start with.
+fn write_node(ctx: &mut RenderContext<'_>, plan: &Plan, position: NodePosition) -> String {
     let explain = ctx.explain;
     let width = ctx.options.width;
     let mode = ctx.options.mode;
     let style = ctx.options.tree_style;
-    let NodePosition { prefix, last_child } = position;
+    let NodePosition {
+        prefix,
+        last_child,
+        path,
+    } = position;
     let mut source_prefix = prefix;
 
     writeln!(
@@ -247,13 +547,22 @@ fn write_plan(ctx: &mut RenderContext<'_>, plan: &Plan, position: NodePosition)
     .expect("write to string");
 
     let joint = styled_node_joint(&style, plan.plans.len(), last_child);
+    let node_id = if ctx.options.node_ids {
+        format!(
+            "{} ",
+            ctx.paint(format!("#{}", plan.identity.node_id), "muted")
+        )
+    } else {
+        String::new()
+    };
 
     writeln!(
         ctx.buffer,
-        "{}{} {}{} {}",
+        "{}{} {}{}{} {}",
         ctx.paint(&source_prefix, "prefix"),
         ctx.paint(format!("{joint}{}", style.node_connector), "prefix"),
-        ctx.paint(&plan.identity.node_type, "bold"),
+        node_id,
+        ctx.paint(plan.identity.node_type.as_str(), "bold"),
         ctx.paint(format_details(plan), "muted"),
         ctx.paint(format_tags(plan), "tag")
     )
@@ -274,7 +583,11 @@ fn write_plan(ctx: &mut RenderContext<'_>, plan: &Plan, position: NodePosition)
     let cols = width.saturating_sub(current_prefix.len());
 
     if mode != RenderMode::Condensed {
-        for line in textwrap::fill(DESCRIPTIONS[plan.identity.node_type.as_str()], cols).split('\n')
+        for line in textwrap::fill(
+            plan.identity.node_type.description(&plan.identity.strategy),
+            cols,
+        )
+        .split('\n')
         {
             writeln!(
                 ctx.buffer,
@@ -312,8 +625,27 @@ fn write_plan(ctx: &mut RenderContext<'_>, plan: &Plan, position: NodePosition)
         plan.actuals.actual_rows
     )
     .expect("write to string");
+    for (key, value) in plan.annotations.iter() {
+        writeln!(
+            ctx.buffer,
+            "{}○ {}: {}",
+            ctx.paint(&current_prefix, "prefix"),
+            key,
+            json_value_text(value),
+        )
+        .expect("write to string");
+    }
 
     if mode == RenderMode::Verbose {
+        if ctx.options.node_ids {
+            writeln!(
+                ctx.buffer,
+                "{}○ Path: {}",
+                ctx.paint(&current_prefix, "prefix"),
+                path
+            )
+            .expect("write to string");
+        }
         writeln!(
             ctx.buffer,
             "{}○ Loops: {}",
@@ -344,6 +676,17 @@ fn write_plan(ctx: &mut RenderContext<'_>, plan: &Plan, position: NodePosition)
             )
             .expect("write to string");
         }
+        if !plan.wal.is_empty() {
+            writeln!(
+                ctx.buffer,
+                "{}○ WAL: records={} fpi={} bytes={}",
+                ctx.paint(&current_prefix, "prefix"),
+                plan.wal.wal_records,
+                plan.wal.wal_fpi,
+                format_bytes(plan.wal.wal_bytes),
+            )
+            .expect("write to string");
+        }
         if plan.io_timing.io_read_time > 0.0 || plan.io_timing.io_write_time > 0.0 {
             writeln!(
                 ctx.buffer,
@@ -354,6 +697,23 @@ fn write_plan(ctx: &mut RenderContext<'_>, plan: &Plan, position: NodePosition)
             )
             .expect("write to string");
         }
+        writeln!(
+            ctx.buffer,
+            "{}○ Cache: {}",
+            ctx.paint(&current_prefix, "prefix"),
+            format_io_profile(&IoProfile::for_plan(plan, ctx.options.block_size)),
+        )
+        .expect("write to string");
+        for (key, value) in &plan.extras {
+            writeln!(
+                ctx.buffer,
+                "{}○ {}: {}",
+                ctx.paint(&current_prefix, "prefix"),
+                key,
+                ctx.paint(json_value_text(value), "muted"),
+            )
+            .expect("write to string");
+        }
     }
 
     current_prefix.push_str("  ");
@@ -464,35 +824,20 @@ fn write_plan(ctx: &mut RenderContext<'_>, plan: &Plan, position: NodePosition)
         }
     }
 
-    let last_index = plan.plans.len().saturating_sub(1);
-    let child_count = plan.plans.len();
-    for (index, child_plan) in plan.plans.iter().enumerate() {
-        let prefix = if index == child_count - 1 {
-            std::mem::take(&mut source_prefix)
-        } else {
-            source_prefix.clone()
-        };
-        write_plan(
-            ctx,
-            child_plan,
-            NodePosition {
-                prefix,
-                last_child: index == last_index,
-            },
-        );
-    }
+    source_prefix
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::analysis::process_all;
     use crate::structure::data::actuals::PlanActuals;
     use crate::structure::data::buffers::PlanBuffers;
     use crate::structure::data::estimates::PlanEstimates;
 
     fn child_plan(node_type: &str) -> Plan {
         let mut plan = Plan::default();
-        plan.identity.node_type = node_type.to_string();
+        plan.identity.node_type = node_type.into();
         plan.actuals = PlanActuals {
             actual_total_time: 1.0,
             actual_duration: 1.0,
@@ -621,6 +966,185 @@ mod tests {
         assert!(rendered.contains("○ I/O Time:"));
     }
 
+    #[test]
+    fn verbose_mode_classifies_nodes_and_detailed_summary_reports_cache() {
+        let mut explain = sample_explain();
+        explain.plan.plans[0].buffers.shared_read_blocks = 100;
+        explain.plan.plans[0].io_timing.io_read_time = 0.9;
+        explain.plan.buffers.shared_read_blocks = 100;
+        explain.plan.io_timing.io_read_time = 0.9;
+        let options = RenderOptions::new(80)
+            .with_theme(Theme::NoColor)
+            .with_mode(RenderMode::Verbose);
+
+        let rendered = render_explain(&explain, options);
+        let default = render_explain(&explain, RenderOptions::new(80).with_theme(Theme::NoColor));
+
+        assert!(rendered.contains("○ Cache: hit=0.0%"));
+        assert!(rendered.contains("io=90.0% (disk-bound)"));
+        assert!(rendered.contains("(cpu-bound)"));
+        assert!(!default.contains("○ Cache:"));
+    }
+
+    #[test]
+    fn by_relation_appends_rollup_and_flags_unpaid_indexes() {
+        let mut explain = sample_explain();
+        explain.plan.plans[0].identity.schema = "public".into();
+        explain.plan.plans[0].identity.relation_name = "users".into();
+        explain.plan.plans[1].identity.schema = "public".into();
+        explain.plan.plans[1].identity.relation_name = "users".into();
+        explain.plan.plans[1].identity.index_name = "users_email_idx".into();
+        explain.plan.plans[1].actuals.actual_loops = 0;
+
+        let default = render_explain(&explain, RenderOptions::new(80).with_theme(Theme::NoColor));
+        let rendered = render_explain(
+            &explain,
+            RenderOptions::new(80)
+                .with_theme(Theme::NoColor)
+                .with_by_relation(true),
+        );
+
+        assert!(!default.contains("○ Relations:"));
+        assert!(rendered.contains("○ Relations:"));
+        assert!(rendered.contains("public.users scans=2 methods=Index Scan,Seq Scan"));
+        assert!(rendered.contains("○ Indexes:"));
+        assert!(rendered.contains("users_email_idx on public.users"));
+        assert!(rendered.contains(" never executed "));
+    }
+
+    #[test]
+    fn suggest_indexes_appends_labeled_heuristics() {
+        let mut explain = sample_explain();
+        let scan = &mut explain.plan.plans[0];
+        scan.identity.relation_name = "orders".into();
+        scan.predicates.filter = "(status = 'open'::text)".into();
+        scan.predicates.rows_removed_by_filter = 10_000;
+        let explain = process_all(explain);
+
+        let rendered = render_explain(
+            &explain,
+            RenderOptions::new(80)
+                .with_theme(Theme::NoColor)
+                .with_suggest_indexes(true),
+        );
+
+        assert!(rendered.contains("○ Index suggestions (heuristic"));
+        assert!(rendered.contains("heuristic CREATE INDEX ON orders (status);"));
+        assert!(rendered.contains("from Seq Scan at Plan.Plans[0]"));
+    }
+
+    #[test]
+    fn check_casts_names_the_column_and_the_comparison() {
+        let mut explain = sample_explain();
+        let scan = &mut explain.plan.plans[0];
+        scan.identity.schema = "public".into();
+        scan.identity.relation_name = "orders".into();
+        scan.predicates.filter = "((account_id)::text = '123'::text)".into();
+        let explain = process_all(explain);
+
+        let rendered = render_explain(
+            &explain,
+            RenderOptions::new(80)
+                .with_theme(Theme::NoColor)
+                .with_check_casts(true),
+        );
+
+        assert!(rendered.contains("○ Predicates that defeat indexes:"));
+        assert!(rendered.contains(
+            "public.orders.account_id cast to text in Filter of Seq Scan at Plan.Plans[0]"
+        ));
+        assert!(rendered.contains("    ((account_id)::text = '123'::text)"));
+    }
+
+    #[test]
+    fn describes_nodes_outside_the_original_catalog_and_hashed_aggregates() {
+        let mut aggregate = child_plan("Aggregate");
+        aggregate.identity.strategy = "Hashed".into();
+        let mut root = child_plan("Gather Merge");
+        root.plans.push(child_plan("Memoize"));
+        root.plans.push(aggregate);
+        let explain = Explain {
+            plan: root,
+            ..Explain::default()
+        };
+
+        let rendered = render_explain(&explain, RenderOptions::new(200).with_theme(Theme::NoColor));
+
+        assert!(rendered.contains("Collects sorted rows from parallel workers"));
+        assert!(rendered.contains("Caches the results of its parameterized subplan"));
+        assert!(rendered.contains("Hash Aggregate uses a hash"));
+    }
+
+    #[test]
+    fn detailed_summary_recommends_work_mem_for_spills() {
+        let mut explain = sample_explain();
+        explain.plan.plans[0].memory.sort_space_type = "Disk".into();
+        explain.plan.plans[0].memory.sort_space_used = 3_000;
+
+        let compact = render_explain(&explain, RenderOptions::new(80).with_theme(Theme::NoColor));
+        let detailed = render_explain(
+            &explain,
+            RenderOptions::new(80)
+                .with_theme(Theme::NoColor)
+                .with_summary(SummaryStyle::Detailed),
+        );
+
+        assert!(!compact.contains("○ work_mem"));
+        assert!(detailed.contains("○ work_mem: ≥ 6MB per operation (1 spilling, up to 6MB"));
+        assert!(detailed.contains("Seq Scan at Plan.Plans[0]: external sort needs 6000kB"));
+    }
+
+    #[test]
+    fn detailed_summary_lists_settings_and_flags_distorting_ones() {
+        let mut explain = sample_explain();
+        explain.settings.insert("work_mem".into(), "64MB".into());
+        explain
+            .settings
+            .insert("enable_seqscan".into(), "off".into());
+
+        let compact = render_explain(&explain, RenderOptions::new(80).with_theme(Theme::NoColor));
+        let detailed = render_explain(
+            &explain,
+            RenderOptions::new(80)
+                .with_theme(Theme::NoColor)
+                .with_summary(SummaryStyle::Detailed),
+        );
+
+        assert!(!compact.contains("○ Settings"));
+        assert!(detailed.contains("○ Settings: enable_seqscan=off, work_mem=64MB"));
+        assert!(detailed.contains("  enable_seqscan = off: the planner avoids this plan type"));
+        assert!(!detailed.contains("work_mem = 64MB:"));
+    }
+
+    #[test]
+    fn detailed_summary_separates_planning_buffers_and_reports_wal() {
+        let mut explain = sample_explain();
+        explain.plan.buffers.shared_hit_blocks = 40;
+        explain.plan.wal.wal_records = 1_200;
+        explain.plan.wal.wal_fpi = 30;
+        explain.plan.wal.wal_bytes = 3 * 1024 * 1024;
+        explain.planning.buffers.shared_hit_blocks = 9;
+
+        let detailed = render_explain(
+            &explain,
+            RenderOptions::new(80)
+                .with_theme(Theme::NoColor)
+                .with_summary(SummaryStyle::Detailed),
+        );
+        let verbose = render_explain(
+            &explain,
+            RenderOptions::new(80)
+                .with_theme(Theme::NoColor)
+                .with_mode(RenderMode::Verbose),
+        );
+
+        assert!(detailed.contains("○ Buffers: shared hit=40 read=0"));
+        assert!(detailed.contains("○ Planning Buffers: shared hit=9 read=0"));
+        assert!(detailed.contains("○ WAL: records=1200 fpi=30 bytes=3.00 MiB"));
+        assert!(verbose.contains("○ WAL: records=1200 fpi=30 bytes=3.00 MiB\n"));
+        assert_eq!(verbose.matches("○ WAL:").count(), 2);
+    }
+
     #[test]
     fn verbose_render_mode_implies_detailed_summary() {
         let mut explain = sample_explain();
@@ -0,0 +1,316 @@
+//! Per-relation and per-index rollups across the analyzed plan.
+//!
+//! A query that touches the same table in several places produces one plan
+//! node per access. These rollups fold those nodes into one entry per
+//! relation and one per index so repeated access, total I/O, and indexes that
+//! never pay off are visible at a glance.
+//!
+//! `Bitmap Index Scan` nodes carry no relation name of their own; they are
+//! attributed to the relation of the nearest enclosing `Bitmap Heap Scan`,
+//! along with that heap scan's removed-row counts.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::structure::data::explain::Explain;
+use crate::structure::data::node_type::NodeType;
+use crate::structure::data::plan::Plan;
+use crate::summary::BufferTotals;
+
+/// Aggregated access statistics for one relation.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct RelationRollup {
+    pub schema: String,
+    pub relation: String,
+    /// Number of plan nodes that scan the relation.
+    pub scans: u64,
+    /// Distinct node types used to access the relation, sorted.
+    pub access_methods: Vec<String>,
+    /// Rows produced across all loops of all scans.
+    pub rows: u64,
+    pub buffers: BufferTotals,
+    /// Exclusive time across all scans, in milliseconds.
+    pub exclusive_time: f64,
+}
+
+/// Whether an index earned its place in the plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexPayoff {
+    #[default]
+    Effective,
+    /// Every scan using the index was planned but never executed.
+    NeverExecuted,
+    /// Filters and rechecks discarded more rows than the index returned.
+    Unselective,
+}
+
+impl IndexPayoff {
+    pub fn pays_off(self) -> bool {
+        self == IndexPayoff::Effective
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            IndexPayoff::Effective => "effective",
+            IndexPayoff::NeverExecuted => "never executed",
+            IndexPayoff::Unselective => "unselective",
+        }
+    }
+}
+
+/// Aggregated usage statistics for one index.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct IndexRollup {
+    pub schema: String,
+    pub relation: String,
+    pub index: String,
+    pub scans: u64,
+    pub access_methods: Vec<String>,
+    pub rows: u64,
+    /// Rows the index returned that a filter or recheck then discarded.
+    pub rows_removed: u64,
+    pub loops: u64,
+    pub buffers: BufferTotals,
+    pub exclusive_time: f64,
+    pub payoff: IndexPayoff,
+}
+
+/// Relation and index rollups for a whole plan, sorted by exclusive time
+/// (descending) so the most expensive objects come first.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct RelationReport {
+    pub relations: Vec<RelationRollup>,
+    pub indexes: Vec<IndexRollup>,
+}
+
+impl RelationReport {
+    pub fn from_explain(explain: &Explain) -> Self {
+        let mut builder = ReportBuilder::default();
+        builder.visit(&explain.plan, &HeapContext::default());
+        builder.finish()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relations.is_empty() && self.indexes.is_empty()
+    }
+}
+
+/// Relation and removed-row context inherited from a `Bitmap Heap Scan`.
+#[derive(Debug, Clone, Default)]
+struct HeapContext {
+    schema: String,
+    relation: String,
+    rows_removed: u64,
+}
+
+#[derive(Debug, Default)]
+struct ReportBuilder {
+    relations: BTreeMap<(String, String), (RelationRollup, BTreeSet<String>)>,
+    indexes: BTreeMap<(String, String, String), (IndexRollup, BTreeSet<String>)>,
+}
+
+impl ReportBuilder {
+    fn visit(&mut self, plan: &Plan, heap: &HeapContext) {
+        let identity = &plan.identity;
+        let loops = plan.actuals.actual_loops;
+        let rows = plan.actuals.actual_rows.saturating_mul(loops.max(1));
+        let rows_removed = (plan.predicates.rows_removed_by_filter
+            + plan.predicates.rows_removed_by_index_recheck)
+            .saturating_mul(loops.max(1));
+
+        if !identity.relation_name.is_empty() {
+            let key = (identity.schema.clone(), identity.relation_name.clone());
+            let (entry, methods) = self.relations.entry(key).or_insert_with(|| {
+                let rollup = RelationRollup {
+                    schema: identity.schema.clone(),
+                    relation: identity.relation_name.clone(),
+                    ..RelationRollup::default()
+                };
+                (rollup, BTreeSet::new())
+            });
+            entry.scans += 1;
+            entry.rows += rows;
+            entry.buffers.add(&plan.buffers);
+            entry.exclusive_time += plan.actuals.actual_duration;
+            methods.insert(identity.node_type.to_string());
+        }
+
+        if !identity.index_name.is_empty() {
+            let (schema, relation, removed) = if identity.relation_name.is_empty() {
+                (
+                    heap.schema.clone(),
+                    heap.relation.clone(),
+                    heap.rows_removed,
+                )
+            } else {
+                (
+                    identity.schema.clone(),
+                    identity.relation_name.clone(),
+                    rows_removed,
+                )
+            };
+            let key = (
+                schema.clone(),
+                relation.clone(),
+                identity.index_name.clone(),
+            );
+            let (entry, methods) = self.indexes.entry(key).or_insert_with(|| {
+                let rollup = IndexRollup {
+                    schema,
+                    relation,
+                    index: identity.index_name.clone(),
+                    ..IndexRollup::default()
+                };
+                (rollup, BTreeSet::new())
+            });
+            entry.scans += 1;
+            entry.rows += rows;
+            entry.rows_removed += removed;
+            entry.loops += loops;
+            entry.buffers.add(&plan.buffers);
+            entry.exclusive_time += plan.actuals.actual_duration;
+            methods.insert(identity.node_type.to_string());
+        }
+
+        let child_heap = if identity.node_type == NodeType::BitmapHeapScan {
+            HeapContext {
+                schema: identity.schema.clone(),
+                relation: identity.relation_name.clone(),
+                rows_removed,
+            }
+        } else {
+            heap.clone()
+        };
+        for child in &plan.plans {
+            self.visit(child, &child_heap);
+        }
+    }
+
+    fn finish(self) -> RelationReport {
+        let mut relations: Vec<RelationRollup> = self
+            .relations
+            .into_values()
+            .map(|(mut rollup, methods)| {
+                rollup.access_methods = methods.into_iter().collect();
+                rollup
+            })
+            .collect();
+        let mut indexes: Vec<IndexRollup> = self
+            .indexes
+            .into_values()
+            .map(|(mut rollup, methods)| {
+                rollup.access_methods = methods.into_iter().collect();
+                rollup.payoff = if rollup.loops == 0 {
+                    IndexPayoff::NeverExecuted
+                } else if rollup.rows_removed > rollup.rows {
+                    IndexPayoff::Unselective
+                } else {
+                    IndexPayoff::Effective
+                };
+                rollup
+            })
+            .collect();
+        relations.sort_by(|a, b| b.exclusive_time.total_cmp(&a.exclusive_time));
+        indexes.sort_by(|a, b| b.exclusive_time.total_cmp(&a.exclusive_time));
+        RelationReport { relations, indexes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{explain_for, with_children, LeafBuilder};
+
+    #[test]
+    fn rolls_up_repeated_relation_access() {
+        let mut seq = LeafBuilder::new("Seq Scan")
+            .relation("public", "users")
+            .rows(10, 10)
+            .build();
+        seq.actuals.actual_duration = 2.0;
+        seq.buffers.shared_hit_blocks = 4;
+        let mut idx = LeafBuilder::new("Index Scan")
+            .relation("public", "users")
+            .index("users_pkey")
+            .rows(1, 1)
+            .loops(5)
+            .build();
+        idx.actuals.actual_duration = 1.0;
+        idx.buffers.shared_hit_blocks = 6;
+        let plan = with_children(LeafBuilder::new("Nested Loop").build(), [seq, idx]);
+
+        let report = RelationReport::from_explain(&explain_for(plan, 3.0, 1.0));
+
+        assert_eq!(report.relations.len(), 1);
+        let users = &report.relations[0];
+        assert_eq!(users.scans, 2);
+        assert_eq!(users.access_methods, vec!["Index Scan", "Seq Scan"]);
+        assert_eq!(users.rows, 15);
+        assert_eq!(users.buffers.shared_hit_blocks, 10);
+        assert!((users.exclusive_time - 3.0).abs() < 1e-9);
+        assert_eq!(report.indexes.len(), 1);
+        assert_eq!(report.indexes[0].payoff, IndexPayoff::Effective);
+    }
+
+    #[test]
+    fn bitmap_index_scans_inherit_heap_relation_and_flag_unselective_indexes() {
+        let index = LeafBuilder::new("Bitmap Index Scan")
+            .index("events_kind_idx")
+            .rows(100, 100)
+            .build();
+        let mut heap = with_children(
+            LeafBuilder::new("Bitmap Heap Scan")
+                .relation("public", "events")
+                .rows(10, 10)
+                .build(),
+            [index],
+        );
+        heap.predicates.rows_removed_by_filter = 150;
+        heap.predicates.rows_removed_by_index_recheck = 40;
+
+        let report = RelationReport::from_explain(&explain_for(heap, 1.0, 1.0));
+
+        let rollup = &report.indexes[0];
+        assert_eq!(rollup.schema, "public");
+        assert_eq!(rollup.relation, "events");
+        assert_eq!(rollup.rows_removed, 190);
+        assert_eq!(rollup.payoff, IndexPayoff::Unselective);
+        assert_eq!(report.relations[0].access_methods, vec!["Bitmap Heap Scan"]);
+    }
+
+    #[test]
+    fn never_executed_index_is_flagged() {
+        let index = LeafBuilder::new("Index Scan")
+            .relation("public", "orders")
+            .index("orders_status_idx")
+            .rows(5, 0)
+            .loops(0)
+            .build();
+        let mut filtered = LeafBuilder::new("Index Scan")
+            .relation("public", "orders")
+            .index("orders_created_idx")
+            .rows(5, 5)
+            .build();
+        filtered.predicates.rows_removed_by_filter = 1_000;
+        let plan = with_children(LeafBuilder::new("Append").build(), [index, filtered]);
+
+        let report = RelationReport::from_explain(&explain_for(plan, 1.0, 1.0));
+        let payoff = |name: &str| {
+            report
+                .indexes
+                .iter()
+                .find(|rollup| rollup.index == name)
+                .map(|rollup| rollup.payoff)
+        };
+
+        assert_eq!(
+            payoff("orders_status_idx"),
+            Some(IndexPayoff::NeverExecuted)
+        );
+        assert_eq!(payoff("orders_created_idx"), Some(IndexPayoff::Unselective));
+        assert!(!IndexPayoff::Unselective.pays_off());
+    }
+}
@@ -0,0 +1,466 @@
+//! The EXPLAIN JSON input contract, checked while raw plans become domain
+//! plans.
+//!
+//! Well-formed input is deserialized once into [`RawExplain`] and every rule
+//! is checked during the single walk that builds the [`Plan`] tree, which
+//! also assigns node ids and paths. Only when typed deserialization fails is
+//! the input re-read as a `serde_json::Value`, so the failure can be reported
+//! as the first contract rule it breaks (`$[0].Plan.Total Cost must be a
+//! number if present`) rather than as a bare serde error.
+
+use serde_json::{Map, Value};
+
+use crate::constants::{MAX_PLAN_DEPTH, MAX_PLAN_DEPTH_CEILING, MAX_PLAN_NODES};
+use crate::diagnostic::JSON_PATH_ROOT;
+use crate::structure::data::explain::Explain;
+use crate::structure::data::plan::Plan;
+use crate::structure::raw::{PostgresSchemaProfile, RawExplain, RawExplainMetadata, RawPlan};
+use crate::structure::streaming;
+use crate::structure::tree::{json_path, NEGATIVE_HINT, NODE_TYPE_HINT, ROOT_NODE_PATH};
+use crate::{PlanViolation, VisualizeError};
+
+/// Numeric node fields that must be non-negative when present.
+pub const NON_NEGATIVE_FLOAT_FIELDS: &[&str] = &[
+    "Startup Cost",
+    "Total Cost",
+    "Actual Cost",
+    "Actual Duration",
+    "Actual Startup Time",
+    "Actual Total Time",
+    "I/O Read Time",
+    "I/O Write Time",
+];
+
+/// Node fields that must be non-negative integers when present.
+pub const NON_NEGATIVE_INT_FIELDS: &[&str] = &[
+    "Actual Loops",
+    "Actual Rows",
+    "Plan Rows",
+    "Plan Width",
+    "Rows Removed By Filter",
+    "Rows Removed By Index Recheck",
+    "Rows Removed By Join Filter",
+    "Rows Removed by Filter",
+    "Rows Removed by Index Recheck",
+    "Rows Removed by Join Filter",
+    "Shared Dirtied Blocks",
+    "Shared Hit Blocks",
+    "Shared Read Blocks",
+    "Shared Written Blocks",
+    "Local Dirtied Blocks",
+    "Local Hit Blocks",
+    "Local Read Blocks",
+    "Local Written Blocks",
+    "Temp Read Blocks",
+    "Temp Written Blocks",
+    "Heap Fetches",
+    "Sort Space Used",
+    "Peak Memory Usage",
+    "Disk Usage",
+    "HashAgg Batches",
+    "Hash Buckets",
+    "Original Hash Buckets",
+    "Hash Batches",
+    "Original Hash Batches",
+    "Workers Planned",
+    "Workers Launched",
+    "WAL Records",
+    "WAL FPI",
+    "WAL Bytes",
+];
+
+/// Size limits a plan must stay within. The defaults are
+/// [`MAX_PLAN_DEPTH`] and [`MAX_PLAN_NODES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlanLimits {
+    /// Deepest allowed node; the root is at depth 0. Values above
+    /// [`MAX_PLAN_DEPTH_CEILING`] act as the ceiling.
+    pub max_depth: usize,
+    pub max_nodes: usize,
+}
+
+impl Default for PlanLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: MAX_PLAN_DEPTH,
+            max_nodes: MAX_PLAN_NODES,
+        }
+    }
+}
+
+impl PlanLimits {
+    /// The depth limit in force: `max_depth`, capped at
+    /// [`MAX_PLAN_DEPTH_CEILING`]. Rendering, serialization, and the analysis
+    /// passes recurse once per level, and pretty and JSON output grow with
+    /// the square of the depth, so deeper plans are rejected rather than
+    /// left to overflow the stack or exhaust memory.
+    pub fn depth_limit(&self) -> usize {
+        self.max_depth.min(MAX_PLAN_DEPTH_CEILING)
+    }
+
+    pub(crate) fn check_depth(&self, depth: usize, path: &str) -> Result<(), VisualizeError> {
+        let max_depth = self.depth_limit();
+        if depth > max_depth {
+            return Err(violation(
+                path,
+                format!("{path} exceeds maximum supported plan depth of {max_depth}"),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_node_count(&self, count: usize, path: &str) -> Result<(), VisualizeError> {
+        if count > self.max_nodes {
+            let max_nodes = self.max_nodes;
+            return Err(violation(
+                path,
+                format!("plan exceeds maximum supported node count of {max_nodes}"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn violation(path: impl Into<String>, message: impl Into<String>) -> VisualizeError {
+    VisualizeError::InvalidPlan(PlanViolation::contract(path, message))
+}
+
+/// Values of [`NON_NEGATIVE_FLOAT_FIELDS`] as typed deserialization read
+/// them, in the same order. Absent keys read as zero, which passes.
+fn typed_float_fields(raw: &RawPlan) -> [(&'static str, f64); 8] {
+    [
+        ("Startup Cost", raw.estimates.startup_cost),
+        ("Total Cost", raw.estimates.total_cost),
+        ("Actual Cost", raw.actuals.actual_cost),
+        ("Actual Duration", raw.actuals.actual_duration),
+        ("Actual Startup Time", raw.actuals.actual_startup_time),
+        ("Actual Total Time", raw.actuals.actual_total_time),
+        ("I/O Read Time", raw.io_timing.canonical.io_read_time),
+        ("I/O Write Time", raw.io_timing.canonical.io_write_time),
+    ]
+}
+
+pub(crate) fn check_non_negative(value: f64, path: &str, key: &str) -> Result<(), VisualizeError> {
+    if value < 0.0 {
+        return Err(VisualizeError::InvalidPlan(
+            PlanViolation::contract(
+                format!("{path}.{key}"),
+                format!("{path}.{key} must be non-negative"),
+            )
+            .with_hint(NEGATIVE_HINT),
+        ));
+    }
+    Ok(())
+}
+
+/// Check the explain-level rules that typed deserialization cannot.
+pub(crate) fn check_explain_times(metadata: &RawExplainMetadata) -> Result<(), VisualizeError> {
+    let root = JSON_PATH_ROOT.to_string() + "[0]";
+    check_non_negative(metadata.planning_time, &root, "Planning Time")?;
+    check_non_negative(metadata.execution_time, &root, "Execution Time")
+}
+
+/// Check the node rules that typed deserialization cannot, given the node's
+/// `Plan.Plans[i]…` path. An empty node type is returned rather than raised
+/// so callers can let a later contract violation take precedence.
+pub(crate) fn check_typed_node(
+    raw: &RawPlan,
+    node_path: &str,
+) -> Result<Option<PlanViolation>, VisualizeError> {
+    if let Some((key, value)) = typed_float_fields(raw)
+        .into_iter()
+        .find(|(_, value)| *value < 0.0)
+    {
+        check_non_negative(value, &json_path(node_path), key)?;
+    }
+    if raw.identity.node_type.as_str().trim().is_empty() {
+        return Ok(Some(
+            PlanViolation::invariant(
+                json_path(&format!("{node_path}.Node Type")),
+                format!("{node_path}.Node Type must be populated"),
+            )
+            .with_hint(NODE_TYPE_HINT),
+        ));
+    }
+    Ok(None)
+}
+
+/// Builds the domain tree from typed raw plans, checking every rule on the
+/// way down.
+///
+/// Contract violations are reported in pre-order as soon as they are found.
+/// Invariant violations (an empty node type) are held until the walk ends so
+/// a contract violation anywhere in the tree takes precedence.
+struct DomainBuilder {
+    schema_profile: PostgresSchemaProfile,
+    limits: PlanLimits,
+    node_count: usize,
+    deferred: Option<PlanViolation>,
+}
+
+impl DomainBuilder {
+    fn build(
+        &mut self,
+        mut raw: RawPlan,
+        depth: usize,
+        path: String,
+    ) -> Result<Plan, VisualizeError> {
+        self.limits.check_depth(depth, &json_path(&path))?;
+        self.node_count += 1;
+        self.limits
+            .check_node_count(self.node_count, &json_path(&path))?;
+        let invariant = check_typed_node(&raw, &path)?;
+        if self.deferred.is_none() {
+            self.deferred = invariant;
+        }
+
+        let node_id = self.node_count as u64;
+        let children = std::mem::take(&mut raw.plans);
+        let mut plans = Vec::with_capacity(children.len());
+        for (index, child) in children.into_iter().enumerate() {
+            plans.push(self.build(child, depth + 1, format!("{path}.Plans[{index}]"))?);
+        }
+        let mut plan = raw.into_plan(self.schema_profile, plans);
+        plan.identity.node_id = node_id;
+        Ok(plan)
+    }
+}
+
+/// Check the explain-level rules on `raw`, then build and validate its plan
+/// tree in one walk. Node ids are assigned as in
+/// [`crate::structure::tree::assign_node_ids`].
+pub fn build_validated_explain(
+    raw: RawExplain,
+    schema_profile: PostgresSchemaProfile,
+    limits: PlanLimits,
+) -> Result<Explain, VisualizeError> {
+    check_explain_times(&raw.metadata)?;
+    let plan = raw.plan.ok_or(VisualizeError::MissingPlan)?;
+
+    let mut builder = DomainBuilder {
+        schema_profile,
+        limits,
+        node_count: 0,
+        deferred: None,
+    };
+    let plan = builder.build(plan, 0, ROOT_NODE_PATH.to_string())?;
+    if let Some(deferred) = builder.deferred {
+        return Err(VisualizeError::InvalidPlan(deferred));
+    }
+
+    Ok(Explain {
+        plan,
+        postgres_version: raw.metadata.postgres_version,
+        planning_time: raw.metadata.planning_time,
+        execution_time: raw.metadata.execution_time,
+        settings: raw.metadata.settings,
+        planning: raw.metadata.planning,
+        extras: raw.extras,
+        ..Default::default()
+    })
+}
+
+/// Turn a failed typed parse of `input` into the most specific error: a
+/// syntax error when the input is not JSON, the first contract rule it
+/// breaks otherwise, and `err` itself when no rule explains it.
+pub fn explain_parse_failure(
+    input: &str,
+    err: serde_json::Error,
+    limits: PlanLimits,
+) -> VisualizeError {
+    let value: Value = match serde_json::from_str(input) {
+        Ok(value) => value,
+        // Nesting past serde_json's recursion limit is also a syntax error;
+        // the iterative reader finds the plan limit that explains it.
+        Err(syntax) => {
+            return match streaming::read_explain(input, limits) {
+                Ok(streamed) => streamed.stop.unwrap_or(VisualizeError::InvalidJson(syntax)),
+                Err(err) => err,
+            }
+        }
+    };
+    match check_document(&value, limits) {
+        Err(violation) => violation,
+        Ok(()) => VisualizeError::InvalidJson(err),
+    }
+}
+
+/// Check the contract on an untyped document, reporting the first violation
+/// in the same order as the typed walk.
+pub fn check_document(document: &Value, limits: PlanLimits) -> Result<(), VisualizeError> {
+    let entries = document
+        .as_array()
+        .ok_or_else(|| violation(JSON_PATH_ROOT, "top-level JSON must be an array"))?;
+    let first = entries.first().ok_or(VisualizeError::EmptyExplainArray)?;
+    let root = JSON_PATH_ROOT.to_string() + "[0]";
+    let first = first
+        .as_object()
+        .ok_or_else(|| violation(root.clone(), "first explain entry must be a JSON object"))?;
+
+    check_optional_number(first, "Planning Time", &root)?;
+    check_optional_number(first, "Execution Time", &root)?;
+    check_optional_settings(first, &root)?;
+
+    match first.get("Plan") {
+        Some(Value::Object(plan)) => {
+            let mut node_count = 0;
+            check_node(plan, &json_path(ROOT_NODE_PATH), 0, &mut node_count, limits)
+        }
+        _ => Err(VisualizeError::MissingPlan),
+    }
+}
+
+pub(crate) fn check_optional_number(
+    obj: &Map<String, Value>,
+    key: &str,
+    path: &str,
+) -> Result<(), VisualizeError> {
+    if let Some(value) = obj.get(key) {
+        let n = value.as_f64().ok_or_else(|| {
+            violation(
+                format!("{path}.{key}"),
+                format!("{path}.{key} must be a number if present"),
+            )
+        })?;
+        check_non_negative(n, path, key)?;
+    }
+    Ok(())
+}
+
+/// `Settings` maps setting names to scalar values.
+fn check_optional_settings(obj: &Map<String, Value>, path: &str) -> Result<(), VisualizeError> {
+    let Some(settings) = obj.get("Settings") else {
+        return Ok(());
+    };
+    let settings = settings.as_object().ok_or_else(|| {
+        violation(
+            format!("{path}.Settings"),
+            format!("{path}.Settings must be an object if present"),
+        )
+    })?;
+    if let Some(name) = settings
+        .iter()
+        .find(|(_, value)| !(value.is_string() || value.is_number() || value.is_boolean()))
+        .map(|(name, _)| name)
+    {
+        return Err(violation(
+            format!("{path}.Settings.{name}"),
+            format!("{path}.Settings.{name} must be a string, number, or boolean"),
+        ));
+    }
+    Ok(())
+}
+
+fn check_optional_u64(
+    obj: &Map<String, Value>,
+    key: &str,
+    path: &str,
+) -> Result<(), VisualizeError> {
+    if obj.get(key).is_some_and(|value| value.as_u64().is_none()) {
+        return Err(violation(
+            format!("{path}.{key}"),
+            format!("{path}.{key} must be a non-negative integer if present"),
+        ));
+    }
+    Ok(())
+}
+
+/// Check the untyped fields of a single node; its children are not visited.
+pub(crate) fn check_node_fields(
+    plan: &Map<String, Value>,
+    path: &str,
+) -> Result<(), VisualizeError> {
+    for field in NON_NEGATIVE_FLOAT_FIELDS {
+        check_optional_number(plan, field, path)?;
+    }
+    for field in NON_NEGATIVE_INT_FIELDS {
+        check_optional_u64(plan, field, path)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn plans_not_array(path: &str) -> VisualizeError {
+    violation(
+        format!("{path}.Plans"),
+        format!("{path}.Plans must be an array if present"),
+    )
+}
+
+pub(crate) fn child_not_object(child_path: &str) -> VisualizeError {
+    violation(child_path, format!("{child_path} must be an object"))
+}
+
+fn check_node(
+    plan: &Map<String, Value>,
+    path: &str,
+    depth: usize,
+    node_count: &mut usize,
+    limits: PlanLimits,
+) -> Result<(), VisualizeError> {
+    limits.check_depth(depth, path)?;
+    *node_count += 1;
+    limits.check_node_count(*node_count, path)?;
+    check_node_fields(plan, path)?;
+
+    if let Some(children) = plan.get("Plans") {
+        let children = children.as_array().ok_or_else(|| plans_not_array(path))?;
+        for (index, child) in children.iter().enumerate() {
+            let child_path = format!("{path}.Plans[{index}]");
+            let child = child
+                .as_object()
+                .ok_or_else(|| child_not_object(&child_path))?;
+            check_node(child, &child_path, depth + 1, node_count, limits)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_float_fields_follow_the_contract_order() {
+        let names: Vec<&str> = typed_float_fields(&RawPlan::default())
+            .iter()
+            .map(|(name, _)| *name)
+            .collect();
+        assert_eq!(names, NON_NEGATIVE_FLOAT_FIELDS);
+    }
+
+    fn message(result: Result<(), VisualizeError>) -> String {
+        result.expect_err("violation").to_string()
+    }
+
+    #[test]
+    fn check_document_reports_shape_and_type_violations() {
+        assert_eq!(
+            message(check_document(
+                &serde_json::json!({}),
+                PlanLimits::default()
+            )),
+            "top-level JSON must be an array"
+        );
+        assert_eq!(
+            message(check_document(
+                &serde_json::json!([1]),
+                PlanLimits::default()
+            )),
+            "first explain entry must be a JSON object"
+        );
+        assert_eq!(
+            message(check_document(
+                &serde_json::json!([{
+                    "Plan": {"Node Type": "Hash", "Plans": [{"Node Type": "Seq Scan", "Plan Rows": "many"}]}
+                }]),
+                PlanLimits::default()
+            )),
+            "$[0].Plan.Plans[0].Plan Rows must be a non-negative integer if present"
+        );
+        assert!(matches!(
+            check_document(&serde_json::json!([{"Plan": []}]), PlanLimits::default()),
+            Err(VisualizeError::MissingPlan)
+        ));
+    }
+}
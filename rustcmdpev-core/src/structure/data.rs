@@ -1,9 +1,13 @@
 pub mod actuals;
 pub mod analysis_flags;
+pub mod annotations;
 pub mod buffers;
 pub mod estimates;
 pub mod explain;
 pub mod identity;
 pub mod io_timing;
+pub mod memory;
+pub mod node_type;
 pub mod plan;
 pub mod predicates;
+pub mod wal;
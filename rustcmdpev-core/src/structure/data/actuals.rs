@@ -1,7 +1,8 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Runtime actual metrics for a plan node.
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
 pub struct PlanActuals {
     #[serde(default, rename(deserialize = "Actual Cost"))]
     pub actual_cost: f64,
@@ -15,4 +16,6 @@ pub struct PlanActuals {
     pub actual_startup_time: f64,
     #[serde(default, rename(deserialize = "Actual Total Time"))]
     pub actual_total_time: f64,
+    #[serde(default, rename(deserialize = "Workers Launched"))]
+    pub workers_launched: u64,
 }
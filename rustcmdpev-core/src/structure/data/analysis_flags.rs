@@ -1,9 +1,10 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Derived analysis flags and planner estimate diagnostics for a plan node.
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
 pub struct PlanAnalysisFlags {
-    #[serde(default, rename(deserialize = "Costliest"))]
+    #[serde(default, alias = "Costliest")]
     pub costliest: bool,
     #[serde(default)]
     pub largest: bool,
@@ -13,4 +14,20 @@ pub struct PlanAnalysisFlags {
     pub planner_row_estimate_direction: String,
     #[serde(default)]
     pub planner_row_estimate_factor: f64,
+    #[serde(default)]
+    pub estimate_error_source: EstimateErrorSource,
+}
+
+/// Where a node's planner row misestimate comes from.
+///
+/// `Origin` marks the lowest node whose misestimate is not explained by its
+/// children — the place to fix with statistics or extended stats. `Inherited`
+/// marks nodes whose bad estimate is carried up from a misestimated child.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateErrorSource {
+    #[default]
+    None,
+    Origin,
+    Inherited,
 }
@@ -0,0 +1,102 @@
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A typed value an analysis pass attaches to a plan node.
+///
+/// Annotations are stored as JSON under [`Annotation::KEY`], so they show up
+/// in `--format json` output and the pretty renderer without either knowing
+/// the concrete type. Prefix keys with a namespace (`acme.hot_path`) to keep
+/// passes from different teams apart.
+pub trait Annotation: Serialize + DeserializeOwned {
+    const KEY: &'static str;
+}
+
+/// Annotations attached to one node, in insertion order.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(transparent)]
+pub struct Annotations(Map<String, Value>);
+
+impl Annotations {
+    /// Store `annotation`, replacing any previous value under the same key.
+    pub fn insert<A: Annotation>(&mut self, annotation: &A) -> Result<(), serde_json::Error> {
+        let value = serde_json::to_value(annotation)?;
+        self.0.insert(A::KEY.to_string(), value);
+        Ok(())
+    }
+
+    /// Decode the annotation stored under `A::KEY`; `None` when it is absent
+    /// or holds a value of a different shape.
+    pub fn get<A: Annotation>(&self) -> Option<A> {
+        self.0
+            .get(A::KEY)
+            .and_then(|value| A::deserialize(value).ok())
+    }
+
+    pub fn remove<A: Annotation>(&mut self) -> bool {
+        self.0.shift_remove(A::KEY).is_some()
+    }
+
+    /// Store an untyped value under `key`.
+    pub fn insert_value(&mut self, key: impl Into<String>, value: Value) {
+        self.0.insert(key.into(), value);
+    }
+
+    pub fn get_value(&self, key: &str) -> Option<&Value> {
+        self.0.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct HotPath {
+        share: f64,
+    }
+
+    impl Annotation for HotPath {
+        const KEY: &'static str = "acme.hot_path";
+    }
+
+    #[test]
+    fn typed_annotations_round_trip_through_json_values() {
+        let mut annotations = Annotations::default();
+        annotations
+            .insert(&HotPath { share: 0.75 })
+            .expect("serialize annotation");
+
+        assert_eq!(annotations.get::<HotPath>(), Some(HotPath { share: 0.75 }));
+        assert_eq!(
+            annotations.get_value("acme.hot_path"),
+            Some(&serde_json::json!({"share": 0.75}))
+        );
+        assert_eq!(
+            serde_json::to_value(&annotations).expect("serialize"),
+            serde_json::json!({"acme.hot_path": {"share": 0.75}})
+        );
+        assert!(annotations.remove::<HotPath>());
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn mismatched_shapes_decode_as_absent() {
+        let mut annotations = Annotations::default();
+        annotations.insert_value(HotPath::KEY, Value::from("not an object"));
+        assert_eq!(annotations.get::<HotPath>(), None);
+    }
+}
@@ -1,7 +1,8 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Buffer and block counters for a plan node.
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
 pub struct PlanBuffers {
     #[serde(default, rename(deserialize = "Heap Fetches"))]
     pub heap_fetches: u64,
@@ -26,3 +27,20 @@ pub struct PlanBuffers {
     #[serde(default, rename(deserialize = "Temp Written Blocks"))]
     pub temp_written_blocks: u64,
 }
+
+impl PlanBuffers {
+    /// Whether every counter is zero, as when `BUFFERS` was not requested.
+    pub fn is_empty(&self) -> bool {
+        self.heap_fetches == 0
+            && self.shared_dirtied_blocks == 0
+            && self.shared_hit_blocks == 0
+            && self.shared_read_blocks == 0
+            && self.shared_written_blocks == 0
+            && self.local_dirtied_blocks == 0
+            && self.local_hit_blocks == 0
+            && self.local_read_blocks == 0
+            && self.local_written_blocks == 0
+            && self.temp_read_blocks == 0
+            && self.temp_written_blocks == 0
+    }
+}
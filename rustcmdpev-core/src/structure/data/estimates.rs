@@ -1,7 +1,8 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Planner-side estimates for a plan node.
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
 pub struct PlanEstimates {
     #[serde(default, rename(deserialize = "Startup Cost"))]
     pub startup_cost: f64,
@@ -11,4 +12,6 @@ pub struct PlanEstimates {
     pub plan_rows: u64,
     #[serde(default, rename(deserialize = "Plan Width"))]
     pub plan_width: u64,
+    #[serde(default, rename(deserialize = "Workers Planned"))]
+    pub workers_planned: u64,
 }
@@ -1,7 +1,13 @@
+use std::collections::BTreeMap;
+
+use crate::structure::data::buffers::PlanBuffers;
+use crate::structure::data::plan::Extras;
+use crate::structure::tree::ROOT_NODE_PATH;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// The Explain struct
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct Explain {
     #[serde(default, rename(deserialize = "Plan"))]
     pub plan: crate::structure::data::plan::Plan,
@@ -19,6 +25,39 @@ pub struct Explain {
     pub max_cost: f64,
     #[serde(default, rename(deserialize = "Max Duration"))]
     pub max_duration: f64,
+    /// Planner settings that differ from the server defaults, by name, as
+    /// listed by `EXPLAIN (SETTINGS)` on PostgreSQL 12+.
+    #[serde(
+        default,
+        rename(deserialize = "Settings"),
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub settings: BTreeMap<String, String>,
+    /// Buffer usage of the planning phase, which the plan nodes' counters
+    /// do not include.
+    #[serde(
+        default,
+        rename(deserialize = "Planning"),
+        skip_serializing_if = "PlanningUsage::is_empty"
+    )]
+    pub planning: PlanningUsage,
+    /// Top-level keys the typed model does not recognize (e.g. `Triggers`,
+    /// `JIT`), in source order.
+    #[serde(default, skip_serializing_if = "Extras::is_empty")]
+    pub extras: Extras,
+    /// Path of `plan` in the parsed document when output is narrowed to a
+    /// subtree, so node paths keep naming the original positions; `None`
+    /// when `plan` is the document's root.
+    #[serde(skip)]
+    pub focus_path: Option<String>,
+}
+
+impl Explain {
+    /// Path of `plan` in the parsed document: `Plan` unless output is
+    /// narrowed to a subtree.
+    pub fn root_path(&self) -> &str {
+        self.focus_path.as_deref().unwrap_or(ROOT_NODE_PATH)
+    }
 }
 
 impl Default for Explain {
@@ -34,6 +73,27 @@ impl Default for Explain {
             max_rows: 0,
             max_cost: 0.0,
             max_duration: 0.0,
+            settings: BTreeMap::new(),
+            planning: PlanningUsage::default(),
+            extras: Extras::new(),
+            focus_path: None,
         }
     }
 }
+
+/// The top-level `Planning` object of `EXPLAIN (BUFFERS)` on PostgreSQL 13+.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+pub struct PlanningUsage {
+    #[serde(flatten)]
+    pub buffers: PlanBuffers,
+    /// Other planning keys, such as `Memory Used` from `EXPLAIN (MEMORY)`,
+    /// in source order.
+    #[serde(flatten)]
+    pub extras: Extras,
+}
+
+impl PlanningUsage {
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty() && self.extras.is_empty()
+    }
+}
@@ -1,10 +1,24 @@
+use crate::fingerprint::Fingerprint;
+use crate::structure::data::node_type::NodeType;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Identifying and classification fields for a plan node.
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
 pub struct PlanIdentity {
+    /// Pre-order position in the plan, starting at 1 for the root (`#1`).
+    /// Assigned when the tree is validated; 0 for hand-built plans. The
+    /// node's path is not stored; [`crate::structure::visit::pre_order`]
+    /// yields it, and JSON output writes it as `node_path`.
+    #[serde(default)]
+    pub node_id: u64,
+    /// Shape hash of the subtree rooted here; see [`Fingerprint`]. Assigned
+    /// by the `fingerprint` analysis pass.
+    #[serde(default, skip_deserializing)]
+    #[schemars(with = "String")]
+    pub fingerprint: Fingerprint,
     #[serde(default, rename(deserialize = "Node Type"))]
-    pub node_type: String,
+    pub node_type: NodeType,
     #[serde(default, rename(deserialize = "Parent Relationship"))]
     pub parent_relationship: String,
     #[serde(default, rename(deserialize = "Join Type"))]
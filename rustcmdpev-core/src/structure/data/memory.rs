@@ -0,0 +1,28 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Memory and spill details reported by Sort, Hash, and hashed Aggregate
+/// nodes. Sizes are in kilobytes, as PostgreSQL reports them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+pub struct PlanMemory {
+    #[serde(default, rename(deserialize = "Sort Method"))]
+    pub sort_method: String,
+    #[serde(default, rename(deserialize = "Sort Space Used"))]
+    pub sort_space_used: u64,
+    #[serde(default, rename(deserialize = "Sort Space Type"))]
+    pub sort_space_type: String,
+    #[serde(default, rename(deserialize = "Peak Memory Usage"))]
+    pub peak_memory_usage: u64,
+    #[serde(default, rename(deserialize = "Disk Usage"))]
+    pub disk_usage: u64,
+    #[serde(default, rename(deserialize = "HashAgg Batches"))]
+    pub hashagg_batches: u64,
+    #[serde(default, rename(deserialize = "Hash Buckets"))]
+    pub hash_buckets: u64,
+    #[serde(default, rename(deserialize = "Original Hash Buckets"))]
+    pub original_hash_buckets: u64,
+    #[serde(default, rename(deserialize = "Hash Batches"))]
+    pub hash_batches: u64,
+    #[serde(default, rename(deserialize = "Original Hash Batches"))]
+    pub original_hash_batches: u64,
+}
@@ -0,0 +1,411 @@
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+
+/// Broad family a plan node belongs to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeCategory {
+    Scan,
+    Join,
+    Aggregate,
+    Sort,
+    SetOperation,
+    Modify,
+    Parallel,
+    Materialization,
+    Control,
+    Other,
+}
+
+impl NodeCategory {
+    pub fn label(self) -> &'static str {
+        match self {
+            NodeCategory::Scan => "scan",
+            NodeCategory::Join => "join",
+            NodeCategory::Aggregate => "aggregate",
+            NodeCategory::Sort => "sort",
+            NodeCategory::SetOperation => "set operation",
+            NodeCategory::Modify => "modify",
+            NodeCategory::Parallel => "parallel",
+            NodeCategory::Materialization => "materialization",
+            NodeCategory::Control => "control",
+            NodeCategory::Other => "other",
+        }
+    }
+}
+
+/// The `Node Type` of a plan node, as PostgreSQL's `EXPLAIN` spells it.
+///
+/// Every node type PostgreSQL emits (through version 17) has a variant;
+/// anything else (extensions, future releases, or PostgreSQL 14's
+/// `Result Cache`, later renamed `Memoize`) is kept verbatim in
+/// [`NodeType::Other`] so serialization stays lossless.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum NodeType {
+    Result,
+    ProjectSet,
+    ModifyTable,
+    Append,
+    MergeAppend,
+    RecursiveUnion,
+    BitmapAnd,
+    BitmapOr,
+    NestedLoop,
+    MergeJoin,
+    HashJoin,
+    SeqScan,
+    SampleScan,
+    Gather,
+    GatherMerge,
+    IndexScan,
+    IndexOnlyScan,
+    BitmapIndexScan,
+    BitmapHeapScan,
+    TidScan,
+    TidRangeScan,
+    SubqueryScan,
+    FunctionScan,
+    TableFunctionScan,
+    ValuesScan,
+    CteScan,
+    NamedTuplestoreScan,
+    WorkTableScan,
+    ForeignScan,
+    CustomScan,
+    Materialize,
+    Memoize,
+    Sort,
+    IncrementalSort,
+    Group,
+    Aggregate,
+    WindowAgg,
+    Unique,
+    SetOp,
+    LockRows,
+    Limit,
+    Hash,
+    Other(String),
+}
+
+impl Default for NodeType {
+    fn default() -> Self {
+        NodeType::Other(String::new())
+    }
+}
+
+impl NodeType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            NodeType::Result => "Result",
+            NodeType::ProjectSet => "ProjectSet",
+            NodeType::ModifyTable => "ModifyTable",
+            NodeType::Append => "Append",
+            NodeType::MergeAppend => "Merge Append",
+            NodeType::RecursiveUnion => "Recursive Union",
+            NodeType::BitmapAnd => "BitmapAnd",
+            NodeType::BitmapOr => "BitmapOr",
+            NodeType::NestedLoop => "Nested Loop",
+            NodeType::MergeJoin => "Merge Join",
+            NodeType::HashJoin => "Hash Join",
+            NodeType::SeqScan => "Seq Scan",
+            NodeType::SampleScan => "Sample Scan",
+            NodeType::Gather => "Gather",
+            NodeType::GatherMerge => "Gather Merge",
+            NodeType::IndexScan => "Index Scan",
+            NodeType::IndexOnlyScan => "Index Only Scan",
+            NodeType::BitmapIndexScan => "Bitmap Index Scan",
+            NodeType::BitmapHeapScan => "Bitmap Heap Scan",
+            NodeType::TidScan => "Tid Scan",
+            NodeType::TidRangeScan => "Tid Range Scan",
+            NodeType::SubqueryScan => "Subquery Scan",
+            NodeType::FunctionScan => "Function Scan",
+            NodeType::TableFunctionScan => "Table Function Scan",
+            NodeType::ValuesScan => "Values Scan",
+            NodeType::CteScan => "CTE Scan",
+            NodeType::NamedTuplestoreScan => "Named Tuplestore Scan",
+            NodeType::WorkTableScan => "WorkTable Scan",
+            NodeType::ForeignScan => "Foreign Scan",
+            NodeType::CustomScan => "Custom Scan",
+            NodeType::Materialize => "Materialize",
+            NodeType::Memoize => "Memoize",
+            NodeType::Sort => "Sort",
+            NodeType::IncrementalSort => "Incremental Sort",
+            NodeType::Group => "Group",
+            NodeType::Aggregate => "Aggregate",
+            NodeType::WindowAgg => "WindowAgg",
+            NodeType::Unique => "Unique",
+            NodeType::SetOp => "SetOp",
+            NodeType::LockRows => "LockRows",
+            NodeType::Limit => "Limit",
+            NodeType::Hash => "Hash",
+            NodeType::Other(name) => name,
+        }
+    }
+
+    /// Whether this is a node type PostgreSQL is known to emit.
+    pub fn is_known(&self) -> bool {
+        !matches!(self, NodeType::Other(_))
+    }
+
+    pub fn category(&self) -> NodeCategory {
+        match self {
+            NodeType::SeqScan
+            | NodeType::SampleScan
+            | NodeType::IndexScan
+            | NodeType::IndexOnlyScan
+            | NodeType::BitmapIndexScan
+            | NodeType::BitmapHeapScan
+            | NodeType::BitmapAnd
+            | NodeType::BitmapOr
+            | NodeType::TidScan
+            | NodeType::TidRangeScan
+            | NodeType::SubqueryScan
+            | NodeType::FunctionScan
+            | NodeType::TableFunctionScan
+            | NodeType::ValuesScan
+            | NodeType::CteScan
+            | NodeType::NamedTuplestoreScan
+            | NodeType::WorkTableScan
+            | NodeType::ForeignScan
+            | NodeType::CustomScan => NodeCategory::Scan,
+            NodeType::NestedLoop | NodeType::MergeJoin | NodeType::HashJoin | NodeType::Hash => {
+                NodeCategory::Join
+            }
+            NodeType::Aggregate | NodeType::Group | NodeType::WindowAgg | NodeType::Unique => {
+                NodeCategory::Aggregate
+            }
+            NodeType::Sort | NodeType::IncrementalSort => NodeCategory::Sort,
+            NodeType::Append
+            | NodeType::MergeAppend
+            | NodeType::RecursiveUnion
+            | NodeType::SetOp => NodeCategory::SetOperation,
+            NodeType::ModifyTable | NodeType::LockRows => NodeCategory::Modify,
+            NodeType::Gather | NodeType::GatherMerge => NodeCategory::Parallel,
+            NodeType::Materialize | NodeType::Memoize => NodeCategory::Materialization,
+            NodeType::Result | NodeType::ProjectSet | NodeType::Limit => NodeCategory::Control,
+            NodeType::Other(_) => NodeCategory::Other,
+        }
+    }
+
+    /// Human-readable description of what the node does.
+    ///
+    /// `strategy` is the node's `Strategy` field; it distinguishes hashed,
+    /// sorted, and plain variants of `Aggregate` and `SetOp`. Unknown node
+    /// types describe as an empty string.
+    pub fn description(&self, strategy: &str) -> &'static str {
+        match (self, strategy) {
+            (NodeType::Aggregate, "Hashed") => "Groups records together based on a GROUP BY or aggregate function (e.g. sum()). Hash Aggregate uses a hash to first organize the records by a key.",
+            (NodeType::Aggregate, "Sorted") => "Groups records together based on a GROUP BY or aggregate function (e.g. sum()). Sorted Aggregate reads input already ordered by the group key.",
+            (NodeType::Aggregate, "Mixed") => "Groups records together for several grouping sets at once, hashing some and sorting others.",
+            (NodeType::Aggregate, _) => "Groups records together based on a GROUP BY or aggregate function (e.g. sum()).",
+            (NodeType::SetOp, "Hashed") => "Computes INTERSECT or EXCEPT by hashing the rows of both inputs.",
+            (NodeType::SetOp, _) => "Computes INTERSECT or EXCEPT by comparing adjacent rows of sorted inputs.",
+            (NodeType::Result, _) => "Computes a result without scanning a table, e.g. constant expressions or a one-time filter that can skip its subplan.",
+            (NodeType::ProjectSet, _) => "Evaluates set-returning functions in the select list, emitting one row per returned element.",
+            (NodeType::ModifyTable, _) => "Applies INSERT, UPDATE, DELETE or MERGE to the target table using rows from its subplan.",
+            (NodeType::Append, _) => "Used in a UNION to merge multiple record sets by appending them together.",
+            (NodeType::MergeAppend, _) => "Merges several sorted record sets into one, preserving their order.",
+            (NodeType::RecursiveUnion, _) => "Evaluates a recursive CTE by repeatedly running the recursive term over the previous iteration's rows.",
+            (NodeType::BitmapAnd, _) => "Intersects the page bitmaps of several Bitmap Index Scans.",
+            (NodeType::BitmapOr, _) => "Unions the page bitmaps of several Bitmap Index Scans.",
+            (NodeType::NestedLoop, _) => "Merges two record sets by looping through every record in the first set and trying to find a match in the second set. All matching records are returned.",
+            (NodeType::MergeJoin, _) => "Merges two record sets by first sorting them on a join key.",
+            (NodeType::HashJoin, _) => "Joins to record sets by hashing one of them (using a Hash Scan).",
+            (NodeType::SeqScan, _) => "Finds relevant records by sequentially scanning the input record set. When reading from a table, Seq Scans (unlike Index Scans) perform a single read operation (only the table is read).",
+            (NodeType::SampleScan, _) => "Reads a random sample of a table's pages or rows (TABLESAMPLE).",
+            (NodeType::Gather, _) => "Collects rows from parallel workers running its subplan, in no particular order.",
+            (NodeType::GatherMerge, _) => "Collects sorted rows from parallel workers running its subplan, preserving their order.",
+            (NodeType::IndexScan, _) => "Finds relevant records based on an Index. Index Scans perform 2 read operations: one to read the index and another to read the actual value from the table.",
+            (NodeType::IndexOnlyScan, _) => "Finds relevant records based on an Index. Index Only Scans perform a single read operation from the index and do not read from the corresponding table.",
+            (NodeType::BitmapIndexScan, _) => "Uses a Bitmap Index (index which uses 1 bit per page) to find all relevant pages. Results of this node are fed to the Bitmap Heap Scan.",
+            (NodeType::BitmapHeapScan, _) => "Searches through the pages returned by the Bitmap Index Scan for relevant rows.",
+            (NodeType::TidScan, _) => "Fetches rows directly by their physical location (ctid = ...).",
+            (NodeType::TidRangeScan, _) => "Fetches rows within a range of physical locations (ctid > ...).",
+            (NodeType::SubqueryScan, _) => "Scans the output of a subquery in the FROM clause, applying any outer filter or projection.",
+            (NodeType::FunctionScan, _) => "Returns the rows produced by a set-returning function in the FROM clause.",
+            (NodeType::TableFunctionScan, _) => "Returns the rows produced by a table function such as XMLTABLE or JSON_TABLE.",
+            (NodeType::ValuesScan, _) => "Returns the rows of a VALUES list.",
+            (NodeType::CteScan, _) => "Performs a sequential scan of Common Table Expression (CTE) query results. Note that results of a CTE are materialized (calculated and temporarily stored).",
+            (NodeType::NamedTuplestoreScan, _) => "Scans a named tuplestore, such as the transition table of an AFTER trigger.",
+            (NodeType::WorkTableScan, _) => "Scans the working table of a recursive CTE, i.e. the rows produced by the previous iteration.",
+            (NodeType::ForeignScan, _) => "Reads rows from a foreign table through its foreign data wrapper, possibly pushing work down to the remote server.",
+            (NodeType::CustomScan, _) => "Runs a scan implemented by an extension through the custom scan provider API.",
+            (NodeType::Materialize, _) => "Stores the rows of its subplan in memory (spilling to disk if needed) so they can be rescanned cheaply.",
+            (NodeType::Memoize, _) => "Caches the results of its parameterized subplan by parameter value so repeated lookups skip the subplan.",
+            (NodeType::Sort, _) => "Sorts a record set based on the specified sort key.",
+            (NodeType::IncrementalSort, _) => "Sorts a record set that is already ordered by a prefix of the sort key, one group of equal prefix values at a time.",
+            (NodeType::Group, _) => "Groups sorted records by the GROUP BY key without computing aggregates.",
+            (NodeType::WindowAgg, _) => "Computes window functions (OVER (...)) over partitions of sorted input.",
+            (NodeType::Unique, _) => "Removes adjacent duplicate rows from sorted input (e.g. SELECT DISTINCT).",
+            (NodeType::LockRows, _) => "Locks the rows returned by its subplan (SELECT ... FOR UPDATE / FOR SHARE).",
+            (NodeType::Limit, _) => "Returns a specified number of rows from a record set.",
+            (NodeType::Hash, _) => "Generates a hash table from the records in the input recordset. Hash is used by Hash Join.",
+            (NodeType::Other(_), _) => "",
+        }
+    }
+}
+
+impl From<&str> for NodeType {
+    fn from(name: &str) -> Self {
+        match name {
+            "Result" => NodeType::Result,
+            "ProjectSet" => NodeType::ProjectSet,
+            "ModifyTable" => NodeType::ModifyTable,
+            "Append" => NodeType::Append,
+            "Merge Append" => NodeType::MergeAppend,
+            "Recursive Union" => NodeType::RecursiveUnion,
+            "BitmapAnd" => NodeType::BitmapAnd,
+            "BitmapOr" => NodeType::BitmapOr,
+            "Nested Loop" => NodeType::NestedLoop,
+            "Merge Join" => NodeType::MergeJoin,
+            "Hash Join" => NodeType::HashJoin,
+            "Seq Scan" => NodeType::SeqScan,
+            "Sample Scan" => NodeType::SampleScan,
+            "Gather" => NodeType::Gather,
+            "Gather Merge" => NodeType::GatherMerge,
+            "Index Scan" => NodeType::IndexScan,
+            "Index Only Scan" => NodeType::IndexOnlyScan,
+            "Bitmap Index Scan" => NodeType::BitmapIndexScan,
+            "Bitmap Heap Scan" => NodeType::BitmapHeapScan,
+            "Tid Scan" => NodeType::TidScan,
+            "Tid Range Scan" => NodeType::TidRangeScan,
+            "Subquery Scan" => NodeType::SubqueryScan,
+            "Function Scan" => NodeType::FunctionScan,
+            "Table Function Scan" => NodeType::TableFunctionScan,
+            "Values Scan" => NodeType::ValuesScan,
+            "CTE Scan" => NodeType::CteScan,
+            "Named Tuplestore Scan" => NodeType::NamedTuplestoreScan,
+            "WorkTable Scan" => NodeType::WorkTableScan,
+            "Foreign Scan" => NodeType::ForeignScan,
+            "Custom Scan" => NodeType::CustomScan,
+            "Materialize" => NodeType::Materialize,
+            "Memoize" => NodeType::Memoize,
+            "Sort" => NodeType::Sort,
+            "Incremental Sort" => NodeType::IncrementalSort,
+            "Group" => NodeType::Group,
+            "Aggregate" => NodeType::Aggregate,
+            "WindowAgg" => NodeType::WindowAgg,
+            "Unique" => NodeType::Unique,
+            "SetOp" => NodeType::SetOp,
+            "LockRows" => NodeType::LockRows,
+            "Limit" => NodeType::Limit,
+            "Hash" => NodeType::Hash,
+            other => NodeType::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for NodeType {
+    fn from(name: String) -> Self {
+        match NodeType::from(name.as_str()) {
+            NodeType::Other(_) => NodeType::Other(name),
+            known => known,
+        }
+    }
+}
+
+impl AsRef<str> for NodeType {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq<str> for NodeType {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for NodeType {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for NodeType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for NodeType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(NodeType::from)
+    }
+}
+
+impl JsonSchema for NodeType {
+    fn schema_name() -> Cow<'static, str> {
+        "NodeType".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "description": "PostgreSQL `Node Type` as written by EXPLAIN, e.g. \"Seq Scan\"; names outside the known catalog pass through verbatim."
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_names_round_trip_and_unknown_names_are_preserved() {
+        for name in [
+            "Seq Scan",
+            "Gather Merge",
+            "Memoize",
+            "WorkTable Scan",
+            "ProjectSet",
+        ] {
+            let node_type = NodeType::from(name);
+            assert!(node_type.is_known(), "{name}");
+            assert_eq!(node_type.as_str(), name);
+        }
+        let custom = NodeType::from("Result Cache");
+        assert_eq!(custom, NodeType::Other("Result Cache".into()));
+        assert_eq!(custom.to_string(), "Result Cache");
+        assert_eq!(custom.category(), NodeCategory::Other);
+        assert_eq!(custom.description(""), "");
+    }
+
+    #[test]
+    fn aggregate_description_depends_on_strategy() {
+        let plain = NodeType::Aggregate.description("Plain");
+        let hashed = NodeType::Aggregate.description("Hashed");
+        assert_ne!(plain, hashed);
+        assert!(hashed.contains("Hash Aggregate"));
+        assert_ne!(
+            NodeType::SetOp.description("Hashed"),
+            NodeType::SetOp.description("Sorted")
+        );
+    }
+
+    #[test]
+    fn categories_group_related_nodes() {
+        assert_eq!(NodeType::BitmapHeapScan.category(), NodeCategory::Scan);
+        assert_eq!(NodeType::HashJoin.category(), NodeCategory::Join);
+        assert_eq!(NodeType::WindowAgg.category(), NodeCategory::Aggregate);
+        assert_eq!(NodeType::ModifyTable.category(), NodeCategory::Modify);
+        assert_eq!(NodeType::GatherMerge.category(), NodeCategory::Parallel);
+        assert_eq!(NodeType::Memoize.category(), NodeCategory::Materialization);
+    }
+
+    #[test]
+    fn serializes_as_postgres_name() {
+        let json = serde_json::to_string(&NodeType::IndexOnlyScan).unwrap();
+        assert_eq!(json, "\"Index Only Scan\"");
+        let parsed: NodeType = serde_json::from_str("\"Incremental Sort\"").unwrap();
+        assert_eq!(parsed, NodeType::IncrementalSort);
+    }
+}
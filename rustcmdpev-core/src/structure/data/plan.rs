@@ -1,21 +1,34 @@
 //https://github.com/serde-rs/serde/pull/238
 use crate::structure::data::actuals::PlanActuals;
 use crate::structure::data::analysis_flags::PlanAnalysisFlags;
+use crate::structure::data::annotations::Annotations;
 use crate::structure::data::buffers::PlanBuffers;
 use crate::structure::data::estimates::PlanEstimates;
 use crate::structure::data::identity::PlanIdentity;
 use crate::structure::data::io_timing::PlanIoTiming;
+use crate::structure::data::memory::PlanMemory;
 use crate::structure::data::predicates::PlanPredicates;
+use crate::structure::data::wal::PlanWal;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+/// PostgreSQL keys the typed model does not recognize, in source order.
+///
+/// Extension fields, fields added by newer PostgreSQL releases, and custom
+/// scan provider data land here so they survive JSON output and the lossless
+/// `postgres` format.
+pub type Extras = serde_json::Map<String, serde_json::Value>;
+
 /// The Plan struct.
 ///
 /// Storage is grouped into typed sub-structs (`identity`, `predicates`,
-/// `buffers`, `io_timing`, `estimates`, `actuals`, `analysis_flags`) so that
-/// related fields stay co-located and downstream code can pass cohesive
-/// slices of state instead of every field individually.
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// `buffers`, `io_timing`, `wal`, `memory`, `estimates`, `actuals`,
+/// `analysis_flags`) so that related fields stay co-located and downstream
+/// code can pass cohesive slices of state instead of every field
+/// individually. Unrecognized keys are kept in `extras`; values attached by
+/// analysis passes live in `annotations`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
 pub struct Plan {
     #[serde(flatten)]
     pub actuals: PlanActuals,
@@ -31,13 +44,21 @@ pub struct Plan {
     pub estimates: PlanEstimates,
     #[serde(flatten)]
     pub io_timing: PlanIoTiming,
+    #[serde(flatten)]
+    pub wal: PlanWal,
+    #[serde(flatten)]
+    pub memory: PlanMemory,
+    #[serde(default, skip_serializing_if = "Extras::is_empty")]
+    pub extras: Extras,
+    #[serde(default, skip_serializing_if = "Annotations::is_empty")]
+    pub annotations: Annotations,
     #[serde(default, rename(deserialize = "Plans"))]
     pub plans: Vec<Plan>,
 }
 
 impl fmt::Display for Plan {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(&self.identity.node_type)
+        f.write_str(self.identity.node_type.as_str())
     }
 }
 
@@ -100,7 +121,7 @@ mod tests {
     #[test]
     fn display_uses_grouped_identity_node_type() {
         let mut plan = Plan::default();
-        plan.identity.node_type = "Hash Join".to_string();
+        plan.identity.node_type = "Hash Join".into();
         assert_eq!(format!("{plan}"), "Hash Join");
     }
 }
@@ -1,7 +1,9 @@
+use crate::expression::{self, Expr};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Predicate and projection details for a plan node.
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
 pub struct PlanPredicates {
     #[serde(default, rename(deserialize = "Filter"))]
     pub filter: String,
@@ -9,12 +11,70 @@ pub struct PlanPredicates {
     pub index_condition: String,
     #[serde(default, rename(deserialize = "Hash Cond"))]
     pub hash_condition: String,
+    #[serde(default, rename(deserialize = "Merge Cond"))]
+    pub merge_condition: String,
+    #[serde(default, rename(deserialize = "Join Filter"))]
+    pub join_filter: String,
     #[serde(default, rename(deserialize = "Group Key"))]
     pub group_key: Vec<String>,
     #[serde(default, rename(deserialize = "Output"))]
     pub output: Vec<String>,
-    #[serde(default, rename(deserialize = "Rows Removed By Filter"))]
+    #[serde(
+        default,
+        rename(deserialize = "Rows Removed By Filter"),
+        alias = "Rows Removed by Filter"
+    )]
     pub rows_removed_by_filter: u64,
-    #[serde(default, rename(deserialize = "Rows Removed By Index Recheck"))]
+    #[serde(
+        default,
+        rename(deserialize = "Rows Removed By Index Recheck"),
+        alias = "Rows Removed by Index Recheck"
+    )]
     pub rows_removed_by_index_recheck: u64,
+    #[serde(
+        default,
+        rename(deserialize = "Rows Removed By Join Filter"),
+        alias = "Rows Removed by Join Filter"
+    )]
+    pub rows_removed_by_join_filter: u64,
+    /// The expression fields above, parsed. Filled by the `expressions`
+    /// analysis pass and not serialized.
+    #[serde(skip)]
+    pub parsed: ParsedPredicates,
+}
+
+/// Parsed forms of a node's [`PlanPredicates`]. Each is `None` when the
+/// field is empty or uses syntax [`expression::parse`] does not support.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedPredicates {
+    pub filter: Option<Expr>,
+    pub index_condition: Option<Expr>,
+    pub hash_condition: Option<Expr>,
+    pub merge_condition: Option<Expr>,
+    pub join_filter: Option<Expr>,
+    /// One entry per `Group Key` item.
+    pub group_key: Vec<Option<Expr>>,
+    /// One entry per `Output` item.
+    pub output: Vec<Option<Expr>>,
+}
+
+impl ParsedPredicates {
+    pub fn of(predicates: &PlanPredicates) -> Self {
+        let parse = |text: &String| {
+            if text.is_empty() {
+                None
+            } else {
+                expression::parse(text).ok()
+            }
+        };
+        Self {
+            filter: parse(&predicates.filter),
+            index_condition: parse(&predicates.index_condition),
+            hash_condition: parse(&predicates.hash_condition),
+            merge_condition: parse(&predicates.merge_condition),
+            join_filter: parse(&predicates.join_filter),
+            group_key: predicates.group_key.iter().map(parse).collect(),
+            output: predicates.output.iter().map(parse).collect(),
+        }
+    }
 }
@@ -0,0 +1,22 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// WAL generated by a plan node, from `EXPLAIN (ANALYZE, WAL)` on PostgreSQL
+/// 13+. Like buffer counters, these include the node's children.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+pub struct PlanWal {
+    #[serde(default, rename(deserialize = "WAL Records"))]
+    pub wal_records: u64,
+    /// Full-page images: whole pages logged on their first change after a
+    /// checkpoint.
+    #[serde(default, rename(deserialize = "WAL FPI"))]
+    pub wal_fpi: u64,
+    #[serde(default, rename(deserialize = "WAL Bytes"))]
+    pub wal_bytes: u64,
+}
+
+impl PlanWal {
+    pub fn is_empty(&self) -> bool {
+        self.wal_records == 0 && self.wal_fpi == 0 && self.wal_bytes == 0
+    }
+}
@@ -0,0 +1,466 @@
+//! Arena-backed plan tree with parent and sibling links.
+//!
+//! A [`PlanGraph`] numbers the nodes of a plan in pre-order and keeps, per
+//! [`NodeIndex`], links to the node's parent, siblings, and the extent of its
+//! subtree, so each is reachable in O(1). Nodes stay in the `plans` vector
+//! they were parsed into: the arena holds every sibling group side by side,
+//! so building it from an owned tree and handing the tree back only moves
+//! vector headers, however large each [`Plan`] is. [`NodeSlots`] hold
+//! per-node values a pass computes without storing them on the plans.
+//!
+//! Both conversions are iterative, so they also suit very deep plans.
+
+use std::ops::{Index, IndexMut, Range};
+
+use crate::structure::data::explain::Explain;
+use crate::structure::data::plan::Plan;
+
+/// Position of a node in a [`PlanGraph`]; nodes are numbered in pre-order
+/// from 0, so the root is [`NodeIndex::ROOT`] and a node's
+/// `identity.node_id` is its index plus one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeIndex(usize);
+
+impl NodeIndex {
+    pub const ROOT: NodeIndex = NodeIndex(0);
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Links {
+    /// Sibling group holding the node, and its position there.
+    group: usize,
+    slot: usize,
+    /// Sibling group holding the node's children, if it has any.
+    children: Option<usize>,
+    parent: Option<NodeIndex>,
+    prev_sibling: Option<NodeIndex>,
+    next_sibling: Option<NodeIndex>,
+    depth: usize,
+    /// One past the last node of this node's subtree.
+    subtree_end: usize,
+}
+
+/// A plan tree stored as an arena of sibling groups, indexed in pre-order.
+#[derive(Debug, Clone)]
+pub struct PlanGraph {
+    /// `groups[0]` holds the root; every other group is the detached
+    /// `plans` vector of one node.
+    groups: Vec<Vec<Plan>>,
+    links: Vec<Links>,
+}
+
+/// A sibling group being numbered by [`PlanGraph::from_plan`].
+struct Frame {
+    parent: NodeIndex,
+    group: usize,
+    next_slot: usize,
+    prev_sibling: Option<NodeIndex>,
+}
+
+impl PlanGraph {
+    /// Move `root` into the arena, detaching each node's children.
+    pub fn from_plan(root: Plan) -> Self {
+        let mut graph = PlanGraph {
+            groups: vec![vec![root]],
+            links: Vec::new(),
+        };
+        let mut pending = Vec::new();
+        graph.place(&mut pending, 0, 0, None, None);
+        while let Some(frame) = pending.last_mut() {
+            let slot = frame.next_slot;
+            if slot == graph.groups[frame.group].len() {
+                let parent = frame.parent;
+                pending.pop();
+                graph.links[parent.0].subtree_end = graph.links.len();
+                continue;
+            }
+            frame.next_slot += 1;
+            let (group, parent, prev_sibling) = (frame.group, frame.parent, frame.prev_sibling);
+            frame.prev_sibling = Some(NodeIndex(graph.links.len()));
+            graph.place(&mut pending, group, slot, Some(parent), prev_sibling);
+        }
+        graph
+    }
+
+    /// Number the node at `group[slot]` and queue its children.
+    fn place(
+        &mut self,
+        pending: &mut Vec<Frame>,
+        group: usize,
+        slot: usize,
+        parent: Option<NodeIndex>,
+        prev_sibling: Option<NodeIndex>,
+    ) {
+        let index = NodeIndex(self.links.len());
+        if let Some(prev) = prev_sibling {
+            self.links[prev.0].next_sibling = Some(index);
+        }
+        let plans = std::mem::take(&mut self.groups[group][slot].plans);
+        let children = (!plans.is_empty()).then(|| {
+            self.groups.push(plans);
+            self.groups.len() - 1
+        });
+        self.links.push(Links {
+            group,
+            slot,
+            children,
+            parent,
+            prev_sibling,
+            next_sibling: None,
+            depth: pending.len(),
+            subtree_end: index.0 + 1,
+        });
+        if let Some(children) = children {
+            pending.push(Frame {
+                parent: index,
+                group: children,
+                next_slot: 0,
+                prev_sibling: None,
+            });
+        }
+    }
+
+    /// Reattach every sibling group and return the owned tree.
+    pub fn into_plan(self) -> Plan {
+        let PlanGraph { mut groups, links } = self;
+        for link in links.iter().rev() {
+            if let Some(children) = link.children {
+                groups[link.group][link.slot].plans = std::mem::take(&mut groups[children]);
+            }
+        }
+        groups
+            .swap_remove(0)
+            .pop()
+            .expect("a plan graph always has a root")
+    }
+
+    /// Number of nodes; never zero.
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    pub fn root(&self) -> NodeIndex {
+        NodeIndex::ROOT
+    }
+
+    pub fn get(&self, index: usize) -> Option<NodeIndex> {
+        (index < self.len()).then_some(NodeIndex(index))
+    }
+
+    /// Every node in pre-order. Reversed, every node comes after all of its
+    /// descendants.
+    pub fn nodes(&self) -> impl DoubleEndedIterator<Item = NodeIndex> + ExactSizeIterator {
+        (0..self.len()).map(NodeIndex)
+    }
+
+    pub fn parent(&self, node: NodeIndex) -> Option<NodeIndex> {
+        self.links[node.0].parent
+    }
+
+    /// Distance from the root; the root is at depth 0.
+    pub fn depth(&self, node: NodeIndex) -> usize {
+        self.links[node.0].depth
+    }
+
+    pub fn next_sibling(&self, node: NodeIndex) -> Option<NodeIndex> {
+        self.links[node.0].next_sibling
+    }
+
+    pub fn prev_sibling(&self, node: NodeIndex) -> Option<NodeIndex> {
+        self.links[node.0].prev_sibling
+    }
+
+    pub fn child_count(&self, node: NodeIndex) -> usize {
+        self.child_plans(node).len()
+    }
+
+    pub fn first_child(&self, node: NodeIndex) -> Option<NodeIndex> {
+        self.links[node.0].children.map(|_| NodeIndex(node.0 + 1))
+    }
+
+    pub fn children(&self, node: NodeIndex) -> impl Iterator<Item = NodeIndex> + '_ {
+        std::iter::successors(self.first_child(node), |child| self.next_sibling(*child))
+    }
+
+    /// The plans of `node`'s children, in order.
+    pub fn child_plans(&self, node: NodeIndex) -> &[Plan] {
+        self.links[node.0]
+            .children
+            .map_or(&[], |children| &self.groups[children])
+    }
+
+    /// Parent, grandparent, and so on up to the root.
+    pub fn ancestors(&self, node: NodeIndex) -> impl Iterator<Item = NodeIndex> + '_ {
+        std::iter::successors(self.parent(node), |ancestor| self.parent(*ancestor))
+    }
+
+    /// `node` and its descendants, in pre-order.
+    pub fn subtree(
+        &self,
+        node: NodeIndex,
+    ) -> impl DoubleEndedIterator<Item = NodeIndex> + ExactSizeIterator {
+        self.subtree_range(node).map(NodeIndex)
+    }
+
+    pub fn is_ancestor_of(&self, ancestor: NodeIndex, node: NodeIndex) -> bool {
+        ancestor != node && self.subtree_range(ancestor).contains(&node.0)
+    }
+
+    fn subtree_range(&self, node: NodeIndex) -> Range<usize> {
+        node.0..self.links[node.0].subtree_end
+    }
+
+    /// `node`'s plan, mutably, alongside its children's plans, so a node can
+    /// be derived from its children in place.
+    pub fn with_children_mut(&mut self, node: NodeIndex) -> (&mut Plan, &[Plan]) {
+        let link = &self.links[node.0];
+        match link.children {
+            // A node's children are detached after the node itself, so their
+            // group always comes later.
+            Some(children) => {
+                let (head, tail) = self.groups.split_at_mut(children);
+                (&mut head[link.group][link.slot], &tail[0])
+            }
+            None => (&mut self.groups[link.group][link.slot], &[]),
+        }
+    }
+
+    /// Every node's plan, grouped by parent rather than in pre-order; for
+    /// passes that visit each node independently.
+    pub fn plans(&self) -> impl Iterator<Item = &Plan> {
+        self.groups.iter().flatten()
+    }
+
+    /// Mutable form of [`PlanGraph::plans`].
+    pub fn plans_mut(&mut self) -> impl Iterator<Item = &mut Plan> {
+        self.groups.iter_mut().flatten()
+    }
+}
+
+impl From<Plan> for PlanGraph {
+    fn from(root: Plan) -> Self {
+        PlanGraph::from_plan(root)
+    }
+}
+
+impl From<PlanGraph> for Plan {
+    fn from(graph: PlanGraph) -> Self {
+        graph.into_plan()
+    }
+}
+
+impl Index<NodeIndex> for PlanGraph {
+    type Output = Plan;
+
+    fn index(&self, node: NodeIndex) -> &Plan {
+        let link = &self.links[node.0];
+        &self.groups[link.group][link.slot]
+    }
+}
+
+impl IndexMut<NodeIndex> for PlanGraph {
+    fn index_mut(&mut self, node: NodeIndex) -> &mut Plan {
+        let link = &self.links[node.0];
+        &mut self.groups[link.group][link.slot]
+    }
+}
+
+/// One value per node of a [`PlanGraph`], indexed by [`NodeIndex`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeSlots<T> {
+    values: Vec<T>,
+}
+
+impl<T: Clone> NodeSlots<T> {
+    /// A slot per node of `graph`, each starting as `value`.
+    pub fn new(graph: &PlanGraph, value: T) -> Self {
+        Self {
+            values: vec![value; graph.len()],
+        }
+    }
+}
+
+impl<T> NodeSlots<T> {
+    /// Compute each node's slot from its plan.
+    pub fn from_fn(graph: &PlanGraph, mut f: impl FnMut(NodeIndex, &Plan) -> T) -> Self {
+        Self {
+            values: graph.nodes().map(|node| f(node, &graph[node])).collect(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NodeIndex, &T)> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (NodeIndex(index), value))
+    }
+
+    /// The values in pre-order.
+    pub fn into_vec(self) -> Vec<T> {
+        self.values
+    }
+}
+
+impl<T> Index<NodeIndex> for NodeSlots<T> {
+    type Output = T;
+
+    fn index(&self, node: NodeIndex) -> &T {
+        &self.values[node.0]
+    }
+}
+
+impl<T> IndexMut<NodeIndex> for NodeSlots<T> {
+    fn index_mut(&mut self, node: NodeIndex) -> &mut T {
+        &mut self.values[node.0]
+    }
+}
+
+/// An explain document whose plan tree lives in a [`PlanGraph`].
+///
+/// `explain` carries the document-level fields; its `plan` is left at the
+/// default while the graph holds the nodes.
+#[derive(Debug, Clone)]
+pub struct ExplainGraph {
+    pub explain: Explain,
+    pub graph: PlanGraph,
+}
+
+impl ExplainGraph {
+    /// Rebuild the owned explain document.
+    pub fn into_explain(self) -> Explain {
+        Explain {
+            plan: self.graph.into_plan(),
+            ..self.explain
+        }
+    }
+
+    /// Run `f` on the owned form and convert back, for passes written
+    /// against the tree.
+    pub fn with_tree(&mut self, f: impl FnOnce(&mut Explain)) {
+        let graph = std::mem::replace(&mut self.graph, PlanGraph::from_plan(Plan::default()));
+        let mut explain = Explain {
+            plan: graph.into_plan(),
+            ..std::mem::take(&mut self.explain)
+        };
+        f(&mut explain);
+        *self = ExplainGraph::from(explain);
+    }
+
+    /// Run `f` on the graph form of `explain` and convert back, for passes
+    /// written against the graph.
+    pub fn apply(explain: &mut Explain, f: impl FnOnce(&mut ExplainGraph)) {
+        let mut graph = ExplainGraph::from(std::mem::take(explain));
+        f(&mut graph);
+        *explain = graph.into_explain();
+    }
+}
+
+impl From<Explain> for ExplainGraph {
+    fn from(mut explain: Explain) -> Self {
+        let graph = PlanGraph::from_plan(std::mem::take(&mut explain.plan));
+        Self { explain, graph }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{with_children, LeafBuilder};
+
+    /// Sort → Hash Join → (Seq Scan a, Hash → Seq Scan b), Limit sibling.
+    fn sample() -> Plan {
+        let join = with_children(
+            LeafBuilder::new("Hash Join").build(),
+            [
+                LeafBuilder::new("Seq Scan").relation("public", "a").build(),
+                with_children(
+                    LeafBuilder::new("Hash").build(),
+                    [LeafBuilder::new("Seq Scan").relation("public", "b").build()],
+                ),
+            ],
+        );
+        with_children(
+            LeafBuilder::new("Sort").build(),
+            [join, LeafBuilder::new("Limit").build()],
+        )
+    }
+
+    fn node_types(graph: &PlanGraph, nodes: impl Iterator<Item = NodeIndex>) -> Vec<String> {
+        nodes
+            .map(|node| graph[node].identity.node_type.as_str().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn nodes_are_stored_in_pre_order_with_links() {
+        let graph = PlanGraph::from_plan(sample());
+        assert_eq!(
+            node_types(&graph, graph.nodes()),
+            ["Sort", "Hash Join", "Seq Scan", "Hash", "Seq Scan", "Limit"]
+        );
+        assert!(graph.plans().all(|plan| plan.plans.is_empty()));
+        assert_eq!(graph.plans().count(), graph.len());
+
+        let join = graph.get(1).expect("join");
+        let hash = graph.get(3).expect("hash");
+        let limit = graph.get(5).expect("limit");
+        assert_eq!(graph.parent(hash), Some(join));
+        assert_eq!(graph.parent(NodeIndex::ROOT), None);
+        assert_eq!(graph.depth(graph.get(4).expect("scan b")), 3);
+        assert_eq!(graph.next_sibling(join), Some(limit));
+        assert_eq!(graph.prev_sibling(limit), Some(join));
+        assert_eq!(graph.prev_sibling(join), None);
+        assert_eq!(
+            node_types(&graph, graph.children(join)),
+            ["Seq Scan", "Hash"]
+        );
+        assert_eq!(
+            node_types(&graph, graph.subtree(join)),
+            ["Hash Join", "Seq Scan", "Hash", "Seq Scan"]
+        );
+        assert_eq!(graph.child_count(hash), 1);
+        assert_eq!(graph.subtree(hash).len(), 2);
+        assert_eq!(
+            graph
+                .ancestors(graph.get(4).expect("scan b"))
+                .collect::<Vec<_>>(),
+            [hash, join, NodeIndex::ROOT]
+        );
+        assert!(graph.is_ancestor_of(join, hash));
+        assert!(!graph.is_ancestor_of(join, limit));
+    }
+
+    #[test]
+    fn round_trips_through_the_owned_tree() {
+        let plan = sample();
+        let rebuilt = PlanGraph::from_plan(plan.clone()).into_plan();
+        assert_eq!(
+            serde_json::to_value(&rebuilt).expect("serialize"),
+            serde_json::to_value(&plan).expect("serialize")
+        );
+    }
+
+    #[test]
+    fn slots_hold_per_node_values() {
+        let graph = PlanGraph::from_plan(sample());
+        let mut sizes = NodeSlots::new(&graph, 0usize);
+        for node in graph.nodes().rev() {
+            sizes[node] = 1 + graph
+                .children(node)
+                .map(|child| sizes[child])
+                .sum::<usize>();
+        }
+        assert_eq!(sizes.into_vec(), [6, 4, 1, 2, 1, 1]);
+
+        let depths = NodeSlots::from_fn(&graph, |node, _| graph.depth(node));
+        assert_eq!(depths[NodeIndex::ROOT], 0);
+    }
+}
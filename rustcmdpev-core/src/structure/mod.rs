@@ -1,5 +1,10 @@
-//! Structured PostgreSQL EXPLAIN models, raw ingestion, and tree validation.
+//! Structured PostgreSQL EXPLAIN models, raw ingestion, the input contract,
+//! tree validation, and traversal.
 
+pub mod contract;
 pub mod data;
+pub mod graph;
 pub mod raw;
+pub mod streaming;
 pub mod tree;
+pub mod visit;
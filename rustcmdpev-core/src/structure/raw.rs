@@ -1,14 +1,21 @@
-use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
 
 use crate::structure::data::actuals::PlanActuals;
 use crate::structure::data::analysis_flags::PlanAnalysisFlags;
+use crate::structure::data::annotations::Annotations;
 use crate::structure::data::buffers::PlanBuffers;
 use crate::structure::data::estimates::PlanEstimates;
-use crate::structure::data::explain::Explain;
+use crate::structure::data::explain::{Explain, PlanningUsage};
 use crate::structure::data::identity::PlanIdentity;
 use crate::structure::data::io_timing::PlanIoTiming;
-use crate::structure::data::plan::Plan;
+use crate::structure::data::memory::PlanMemory;
+use crate::structure::data::plan::{Extras, Plan};
 use crate::structure::data::predicates::PlanPredicates;
+use crate::structure::data::wal::PlanWal;
 use crate::VisualizeError;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -24,6 +31,10 @@ pub struct RawExplain {
     pub metadata: RawExplainMetadata,
     #[serde(default, rename = "Plan")]
     pub plan: Option<RawPlan>,
+    /// Keys not claimed by `metadata` or `plan`; must stay the last
+    /// flattened field so it only receives leftovers.
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 impl RawExplain {
@@ -52,6 +63,9 @@ impl RawExplain {
             postgres_version: self.metadata.postgres_version,
             planning_time: self.metadata.planning_time,
             execution_time: self.metadata.execution_time,
+            settings: self.metadata.settings,
+            planning: self.metadata.planning,
+            extras: self.extras,
             ..Default::default()
         })
     }
@@ -65,6 +79,31 @@ pub struct RawExplainMetadata {
     pub execution_time: f64,
     #[serde(default, rename = "PostgreSQL Version", alias = "Postgres Version")]
     pub postgres_version: Option<String>,
+    #[serde(
+        default,
+        rename = "Settings",
+        deserialize_with = "deserialize_settings"
+    )]
+    pub settings: BTreeMap<String, String>,
+    #[serde(default, rename = "Planning")]
+    pub planning: PlanningUsage,
+}
+
+/// `Settings` values are strings in PostgreSQL's output; numbers and booleans
+/// from hand-edited plans are kept as their JSON text.
+fn deserialize_settings<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, String>, D::Error> {
+    BTreeMap::<String, Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, value)| match value {
+            Value::String(value) => Ok((name, value)),
+            Value::Number(_) | Value::Bool(_) => Ok((name, value.to_string())),
+            _ => Err(D::Error::custom(format!(
+                "setting {name} must be a string, number, or boolean"
+            ))),
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -81,34 +120,73 @@ pub struct RawPlan {
     pub estimates: PlanEstimates,
     #[serde(flatten)]
     pub io_timing: RawPlanIoTiming,
+    #[serde(flatten)]
+    pub wal: PlanWal,
+    #[serde(flatten)]
+    pub memory: PlanMemory,
     #[serde(default, rename = "Plans")]
     pub plans: Vec<RawPlan>,
+    /// Keys not claimed by the typed groups above; must stay the last
+    /// flattened field so it only receives leftovers.
+    #[serde(flatten)]
+    pub extras: Extras,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
+#[serde(from = "RawPlanIoTimingFields")]
 pub struct RawPlanIoTiming {
-    #[serde(default, flatten)]
     pub canonical: PlanIoTiming,
-    #[serde(default, rename = "IO Read Time")]
     pub legacy_io_read_time: Option<f64>,
-    #[serde(default, rename = "IO Write Time")]
     pub legacy_io_write_time: Option<f64>,
 }
 
+/// Flat wire shape of [`RawPlanIoTiming`]. A nested `flatten` would not
+/// consume its keys, so the legacy and canonical names are listed side by
+/// side and the leftovers reach `RawPlan::extras` untouched.
+#[derive(Deserialize)]
+struct RawPlanIoTimingFields {
+    #[serde(default, rename = "I/O Read Time")]
+    io_read_time: f64,
+    #[serde(default, rename = "I/O Write Time")]
+    io_write_time: f64,
+    #[serde(default, rename = "IO Read Time")]
+    legacy_io_read_time: Option<f64>,
+    #[serde(default, rename = "IO Write Time")]
+    legacy_io_write_time: Option<f64>,
+}
+
+impl From<RawPlanIoTimingFields> for RawPlanIoTiming {
+    fn from(fields: RawPlanIoTimingFields) -> Self {
+        Self {
+            canonical: PlanIoTiming {
+                io_read_time: fields.io_read_time,
+                io_write_time: fields.io_write_time,
+            },
+            legacy_io_read_time: fields.legacy_io_read_time,
+            legacy_io_write_time: fields.legacy_io_write_time,
+        }
+    }
+}
+
 impl RawPlan {
     pub fn into_domain(
-        self,
+        mut self,
         schema_profile: PostgresSchemaProfile,
     ) -> Result<Plan, VisualizeError> {
-        let plans = self
-            .plans
+        let plans = std::mem::take(&mut self.plans)
             .into_iter()
             .map(|plan| plan.into_domain(schema_profile))
             .collect::<Result<Vec<_>, _>>()?;
 
+        Ok(self.into_plan(schema_profile, plans))
+    }
+
+    /// Convert this node alone, adopting already-converted `plans` as its
+    /// children in place of [`RawPlan::plans`].
+    pub(crate) fn into_plan(self, schema_profile: PostgresSchemaProfile, plans: Vec<Plan>) -> Plan {
         let resolved_io = self.io_timing.resolve(schema_profile);
 
-        Ok(Plan {
+        Plan {
             actuals: self.actuals,
             analysis_flags: PlanAnalysisFlags::default(),
             identity: self.identity,
@@ -116,8 +194,12 @@ impl RawPlan {
             buffers: self.buffers,
             estimates: self.estimates,
             io_timing: resolved_io,
+            wal: self.wal,
+            memory: self.memory,
+            extras: self.extras,
+            annotations: Annotations::default(),
             plans,
-        })
+        }
     }
 }
 
@@ -0,0 +1,458 @@
+//! Iterative ingestion for plans too deep for recursive deserialization.
+//!
+//! `serde_json` recurses once per nesting level and gives up at 128 levels,
+//! which a plan reaches at a depth of about 60. This reader walks the plan
+//! tree with an explicit stack instead: each node's own fields are read into
+//! a map and deserialized as a childless [`RawPlan`], and the domain tree is
+//! assembled bottom-up from the resulting pre-order arena. Only values inside
+//! a single node (`Output`, `Workers`, …) go through `serde_json` whole.
+
+use std::fmt::Write as _;
+
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::diagnostic::JSON_PATH_ROOT;
+use crate::structure::contract::{
+    check_explain_times, check_node_fields, check_optional_number, check_typed_node,
+    child_not_object, plans_not_array, violation, PlanLimits,
+};
+use crate::structure::data::explain::Explain;
+use crate::structure::data::plan::Plan;
+use crate::structure::raw::{PostgresSchemaProfile, RawExplain, RawPlan};
+use crate::structure::tree::{json_path, ROOT_NODE_PATH};
+use crate::VisualizeError;
+
+/// The reader hit input that is not JSON; [`syntax_error`] describes it.
+struct Malformed;
+
+/// A byte cursor that validates JSON structure without building values.
+struct Reader<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            bytes: input.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    /// Next non-whitespace byte, without consuming it.
+    fn peek(&mut self) -> Option<u8> {
+        while matches!(self.bytes.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Malformed> {
+        if self.eat(byte) {
+            Ok(())
+        } else {
+            Err(Malformed)
+        }
+    }
+
+    fn string_token(&mut self) -> Result<&'a str, Malformed> {
+        if self.peek() != Some(b'"') {
+            return Err(Malformed);
+        }
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.bytes.get(self.pos).ok_or(Malformed)? {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    return Ok(&self.input[start..self.pos]);
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// An object member name and its `:`.
+    fn key(&mut self) -> Result<String, Malformed> {
+        let key = serde_json::from_str(self.string_token()?).map_err(|_| Malformed)?;
+        self.expect(b':')?;
+        Ok(key)
+    }
+
+    fn scalar_token(&mut self) -> Result<(), Malformed> {
+        let start = self.pos;
+        while matches!(
+            self.bytes.get(self.pos),
+            Some(b'0'..=b'9' | b'a'..=b'z' | b'E' | b'+' | b'-' | b'.')
+        ) {
+            self.pos += 1;
+        }
+        let token = &self.input[start..self.pos];
+        if token.is_empty() || serde_json::from_str::<IgnoredAny>(token).is_err() {
+            return Err(Malformed);
+        }
+        Ok(())
+    }
+
+    /// Consume one complete value of any depth and return its text.
+    fn value_token(&mut self) -> Result<&'a str, Malformed> {
+        self.peek();
+        let start = self.pos;
+        let mut closers = Vec::new();
+        loop {
+            match self.peek().ok_or(Malformed)? {
+                open @ (b'{' | b'[') => {
+                    self.pos += 1;
+                    let close = if open == b'{' { b'}' } else { b']' };
+                    if !self.eat(close) {
+                        closers.push(close);
+                        if close == b'}' {
+                            self.string_token()?;
+                            self.expect(b':')?;
+                        }
+                        continue;
+                    }
+                }
+                b'"' => {
+                    self.string_token()?;
+                }
+                _ => self.scalar_token()?,
+            }
+            loop {
+                let Some(&close) = closers.last() else {
+                    return Ok(&self.input[start..self.pos]);
+                };
+                if self.eat(b',') {
+                    if close == b'}' {
+                        self.string_token()?;
+                        self.expect(b':')?;
+                    }
+                    break;
+                }
+                self.expect(close)?;
+                closers.pop();
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, Malformed> {
+        serde_json::from_str(self.value_token()?).map_err(|_| Malformed)
+    }
+}
+
+/// The most precise syntax error available for `input`.
+///
+/// `serde_json` errors are the only kind [`VisualizeError::InvalidJson`]
+/// carries, so the input is re-read as the recursive parser's fallback reads
+/// it. For malformed input nested past `serde_json`'s recursion limit this
+/// reports where the limit was hit.
+fn syntax_error(input: &str) -> VisualizeError {
+    let err = serde_json::from_str::<Value>(input)
+        .err()
+        .unwrap_or_else(|| <serde_json::Error as serde::de::Error>::custom("malformed JSON input"));
+    VisualizeError::InvalidJson(err)
+}
+
+/// A contract violation for well-formed input, a syntax error otherwise.
+fn shape_error(input: &str, path: &str, message: &str) -> VisualizeError {
+    match serde_json::from_str::<Value>(input) {
+        Ok(_) => violation(path, message),
+        Err(err) => VisualizeError::InvalidJson(err),
+    }
+}
+
+/// One plan node's own fields, in pre-order.
+#[derive(Debug)]
+struct Node {
+    parent: Option<usize>,
+    /// Position in the parent's `Plans` array.
+    index: usize,
+    fields: Map<String, Value>,
+}
+
+/// Path of `nodes[index]`, rebuilt from its ancestors so that nodes do not
+/// each hold a copy of their parent's path.
+fn node_path(nodes: &[Node], mut index: usize) -> String {
+    let mut positions = Vec::new();
+    while let Some(parent) = nodes[index].parent {
+        positions.push(nodes[index].index);
+        index = parent;
+    }
+    let mut path = ROOT_NODE_PATH.to_string();
+    for position in positions.iter().rev() {
+        // Writing to a String cannot fail.
+        let _ = write!(path, ".Plans[{position}]");
+    }
+    path
+}
+
+/// An open node on the reader's stack.
+struct Frame {
+    index: usize,
+    depth: usize,
+    in_plans: bool,
+    first: bool,
+    children: usize,
+}
+
+/// The first explain entry, read without recursion but not yet checked.
+#[derive(Debug)]
+pub(crate) struct StreamedExplain {
+    pub(crate) raw: RawExplain,
+    nodes: Vec<Node>,
+    /// A violation that stopped reading after `nodes`; reported once the
+    /// nodes before it pass their checks.
+    pub(crate) stop: Option<VisualizeError>,
+}
+
+/// Read the first explain entry of `input`, stopping at the first node that
+/// breaks `limits` or the shape of a plan tree.
+pub(crate) fn read_explain(
+    input: &str,
+    limits: PlanLimits,
+) -> Result<StreamedExplain, VisualizeError> {
+    read_explain_inner(input, limits).map_err(|err| match err {
+        ReadError::Malformed => syntax_error(input),
+        ReadError::Invalid(err) => err,
+    })
+}
+
+enum ReadError {
+    Malformed,
+    Invalid(VisualizeError),
+}
+
+impl From<Malformed> for ReadError {
+    fn from(_: Malformed) -> Self {
+        ReadError::Malformed
+    }
+}
+
+fn read_explain_inner(input: &str, limits: PlanLimits) -> Result<StreamedExplain, ReadError> {
+    let mut reader = Reader::new(input);
+    if !reader.eat(b'[') {
+        let err = shape_error(input, JSON_PATH_ROOT, "top-level JSON must be an array");
+        return Err(ReadError::Invalid(err));
+    }
+    if reader.eat(b']') {
+        return match reader.peek() {
+            None => Err(ReadError::Invalid(VisualizeError::EmptyExplainArray)),
+            Some(_) => Err(ReadError::Malformed),
+        };
+    }
+    let root = JSON_PATH_ROOT.to_string() + "[0]";
+    if !reader.eat(b'{') {
+        let err = shape_error(input, &root, "first explain entry must be a JSON object");
+        return Err(ReadError::Invalid(err));
+    }
+
+    let mut metadata = Map::new();
+    let mut nodes = Vec::new();
+    let mut stop = None;
+    if !reader.eat(b'}') {
+        loop {
+            let key = reader.key()?;
+            if key == "Plan" && reader.peek() == Some(b'{') {
+                stop = read_plan(&mut reader, limits, &mut nodes)?;
+                if stop.is_some() {
+                    break;
+                }
+            } else if key == "Plan" {
+                reader.value_token()?;
+            } else {
+                metadata.insert(key, reader.value()?);
+            }
+            if !reader.eat(b',') {
+                reader.expect(b'}')?;
+                break;
+            }
+        }
+    }
+    if stop.is_none() {
+        while reader.eat(b',') {
+            reader.value_token()?;
+        }
+        reader.expect(b']')?;
+        if reader.peek().is_some() {
+            return Err(ReadError::Malformed);
+        }
+    }
+
+    let metadata = Value::Object(metadata);
+    let raw = RawExplain::deserialize(&metadata).map_err(|err| {
+        let fields = metadata.as_object().expect("built as an object");
+        let untyped = check_optional_number(fields, "Planning Time", &root)
+            .and_then(|()| check_optional_number(fields, "Execution Time", &root));
+        ReadError::Invalid(untyped.err().unwrap_or(VisualizeError::InvalidJson(err)))
+    })?;
+
+    Ok(StreamedExplain { raw, nodes, stop })
+}
+
+/// Read the plan object at the cursor into `nodes`. Returns the violation
+/// that stopped it early, if any.
+fn read_plan(
+    reader: &mut Reader<'_>,
+    limits: PlanLimits,
+    nodes: &mut Vec<Node>,
+) -> Result<Option<VisualizeError>, ReadError> {
+    reader.expect(b'{')?;
+    nodes.push(Node {
+        parent: None,
+        index: 0,
+        fields: Map::new(),
+    });
+    let mut stack = vec![Frame {
+        index: 0,
+        depth: 0,
+        in_plans: false,
+        first: true,
+        children: 0,
+    }];
+
+    while let Some(frame) = stack.last_mut() {
+        if frame.in_plans {
+            if reader.eat(b']') {
+                frame.in_plans = false;
+                continue;
+            }
+            if frame.children > 0 {
+                reader.expect(b',')?;
+            }
+            let parent = frame.index;
+            let depth = frame.depth + 1;
+            let index = frame.children;
+            let path = format!("{}.Plans[{index}]", node_path(nodes, parent));
+            frame.children += 1;
+            if reader.peek() != Some(b'{') {
+                return Ok(Some(child_not_object(&json_path(&path))));
+            }
+            if let Err(err) = limits
+                .check_depth(depth, &json_path(&path))
+                .and_then(|()| limits.check_node_count(nodes.len() + 1, &json_path(&path)))
+            {
+                return Ok(Some(err));
+            }
+            reader.pos += 1;
+            nodes.push(Node {
+                parent: Some(parent),
+                index,
+                fields: Map::new(),
+            });
+            stack.push(Frame {
+                index: nodes.len() - 1,
+                depth,
+                in_plans: false,
+                first: true,
+                children: 0,
+            });
+            continue;
+        }
+
+        if reader.eat(b'}') {
+            stack.pop();
+            continue;
+        }
+        if !frame.first {
+            reader.expect(b',')?;
+        }
+        frame.first = false;
+        let key = reader.key()?;
+        if key == "Plans" {
+            if !reader.eat(b'[') {
+                return Ok(Some(plans_not_array(&json_path(&node_path(
+                    nodes,
+                    frame.index,
+                )))));
+            }
+            frame.in_plans = true;
+        } else {
+            let value = reader.value()?;
+            nodes[frame.index].fields.insert(key, value);
+        }
+    }
+
+    Ok(None)
+}
+
+impl StreamedExplain {
+    /// Check every node in pre-order and assemble the domain tree bottom-up,
+    /// reporting violations in the same order as the recursive parser.
+    pub(crate) fn into_explain(
+        self,
+        schema_profile: PostgresSchemaProfile,
+    ) -> Result<Explain, VisualizeError> {
+        let StreamedExplain {
+            raw,
+            mut nodes,
+            stop,
+        } = self;
+        check_explain_times(&raw.metadata)?;
+        if nodes.is_empty() {
+            return Err(stop.unwrap_or(VisualizeError::MissingPlan));
+        }
+
+        let mut deferred = None;
+        let mut typed = Vec::with_capacity(nodes.len());
+        for index in 0..nodes.len() {
+            let path = node_path(&nodes, index);
+            let node = &mut nodes[index];
+            let fields = Value::Object(std::mem::take(&mut node.fields));
+            let plan = RawPlan::deserialize(&fields).map_err(|err| {
+                let fields = fields.as_object().expect("built as an object");
+                check_node_fields(fields, &json_path(&path))
+                    .err()
+                    .unwrap_or(VisualizeError::InvalidJson(err))
+            })?;
+            let invariant = check_typed_node(&plan, &path)?;
+            if deferred.is_none() {
+                deferred = invariant;
+            }
+            typed.push((plan, node.parent));
+        }
+        if let Some(err) = stop {
+            return Err(err);
+        }
+        if let Some(deferred) = deferred {
+            return Err(VisualizeError::InvalidPlan(deferred));
+        }
+
+        let mut children: Vec<Vec<Plan>> =
+            std::iter::repeat_with(Vec::new).take(typed.len()).collect();
+        let mut root = None;
+        for (index, (raw_plan, parent)) in typed.into_iter().enumerate().rev() {
+            let mut plans = std::mem::take(&mut children[index]);
+            plans.reverse();
+            let mut plan = raw_plan.into_plan(schema_profile, plans);
+            plan.identity.node_id = index as u64 + 1;
+            match parent {
+                Some(parent) => children[parent].push(plan),
+                None => root = Some(plan),
+            }
+        }
+
+        Ok(Explain {
+            plan: root.expect("node 0 is the root"),
+            postgres_version: raw.metadata.postgres_version,
+            planning_time: raw.metadata.planning_time,
+            execution_time: raw.metadata.execution_time,
+            settings: raw.metadata.settings,
+            planning: raw.metadata.planning,
+            extras: raw.extras,
+            ..Default::default()
+        })
+    }
+}
@@ -1,6 +1,26 @@
-use crate::constants::{MAX_PLAN_DEPTH, MAX_PLAN_NODES};
+use std::fmt;
+
+use crate::diagnostic::JSON_PATH_ROOT;
+use crate::structure::contract::PlanLimits;
 use crate::structure::data::plan::Plan;
-use crate::VisualizeError;
+use crate::structure::visit::{
+    pre_order, walk, walk_mut, NodeContext, NodeRef, PlanVisitor, PlanVisitorMut, Walk,
+};
+use crate::{PlanViolation, VisualizeError};
+
+/// Path of the root node; children append `.Plans[i]`.
+pub const ROOT_NODE_PATH: &str = "Plan";
+
+pub(crate) const NEGATIVE_HINT: &str =
+    "PostgreSQL never reports negative costs or timings; check whether the plan was edited.";
+pub(crate) const NODE_TYPE_HINT: &str =
+    "Every plan node needs a \"Node Type\"; pass unmodified EXPLAIN (FORMAT JSON) output.";
+
+/// JSON path of the value at `node_path` (`Plan.Plans[0]…`) within the
+/// document the tree was parsed from.
+pub(crate) fn json_path(node_path: &str) -> String {
+    format!("{JSON_PATH_ROOT}[0].{node_path}")
+}
 
 /// Summary information derived while validating a plan tree.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -17,10 +37,16 @@ pub struct PlanTree {
 }
 
 impl PlanTree {
-    /// Build a validated plan tree rooted at the supplied plan node.
+    /// Build a validated plan tree rooted at the supplied plan node and
+    /// assign every node its id.
     pub fn new(root: Plan) -> Result<Self, VisualizeError> {
-        let mut node_count = 0;
-        let stats = validate_node(&root, 0, "Plan", &mut node_count)?;
+        Self::with_limits(root, PlanLimits::default())
+    }
+
+    /// Like [`PlanTree::new`] but enforcing `limits` instead of the defaults.
+    pub fn with_limits(mut root: Plan, limits: PlanLimits) -> Result<Self, VisualizeError> {
+        let stats = validate_tree(&root, limits)?;
+        assign_node_ids(&mut root);
         Ok(Self { root, stats })
     }
 
@@ -40,76 +66,169 @@ impl PlanTree {
     }
 }
 
-fn validate_node(
-    plan: &Plan,
-    depth: usize,
-    path: &str,
-    node_count: &mut usize,
-) -> Result<TreeStats, VisualizeError> {
-    if depth > MAX_PLAN_DEPTH {
-        return Err(VisualizeError::InvalidPlan(format!(
-            "{path} exceeds maximum supported plan depth of {MAX_PLAN_DEPTH}"
-        )));
-    }
-    if plan.identity.node_type.trim().is_empty() {
-        return Err(VisualizeError::InvalidPlan(format!(
-            "{path}.Node Type must be populated"
-        )));
+/// Single walk that checks every node and gathers [`TreeStats`]; stops at the
+/// first violation.
+#[derive(Debug)]
+struct Validator {
+    limits: PlanLimits,
+    node_count: usize,
+    max_depth: usize,
+    error: Option<VisualizeError>,
+}
+
+impl Validator {
+    fn check(&mut self, plan: &Plan, ctx: &NodeContext<'_>) -> Result<(), VisualizeError> {
+        let path = ctx.path;
+        let max_depth = self.limits.depth_limit();
+        if ctx.depth > max_depth {
+            return Err(VisualizeError::InvalidPlan(PlanViolation::invariant(
+                json_path(path),
+                format!("{path} exceeds maximum supported plan depth of {max_depth}"),
+            )));
+        }
+        if plan.identity.node_type.as_str().trim().is_empty() {
+            return Err(VisualizeError::InvalidPlan(
+                PlanViolation::invariant(
+                    json_path(&format!("{path}.Node Type")),
+                    format!("{path}.Node Type must be populated"),
+                )
+                .with_hint(NODE_TYPE_HINT),
+            ));
+        }
+        self.node_count += 1;
+        if self.node_count > self.limits.max_nodes {
+            let max_nodes = self.limits.max_nodes;
+            return Err(VisualizeError::InvalidPlan(PlanViolation::invariant(
+                json_path(path),
+                format!("plan exceeds maximum supported node count of {max_nodes}"),
+            )));
+        }
+        validate_non_negative(plan.estimates.startup_cost, &format!("{path}.Startup Cost"))?;
+        validate_non_negative(plan.estimates.total_cost, &format!("{path}.Total Cost"))?;
+        validate_non_negative(
+            plan.actuals.actual_startup_time,
+            &format!("{path}.Actual Startup Time"),
+        )?;
+        validate_non_negative(
+            plan.actuals.actual_total_time,
+            &format!("{path}.Actual Total Time"),
+        )?;
+        validate_non_negative(
+            plan.io_timing.io_read_time,
+            &format!("{path}.I/O Read Time"),
+        )?;
+        validate_non_negative(
+            plan.io_timing.io_write_time,
+            &format!("{path}.I/O Write Time"),
+        )?;
+        self.max_depth = self.max_depth.max(ctx.depth);
+        Ok(())
     }
-    *node_count += 1;
-    if *node_count > MAX_PLAN_NODES {
-        return Err(VisualizeError::InvalidPlan(format!(
-            "plan exceeds maximum supported node count of {MAX_PLAN_NODES}"
-        )));
+}
+
+impl<'a> PlanVisitor<'a> for Validator {
+    fn pre(&mut self, plan: &'a Plan, _parent: Option<&'a Plan>, ctx: &NodeContext<'_>) -> Walk {
+        match self.check(plan, ctx) {
+            Ok(()) => Walk::Continue,
+            Err(err) => {
+                self.error = Some(err);
+                Walk::Stop
+            }
+        }
     }
-    validate_non_negative(plan.estimates.startup_cost, &format!("{path}.Startup Cost"))?;
-    validate_non_negative(plan.estimates.total_cost, &format!("{path}.Total Cost"))?;
-    validate_non_negative(
-        plan.actuals.actual_startup_time,
-        &format!("{path}.Actual Startup Time"),
-    )?;
-    validate_non_negative(
-        plan.actuals.actual_total_time,
-        &format!("{path}.Actual Total Time"),
-    )?;
-    validate_non_negative(
-        plan.io_timing.io_read_time,
-        &format!("{path}.I/O Read Time"),
-    )?;
-    validate_non_negative(
-        plan.io_timing.io_write_time,
-        &format!("{path}.I/O Write Time"),
-    )?;
-
-    let mut stats = TreeStats {
-        node_count: 1,
-        max_depth: depth,
+}
+
+fn validate_tree(root: &Plan, limits: PlanLimits) -> Result<TreeStats, VisualizeError> {
+    let mut validator = Validator {
+        limits,
+        node_count: 0,
+        max_depth: 0,
+        error: None,
     };
+    walk(root, &mut validator);
+    match validator.error {
+        Some(err) => Err(err),
+        None => Ok(TreeStats {
+            node_count: validator.node_count,
+            max_depth: validator.max_depth,
+        }),
+    }
+}
 
-    for (index, child) in plan.plans.iter().enumerate() {
-        let child_stats = validate_node(
-            child,
-            depth + 1,
-            &format!("{path}.Plans[{index}]"),
-            node_count,
-        )?;
-        stats.node_count += child_stats.node_count;
-        stats.max_depth = stats.max_depth.max(child_stats.max_depth);
+/// Number nodes in pre-order starting at 1.
+pub fn assign_node_ids(root: &mut Plan) {
+    struct Numberer {
+        next_id: u64,
+    }
+
+    impl PlanVisitorMut for Numberer {
+        fn pre(&mut self, plan: &mut Plan, _ctx: &NodeContext<'_>) -> Walk {
+            plan.identity.node_id = self.next_id;
+            self.next_id += 1;
+            Walk::Continue
+        }
     }
 
-    Ok(stats)
+    walk_mut(root, &mut Numberer { next_id: 1 });
+}
+
+/// Addresses a single plan node by id (`3`, `#3`) or path
+/// (`Plan.Plans[0].Plans[1]`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeSelector {
+    Id(u64),
+    Path(String),
+}
+
+impl NodeSelector {
+    /// Parse a selector; `None` when the text is neither an id nor a path.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let digits = text.strip_prefix('#').unwrap_or(text);
+        if let Ok(id) = digits.parse::<u64>() {
+            return Some(NodeSelector::Id(id));
+        }
+        let is_path = text == ROOT_NODE_PATH
+            || text
+                .strip_prefix(ROOT_NODE_PATH)
+                .is_some_and(|rest| rest.starts_with(".Plans["));
+        is_path.then(|| NodeSelector::Path(text.to_string()))
+    }
+
+    pub fn matches(&self, node: &NodeRef<'_>) -> bool {
+        match self {
+            NodeSelector::Id(id) => node.plan.identity.node_id == *id,
+            NodeSelector::Path(path) => node.path == *path,
+        }
+    }
+}
+
+impl fmt::Display for NodeSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NodeSelector::Id(id) => write!(f, "#{id}"),
+            NodeSelector::Path(path) => f.write_str(path),
+        }
+    }
+}
+
+/// Find the node addressed by `selector` in pre-order, with its path.
+pub fn find_node<'a>(plan: &'a Plan, selector: &NodeSelector) -> Option<NodeRef<'a>> {
+    pre_order(plan).find(|node| selector.matches(node))
 }
 
 fn validate_non_negative(value: f64, path: &str) -> Result<(), VisualizeError> {
     if !value.is_finite() {
-        return Err(VisualizeError::InvalidPlan(format!(
-            "{path} must be finite"
+        return Err(VisualizeError::InvalidPlan(PlanViolation::invariant(
+            json_path(path),
+            format!("{path} must be finite"),
         )));
     }
     if value < 0.0 {
-        return Err(VisualizeError::InvalidPlan(format!(
-            "{path} must be non-negative"
-        )));
+        return Err(VisualizeError::InvalidPlan(
+            PlanViolation::invariant(json_path(path), format!("{path} must be non-negative"))
+                .with_hint(NEGATIVE_HINT),
+        ));
     }
     Ok(())
 }
@@ -0,0 +1,421 @@
+//! Visitor traits and iterators over a plan tree.
+//!
+//! Passes that need every node implement [`PlanVisitor`] (read-only) or
+//! [`PlanVisitorMut`] and hand themselves to [`walk`] / [`walk_mut`]. Both
+//! receive a [`NodeContext`] carrying the node's depth and path, which is the
+//! same `Plan.Plans[i]…` address used by node ids and validation errors.
+//! When a pass only needs to look at nodes, [`pre_order`] and [`post_order`]
+//! yield a [`NodeRef`] per node instead.
+
+use std::fmt::Write as _;
+
+use crate::structure::data::plan::Plan;
+use crate::structure::tree::ROOT_NODE_PATH;
+
+const CHILD_SEGMENT: &str = ".Plans[";
+
+/// Controls how a walk proceeds after a hook returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Walk {
+    /// Keep going, descending into the node's children.
+    #[default]
+    Continue,
+    /// Do not descend into this node's children. The node's `post` hook
+    /// still runs. Treated as [`Walk::Continue`] when returned from `post`.
+    SkipChildren,
+    /// End the walk immediately; no further hooks run.
+    Stop,
+}
+
+/// Where a visited node sits in the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeContext<'c> {
+    /// Distance from the root; the root is at depth 0.
+    pub depth: usize,
+    /// Address of the node, e.g. `Plan.Plans[0].Plans[1]`.
+    pub path: &'c str,
+    /// Position of the node in its parent's `Plans` array; `None` for the root.
+    pub index: Option<usize>,
+}
+
+impl<'c> NodeContext<'c> {
+    pub fn is_root(&self) -> bool {
+        self.depth == 0
+    }
+
+    /// Address of the parent node, or `None` for the root.
+    pub fn parent_path(&self) -> Option<&'c str> {
+        self.path.rfind(CHILD_SEGMENT).map(|end| &self.path[..end])
+    }
+}
+
+/// Read-only pass over a plan tree.
+///
+/// `pre` runs before a node's children are visited and `post` after. Both
+/// receive the parent node, which is `None` for the root.
+pub trait PlanVisitor<'a> {
+    fn pre(&mut self, _plan: &'a Plan, _parent: Option<&'a Plan>, _ctx: &NodeContext<'_>) -> Walk {
+        Walk::Continue
+    }
+
+    fn post(&mut self, _plan: &'a Plan, _parent: Option<&'a Plan>, _ctx: &NodeContext<'_>) -> Walk {
+        Walk::Continue
+    }
+}
+
+/// Mutating pass over a plan tree.
+///
+/// The parent cannot be borrowed while a child is mutated, so hooks get
+/// [`NodeContext::parent_path`] instead. A `post` hook sees its children
+/// after they have been visited, which suits bottom-up passes.
+pub trait PlanVisitorMut {
+    fn pre(&mut self, _plan: &mut Plan, _ctx: &NodeContext<'_>) -> Walk {
+        Walk::Continue
+    }
+
+    fn post(&mut self, _plan: &mut Plan, _ctx: &NodeContext<'_>) -> Walk {
+        Walk::Continue
+    }
+}
+
+/// Visit every node under `root` in depth-first order. Returns
+/// [`Walk::Stop`] when a hook ended the walk early and
+/// [`Walk::Continue`] otherwise.
+pub fn walk<'a, V: PlanVisitor<'a> + ?Sized>(root: &'a Plan, visitor: &mut V) -> Walk {
+    let mut path = ROOT_NODE_PATH.to_string();
+    walk_node(root, None, 0, None, &mut path, visitor)
+}
+
+/// Mutable counterpart of [`walk`].
+pub fn walk_mut<V: PlanVisitorMut + ?Sized>(root: &mut Plan, visitor: &mut V) -> Walk {
+    let mut path = ROOT_NODE_PATH.to_string();
+    walk_node_mut(root, 0, None, &mut path, visitor)
+}
+
+/// Path of the child at `index` of the node at `path`.
+pub fn child_path(path: &str, index: usize) -> String {
+    format!("{path}{CHILD_SEGMENT}{index}]")
+}
+
+fn push_child_segment(path: &mut String, index: usize) {
+    // Writing to a String cannot fail.
+    let _ = write!(path, "{CHILD_SEGMENT}{index}]");
+}
+
+fn walk_node<'a, V: PlanVisitor<'a> + ?Sized>(
+    plan: &'a Plan,
+    parent: Option<&'a Plan>,
+    depth: usize,
+    index: Option<usize>,
+    path: &mut String,
+    visitor: &mut V,
+) -> Walk {
+    let ctx = NodeContext { depth, path, index };
+    match visitor.pre(plan, parent, &ctx) {
+        Walk::Stop => return Walk::Stop,
+        Walk::SkipChildren => {}
+        Walk::Continue => {
+            for (child_index, child) in plan.plans.iter().enumerate() {
+                let len = path.len();
+                push_child_segment(path, child_index);
+                let outcome = walk_node(
+                    child,
+                    Some(plan),
+                    depth + 1,
+                    Some(child_index),
+                    path,
+                    visitor,
+                );
+                path.truncate(len);
+                if outcome == Walk::Stop {
+                    return Walk::Stop;
+                }
+            }
+        }
+    }
+    let ctx = NodeContext { depth, path, index };
+    match visitor.post(plan, parent, &ctx) {
+        Walk::Stop => Walk::Stop,
+        Walk::Continue | Walk::SkipChildren => Walk::Continue,
+    }
+}
+
+fn walk_node_mut<V: PlanVisitorMut + ?Sized>(
+    plan: &mut Plan,
+    depth: usize,
+    index: Option<usize>,
+    path: &mut String,
+    visitor: &mut V,
+) -> Walk {
+    let ctx = NodeContext { depth, path, index };
+    match visitor.pre(plan, &ctx) {
+        Walk::Stop => return Walk::Stop,
+        Walk::SkipChildren => {}
+        Walk::Continue => {
+            for (child_index, child) in plan.plans.iter_mut().enumerate() {
+                let len = path.len();
+                push_child_segment(path, child_index);
+                let outcome = walk_node_mut(child, depth + 1, Some(child_index), path, visitor);
+                path.truncate(len);
+                if outcome == Walk::Stop {
+                    return Walk::Stop;
+                }
+            }
+        }
+    }
+    let ctx = NodeContext { depth, path, index };
+    match visitor.post(plan, &ctx) {
+        Walk::Stop => Walk::Stop,
+        Walk::Continue | Walk::SkipChildren => Walk::Continue,
+    }
+}
+
+/// A node yielded by [`pre_order`] or [`post_order`].
+#[derive(Debug, Clone)]
+pub struct NodeRef<'a> {
+    pub plan: &'a Plan,
+    pub depth: usize,
+    pub path: String,
+    /// `None` for the root.
+    pub parent: Option<&'a Plan>,
+}
+
+impl<'a> NodeRef<'a> {
+    fn root(plan: &'a Plan, path: &str) -> Self {
+        Self {
+            plan,
+            depth: 0,
+            path: path.to_string(),
+            parent: None,
+        }
+    }
+
+    /// Children of this node, in order, ready to be yielded.
+    fn children(&self) -> impl DoubleEndedIterator<Item = NodeRef<'a>> + '_ {
+        let parent = self.plan;
+        parent
+            .plans
+            .iter()
+            .enumerate()
+            .map(move |(index, child)| NodeRef {
+                plan: child,
+                depth: self.depth + 1,
+                path: child_path(&self.path, index),
+                parent: Some(parent),
+            })
+    }
+}
+
+/// Iterate nodes parent-first, in the same order node ids are assigned.
+pub fn pre_order(root: &Plan) -> PreOrder<'_> {
+    pre_order_at(root, ROOT_NODE_PATH)
+}
+
+/// Like [`pre_order`], but with paths relative to `path` instead of `Plan`,
+/// for a subtree taken out of a larger plan.
+pub fn pre_order_at<'a>(root: &'a Plan, path: &str) -> PreOrder<'a> {
+    PreOrder {
+        stack: vec![NodeRef::root(root, path)],
+    }
+}
+
+/// Iterate nodes children-first; the root is yielded last.
+pub fn post_order(root: &Plan) -> PostOrder<'_> {
+    PostOrder {
+        stack: vec![(NodeRef::root(root, ROOT_NODE_PATH), false)],
+    }
+}
+
+/// Iterator returned by [`pre_order`].
+#[derive(Debug, Clone)]
+pub struct PreOrder<'a> {
+    stack: Vec<NodeRef<'a>>,
+}
+
+impl<'a> Iterator for PreOrder<'a> {
+    type Item = NodeRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        let children: Vec<_> = node.children().rev().collect();
+        self.stack.extend(children);
+        Some(node)
+    }
+}
+
+/// Iterator returned by [`post_order`].
+#[derive(Debug, Clone)]
+pub struct PostOrder<'a> {
+    /// Pending nodes paired with whether their children were already pushed.
+    stack: Vec<(NodeRef<'a>, bool)>,
+}
+
+impl<'a> Iterator for PostOrder<'a> {
+    type Item = NodeRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, expanded) = self.stack.pop()?;
+            if expanded || node.plan.plans.is_empty() {
+                return Some(node);
+            }
+            let children: Vec<_> = node.children().rev().map(|child| (child, false)).collect();
+            self.stack.push((node, true));
+            self.stack.extend(children);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(node_type: &str, children: Vec<Plan>) -> Plan {
+        let mut plan = Plan::default();
+        plan.identity.node_type = node_type.into();
+        plan.plans = children;
+        plan
+    }
+
+    /// Hash Join ─┬─ Seq Scan
+    ///            └─ Hash ── Index Scan
+    fn sample() -> Plan {
+        node(
+            "Hash Join",
+            vec![
+                node("Seq Scan", vec![]),
+                node("Hash", vec![node("Index Scan", vec![])]),
+            ],
+        )
+    }
+
+    fn types<'a>(nodes: impl Iterator<Item = NodeRef<'a>>) -> Vec<String> {
+        nodes
+            .map(|node| node.plan.identity.node_type.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn pre_order_yields_parents_before_children_with_paths() {
+        let plan = sample();
+        let nodes: Vec<_> = pre_order(&plan).collect();
+
+        assert_eq!(
+            types(nodes.iter().cloned()),
+            ["Hash Join", "Seq Scan", "Hash", "Index Scan"]
+        );
+        assert_eq!(nodes[3].path, "Plan.Plans[1].Plans[0]");
+        assert_eq!(nodes[3].depth, 2);
+        assert_eq!(
+            nodes[3].parent.map(|p| p.identity.node_type.as_str()),
+            Some("Hash")
+        );
+        assert!(nodes[0].parent.is_none());
+    }
+
+    #[test]
+    fn pre_order_at_addresses_a_subtree_by_its_original_path() {
+        let plan = sample();
+        let paths: Vec<String> = pre_order_at(&plan.plans[1], "Plan.Plans[1]")
+            .map(|node| node.path)
+            .collect();
+        assert_eq!(paths, ["Plan.Plans[1]", "Plan.Plans[1].Plans[0]"]);
+    }
+
+    #[test]
+    fn post_order_yields_children_before_parents() {
+        let plan = sample();
+        assert_eq!(
+            types(post_order(&plan)),
+            ["Seq Scan", "Index Scan", "Hash", "Hash Join"]
+        );
+    }
+
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl<'a> PlanVisitor<'a> for Trace {
+        fn pre(&mut self, plan: &'a Plan, parent: Option<&'a Plan>, ctx: &NodeContext<'_>) -> Walk {
+            self.0.push(format!(
+                "pre {} {} parent={}",
+                plan.identity.node_type,
+                ctx.path,
+                parent.map_or("-", |p| p.identity.node_type.as_str())
+            ));
+            if plan.identity.node_type == "Hash" {
+                Walk::SkipChildren
+            } else {
+                Walk::Continue
+            }
+        }
+
+        fn post(
+            &mut self,
+            plan: &'a Plan,
+            _parent: Option<&'a Plan>,
+            ctx: &NodeContext<'_>,
+        ) -> Walk {
+            self.0
+                .push(format!("post {} {}", plan.identity.node_type, ctx.depth));
+            Walk::Continue
+        }
+    }
+
+    #[test]
+    fn walk_runs_hooks_in_order_and_honours_skip_children() {
+        let plan = sample();
+        let mut trace = Trace::default();
+
+        assert_eq!(walk(&plan, &mut trace), Walk::Continue);
+        assert_eq!(
+            trace.0,
+            [
+                "pre Hash Join Plan parent=-",
+                "pre Seq Scan Plan.Plans[0] parent=Hash Join",
+                "post Seq Scan 1",
+                "pre Hash Plan.Plans[1] parent=Hash Join",
+                "post Hash 1",
+                "post Hash Join 0",
+            ]
+        );
+    }
+
+    struct StopAt(&'static str, usize);
+
+    impl PlanVisitorMut for StopAt {
+        fn pre(&mut self, plan: &mut Plan, _ctx: &NodeContext<'_>) -> Walk {
+            self.1 += 1;
+            if plan.identity.node_type == self.0 {
+                Walk::Stop
+            } else {
+                Walk::Continue
+            }
+        }
+    }
+
+    #[test]
+    fn walk_mut_stops_early() {
+        let mut plan = sample();
+        let mut visitor = StopAt("Seq Scan", 0);
+
+        assert_eq!(walk_mut(&mut plan, &mut visitor), Walk::Stop);
+        assert_eq!(visitor.1, 2);
+    }
+
+    #[test]
+    fn node_context_derives_parent_path() {
+        let ctx = NodeContext {
+            depth: 2,
+            path: "Plan.Plans[1].Plans[0]",
+            index: Some(0),
+        };
+        assert_eq!(ctx.parent_path(), Some("Plan.Plans[1]"));
+        let root = NodeContext {
+            depth: 0,
+            path: ROOT_NODE_PATH,
+            index: None,
+        };
+        assert!(root.is_root());
+        assert_eq!(root.parent_path(), None);
+    }
+}
@@ -5,12 +5,19 @@
 //! re-walking the plan tree, and gives tests a stable surface to assert
 //! against.
 
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::DEFAULT_BLOCK_SIZE;
+use crate::io_profile::IoProfile;
 use crate::structure::data::buffers::PlanBuffers;
 use crate::structure::data::explain::Explain;
 use crate::structure::data::plan::Plan;
+use crate::structure::data::wal::PlanWal;
+use crate::structure::visit::pre_order;
 
 /// Aggregated buffer counters across every node in the plan.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
 pub struct BufferTotals {
     pub shared_hit_blocks: u64,
     pub shared_read_blocks: u64,
@@ -42,7 +49,7 @@ impl BufferTotals {
         self.total() == 0
     }
 
-    fn add(&mut self, b: &PlanBuffers) {
+    pub(crate) fn add(&mut self, b: &PlanBuffers) {
         self.shared_hit_blocks += b.shared_hit_blocks;
         self.shared_read_blocks += b.shared_read_blocks;
         self.shared_written_blocks += b.shared_written_blocks;
@@ -56,8 +63,34 @@ impl BufferTotals {
     }
 }
 
+/// WAL generated by the whole plan.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+pub struct WalTotals {
+    pub records: u64,
+    /// Full-page images; a large share means the first change to each page
+    /// after a checkpoint dominates the WAL volume.
+    pub fpi: u64,
+    pub bytes: u64,
+}
+
+impl WalTotals {
+    /// Totals from a node's counters, which already include its children.
+    pub fn inclusive(wal: &PlanWal) -> Self {
+        Self {
+            records: wal.wal_records,
+            fpi: wal.wal_fpi,
+            bytes: wal.wal_bytes,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records == 0 && self.fpi == 0 && self.bytes == 0
+    }
+}
+
 /// One-shot rollup used to render the header summary block.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PlanSummary {
     pub total_cost: f64,
     pub planning_time: f64,
@@ -66,29 +99,45 @@ pub struct PlanSummary {
     pub total_io_read_time: f64,
     pub total_io_write_time: f64,
     pub buffers: BufferTotals,
+    /// Buffers the planner touched, e.g. reading catalogs; not part of
+    /// `buffers`.
+    pub planning_buffers: BufferTotals,
+    /// Taken from the root node, whose counters include every child.
+    pub wal: WalTotals,
     pub node_count: u64,
+    /// Whole-plan cache hit ratio, read throughput, and I/O time share.
+    pub io: IoProfile,
 }
 
 impl PlanSummary {
     pub fn from_explain(explain: &Explain) -> Self {
+        Self::from_explain_with_block_size(explain, DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Like [`PlanSummary::from_explain`] for servers built with a
+    /// non-default `BLCKSZ`.
+    pub fn from_explain_with_block_size(explain: &Explain, block_size: u64) -> Self {
         let mut summary = Self {
             total_cost: explain.total_cost,
             planning_time: explain.planning_time,
             execution_time: explain.execution_time,
+            io: IoProfile::for_explain(explain, block_size),
+            wal: WalTotals::inclusive(&explain.plan.wal),
             ..Self::default()
         };
+        summary.planning_buffers.add(&explain.planning.buffers);
         summary.accumulate(&explain.plan);
         summary
     }
 
-    fn accumulate(&mut self, plan: &Plan) {
-        self.node_count += 1;
-        self.total_loops = self.total_loops.saturating_add(plan.actuals.actual_loops);
-        self.total_io_read_time += plan.io_timing.io_read_time;
-        self.total_io_write_time += plan.io_timing.io_write_time;
-        self.buffers.add(&plan.buffers);
-        for child in &plan.plans {
-            self.accumulate(child);
+    fn accumulate(&mut self, root: &Plan) {
+        for node in pre_order(root) {
+            let plan = node.plan;
+            self.node_count += 1;
+            self.total_loops = self.total_loops.saturating_add(plan.actuals.actual_loops);
+            self.total_io_read_time += plan.io_timing.io_read_time;
+            self.total_io_write_time += plan.io_timing.io_write_time;
+            self.buffers.add(&plan.buffers);
         }
     }
 }
@@ -137,10 +186,54 @@ mod tests {
         assert_eq!(summary.execution_time, 9.0);
     }
 
+    #[test]
+    fn summary_profiles_io_from_root_counters() {
+        let mut root = leaf(1, 90, 10, 0.0);
+        root.plans.push(leaf(1, 90, 10, 0.0));
+        let explain = Explain {
+            plan: root,
+            ..Explain::default()
+        };
+
+        let summary = PlanSummary::from_explain(&explain);
+
+        assert_eq!(summary.buffers.shared_hit_blocks, 180);
+        assert!((summary.io.cache_hit_ratio.unwrap() - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn summary_takes_wal_from_the_root_and_keeps_planning_buffers_apart() {
+        let mut root = leaf(1, 10, 0, 0.0);
+        root.identity.node_type = "ModifyTable".into();
+        root.wal.wal_records = 1_000;
+        root.wal.wal_fpi = 40;
+        root.wal.wal_bytes = 400_000;
+        let mut scan = leaf(1, 10, 0, 0.0);
+        scan.wal.wal_records = 3;
+        root.plans.push(scan);
+        let mut explain = Explain {
+            plan: root,
+            ..Explain::default()
+        };
+        explain.planning.buffers.shared_hit_blocks = 25;
+        explain.planning.buffers.shared_read_blocks = 2;
+
+        let summary = PlanSummary::from_explain(&explain);
+
+        assert_eq!(summary.wal.records, 1_000);
+        assert_eq!(summary.wal.fpi, 40);
+        assert_eq!(summary.wal.bytes, 400_000);
+        assert_eq!(summary.planning_buffers.shared_hit_blocks, 25);
+        assert_eq!(summary.planning_buffers.shared_read_blocks, 2);
+        assert_eq!(summary.buffers.shared_hit_blocks, 20);
+    }
+
     #[test]
     fn summary_buffer_totals_is_empty_when_no_io() {
         let explain = Explain::default();
         let summary = PlanSummary::from_explain(&explain);
         assert!(summary.buffers.is_empty());
+        assert!(summary.planning_buffers.is_empty());
+        assert!(summary.wal.is_empty());
     }
 }
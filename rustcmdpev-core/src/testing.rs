@@ -17,6 +17,7 @@ use crate::structure::data::actuals::PlanActuals;
 use crate::structure::data::estimates::PlanEstimates;
 use crate::structure::data::explain::Explain;
 use crate::structure::data::plan::Plan;
+use crate::structure::visit::pre_order;
 
 /// Builder for a leaf [`Plan`] with the most common fields populated.
 #[derive(Debug, Clone)]
@@ -78,7 +79,7 @@ impl LeafBuilder {
 
     pub fn build(self) -> Plan {
         let mut plan = Plan::default();
-        plan.identity.node_type = self.node_type;
+        plan.identity.node_type = self.node_type.into();
         if let Some((schema, name)) = self.relation {
             plan.identity.schema = schema;
             plan.identity.relation_name = name;
@@ -167,16 +168,7 @@ pub fn node_count(plan: &Plan) -> usize {
 
 /// Recursively collect every node in a plan tree (depth-first, parent-first).
 pub fn collect_nodes(plan: &Plan) -> Vec<&Plan> {
-    let mut out = Vec::new();
-    collect(plan, &mut out);
-    out
-}
-
-fn collect<'a>(plan: &'a Plan, out: &mut Vec<&'a Plan>) {
-    out.push(plan);
-    for child in &plan.plans {
-        collect(child, out);
-    }
+    pre_order(plan).map(|node| node.plan).collect()
 }
 
 #[cfg(test)]
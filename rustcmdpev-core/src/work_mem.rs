@@ -0,0 +1,304 @@
+//! `work_mem` recommendations from sort and hash spill data.
+//!
+//! Each operation that spilled to disk gets an estimate of the memory it
+//! would have needed to stay in memory:
+//!
+//! - **Sort** with `Sort Space Type: Disk`: the on-disk size times
+//!   [`SORT_SPILL_MEMORY_FACTOR`], because in-memory tuples carry more
+//!   overhead than the compact tape format.
+//! - **Hash** with more than one batch: peak memory per batch times the batch
+//!   count.
+//! - **Hashed Aggregate** with `Disk Usage` or several `HashAgg Batches`:
+//!   peak memory plus the spilled size.
+//! - Any other node that wrote temp blocks itself: the written bytes. A
+//!   Hash Join over a batched Hash is skipped, since its temp blocks are the
+//!   outer side's batch files and the Hash already accounts for the spill.
+//!
+//! Each process of a parallel query gets its own `work_mem` per operation, so
+//! the query-wide figure multiplies every operation by the participating
+//! processes (launched workers of the enclosing Gather plus the leader).
+//! Hash nodes may also use `hash_mem_multiplier` × `work_mem` on PostgreSQL
+//! 13+; the recommendation ignores the multiplier and so errs high.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::constants::SORT_SPILL_MEMORY_FACTOR;
+use crate::structure::data::explain::Explain;
+use crate::structure::data::node_type::NodeType;
+use crate::structure::data::plan::Plan;
+
+/// Why an operation is considered to have spilled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SpillKind {
+    ExternalSort,
+    HashBatches,
+    HashAggregateDisk,
+    TempFiles,
+}
+
+impl SpillKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            SpillKind::ExternalSort => "external sort",
+            SpillKind::HashBatches => "hash batches",
+            SpillKind::HashAggregateDisk => "hash aggregate spill",
+            SpillKind::TempFiles => "temp files",
+        }
+    }
+}
+
+/// One operation that spilled, with its estimated in-memory requirement.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SpillingOperation {
+    pub node_path: String,
+    pub node_type: NodeType,
+    pub kind: SpillKind,
+    /// Memory needed to avoid the spill in one process, in kilobytes.
+    pub required_kb: u64,
+    /// Processes running the operation (parallel workers plus the leader).
+    pub processes: u64,
+}
+
+/// Minimum `work_mem` that would have kept every operation in memory.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct WorkMemAdvice {
+    pub operations: Vec<SpillingOperation>,
+    /// Largest per-operation requirement, rounded up to whole megabytes; the
+    /// value to set `work_mem` to.
+    pub per_operation_kb: u64,
+    /// Sum over operations and processes, rounded up to whole megabytes; the
+    /// peak memory the query could use with that setting.
+    pub query_total_kb: u64,
+}
+
+/// Recommend `work_mem` for the plan, or `None` when nothing spilled.
+pub fn recommend_work_mem(explain: &Explain, block_size: u64) -> Option<WorkMemAdvice> {
+    let mut operations = Vec::new();
+    collect(
+        &explain.plan,
+        explain.root_path(),
+        1,
+        block_size,
+        &mut operations,
+    );
+    if operations.is_empty() {
+        return None;
+    }
+    let per_operation_kb = operations
+        .iter()
+        .map(|operation| round_up_to_mb(operation.required_kb))
+        .max()
+        .unwrap_or_default();
+    let query_total_kb = round_up_to_mb(
+        operations
+            .iter()
+            .map(|operation| operation.required_kb * operation.processes)
+            .sum(),
+    );
+    Some(WorkMemAdvice {
+        operations,
+        per_operation_kb,
+        query_total_kb,
+    })
+}
+
+/// Format kilobytes the way `SET work_mem` accepts them, e.g. `64MB`.
+pub fn format_kb(kb: u64) -> String {
+    if kb >= 1024 && kb % 1024 == 0 {
+        format!("{}MB", kb / 1024)
+    } else {
+        format!("{kb}kB")
+    }
+}
+
+fn round_up_to_mb(kb: u64) -> u64 {
+    kb.div_ceil(1024) * 1024
+}
+
+fn collect(
+    plan: &Plan,
+    path: &str,
+    processes: u64,
+    block_size: u64,
+    out: &mut Vec<SpillingOperation>,
+) {
+    if let Some((kind, required_kb)) = spill(plan, block_size) {
+        out.push(SpillingOperation {
+            node_path: path.to_string(),
+            node_type: plan.identity.node_type.clone(),
+            kind,
+            required_kb,
+            processes,
+        });
+    }
+
+    let child_processes = if matches!(
+        plan.identity.node_type,
+        NodeType::Gather | NodeType::GatherMerge
+    ) {
+        let workers = if plan.actuals.workers_launched > 0 {
+            plan.actuals.workers_launched
+        } else {
+            plan.estimates.workers_planned
+        };
+        workers + 1
+    } else {
+        processes
+    };
+    for (index, child) in plan.plans.iter().enumerate() {
+        collect(
+            child,
+            &format!("{path}.Plans[{index}]"),
+            child_processes,
+            block_size,
+            out,
+        );
+    }
+}
+
+fn spill(plan: &Plan, block_size: u64) -> Option<(SpillKind, u64)> {
+    let memory = &plan.memory;
+    if memory.sort_space_type == "Disk" || memory.sort_method.starts_with("external") {
+        let required = (memory.sort_space_used as f64 * SORT_SPILL_MEMORY_FACTOR).ceil() as u64;
+        return Some((SpillKind::ExternalSort, required));
+    }
+    if memory.hash_batches > 1 {
+        return Some((
+            SpillKind::HashBatches,
+            memory.peak_memory_usage * memory.hash_batches,
+        ));
+    }
+    if memory.disk_usage > 0 || memory.hashagg_batches > 1 {
+        return Some((
+            SpillKind::HashAggregateDisk,
+            memory.peak_memory_usage + memory.disk_usage,
+        ));
+    }
+
+    // The outer side's batch files of a batched hash join land on the join;
+    // the Hash below it is already sized by its batch count.
+    if plan.identity.node_type == NodeType::HashJoin
+        && plan.plans.iter().any(|child| child.memory.hash_batches > 1)
+    {
+        return None;
+    }
+
+    let children_written: u64 = plan
+        .plans
+        .iter()
+        .map(|child| child.buffers.temp_written_blocks)
+        .sum();
+    let written = plan
+        .buffers
+        .temp_written_blocks
+        .saturating_sub(children_written);
+    (written > 0).then(|| (SpillKind::TempFiles, (written * block_size).div_ceil(1024)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::DEFAULT_BLOCK_SIZE;
+    use crate::testing::{explain_for, with_children, LeafBuilder};
+
+    fn external_sort(space_kb: u64) -> Plan {
+        let mut sort = LeafBuilder::new("Sort").build();
+        sort.memory.sort_method = "external merge".into();
+        sort.memory.sort_space_type = "Disk".into();
+        sort.memory.sort_space_used = space_kb;
+        sort
+    }
+
+    #[test]
+    fn no_advice_when_nothing_spills() {
+        let mut sort = LeafBuilder::new("Sort").build();
+        sort.memory.sort_method = "quicksort".into();
+        sort.memory.sort_space_type = "Memory".into();
+        assert!(recommend_work_mem(&explain_for(sort, 1.0, 1.0), DEFAULT_BLOCK_SIZE).is_none());
+    }
+
+    #[test]
+    fn sizes_external_sort_and_batched_hash() {
+        let mut hash = LeafBuilder::new("Hash").build();
+        hash.memory.hash_batches = 4;
+        hash.memory.peak_memory_usage = 4_000;
+        let plan = with_children(
+            LeafBuilder::new("Hash Join").build(),
+            [external_sort(10_000), hash],
+        );
+
+        let advice =
+            recommend_work_mem(&explain_for(plan, 1.0, 1.0), DEFAULT_BLOCK_SIZE).expect("advice");
+
+        assert_eq!(advice.operations.len(), 2);
+        assert_eq!(advice.operations[0].kind, SpillKind::ExternalSort);
+        assert_eq!(advice.operations[0].required_kb, 20_000);
+        assert_eq!(advice.operations[1].node_path, "Plan.Plans[1]");
+        assert_eq!(advice.operations[1].required_kb, 16_000);
+        assert_eq!(advice.per_operation_kb, 20 * 1024);
+        assert_eq!(advice.query_total_kb, 36 * 1024);
+        assert_eq!(format_kb(advice.per_operation_kb), "20MB");
+    }
+
+    #[test]
+    fn batched_hash_join_outer_temp_files_are_not_double_counted() {
+        let mut hash = LeafBuilder::new("Hash").build();
+        hash.memory.hash_batches = 8;
+        hash.memory.peak_memory_usage = 2_048;
+        hash.buffers.temp_written_blocks = 512;
+        let mut join = with_children(
+            LeafBuilder::new("Hash Join").build(),
+            [LeafBuilder::new("Seq Scan").build(), hash],
+        );
+        join.buffers.temp_written_blocks = 1_536;
+
+        let advice =
+            recommend_work_mem(&explain_for(join, 1.0, 1.0), DEFAULT_BLOCK_SIZE).expect("advice");
+
+        assert_eq!(advice.operations.len(), 1);
+        assert_eq!(advice.operations[0].kind, SpillKind::HashBatches);
+        assert_eq!(advice.operations[0].node_path, "Plan.Plans[1]");
+    }
+
+    #[test]
+    fn parallel_workers_multiply_query_total() {
+        let mut gather = with_children(
+            LeafBuilder::new("Gather Merge").build(),
+            [external_sort(1_024)],
+        );
+        gather.estimates.workers_planned = 2;
+        gather.actuals.workers_launched = 2;
+
+        let advice =
+            recommend_work_mem(&explain_for(gather, 1.0, 1.0), DEFAULT_BLOCK_SIZE).expect("advice");
+
+        assert_eq!(advice.operations[0].processes, 3);
+        assert_eq!(advice.per_operation_kb, 2 * 1024);
+        assert_eq!(advice.query_total_kb, 6 * 1024);
+    }
+
+    #[test]
+    fn hash_aggregate_disk_and_temp_files_are_detected() {
+        let mut agg = LeafBuilder::new("Aggregate").build();
+        agg.identity.strategy = "Hashed".into();
+        agg.memory.peak_memory_usage = 4_096;
+        agg.memory.disk_usage = 8_192;
+        agg.memory.hashagg_batches = 5;
+        let mut window = LeafBuilder::new("WindowAgg").build();
+        window.buffers.temp_written_blocks = 256;
+        let mut plan = with_children(LeafBuilder::new("Append").build(), [agg, window]);
+        plan.buffers.temp_written_blocks = 256;
+
+        let advice =
+            recommend_work_mem(&explain_for(plan, 1.0, 1.0), DEFAULT_BLOCK_SIZE).expect("advice");
+
+        assert_eq!(advice.operations[0].kind, SpillKind::HashAggregateDisk);
+        assert_eq!(advice.operations[0].required_kb, 12_288);
+        assert_eq!(advice.operations[1].kind, SpillKind::TempFiles);
+        assert_eq!(advice.operations[1].required_kb, 2_048);
+        // The parent's inclusive temp counters are not double counted.
+        assert_eq!(advice.operations.len(), 2);
+    }
+}
@@ -1,5 +1,6 @@
 use rustcmdpev_core::display::format::{format_tags, get_terminator};
 use rustcmdpev_core::display::tree::{node_joint, output_terminator, prefix_continuation};
+use rustcmdpev_core::structure::data::analysis_flags::EstimateErrorSource;
 use rustcmdpev_core::structure::data::plan::Plan;
 
 #[test]
@@ -14,6 +15,18 @@ fn format_tags_uses_shared_bad_estimate_threshold() {
     assert!(tags.contains("bad estimate"));
 }
 
+#[test]
+fn format_tags_distinguishes_origin_and_inherited_estimates() {
+    let mut origin = Plan::default();
+    origin.analysis_flags.estimate_error_source = EstimateErrorSource::Origin;
+    let mut inherited = Plan::default();
+    inherited.analysis_flags.estimate_error_source = EstimateErrorSource::Inherited;
+
+    assert!(format_tags(&origin).contains("estimate origin"));
+    assert!(format_tags(&inherited).contains("estimate inherited"));
+    assert!(format_tags(&Plan::default()).is_empty());
+}
+
 #[test]
 fn get_terminator_uses_shared_tree_markers() {
     let leaf = Plan::default();
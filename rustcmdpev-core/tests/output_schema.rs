@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rustcmdpev_core::constants::JSON_OUTPUT_SCHEMA_VERSION;
+use rustcmdpev_core::output::{json_schema, JsonOutput};
+use rustcmdpev_core::parser::parse_explain_document;
+
+const UPDATE_ENV: &str = "RUSTCMDPEV_UPDATE_SCHEMA";
+
+fn schema_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("schema")
+}
+
+fn committed_schema_path(version: u32) -> PathBuf {
+    schema_dir().join(format!("output.v{version}.json"))
+}
+
+fn pretty(value: &serde_json::Value) -> String {
+    serde_json::to_string_pretty(value).expect("serialize schema") + "\n"
+}
+
+/// The committed schema for the current version must match what the core
+/// types generate. A mismatch means the serialized shape changed: if the
+/// current version has been released, bump `JSON_OUTPUT_SCHEMA_VERSION`, then
+/// rerun with `RUSTCMDPEV_UPDATE_SCHEMA=1` to write `schema/output.v<N>.json`.
+#[test]
+fn json_output_shape_matches_committed_schema_for_current_version() {
+    let generated = pretty(&json_schema());
+    let path = committed_schema_path(JSON_OUTPUT_SCHEMA_VERSION);
+
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        fs::create_dir_all(schema_dir()).expect("create schema dir");
+        fs::write(&path, &generated).expect("write schema");
+    }
+
+    let committed = fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "read {}: {err}; run with {UPDATE_ENV}=1 after bumping JSON_OUTPUT_SCHEMA_VERSION",
+            path.display()
+        )
+    });
+    assert!(
+        committed == generated,
+        "JSON output shape drifted from {}. Regenerate it with {UPDATE_ENV}=1, bumping JSON_OUTPUT_SCHEMA_VERSION first if that version was released; never edit the schema of a released version in place.",
+        path.display()
+    );
+}
+
+#[test]
+fn older_schema_versions_differ_from_current() {
+    let current = pretty(&json_schema());
+    let current_path = committed_schema_path(JSON_OUTPUT_SCHEMA_VERSION);
+    for entry in fs::read_dir(schema_dir()).expect("read schema dir") {
+        let path = entry.expect("schema dir entry").path();
+        if path == current_path {
+            continue;
+        }
+        let older = fs::read_to_string(&path).expect("read older schema");
+        assert_ne!(
+            older,
+            current,
+            "{} is identical to v{JSON_OUTPUT_SCHEMA_VERSION}; the version was bumped without a shape change",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn serialized_output_carries_schema_version_and_documented_top_level_keys() {
+    let explain = parse_explain_document(
+        r#"[{"Plan":{"Node Type":"Seq Scan","Total Cost":1.0,"Actual Total Time":0.5,"Actual Rows":1,"Actual Loops":1},"Execution Time":0.6}]"#,
+    )
+    .expect("parse");
+    let output = serde_json::to_value(JsonOutput::new(&explain)).expect("serialize");
+    let schema = json_schema();
+    let properties = schema["properties"].as_object().expect("properties");
+
+    assert_eq!(output["schema_version"], JSON_OUTPUT_SCHEMA_VERSION);
+    for key in output.as_object().expect("object").keys() {
+        assert!(properties.contains_key(key), "undocumented key {key}");
+    }
+    assert!(output["plan"].get("costliest").is_some());
+    assert!(output["plan"].get("Costliest").is_none());
+
+    let plan_properties = schema["$defs"]["Plan"]["properties"]
+        .as_object()
+        .expect("plan properties");
+    for key in output["plan"].as_object().expect("plan object").keys() {
+        assert!(
+            plan_properties.contains_key(key),
+            "undocumented plan key {key}"
+        );
+    }
+    assert_eq!(output["plan"]["node_path"], "Plan");
+}
@@ -12,7 +12,7 @@ use rustcmdpev_core::structure::data::plan::Plan;
 
 fn leaf(node_type: &str, total_cost: f64, total_time: f64, rows: u64) -> Plan {
     let mut plan = Plan::default();
-    plan.identity.node_type = node_type.to_string();
+    plan.identity.node_type = node_type.into();
     plan.estimates = PlanEstimates {
         total_cost,
         ..PlanEstimates::default()
@@ -1,4 +1,8 @@
-use rustcmdpev_core::parser::{build_domain_explain, parse_raw_explains, validate_plan_tree};
+use rustcmdpev_core::constants::MAX_PLAN_DEPTH_CEILING;
+use rustcmdpev_core::parser::{
+    build_domain_explain, parse_explain_document_with, parse_raw_explains, validate_plan_tree,
+    ParseOptions,
+};
 use rustcmdpev_core::render::{render_explain, RenderOptions};
 use rustcmdpev_core::structure::data::io_timing::PlanIoTiming;
 use rustcmdpev_core::structure::raw::{PostgresSchemaProfile, RawPlanIoTiming};
@@ -194,8 +198,8 @@ fn validated_tree_rejects_negative_numeric_invariants() {
 
     let err = rustcmdpev_core::parse_and_process(input).expect_err("expected invariant error");
     match err {
-        VisualizeError::InvalidPlan(message) => {
-            assert!(message.contains("Startup Cost"));
+        VisualizeError::InvalidPlan(violation) => {
+            assert!(violation.message.contains("Startup Cost"));
         }
         other => panic!("expected invalid plan error, got {other:?}"),
     }
@@ -220,8 +224,8 @@ fn validated_tree_rejects_missing_node_types() {
 
     let err = rustcmdpev_core::parse_and_process(input).expect_err("expected invariant error");
     match err {
-        VisualizeError::InvalidPlan(message) => {
-            assert!(message.contains("Node Type"));
+        VisualizeError::InvalidPlan(violation) => {
+            assert!(violation.message.contains("Node Type"));
         }
         other => panic!("expected invalid plan error, got {other:?}"),
     }
@@ -241,8 +245,8 @@ fn validated_tree_rejects_excessive_depth() {
 
     let err = rustcmdpev_core::parse_and_process(&input).expect_err("expected depth guard");
     match err {
-        VisualizeError::InvalidPlan(message) => {
-            assert!(message.contains("maximum supported plan depth"));
+        VisualizeError::InvalidPlan(violation) => {
+            assert!(violation.message.contains("maximum supported plan depth"));
         }
         other => panic!("expected invalid plan error, got {other:?}"),
     }
@@ -261,8 +265,8 @@ fn validated_tree_rejects_excessive_node_count() {
 
     let err = rustcmdpev_core::parse_and_process(&input).expect_err("expected node count guard");
     match err {
-        VisualizeError::InvalidPlan(message) => {
-            assert!(message.contains("maximum supported node count"));
+        VisualizeError::InvalidPlan(violation) => {
+            assert!(violation.message.contains("maximum supported node count"));
         }
         other => panic!("expected invalid plan error, got {other:?}"),
     }
@@ -293,3 +297,220 @@ fn library_can_render_without_printing_as_part_of_analysis() {
     assert!(rendered.contains("Seq Scan"));
     assert!(rendered.contains("Execution Time"));
 }
+
+#[test]
+fn validated_tree_assigns_preorder_ids_and_paths() {
+    use rustcmdpev_core::parser::parse_explain_document;
+    use rustcmdpev_core::structure::tree::{find_node, NodeSelector};
+    use rustcmdpev_core::structure::visit::pre_order;
+
+    let explain = parse_explain_document(
+        r#"[{"Plan":{"Node Type":"Hash Join","Plans":[
+            {"Node Type":"Seq Scan"},
+            {"Node Type":"Hash","Plans":[{"Node Type":"Index Scan"}]}
+        ]}}]"#,
+    )
+    .expect("parse");
+
+    let hash = &explain.plan.plans[1];
+    assert_eq!(explain.plan.identity.node_id, 1);
+    assert_eq!(explain.plan.plans[0].identity.node_id, 2);
+    assert_eq!(hash.identity.node_id, 3);
+    assert_eq!(hash.plans[0].identity.node_id, 4);
+    let paths: Vec<String> = pre_order(&explain.plan).map(|node| node.path).collect();
+    assert_eq!(paths[0], "Plan");
+    assert_eq!(paths[3], "Plan.Plans[1].Plans[0]");
+
+    let by_id = NodeSelector::parse("#3").expect("id selector");
+    let by_path = NodeSelector::parse("Plan.Plans[1].Plans[0]").expect("path selector");
+    assert_eq!(
+        find_node(&explain.plan, &by_id).map(|node| node.path),
+        Some("Plan.Plans[1]".to_string())
+    );
+    assert_eq!(
+        find_node(&explain.plan, &by_path).map(|node| node.plan.identity.node_id),
+        Some(4)
+    );
+    assert!(find_node(&explain.plan, &NodeSelector::Id(9)).is_none());
+    assert!(NodeSelector::parse("Plans[0]").is_none());
+}
+
+#[test]
+fn plan_violations_carry_json_paths_and_positions() {
+    let input = "[\n  {\"Plan\": {\"Node Type\": \"Hash\", \"Plans\": [\n    {\"Node Type\": \"Seq Scan\", \"Startup Cost\": -2.0}\n  ]}}\n]";
+
+    let err = rustcmdpev_core::parse_and_process(input).expect_err("expected contract error");
+    let diagnostic = err.diagnostic(input);
+
+    assert!(err.is_contract_violation());
+    assert_eq!(diagnostic.kind, "contract_violation");
+    assert_eq!(
+        diagnostic.path.as_deref(),
+        Some("$[0].Plan.Plans[0].Startup Cost")
+    );
+    let position = diagnostic.position.expect("position");
+    assert_eq!(position.line, 3);
+    assert!(input[position.byte..].starts_with("-2.0"));
+    assert!(diagnostic.hint.is_some());
+}
+
+#[test]
+fn contract_violations_take_precedence_over_later_invariant_errors() {
+    let input =
+        r#"[{"Plan":{"Node Type":"","Plans":[{"Node Type":"Seq Scan","Total Cost":-1.0}]}}]"#;
+    let err = rustcmdpev_core::parse_and_process(input).expect_err("expected contract error");
+    assert!(err.is_contract_violation());
+    assert_eq!(
+        err.to_string(),
+        "$[0].Plan.Plans[0].Total Cost must be non-negative"
+    );
+
+    let input = r#"[{"Plan":{"Node Type":"Hash","Plans":[{"Node Type":" "}]}}]"#;
+    let err = rustcmdpev_core::parse_and_process(input).expect_err("expected invariant error");
+    assert!(!err.is_contract_violation());
+    assert_eq!(err.diagnostic(input).kind, "invalid_plan");
+    assert_eq!(
+        err.diagnostic(input).path.as_deref(),
+        Some("$[0].Plan.Plans[0].Node Type")
+    );
+}
+
+#[test]
+fn type_mismatches_report_the_contract_rule_they_break() {
+    let input = r#"[{"Plan":{"Node Type":"Seq Scan","Total Cost":"12"}}]"#;
+    let err = rustcmdpev_core::parse_and_process(input).expect_err("expected contract error");
+    assert!(err.is_contract_violation());
+    assert_eq!(
+        err.to_string(),
+        "$[0].Plan.Total Cost must be a number if present"
+    );
+}
+
+#[test]
+fn settings_are_parsed_and_checked() {
+    let input = r#"[{"Plan":{"Node Type":"Seq Scan"},"Settings":{"work_mem":"64MB","jit":false}}]"#;
+    let explain = rustcmdpev_core::parse_and_process(input).expect("expected settings to parse");
+    assert_eq!(explain.settings["work_mem"], "64MB");
+    assert_eq!(explain.settings["jit"], "false");
+    assert!(explain.extras.is_empty());
+
+    let input = r#"[{"Plan":{"Node Type":"Seq Scan"},"Settings":{"work_mem":null}}]"#;
+    let err = rustcmdpev_core::parse_and_process(input).expect_err("expected contract error");
+    assert!(err.is_contract_violation());
+    assert_eq!(
+        err.to_string(),
+        "$[0].Settings.work_mem must be a string, number, or boolean"
+    );
+}
+
+fn nested_plan_json(depth: usize) -> String {
+    let mut json = String::from("[{\"Plan\":");
+    for _ in 0..depth {
+        json.push_str("{\"Node Type\":\"Nested Loop\",\"Total Cost\":2.0,\"Plans\":[");
+    }
+    json.push_str("{\"Node Type\":\"Seq Scan\",\"Total Cost\":1.0}");
+    for _ in 0..depth {
+        json.push_str("]}");
+    }
+    json.push_str(",\"Execution Time\":1.5}]");
+    json
+}
+
+#[test]
+fn plan_limits_are_configurable_through_parse_options() {
+    let input = nested_plan_json(40);
+    let default = rustcmdpev_core::parse_and_process(&input).expect_err("default depth limit");
+    assert!(default
+        .to_string()
+        .contains("maximum supported plan depth of 32"));
+
+    let options = ParseOptions::new().with_max_depth(40);
+    let explain = rustcmdpev_core::parse_and_process_with(&input, &options).expect("raised limit");
+    assert_eq!(explain.plan.plans[0].identity.node_id, 2);
+
+    let options = ParseOptions::new().with_max_nodes(10);
+    let err = parse_explain_document_with(&nested_plan_json(20), &options.with_max_depth(64))
+        .expect_err("node limit");
+    assert_eq!(
+        err.to_string(),
+        "plan exceeds maximum supported node count of 10"
+    );
+}
+
+#[test]
+fn streaming_parses_plans_too_deep_for_recursion() {
+    let input = nested_plan_json(MAX_PLAN_DEPTH_CEILING);
+    let options = ParseOptions::new()
+        .with_max_depth(10_000)
+        .with_streaming(true);
+    let explain = parse_explain_document_with(&input, &options).expect("deep plan");
+
+    let mut node = &explain.plan;
+    let mut depth = 0;
+    while let Some(child) = node.plans.first() {
+        node = child;
+        depth += 1;
+    }
+    assert_eq!(depth, MAX_PLAN_DEPTH_CEILING);
+    assert_eq!(node.identity.node_id, 2_001);
+    assert_eq!(node.identity.node_type.as_str(), "Seq Scan");
+
+    // Limits above the ceiling act as the ceiling.
+    let err = parse_explain_document_with(&nested_plan_json(MAX_PLAN_DEPTH_CEILING + 1), &options)
+        .expect_err("past the ceiling");
+    assert!(err
+        .to_string()
+        .contains("maximum supported plan depth of 2000"));
+}
+
+#[test]
+fn streaming_matches_the_recursive_parser() {
+    let documents = [
+        include_str!("../../example.json"),
+        include_str!("../../rustcmdpev/tests/fixtures/diverse/append_union.json"),
+        include_str!("../../rustcmdpev/tests/fixtures/diverse/cte_recursive.json"),
+        include_str!("../../rustcmdpev/tests/fixtures/diverse/merge_join.json"),
+        r#"[{"Plan":{"Node Type":"Custom Scan","Parallel Aware":false,"Total Cost":1.0,"IO Read Time":1.5,"Plans":[{"Node Type":"Seq Scan","Output":["a","b"]}],"Custom Plan Provider":"columnar"},"Triggers":[],"Execution Time":0.5}]"#,
+    ];
+    let streaming = ParseOptions::new().with_streaming(true);
+    for document in documents {
+        let recursive = parse_explain_document_with(document, &ParseOptions::new()).expect("parse");
+        let streamed = parse_explain_document_with(document, &streaming).expect("stream");
+        assert_eq!(
+            serde_json::to_value(&streamed).expect("serialize"),
+            serde_json::to_value(&recursive).expect("serialize")
+        );
+    }
+}
+
+#[test]
+fn streaming_reports_the_same_errors_as_the_recursive_parser() {
+    let documents = [
+        "",
+        "[",
+        "{}",
+        "[]",
+        "[1]",
+        r#"[{"Plan":[]}]"#,
+        r#"[{"Execution Time":-1,"Plan":{"Node Type":"Seq Scan"}}]"#,
+        r#"[{"Planning Time":"soon","Plan":{"Node Type":"Seq Scan"}}]"#,
+        r#"[{"Plan":{"Node Type":"Hash","Plans":{}}}]"#,
+        r#"[{"Plan":{"Node Type":"Hash","Plans":[1]}}]"#,
+        r#"[{"Plan":{"Node Type":"Hash","Plans":[{"Node Type":"Seq Scan","Plan Rows":-3}]}}]"#,
+        r#"[{"Plan":{"Node Type":"","Plans":[{"Node Type":"Seq Scan","Total Cost":-1}]}}]"#,
+        r#"[{"Plan":{"Node Type":"Hash","Plans":[{"Node Type":" "}]}}]"#,
+        r#"[{"Plan":{"Node Type":"Hash","Plans":[{"Node Type":"Seq Scan",}]}}]"#,
+    ];
+    let streaming = ParseOptions::new().with_streaming(true);
+    for document in documents {
+        let recursive = parse_explain_document_with(document, &ParseOptions::new())
+            .expect_err("recursive error");
+        let streamed = parse_explain_document_with(document, &streaming).expect_err("stream error");
+        assert_eq!(streamed.to_string(), recursive.to_string(), "{document}");
+        assert_eq!(
+            streamed.is_contract_violation(),
+            recursive.is_contract_violation(),
+            "{document}"
+        );
+    }
+}
@@ -28,7 +28,7 @@ struct PlanSpec {
 impl PlanSpec {
     fn into_plan(self) -> Plan {
         let mut plan = Plan::default();
-        plan.identity.node_type = self.node_type;
+        plan.identity.node_type = self.node_type.into();
         plan.estimates = PlanEstimates {
             plan_rows: self.plan_rows,
             total_cost: self.total_cost,
@@ -0,0 +1,326 @@
+//! `rustcmdpev batch`: analyze every plan in a directory or filename pattern.
+//!
+//! Files are analyzed in parallel and each one on its own: a file that cannot
+//! be read, parsed, or rendered becomes a failure entry in the report instead
+//! of stopping the run. The aggregate report goes to stdout; with `--out-dir`
+//! every plan's own output is also written there in the selected `--format`.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use clap::{Args, ValueEnum};
+use colored::control;
+use rustcmdpev_core::batch::{BatchReport, FileFailure, FileRecord, DEFAULT_RANKED_QUERIES};
+use rustcmdpev_core::parser::ParseOptions;
+use rustcmdpev_core::render::RenderOptions;
+use tracing::{debug, info};
+
+use crate::{format_output, load_explain, read_input, to_json, Cli, CliError, OutputFormat};
+
+#[derive(Debug, Args)]
+pub(crate) struct BatchArgs {
+    /// Directories, whose `*.json` files are read, or plan files. A `*` or
+    /// `?` in the file name part selects matching files, e.g. `plans/q*.json`.
+    #[arg(required = true, value_name = "DIR|FILE|PATTERN")]
+    paths: Vec<String>,
+    /// Also write each plan's output, in `--format`, to this directory.
+    #[arg(long = "out-dir", value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+    /// Files analyzed at once. Defaults to the number of CPUs.
+    #[arg(long, short = 'j', value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+    /// Format of the aggregate report on stdout.
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    report: ReportFormat,
+    /// Queries ranked in the text report.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_RANKED_QUERIES)]
+    top: usize,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub(crate) enum ReportFormat {
+    Text,
+    Json,
+    /// One row per file, for spreadsheets and scripts.
+    Csv,
+}
+
+/// What happened to one file: its record, or the failure and its exit code.
+type Outcome = Result<FileRecord, (FileFailure, u8)>;
+
+/// Analyze every file `args` selects and print the aggregate report. Fails
+/// after printing when any file failed, with the first such file's exit code.
+pub(crate) fn run(
+    cli: &Cli,
+    args: &BatchArgs,
+    parse_options: &ParseOptions,
+    render_options: RenderOptions,
+) -> Result<(), CliError> {
+    let files = collect_files(&args.paths)?;
+    let outputs = match &args.out_dir {
+        Some(dir) => Some(output_paths(cli.format, dir, &files)?),
+        None => None,
+    };
+    // Per-file outputs are files, so they are never colored.
+    if outputs.is_some() {
+        control::set_override(false);
+    }
+
+    let jobs = args
+        .jobs
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get)
+        .min(files.len());
+    info!(files = files.len(), jobs, "analyzing plans");
+
+    let next = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<(usize, Outcome)>> = Mutex::new(Vec::with_capacity(files.len()));
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = files.get(index) else {
+                    break;
+                };
+                let output = outputs.as_ref().map(|outputs| outputs[index].as_path());
+                let outcome = analyze_isolated(cli, path, output, parse_options, render_options);
+                outcomes
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .push((index, outcome));
+            });
+        }
+    });
+    let mut outcomes = outcomes
+        .into_inner()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    outcomes.sort_by_key(|(index, _)| *index);
+
+    let mut records = Vec::new();
+    let mut failures = Vec::new();
+    let mut exit_code = None;
+    for (_, outcome) in outcomes {
+        match outcome {
+            Ok(record) => records.push(record),
+            Err((failure, code)) => {
+                exit_code.get_or_insert(code);
+                failures.push(failure);
+            }
+        }
+    }
+
+    let report = BatchReport::new(records, failures).with_ranked(args.top);
+    match args.report {
+        ReportFormat::Text => print!("{report}"),
+        ReportFormat::Json => print!("{}", to_json(&report)?),
+        ReportFormat::Csv => print!("{}", report.to_csv()),
+    }
+    match exit_code {
+        Some(exit_code) => Err(CliError::BatchFailures {
+            failed: report.failures.len(),
+            total: files.len(),
+            exit_code,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// [`analyze`], with a panic turned into a failure of that file alone.
+fn analyze_isolated(
+    cli: &Cli,
+    path: &Path,
+    output: Option<&Path>,
+    parse_options: &ParseOptions,
+    render_options: RenderOptions,
+) -> Outcome {
+    let analyzed = panic::catch_unwind(AssertUnwindSafe(|| {
+        analyze(cli, path, output, parse_options, render_options)
+    }));
+    let (message, exit_code) = match analyzed {
+        Ok(Ok(record)) => return Ok(record),
+        Ok(Err((message, exit_code))) => (message, exit_code),
+        Err(_) => ("analysis panicked".to_string(), 6),
+    };
+    info!(path = %path.display(), %message, "plan failed");
+    let failure = FileFailure {
+        path: path.display().to_string(),
+        message,
+    };
+    Err((failure, exit_code))
+}
+
+fn analyze(
+    cli: &Cli,
+    path: &Path,
+    output: Option<&Path>,
+    parse_options: &ParseOptions,
+    render_options: RenderOptions,
+) -> Result<FileRecord, (String, u8)> {
+    debug!(path = %path.display(), "analyzing plan");
+    let input = read_input(Some(&path.to_path_buf())).map_err(|err| failure(&err, None))?;
+    let explain =
+        load_explain(cli, &input, parse_options).map_err(|err| failure(&err, Some(&input)))?;
+    if let Some(output) = output {
+        let rendered =
+            format_output(cli, &explain, render_options).map_err(|err| failure(&err, None))?;
+        fs::write(output, rendered).map_err(|err| {
+            let err = CliError::OutputSerialization(format!(
+                "failed to write '{}': {err}",
+                output.display()
+            ));
+            failure(&err, None)
+        })?;
+    }
+    Ok(FileRecord::new(
+        path.display().to_string(),
+        &explain,
+        cli.block_size,
+    ))
+}
+
+fn failure(err: &CliError, input: Option<&str>) -> (String, u8) {
+    (err.diagnostic(input).message, err.exit_code())
+}
+
+/// The files `patterns` select, sorted and without duplicates.
+fn collect_files(patterns: &[String]) -> Result<Vec<PathBuf>, CliError> {
+    let mut files = BTreeSet::new();
+    for pattern in patterns {
+        let path = Path::new(pattern);
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let selected = if name.contains(['*', '?']) {
+            let dir = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            list(dir, |file| glob_match(name, file))?
+        } else if path.is_dir() {
+            list(path, |file| {
+                Path::new(file)
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+            })?
+        } else if path.is_file() {
+            vec![path.to_path_buf()]
+        } else {
+            return Err(CliError::InputRead(format!(
+                "'{pattern}' is not a file or directory"
+            )));
+        };
+        if selected.is_empty() {
+            return Err(CliError::InputRead(format!(
+                "'{pattern}' matched no plan files"
+            )));
+        }
+        files.extend(selected);
+    }
+    Ok(files.into_iter().collect())
+}
+
+/// Regular files directly in `dir` whose name passes `keep`.
+fn list(dir: &Path, keep: impl Fn(&str) -> bool) -> Result<Vec<PathBuf>, CliError> {
+    let read_error = |err: std::io::Error| {
+        CliError::InputRead(format!(
+            "failed to read directory '{}': {err}",
+            dir.display()
+        ))
+    };
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(read_error)? {
+        let entry = entry.map_err(read_error)?;
+        let path = entry.path();
+        let name = entry.file_name();
+        if path.is_file() && name.to_str().is_some_and(&keep) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Whether `name` matches `pattern`, where `*` is any run of characters and
+/// `?` exactly one.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was and how much of `name` it has taken so far.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Where each file's output goes: its file stem plus the format's extension.
+/// Refuses names that would collide or overwrite an input.
+fn output_paths(
+    format: OutputFormat,
+    dir: &Path,
+    files: &[PathBuf],
+) -> Result<Vec<PathBuf>, CliError> {
+    fs::create_dir_all(dir).map_err(|err| {
+        CliError::InputRead(format!(
+            "failed to create output directory '{}': {err}",
+            dir.display()
+        ))
+    })?;
+    let extension = match format {
+        OutputFormat::Pretty | OutputFormat::Table => "txt",
+        OutputFormat::Json | OutputFormat::Postgres => "json",
+    };
+    let inputs: BTreeSet<PathBuf> = files
+        .iter()
+        .filter_map(|file| file.canonicalize().ok())
+        .collect();
+    let mut claimed: HashMap<PathBuf, &Path> = HashMap::new();
+    let mut outputs = Vec::with_capacity(files.len());
+    for file in files {
+        let stem = file.file_stem().unwrap_or(file.as_os_str());
+        let output = dir.join(stem).with_extension(extension);
+        if let Some(other) = claimed.insert(output.clone(), file) {
+            return Err(CliError::InputRead(format!(
+                "'{}' and '{}' would both be written to '{}'",
+                other.display(),
+                file.display(),
+                output.display()
+            )));
+        }
+        if output
+            .canonicalize()
+            .is_ok_and(|output| inputs.contains(&output))
+        {
+            return Err(CliError::InputRead(format!(
+                "writing '{}' would overwrite an input; choose another --out-dir",
+                output.display()
+            )));
+        }
+        outputs.push(output);
+    }
+    Ok(outputs)
+}
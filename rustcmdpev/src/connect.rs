@@ -0,0 +1,203 @@
+//! `rustcmdpev run`: EXPLAIN a statement over a live PostgreSQL connection.
+//!
+//! The statement is wrapped in `EXPLAIN (..., FORMAT JSON)` and sent through
+//! the extended query protocol, which accepts exactly one statement, inside a
+//! transaction that is rolled back unless `--commit` is given. The plan comes
+//! back as the server's JSON text and goes through the normal pipeline.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+use postgres::error::ErrorPosition;
+use postgres::types::{FromSql, Type};
+use postgres::{Client, NoTls};
+use rustcmdpev_core::diagnostic::{Diagnostic, SourcePosition};
+use tracing::{debug, info};
+
+use crate::CliError;
+
+#[derive(Debug, Args)]
+pub(crate) struct RunArgs {
+    /// Connection string, as a `postgres://` URL or `key=value` pairs.
+    /// Defaults to `$DATABASE_URL`.
+    #[arg(long, value_name = "DSN")]
+    dsn: Option<String>,
+    /// File holding the statement to explain.
+    #[arg(
+        long,
+        short = 'f',
+        value_name = "PATH",
+        required_unless_present = "sql",
+        conflicts_with = "sql"
+    )]
+    file: Option<PathBuf>,
+    /// Statement to explain, instead of `--file`.
+    #[arg(long = "command", short = 'c', value_name = "SQL")]
+    sql: Option<String>,
+    /// EXPLAIN options to enable; `FORMAT JSON` is always added.
+    #[arg(
+        long = "explain",
+        value_name = "OPTIONS",
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [
+            ExplainOption::Analyze,
+            ExplainOption::Buffers,
+            ExplainOption::Verbose,
+            ExplainOption::Settings,
+        ]
+    )]
+    options: Vec<ExplainOption>,
+    /// Keep the statement's effects instead of rolling them back.
+    #[arg(long)]
+    commit: bool,
+}
+
+impl RunArgs {
+    /// Name of the statement's source in diagnostics.
+    pub(crate) fn source_name(&self) -> String {
+        self.file.as_ref().map_or_else(
+            || "<command>".to_string(),
+            |path| path.display().to_string(),
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub(crate) enum ExplainOption {
+    /// Execute the statement and report actual times and rows.
+    Analyze,
+    Buffers,
+    Verbose,
+    /// Planner settings changed from their defaults (PostgreSQL 12+).
+    Settings,
+    /// WAL records generated (PostgreSQL 13+, needs `analyze`).
+    Wal,
+    /// Planner memory use (PostgreSQL 17+).
+    Memory,
+}
+
+impl ExplainOption {
+    fn keyword(self) -> &'static str {
+        match self {
+            ExplainOption::Analyze => "ANALYZE",
+            ExplainOption::Buffers => "BUFFERS",
+            ExplainOption::Verbose => "VERBOSE",
+            ExplainOption::Settings => "SETTINGS",
+            ExplainOption::Wal => "WAL",
+            ExplainOption::Memory => "MEMORY",
+        }
+    }
+}
+
+/// EXPLAIN output as the server's JSON text, unparsed.
+struct PlanJson(String);
+
+impl<'a> FromSql<'a> for PlanJson {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        Ok(PlanJson(String::from_utf8(raw.to_vec())?))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::JSON
+    }
+}
+
+/// Run the statement under EXPLAIN and return the plan JSON. `source` holds
+/// the statement meanwhile, so a server error is shown against it.
+pub(crate) fn explain(args: &RunArgs, source: &mut Option<String>) -> Result<String, CliError> {
+    let text = read_statement(args)?;
+    *source = Some(text.clone());
+    let statement = text.trim_end().trim_end_matches(';').trim_end();
+    if statement.trim().is_empty() {
+        return Err(CliError::InputRead(format!(
+            "{} holds no statement to explain",
+            args.source_name()
+        )));
+    }
+    let prefix = explain_prefix(&args.options);
+    let query = format!("{prefix}{statement}");
+
+    let dsn = match &args.dsn {
+        Some(dsn) => dsn.clone(),
+        None => env::var("DATABASE_URL").map_err(|_| {
+            CliError::InputRead("no connection string; pass --dsn or set DATABASE_URL".to_string())
+        })?,
+    };
+    info!("connecting to the database");
+    let mut client = Client::connect(&dsn, NoTls)
+        .map_err(|err| CliError::InputRead(format!("failed to connect: {}", describe(&err))))?;
+    let mut transaction = client
+        .transaction()
+        .map_err(|err| CliError::InputRead(format!("failed to begin: {}", describe(&err))))?;
+
+    debug!(%query, "running explain");
+    let row = transaction
+        .query_one(&query, &[])
+        .map_err(|err| statement_error(&err, prefix.len(), statement))?;
+    let plan: PlanJson = row.try_get(0).map_err(|err| {
+        CliError::InputRead(format!("unexpected EXPLAIN result: {}", describe(&err)))
+    })?;
+
+    let finish = if args.commit {
+        info!("committing the explained statement");
+        transaction.commit()
+    } else {
+        debug!("rolling back the explained statement");
+        transaction.rollback()
+    };
+    finish.map_err(|err| CliError::InputRead(format!("failed to end: {}", describe(&err))))?;
+    Ok(plan.0)
+}
+
+fn read_statement(args: &RunArgs) -> Result<String, CliError> {
+    match (&args.sql, &args.file) {
+        (Some(sql), _) => Ok(sql.clone()),
+        (None, Some(path)) => fs::read_to_string(path).map_err(|err| {
+            CliError::InputRead(format!(
+                "failed to read statement file '{}': {err}",
+                path.display()
+            ))
+        }),
+        (None, None) => unreachable!("clap requires --file or --command"),
+    }
+}
+
+fn explain_prefix(options: &[ExplainOption]) -> String {
+    let mut keywords: Vec<&str> = Vec::new();
+    for option in options {
+        if !keywords.contains(&option.keyword()) {
+            keywords.push(option.keyword());
+        }
+    }
+    keywords.push("FORMAT JSON");
+    format!("EXPLAIN ({}) ", keywords.join(", "))
+}
+
+/// The server's message when there is one, else the client's.
+fn describe(err: &postgres::Error) -> String {
+    err.as_db_error()
+        .map_or_else(|| err.to_string(), |db| db.message().to_string())
+}
+
+/// A statement the server rejected, positioned within the user's text.
+fn statement_error(err: &postgres::Error, prefix_len: usize, statement: &str) -> CliError {
+    let Some(db) = err.as_db_error() else {
+        return CliError::InputRead(format!("failed to run EXPLAIN: {err}"));
+    };
+    let mut diagnostic = Diagnostic::new("database_error", db.message().to_string());
+    if let Some(hint) = db.hint() {
+        diagnostic = diagnostic.with_hint(hint.to_string());
+    }
+    // The server counts characters from 1 across the wrapped query.
+    if let Some(ErrorPosition::Original(position)) = db.position() {
+        let offset = (*position as usize).saturating_sub(1 + prefix_len);
+        if let Some((byte, _)) = statement.char_indices().nth(offset) {
+            diagnostic = diagnostic.with_position(SourcePosition::from_byte(statement, byte));
+        }
+    }
+    CliError::InvalidInput(diagnostic)
+}
@@ -0,0 +1,188 @@
+//! `rustcmdpev record` and `rustcmdpev history`: a local plan history.
+//!
+//! The store is a directory holding one JSON-lines file per query key, each
+//! line a [`HistoryEntry`]. `record` appends the analyzed plan; `history`
+//! reads a query's runs back and reports the trend and any regression of the
+//! latest run against the runs before it.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::Args;
+use rustcmdpev_core::constants::HISTORY_BASELINE_RUNS;
+use rustcmdpev_core::history::{query_key, HistoryEntry, HistoryReport};
+use rustcmdpev_core::structure::data::explain::Explain;
+use tracing::{info, warn};
+
+use crate::{Cli, CliError};
+
+const DEFAULT_STORE: &str = ".rustcmdpev/history";
+
+#[derive(Debug, Args)]
+pub(crate) struct StoreArgs {
+    /// Directory holding the history, one `<query>.jsonl` file per query.
+    #[arg(long, value_name = "DIR", default_value = DEFAULT_STORE)]
+    store: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct RecordArgs {
+    /// Plan to record. Defaults to `--input`, then stdin.
+    #[arg(value_name = "PATH")]
+    pub(crate) path: Option<PathBuf>,
+    /// Key to file the run under. Defaults to the plan's `Query Identifier`
+    /// (PostgreSQL 14+ with `compute_query_id`), else a hash of its
+    /// `Query Text`; required when the plan has neither.
+    #[arg(long, value_name = "KEY")]
+    query: Option<String>,
+    #[command(flatten)]
+    store: StoreArgs,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct HistoryArgs {
+    /// Query key, as printed by `record`.
+    #[arg(value_name = "KEY", allow_hyphen_values = true)]
+    query: String,
+    /// Earlier runs each run is compared with.
+    #[arg(long, value_name = "N", default_value_t = NonZeroUsize::new(HISTORY_BASELINE_RUNS).expect("non-zero default"))]
+    baseline: NonZeroUsize,
+    /// List only the most recent N runs.
+    #[arg(long, value_name = "N")]
+    last: Option<usize>,
+    #[command(flatten)]
+    store: StoreArgs,
+}
+
+/// Append `explain` to its query's history and say where it went.
+pub(crate) fn record(cli: &Cli, args: &RecordArgs, explain: &Explain) -> Result<(), CliError> {
+    let query = match &args.query {
+        Some(query) => query.clone(),
+        None => query_key(explain).ok_or_else(|| {
+            CliError::InputRead(
+                "the plan has no Query Identifier or Query Text to file it under; pass --query KEY"
+                    .to_string(),
+            )
+        })?,
+    };
+    let path = history_file(&args.store.store, &query)?;
+    let source = args
+        .path
+        .as_ref()
+        .or(cli.input.as_ref())
+        .map_or_else(|| "<stdin>".to_string(), |path| path.display().to_string());
+    let recorded_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let entry = HistoryEntry::new(query.as_str(), source, explain, cli.block_size, recorded_at);
+
+    let write_error = |err: std::io::Error| {
+        CliError::OutputSerialization(format!("failed to write '{}': {err}", path.display()))
+    };
+    fs::create_dir_all(&args.store.store).map_err(write_error)?;
+    let earlier = match fs::read_to_string(&path) {
+        Ok(history) => history
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .count(),
+        Err(_) => 0,
+    };
+    let mut line = serde_json::to_string(&entry).map_err(|err| {
+        CliError::OutputSerialization(format!("failed to serialize history entry: {err}"))
+    })?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(write_error)?;
+
+    info!(path = %path.display(), "recorded run");
+    println!(
+        "Recorded run {} of query {query} (plan fingerprint {}) in {}",
+        earlier + 1,
+        entry.fingerprint,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Print the trend of a query's recorded runs.
+pub(crate) fn show(args: &HistoryArgs) -> Result<(), CliError> {
+    let path = history_file(&args.store.store, &args.query)?;
+    let history = fs::read_to_string(&path).map_err(|err| {
+        let recorded = recorded_queries(&args.store.store);
+        let known = if recorded.is_empty() {
+            String::new()
+        } else {
+            format!("; recorded queries: {}", recorded.join(", "))
+        };
+        CliError::InputRead(format!(
+            "no history for query {} in '{}' ({err}){known}",
+            args.query,
+            args.store.store.display()
+        ))
+    })?;
+
+    let mut entries = Vec::new();
+    for (index, line) in history.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<HistoryEntry>(line) {
+            Ok(entry) => entries.push(entry),
+            Err(err) => warn!(
+                path = %path.display(),
+                line = index + 1,
+                %err,
+                "skipping unreadable history entry"
+            ),
+        }
+    }
+
+    let mut report = HistoryReport::with_baseline(&args.query, &entries, args.baseline.get());
+    if let Some(last) = args.last {
+        report = report.with_shown(last);
+    }
+    print!("{report}");
+    Ok(())
+}
+
+/// The history file for `query`, which must be usable as a file name.
+fn history_file(store: &Path, query: &str) -> Result<PathBuf, CliError> {
+    let valid = !query.is_empty()
+        && !query.starts_with('.')
+        && query
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(CliError::InputRead(format!(
+            "query key {query:?} may only hold ASCII letters, digits, '-', '_', and '.'"
+        )));
+    }
+    Ok(store.join(format!("{query}.jsonl")))
+}
+
+/// Keys with a history file in `store`, sorted.
+fn recorded_queries(store: &Path) -> Vec<String> {
+    let mut queries: Vec<String> = fs::read_dir(store)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let is_history = path
+                .extension()
+                .is_some_and(|extension| extension == "jsonl");
+            is_history
+                .then(|| path.file_stem()?.to_str().map(str::to_string))
+                .flatten()
+        })
+        .collect();
+    queries.sort();
+    queries
+}
@@ -1,25 +1,60 @@
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::control;
-use rustcmdpev_core::constants::{BAD_ESTIMATE_FACTOR_THRESHOLD, MAX_PLAN_DEPTH, MAX_PLAN_NODES};
+use rustcmdpev_core::analyze_explain_with;
+use rustcmdpev_core::constants::{
+    BAD_ESTIMATE_FACTOR_THRESHOLD, DEFAULT_BLOCK_SIZE, MAX_PLAN_DEPTH, MAX_PLAN_DEPTH_CEILING,
+    MAX_PLAN_NODES,
+};
+use rustcmdpev_core::delta::PlanDelta;
+use rustcmdpev_core::diagnostic::Diagnostic;
 use rustcmdpev_core::display::colors::Theme;
 use rustcmdpev_core::display::tree::TreeStyle;
-use rustcmdpev_core::parser::ParseOptions;
-use rustcmdpev_core::render::{RenderMode, RenderOptions, SummaryStyle};
+use rustcmdpev_core::output::JsonOutput;
+use rustcmdpev_core::parser::{parse_explain_document_with, ParseOptions};
+use rustcmdpev_core::pipeline::Pipeline;
+use rustcmdpev_core::redact::{RedactOptions, RedactPass};
+use rustcmdpev_core::render::{render_explain, RenderMode, RenderOptions, SummaryStyle};
+use rustcmdpev_core::structure::data::analysis_flags::EstimateErrorSource;
 use rustcmdpev_core::structure::data::explain::Explain;
+use rustcmdpev_core::structure::tree::{find_node, NodeSelector};
+use rustcmdpev_core::structure::visit::pre_order_at;
+use serde::Serialize;
 use serde_json::Value;
 use std::env;
+use std::fmt::Write as _;
 use std::fs;
-use std::io::{self, IsTerminal, Read};
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::PathBuf;
 use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
 use tracing::{debug, info};
 use tracing_subscriber::EnvFilter;
 
+mod batch;
+#[cfg(feature = "connect")]
+mod connect;
+mod history;
+
+/// How often `--watch` checks the input file for changes.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
 enum OutputFormat {
     Pretty,
     Json,
     Table,
+    /// EXPLAIN JSON under PostgreSQL's own key names. Known keys holding
+    /// their default value, such as `"Startup Cost": 0`, are omitted.
+    Postgres,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum ErrorFormat {
+    /// Message with the offending input line and a caret.
+    Text,
+    /// One JSON object per failure on stderr.
+    Json,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
@@ -95,6 +130,24 @@ impl From<CliTreeStyle> for TreeStyle {
     }
 }
 
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the JSON Schema describing `--format json` output.
+    Schema,
+    /// Analyze every plan in directories or file patterns and print an
+    /// aggregate report: slowest queries, common hotspots, and findings.
+    Batch(batch::BatchArgs),
+    /// Analyze a plan and append it to the local history of its query.
+    Record(history::RecordArgs),
+    /// Show a query's recorded runs and flag regressions of the latest one
+    /// against the runs before it.
+    History(history::HistoryArgs),
+    /// EXPLAIN a statement on a live server and render its plan. The
+    /// statement runs in a transaction that is rolled back by default.
+    #[cfg(feature = "connect")]
+    Run(connect::RunArgs),
+}
+
 #[derive(Debug, Parser)]
 #[command(
     name = "rustcmdpev",
@@ -102,6 +155,8 @@ impl From<CliTreeStyle> for TreeStyle {
     version
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(long, short, value_name = "PATH")]
     input: Option<PathBuf>,
     #[arg(long, value_enum, default_value_t = OutputFormat::Pretty)]
@@ -120,35 +175,131 @@ struct Cli {
     /// Used to disambiguate IO-timing fields between PG <13 and PG ≥13 plans.
     #[arg(long = "postgres-version", value_name = "VERSION")]
     postgres_version: Option<String>,
+    /// Server block size in bytes for builds with a non-default `BLCKSZ`.
+    /// Used to compute read throughput from block counts.
+    #[arg(long = "block-size", value_name = "BYTES", default_value_t = DEFAULT_BLOCK_SIZE, value_parser = parse_block_size)]
+    block_size: u64,
+    /// Deepest plan node accepted; the root is at depth 0. At most
+    /// 2000, the deepest plan every output format handles.
+    #[arg(long = "max-depth", value_name = "N", default_value_t = MAX_PLAN_DEPTH, value_parser = parse_max_depth)]
+    max_depth: usize,
+    /// Largest number of plan nodes accepted.
+    #[arg(long = "max-nodes", value_name = "N", default_value_t = MAX_PLAN_NODES)]
+    max_nodes: usize,
+    /// Read the plan tree iteratively instead of recursively. Implied when
+    /// `--max-depth` is too deep for the recursive parser.
+    #[arg(long)]
+    stream: bool,
+    /// Append a per-relation and per-index rollup (pretty) or a
+    /// `relations` block (json).
+    #[arg(long = "by-relation")]
+    by_relation: bool,
+    /// Append heuristic `CREATE INDEX` candidates for selective filters and
+    /// join conditions (pretty), or an `index_suggestions` block (json).
+    #[arg(long = "suggest-indexes")]
+    suggest_indexes: bool,
+    /// List casts and function calls on columns in scan filters and join
+    /// conditions that keep an index from being used (pretty), or a
+    /// `cast_issues` block (json).
+    #[arg(long = "check-casts")]
+    check_casts: bool,
+    /// Focus output on one subtree, addressed by node id (`3` or `#3`) or
+    /// path (`Plan.Plans[0]`).
+    #[arg(long = "node", value_name = "ID|PATH", value_parser = parse_node_selector)]
+    node: Option<NodeSelector>,
+    /// Re-read `--input` whenever it changes and redraw the output. An
+    /// unparseable write shows an error banner and waits for the next one.
+    #[arg(long, requires = "input")]
+    watch: bool,
+    /// With `--watch`, print what changed since the previous version on
+    /// stderr: execution time, hotspot, and node types.
+    #[arg(long, requires = "watch")]
+    delta: bool,
+    /// Replace literals in filters, conditions, and outputs with `$n`
+    /// placeholders before analysis, so the output can be shared.
+    #[arg(long)]
+    redact: bool,
+    /// Also replace schema, relation, index, alias, and column names with
+    /// keyed hashes that stay consistent across the plan. Implies `--redact`.
+    #[arg(long = "redact-identifiers")]
+    redact_identifiers: bool,
+    /// Key for `--redact-identifiers` hashes; keep it private so names
+    /// cannot be confirmed by hashing guesses.
+    #[arg(
+        long = "redact-salt",
+        value_name = "KEY",
+        requires = "redact_identifiers"
+    )]
+    redact_salt: Option<String>,
     #[arg(long)]
     width: Option<usize>,
     #[arg(long)]
     compat: bool,
+    /// How failures are reported on stderr.
+    #[arg(long = "error-format", value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
     #[arg(short = 'v', long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
     verbose: u8,
     #[arg(short = 'q', long, conflicts_with = "verbose")]
     quiet: bool,
 }
 
+fn parse_node_selector(value: &str) -> Result<NodeSelector, String> {
+    NodeSelector::parse(value).ok_or_else(|| {
+        format!("expected a node id (e.g. 3 or #3) or a path (e.g. Plan.Plans[0]), got {value:?}")
+    })
+}
+
+fn parse_block_size(value: &str) -> Result<u64, String> {
+    let size: u64 = value
+        .parse()
+        .map_err(|_| format!("'{value}' is not a block size in bytes"))?;
+    if !(1024..=32768).contains(&size) || !size.is_power_of_two() {
+        return Err("block size must be a power of two between 1024 and 32768".to_string());
+    }
+    Ok(size)
+}
+
+fn parse_max_depth(value: &str) -> Result<usize, String> {
+    let depth: usize = value
+        .parse()
+        .map_err(|_| format!("'{value}' is not a plan depth"))?;
+    if depth > MAX_PLAN_DEPTH_CEILING {
+        return Err(format!(
+            "plan depth must be at most {MAX_PLAN_DEPTH_CEILING}"
+        ));
+    }
+    Ok(depth)
+}
+
 #[derive(Debug)]
 enum CliError {
     InputRead(String),
-    ContractViolation(String),
-    InvalidInput(String),
+    InvalidInput(Diagnostic),
     InvalidCompatibility(String),
     OutputSerialization(String),
     Core(rustcmdpev_core::VisualizeError),
+    /// Some files of a batch failed; the first one's code is the exit code.
+    BatchFailures {
+        failed: usize,
+        total: usize,
+        exit_code: u8,
+    },
 }
 
 impl std::fmt::Display for CliError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             CliError::InputRead(msg)
-            | CliError::ContractViolation(msg)
-            | CliError::InvalidInput(msg)
             | CliError::InvalidCompatibility(msg)
             | CliError::OutputSerialization(msg) => write!(f, "{msg}"),
+            CliError::InvalidInput(diagnostic) => {
+                write!(f, "{diagnostic}")
+            }
             CliError::Core(err) => write!(f, "{err}"),
+            CliError::BatchFailures { failed, total, .. } => {
+                write!(f, "{failed} of {total} plans could not be analyzed")
+            }
         }
     }
 }
@@ -157,10 +308,33 @@ impl CliError {
     fn exit_code(&self) -> u8 {
         match self {
             CliError::InputRead(_) => 2,
-            CliError::ContractViolation(_) | CliError::InvalidInput(_) => 3,
+            CliError::InvalidInput(_) => 3,
             CliError::InvalidCompatibility(_) => 4,
             CliError::OutputSerialization(_) => 5,
+            CliError::Core(err) if err.is_contract_violation() => 3,
             CliError::Core(_) => 6,
+            CliError::BatchFailures { exit_code, .. } => *exit_code,
+        }
+    }
+
+    /// Structured form of the error, positioned within `input` when the
+    /// payload was read.
+    fn diagnostic(&self, input: Option<&str>) -> Diagnostic {
+        let diagnostic = match self {
+            CliError::InputRead(msg) => Diagnostic::new("input_read", msg.clone()),
+            CliError::InvalidInput(diagnostic) => diagnostic.clone(),
+            CliError::InvalidCompatibility(msg) => {
+                Diagnostic::new("invalid_compatibility", msg.clone())
+            }
+            CliError::OutputSerialization(msg) => {
+                Diagnostic::new("output_serialization", msg.clone())
+            }
+            CliError::Core(err) => return err.diagnostic(input.unwrap_or_default()),
+            CliError::BatchFailures { .. } => Diagnostic::new("batch_failures", self.to_string()),
+        };
+        match input {
+            Some(input) => diagnostic.located_in(input),
+            None => diagnostic,
         }
     }
 }
@@ -212,148 +386,6 @@ fn read_input(input: Option<&PathBuf>) -> Result<String, CliError> {
     Ok(buffer)
 }
 
-fn validate_stdin_json_contract(input: &str) -> Result<(), CliError> {
-    debug!("validating stdin JSON contract");
-    let parsed: Value = serde_json::from_str(input).map_err(|err| {
-        CliError::InvalidInput(format!(
-            "invalid JSON input: {err}. Ensure input is a PostgreSQL EXPLAIN FORMAT JSON array."
-        ))
-    })?;
-
-    let arr = parsed.as_array().ok_or_else(|| {
-        CliError::ContractViolation("top-level JSON must be an array".to_string())
-    })?;
-    let first = arr.first().ok_or_else(|| {
-        CliError::ContractViolation(
-            "top-level JSON array must contain at least one explain object".to_string(),
-        )
-    })?;
-    let first_obj = first.as_object().ok_or_else(|| {
-        CliError::ContractViolation("first explain entry must be a JSON object".to_string())
-    })?;
-
-    validate_optional_non_negative_number(first_obj, "Planning Time", "$[0]")?;
-    validate_optional_non_negative_number(first_obj, "Execution Time", "$[0]")?;
-
-    match first_obj.get("Plan") {
-        Some(Value::Object(plan)) => {
-            let mut node_count = 0;
-            validate_plan_node(plan, "$[0].Plan", 0, &mut node_count)
-        }
-        _ => Err(CliError::ContractViolation(
-            "first explain object must contain 'Plan' object".to_string(),
-        )),
-    }
-}
-
-fn validate_optional_non_negative_number(
-    obj: &serde_json::Map<String, Value>,
-    key: &str,
-    path: &str,
-) -> Result<(), CliError> {
-    if let Some(value) = obj.get(key) {
-        let n = value.as_f64().ok_or_else(|| {
-            CliError::ContractViolation(format!("{path}.{key} must be a number if present"))
-        })?;
-        if n < 0.0 {
-            return Err(CliError::ContractViolation(format!(
-                "{path}.{key} must be non-negative"
-            )));
-        }
-    }
-    Ok(())
-}
-
-fn validate_optional_non_negative_u64(
-    obj: &serde_json::Map<String, Value>,
-    key: &str,
-    path: &str,
-) -> Result<(), CliError> {
-    if let Some(value) = obj.get(key) {
-        if value.as_u64().is_none() {
-            return Err(CliError::ContractViolation(format!(
-                "{path}.{key} must be a non-negative integer if present"
-            )));
-        }
-    }
-    Ok(())
-}
-
-fn validate_plan_node(
-    plan: &serde_json::Map<String, Value>,
-    path: &str,
-    depth: usize,
-    node_count: &mut usize,
-) -> Result<(), CliError> {
-    const NON_NEGATIVE_FLOAT_FIELDS: &[&str] = &[
-        "Startup Cost",
-        "Total Cost",
-        "Actual Cost",
-        "Actual Duration",
-        "Actual Startup Time",
-        "Actual Total Time",
-        "I/O Read Time",
-        "I/O Write Time",
-    ];
-    const NON_NEGATIVE_INT_FIELDS: &[&str] = &[
-        "Actual Loops",
-        "Actual Rows",
-        "Plan Rows",
-        "Plan Width",
-        "Rows Removed By Filter",
-        "Rows Removed By Index Recheck",
-        "Shared Dirtied Blocks",
-        "Shared Hit Blocks",
-        "Shared Read Blocks",
-        "Shared Written Blocks",
-        "Local Dirtied Blocks",
-        "Local Hit Blocks",
-        "Local Read Blocks",
-        "Local Written Blocks",
-        "Temp Read Blocks",
-        "Temp Written Blocks",
-        "Heap Fetches",
-    ];
-
-    if depth > MAX_PLAN_DEPTH {
-        return Err(CliError::ContractViolation(format!(
-            "{path} exceeds maximum supported plan depth of {MAX_PLAN_DEPTH}"
-        )));
-    }
-    *node_count += 1;
-    if *node_count > MAX_PLAN_NODES {
-        return Err(CliError::ContractViolation(format!(
-            "plan exceeds maximum supported node count of {MAX_PLAN_NODES}"
-        )));
-    }
-
-    for field in NON_NEGATIVE_FLOAT_FIELDS {
-        validate_optional_non_negative_number(plan, field, path)?;
-    }
-    for field in NON_NEGATIVE_INT_FIELDS {
-        validate_optional_non_negative_u64(plan, field, path)?;
-    }
-
-    if let Some(children) = plan.get("Plans") {
-        let child_arr = children.as_array().ok_or_else(|| {
-            CliError::ContractViolation(format!("{path}.Plans must be an array if present"))
-        })?;
-        for (idx, child) in child_arr.iter().enumerate() {
-            let child_obj = child.as_object().ok_or_else(|| {
-                CliError::ContractViolation(format!("{path}.Plans[{idx}] must be an object"))
-            })?;
-            validate_plan_node(
-                child_obj,
-                &format!("{path}.Plans[{idx}]"),
-                depth + 1,
-                node_count,
-            )?;
-        }
-    }
-
-    Ok(())
-}
-
 fn configure_color(mode: ColorMode) {
     let use_color = match mode {
         ColorMode::Always => true,
@@ -369,50 +401,93 @@ fn configure_color(mode: ColorMode) {
 fn parse_and_process_explain(
     input: &str,
     parse_options: &ParseOptions,
+    pipeline: &Pipeline,
 ) -> Result<Explain, CliError> {
     debug!("parsing and processing explain payload");
-    rustcmdpev_core::parse_and_process_with(input, parse_options).map_err(CliError::Core)
-}
-
-fn write_table(explain: &Explain) {
-    println!("NODE | DURATION_MS | COST | ROWS | TAGS");
-    println!("-----|-------------|------|------|-----");
-    write_table_plan(&explain.plan, 0);
+    let explain = parse_explain_document_with(input, parse_options).map_err(CliError::Core)?;
+    Ok(analyze_explain_with(explain, pipeline))
 }
 
-fn write_table_plan(plan: &rustcmdpev_core::structure::data::plan::Plan, depth: usize) {
-    let indent = "  ".repeat(depth);
-    let mut tags: Vec<&str> = Vec::new();
-    if plan.analysis_flags.slowest {
-        tags.push("slowest");
+/// The default passes, preceded by redaction when `--redact` or
+/// `--redact-identifiers` is given.
+fn pipeline(cli: &Cli) -> Pipeline {
+    let pipeline = Pipeline::default();
+    if !cli.redact && !cli.redact_identifiers {
+        return pipeline;
     }
-    if plan.analysis_flags.costliest {
-        tags.push("costliest");
+    let mut options = RedactOptions::new().with_identifiers(cli.redact_identifiers);
+    if let Some(salt) = &cli.redact_salt {
+        options = options.with_salt(salt.clone());
     }
-    if plan.analysis_flags.largest {
-        tags.push("largest");
-    }
-    if plan.analysis_flags.planner_row_estimate_factor >= BAD_ESTIMATE_FACTOR_THRESHOLD {
-        tags.push("bad_estimate");
+    pipeline.with_pass_before("expressions", RedactPass::new(options))
+}
+
+/// Parse and analyze the payload, then narrow it to the `--node` subtree.
+/// Totals and maxima stay those of the whole query so percentages and
+/// outlier tags keep their meaning.
+fn load_explain(cli: &Cli, input: &str, parse_options: &ParseOptions) -> Result<Explain, CliError> {
+    let mut explain = parse_and_process_explain(input, parse_options, &pipeline(cli))?;
+    if let Some(selector) = cli.node.as_ref() {
+        let focused = find_node(&explain.plan, selector).ok_or_else(|| {
+            CliError::InvalidInput(
+                Diagnostic::new(
+                    "invalid_input",
+                    format!("--node {selector} does not match any plan node"),
+                )
+                .with_hint("Run with --format table to list node ids and paths."),
+            )
+        })?;
+        debug!(%selector, "focusing output on subtree");
+        let plan = focused.plan.clone();
+        explain.focus_path = Some(focused.path);
+        explain.plan = plan;
     }
+    Ok(explain)
+}
 
-    println!(
-        "{}{} | {:.3} | {:.3} | {} | {}",
-        indent,
-        plan.identity.node_type,
-        plan.actuals.actual_duration,
-        plan.actuals.actual_cost,
-        plan.actuals.actual_rows,
-        tags.join(",")
-    );
+fn write_table(out: &mut String, explain: &Explain) {
+    out.push_str("ID | NODE | DURATION_MS | COST | ROWS | TAGS | PATH\n");
+    out.push_str("---|------|-------------|------|------|------|-----\n");
+    for node in pre_order_at(&explain.plan, explain.root_path()) {
+        let plan = node.plan;
+        let indent = "  ".repeat(node.depth);
+        let mut tags: Vec<&str> = Vec::new();
+        if plan.analysis_flags.slowest {
+            tags.push("slowest");
+        }
+        if plan.analysis_flags.costliest {
+            tags.push("costliest");
+        }
+        if plan.analysis_flags.largest {
+            tags.push("largest");
+        }
+        if plan.analysis_flags.planner_row_estimate_factor >= BAD_ESTIMATE_FACTOR_THRESHOLD {
+            tags.push("bad_estimate");
+        }
+        match plan.analysis_flags.estimate_error_source {
+            EstimateErrorSource::Origin => tags.push("estimate_origin"),
+            EstimateErrorSource::Inherited => tags.push("estimate_inherited"),
+            EstimateErrorSource::None => {}
+        }
 
-    for child in &plan.plans {
-        write_table_plan(child, depth + 1);
+        let _ = writeln!(
+            out,
+            "{} | {}{} | {:.3} | {:.3} | {} | {} | {}",
+            plan.identity.node_id,
+            indent,
+            plan.identity.node_type,
+            plan.actuals.actual_duration,
+            plan.actuals.actual_cost,
+            plan.actuals.actual_rows,
+            tags.join(","),
+            node.path
+        );
     }
 }
 
-fn run() -> Result<(), CliError> {
-    let cli = Cli::parse();
+/// Run the CLI. The payload is stored in `source` once read so failures can
+/// be reported against it.
+fn run(cli: &Cli, source: &mut Option<String>) -> Result<(), CliError> {
     init_logging(cli.verbose, cli.quiet);
     info!(
         format = ?cli.format,
@@ -423,8 +498,14 @@ fn run() -> Result<(), CliError> {
         quiet = cli.quiet,
         "starting rustcmdpev"
     );
-    let input = read_input(cli.input.as_ref())?;
-
+    if let Some(Command::Schema) = cli.command {
+        let schema = serde_json::to_string_pretty(&rustcmdpev_core::output::json_schema())
+            .map_err(|err| {
+                CliError::OutputSerialization(format!("failed to serialize JSON schema: {err}"))
+            })?;
+        println!("{schema}");
+        return Ok(());
+    }
     configure_color(cli.color);
 
     if cli.compat && cli.format != OutputFormat::Pretty {
@@ -447,6 +528,26 @@ fn run() -> Result<(), CliError> {
             "--compat requires the unicode tree style for parity-target output".to_string(),
         ));
     }
+    if cli.compat && cli.by_relation {
+        return Err(CliError::InvalidCompatibility(
+            "--compat does not support the --by-relation report section".to_string(),
+        ));
+    }
+    if cli.compat && cli.suggest_indexes {
+        return Err(CliError::InvalidCompatibility(
+            "--compat does not support the --suggest-indexes report section".to_string(),
+        ));
+    }
+    if cli.compat && cli.check_casts {
+        return Err(CliError::InvalidCompatibility(
+            "--compat does not support the --check-casts report section".to_string(),
+        ));
+    }
+    if cli.compat && cli.node.is_some() {
+        return Err(CliError::InvalidCompatibility(
+            "--compat renders the whole plan and does not support --node".to_string(),
+        ));
+    }
 
     let width = match (cli.compat, cli.width) {
         (true, Some(60)) | (true, None) => 60,
@@ -464,50 +565,198 @@ fn run() -> Result<(), CliError> {
         .with_theme(Theme::from(cli.theme))
         .with_mode(RenderMode::from(cli.render_mode))
         .with_summary(SummaryStyle::from(cli.summary))
-        .with_tree_style(TreeStyle::from(cli.tree_style));
-
-    let mut parse_options = ParseOptions::new();
+        .with_tree_style(TreeStyle::from(cli.tree_style))
+        .with_block_size(cli.block_size)
+        .with_by_relation(cli.by_relation)
+        .with_suggest_indexes(cli.suggest_indexes)
+        .with_check_casts(cli.check_casts)
+        .with_node_ids(!cli.compat);
+
+    let mut parse_options = ParseOptions::new()
+        .with_max_depth(cli.max_depth)
+        .with_max_nodes(cli.max_nodes)
+        .with_streaming(cli.stream);
     if let Some(version) = cli.postgres_version.as_ref() {
         parse_options = parse_options.with_postgres_version_hint(version.clone());
         debug!(hint = %version, "applying postgres-version parser hint");
     }
 
+    match &cli.command {
+        Some(Command::Batch(args)) => return batch::run(cli, args, &parse_options, render_options),
+        Some(Command::History(args)) => return history::show(args),
+        _ => {}
+    }
+    if cli.watch {
+        let path = cli
+            .input
+            .as_ref()
+            .expect("clap requires --input with --watch");
+        return watch(cli, path, &parse_options, render_options);
+    }
+
+    let input: &str = match &cli.command {
+        #[cfg(feature = "connect")]
+        Some(Command::Run(args)) => {
+            let plan = connect::explain(args, source)?;
+            source.insert(plan)
+        }
+        Some(Command::Record(args)) => {
+            source.insert(read_input(args.path.as_ref().or(cli.input.as_ref()))?)
+        }
+        Some(Command::Schema | Command::Batch(_) | Command::History(_)) | None => {
+            source.insert(read_input(cli.input.as_ref())?)
+        }
+    };
+
+    let explain = load_explain(cli, input, &parse_options)?;
+    if let Some(Command::Record(args)) = &cli.command {
+        return history::record(cli, args, &explain);
+    }
+    emit(cli, &explain, render_options)
+}
+
+/// Write `explain` to stdout in the selected `--format`.
+fn emit(cli: &Cli, explain: &Explain, render_options: RenderOptions) -> Result<(), CliError> {
+    print!("{}", format_output(cli, explain, render_options)?);
+    Ok(())
+}
+
+/// `explain` in the selected `--format`, ending in a newline.
+fn format_output(
+    cli: &Cli,
+    explain: &Explain,
+    render_options: RenderOptions,
+) -> Result<String, CliError> {
     match cli.format {
         OutputFormat::Pretty => {
             info!("rendering pretty output");
-            validate_stdin_json_contract(&input)?;
-            print!(
-                "{}",
-                rustcmdpev_core::render_visualization_full(&input, &parse_options, render_options)
-                    .map_err(CliError::Core)?
-            );
-            Ok(())
+            Ok(render_explain(explain, render_options))
         }
         OutputFormat::Json => {
             info!("rendering json output");
-            validate_stdin_json_contract(&input)?;
-            let explain = parse_and_process_explain(&input, &parse_options)?;
-            let output = serde_json::to_string_pretty(&explain).map_err(|err| {
-                CliError::OutputSerialization(format!("failed to serialize JSON output: {err}"))
-            })?;
-            println!("{output}");
-            Ok(())
+            let mut document = JsonOutput::new(explain)
+                .with_work_mem(cli.block_size)
+                .with_setting_warnings();
+            if cli.by_relation {
+                document = document.with_relations();
+            }
+            if cli.suggest_indexes {
+                document = document.with_index_suggestions();
+            }
+            if cli.check_casts {
+                document = document.with_cast_issues();
+            }
+            to_json(&document)
+        }
+        OutputFormat::Postgres => {
+            info!("rendering postgres json output");
+            to_json(&rustcmdpev_core::lossless::to_postgres_json(explain))
         }
         OutputFormat::Table => {
             info!("rendering table output");
-            validate_stdin_json_contract(&input)?;
-            let explain = parse_and_process_explain(&input, &parse_options)?;
-            write_table(&explain);
-            Ok(())
+            let mut output = String::new();
+            write_table(&mut output, explain);
+            Ok(output)
+        }
+    }
+}
+
+fn to_json(document: &impl Serialize) -> Result<String, CliError> {
+    let mut output = serde_json::to_string_pretty(document).map_err(|err| {
+        CliError::OutputSerialization(format!("failed to serialize JSON output: {err}"))
+    })?;
+    output.push('\n');
+    Ok(output)
+}
+
+/// Redraw `path` every time it changes, until interrupted.
+fn watch(
+    cli: &Cli,
+    path: &PathBuf,
+    parse_options: &ParseOptions,
+    render_options: RenderOptions,
+) -> Result<(), CliError> {
+    info!(path = %path.display(), "watching input file");
+    let clear = io::stdout().is_terminal();
+    let mut seen = None;
+    let mut previous: Option<Explain> = None;
+    loop {
+        let stamp = fs::metadata(path)
+            .ok()
+            .map(|metadata| (metadata.modified().ok(), metadata.len()));
+        if stamp.is_none() || stamp == seen {
+            thread::sleep(WATCH_POLL_INTERVAL);
+            continue;
+        }
+        seen = stamp;
+        if clear {
+            print!("\x1b[2J\x1b[H");
+        }
+        let mut source = None;
+        let loaded = read_input(Some(path)).and_then(|input| {
+            let input: &str = source.insert(input);
+            load_explain(cli, input, parse_options)
+        });
+        match loaded {
+            Ok(explain) => {
+                debug!("redrawing after input change");
+                emit(cli, &explain, render_options)?;
+                if cli.delta {
+                    if let Some(previous) = &previous {
+                        eprint!("{}", PlanDelta::between(previous, &explain));
+                    }
+                }
+                previous = Some(explain);
+            }
+            Err(err) => {
+                eprintln!(
+                    "── {} is not a usable plan; waiting for the next write ──",
+                    path.display()
+                );
+                report_error(cli, &err, source.as_deref());
+            }
+        }
+        io::stdout().flush().map_err(|err| {
+            CliError::OutputSerialization(format!("failed to write output: {err}"))
+        })?;
+    }
+}
+
+fn report_error(cli: &Cli, err: &CliError, input: Option<&str>) {
+    let diagnostic = err.diagnostic(input);
+    match cli.error_format {
+        ErrorFormat::Text => {
+            let source = match &cli.command {
+                #[cfg(feature = "connect")]
+                Some(Command::Run(args)) => args.source_name(),
+                Some(Command::Batch(_)) => "batch".to_string(),
+                Some(Command::Record(history::RecordArgs {
+                    path: Some(path), ..
+                })) => path.display().to_string(),
+                _ => cli
+                    .input
+                    .as_ref()
+                    .map_or_else(|| "<stdin>".to_string(), |path| path.display().to_string()),
+            };
+            eprint!("{}", diagnostic.render(&source, input));
+        }
+        ErrorFormat::Json => {
+            let mut report = serde_json::to_value(&diagnostic).unwrap_or_default();
+            if let Value::Object(fields) = &mut report {
+                fields.insert("exit_code".into(), Value::from(err.exit_code()));
+            }
+            eprintln!("{report}");
         }
     }
 }
 
 fn main() -> ExitCode {
-    match run() {
+    let cli = Cli::parse();
+    let mut source = None;
+    match run(&cli, &mut source) {
         Ok(()) => ExitCode::SUCCESS,
         Err(err) => {
-            eprintln!("error: {err}");
+            report_error(&cli, &err, source.as_deref());
             ExitCode::from(err.exit_code())
         }
     }
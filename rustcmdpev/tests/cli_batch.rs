@@ -0,0 +1,173 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SEQ_SCAN_PLAN: &str = r#"[{"Plan":{"Node Type":"Hash Join","Total Cost":20.0,"Actual Total Time":9.0,"Actual Rows":5,"Actual Loops":1,"Plans":[{"Node Type":"Seq Scan","Relation Name":"orders","Total Cost":10.0,"Actual Total Time":8.0,"Actual Rows":50,"Actual Loops":1},{"Node Type":"Hash","Total Cost":5.0,"Actual Total Time":0.5,"Actual Rows":5,"Actual Loops":1}]},"Execution Time":9.5}]"#;
+const INDEX_SCAN_PLAN: &str = r#"[{"Plan":{"Node Type":"Hash Join","Total Cost":12.0,"Actual Total Time":2.0,"Actual Rows":5,"Actual Loops":1,"Plans":[{"Node Type":"Index Scan","Relation Name":"orders","Index Name":"orders_status_idx","Total Cost":2.0,"Actual Total Time":0.3,"Actual Rows":50,"Actual Loops":1},{"Node Type":"Hash","Total Cost":5.0,"Actual Total Time":0.5,"Actual Rows":5,"Actual Loops":1}]},"Execution Time":2.5}]"#;
+const CUSTOMERS_PLAN: &str = r#"[{"Plan":{"Node Type":"Seq Scan","Relation Name":"customers","Total Cost":30.0,"Actual Total Time":40.0,"Actual Rows":900,"Actual Loops":1},"Execution Time":41.0}]"#;
+
+/// A fresh directory holding `files`.
+fn plan_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock went backwards")
+        .as_nanos();
+    let pid = std::process::id();
+    let dir = env::temp_dir().join(format!("rustcmdpev_{pid}_{nanos}_{name}"));
+    fs::create_dir_all(&dir).expect("create plan directory");
+    for (file, contents) in files {
+        fs::write(dir.join(file), contents).expect("write plan");
+    }
+    dir
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .output()
+        .expect("failed to run rustcmdpev")
+}
+
+fn arg(path: &Path) -> &str {
+    path.to_str().expect("utf-8 temp path")
+}
+
+#[test]
+fn batch_ranks_plans_and_counts_hotspots() {
+    let dir = plan_dir(
+        "report",
+        &[
+            ("seq.json", SEQ_SCAN_PLAN),
+            ("index.json", INDEX_SCAN_PLAN),
+            ("customers.json", CUSTOMERS_PLAN),
+            ("notes.txt", "not a plan"),
+        ],
+    );
+    let output = run(&["batch", arg(&dir), "--jobs", "2"]);
+    fs::remove_dir_all(&dir).expect("remove plan directory");
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("Batch: 3 files, 3 analyzed, 0 failed\n"));
+    let ranking: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.contains(" ms  "))
+        .collect();
+    assert_eq!(ranking.len(), 3);
+    assert!(
+        ranking[0].contains("41.00 ms") && ranking[0].ends_with("hotspot #1 Seq Scan on customers")
+    );
+    assert!(ranking[1].contains("9.50 ms") && ranking[1].contains("seq.json"));
+    assert!(ranking[2].contains("2.50 ms") && ranking[2].contains("index.json"));
+    assert!(stdout.contains("Hotspot node types:\n     2  Seq Scan\n     1  Hash Join\n"));
+    assert!(stdout.contains("Hotspot relations:\n     1  customers\n     1  orders\n"));
+    assert!(stdout.contains("Findings: "));
+}
+
+#[test]
+fn batch_isolates_failures_and_exits_with_their_code() {
+    let dir = plan_dir(
+        "failures",
+        &[
+            ("good.json", SEQ_SCAN_PLAN),
+            ("broken.json", r#"[{"Plan": {"Node Type": "#),
+        ],
+    );
+    let output = run(&["batch", arg(&dir), "--report", "json"]);
+    fs::remove_dir_all(&dir).expect("remove plan directory");
+
+    assert_eq!(output.status.code(), Some(3));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json report");
+    assert_eq!(report["files"].as_array().map(Vec::len), Some(1));
+    assert_eq!(report["files"][0]["execution_time"], 9.5);
+    assert_eq!(report["files"][0]["hotspot"]["node_type"], "Seq Scan");
+    let failure = &report["failures"][0];
+    assert!(failure["path"]
+        .as_str()
+        .is_some_and(|path| path.ends_with("broken.json")));
+    assert!(failure["message"]
+        .as_str()
+        .is_some_and(|message| message.starts_with("invalid JSON")));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("1 of 2 plans could not be analyzed"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn batch_selects_files_by_pattern_and_writes_per_file_outputs() {
+    let dir = plan_dir(
+        "outputs",
+        &[
+            ("q1.json", SEQ_SCAN_PLAN),
+            ("q2.json", INDEX_SCAN_PLAN),
+            ("other.json", CUSTOMERS_PLAN),
+        ],
+    );
+    let out_dir = dir.join("out");
+    let pattern = dir.join("q?.json");
+    let output = run(&[
+        "--format",
+        "table",
+        "batch",
+        arg(&pattern),
+        "--out-dir",
+        arg(&out_dir),
+    ]);
+    let q1 = fs::read_to_string(out_dir.join("q1.txt"));
+    let q2 = fs::read_to_string(out_dir.join("q2.txt"));
+    let other = out_dir.join("other.txt").exists();
+    fs::remove_dir_all(&dir).expect("remove plan directory");
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("2 analyzed"));
+    assert!(q1.expect("q1 output").contains("Seq Scan | 8.000"));
+    assert!(q2.expect("q2 output").contains("Index Scan | 0.300"));
+    assert!(!other);
+}
+
+#[test]
+fn batch_groups_plan_shapes_and_reports_csv() {
+    let dir = plan_dir(
+        "shapes",
+        &[
+            ("monday.json", SEQ_SCAN_PLAN),
+            ("tuesday.json", &SEQ_SCAN_PLAN.replace("9.5}", "14.0}")),
+            ("wednesday.json", INDEX_SCAN_PLAN),
+        ],
+    );
+    let text = run(&["batch", arg(&dir)]);
+    let csv = run(&["batch", arg(&dir), "--report", "csv"]);
+    fs::remove_dir_all(&dir).expect("remove plan directory");
+
+    let text = String::from_utf8_lossy(&text.stdout);
+    assert!(text.contains("Plan shapes: 2 distinct\n"), "{text}");
+
+    let csv = String::from_utf8_lossy(&csv.stdout);
+    let rows: Vec<Vec<&str>> = csv.lines().map(|row| row.split(',').collect()).collect();
+    assert_eq!(rows[0][0], "path");
+    assert_eq!(rows[0][6], "fingerprint");
+    assert_eq!(rows.len(), 4);
+    assert!(rows[1][0].ends_with("tuesday.json"));
+    assert!(rows[3][0].ends_with("wednesday.json"));
+    assert_eq!(rows[1][6], rows[2][6]);
+    assert_ne!(rows[1][6], rows[3][6]);
+    assert_eq!(rows[1][6].len(), 16);
+}
+
+#[test]
+fn batch_rejects_a_pattern_matching_nothing() {
+    let dir = plan_dir("empty", &[]);
+    let output = run(&["batch", arg(&dir.join("*.json"))]);
+    fs::remove_dir_all(&dir).expect("remove plan directory");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("matched no plan files"));
+}
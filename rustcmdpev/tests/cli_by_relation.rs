@@ -0,0 +1,63 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin_payload: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+    {
+        let stdin = child.stdin.as_mut().expect("failed to open stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
+    }
+    child.wait_with_output().expect("failed to wait on child")
+}
+
+const PAYLOAD: &str = r#"[{"Plan":{"Node Type":"Nested Loop","Total Cost":10.0,"Actual Total Time":3.0,"Actual Rows":5,"Actual Loops":1,"Plans":[{"Node Type":"Seq Scan","Schema":"public","Relation Name":"users","Total Cost":4.0,"Actual Total Time":1.0,"Actual Rows":5,"Actual Loops":1,"Shared Hit Blocks":3},{"Node Type":"Index Scan","Schema":"public","Relation Name":"users","Index Name":"users_manager_idx","Total Cost":1.0,"Actual Total Time":0.1,"Actual Rows":0,"Actual Loops":5,"Rows Removed By Filter":4}]},"Execution Time":3.5}]"#;
+
+#[test]
+fn by_relation_appends_rollup_section_to_pretty_output() {
+    let output = run(&["--color", "never", "--by-relation"], PAYLOAD);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("○ Relations:"));
+    assert!(stdout.contains("public.users scans=2"));
+    assert!(stdout.contains("users_manager_idx on public.users"));
+}
+
+#[test]
+fn by_relation_adds_relations_block_to_json_output() {
+    let output = run(&["--format", "json", "--by-relation"], PAYLOAD);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert!(json.get("plan").is_some());
+    let relations = &json["relations"]["relations"];
+    assert_eq!(relations[0]["relation"], "users");
+    assert_eq!(relations[0]["scans"], 2);
+    assert_eq!(
+        json["relations"]["indexes"][0]["index"],
+        "users_manager_idx"
+    );
+}
+
+#[test]
+fn json_output_omits_relations_block_by_default() {
+    let output = run(&["--format", "json"], PAYLOAD);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert!(json.get("relations").is_none());
+}
+
+#[test]
+fn compat_rejects_by_relation() {
+    let output = run(&["--compat", "--by-relation"], PAYLOAD);
+    assert_eq!(output.status.code(), Some(4));
+}
@@ -0,0 +1,72 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin_payload: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+    {
+        let stdin = child.stdin.as_mut().expect("failed to open stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
+    }
+    child.wait_with_output().expect("failed to wait on child")
+}
+
+const PAYLOAD: &str = r#"[{"Plan":{"Node Type":"Hash Join","Join Type":"Inner","Total Cost":2500.0,"Actual Total Time":60.0,"Actual Rows":3,"Actual Loops":1,"Hash Cond":"((o.account_id)::bigint = a.id)","Plans":[{"Node Type":"Seq Scan","Parent Relationship":"Outer","Schema":"public","Relation Name":"orders","Alias":"o","Total Cost":1500.0,"Actual Total Time":40.0,"Actual Rows":3,"Actual Loops":1,"Filter":"((account_id)::text = '123'::text)","Rows Removed by Filter":99997},{"Node Type":"Hash","Parent Relationship":"Inner","Total Cost":20.0,"Actual Total Time":1.0,"Actual Rows":100,"Actual Loops":1,"Plans":[{"Node Type":"Seq Scan","Parent Relationship":"Outer","Schema":"public","Relation Name":"accounts","Alias":"a","Total Cost":20.0,"Actual Total Time":0.5,"Actual Rows":100,"Actual Loops":1,"Filter":"(lower((email)::text) = 'a@example.com'::text)","Rows Removed by Filter":0}]}]},"Execution Time":61.0}]"#;
+
+#[test]
+fn check_casts_lists_each_offending_column() {
+    let output = run(&["--color", "never", "--check-casts"], PAYLOAD);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("○ Predicates that defeat indexes:"));
+    assert!(stdout.contains(
+        "public.orders.account_id cast to bigint to match the joined column in Hash Cond of Hash Join at Plan"
+    ));
+    assert!(stdout
+        .contains("public.orders.account_id cast to text in Filter of Seq Scan at Plan.Plans[0]"));
+    assert!(stdout.contains("public.accounts.email wrapped in lower() in Filter of Seq Scan"));
+    assert!(stdout.contains("((account_id)::text = '123'::text)"));
+}
+
+#[test]
+fn check_casts_adds_json_block() {
+    let output = run(&["--format", "json", "--check-casts"], PAYLOAD);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    let issues = json["cast_issues"].as_array().expect("cast_issues array");
+    assert_eq!(issues.len(), 3);
+    assert_eq!(issues[0]["kind"], "join_type_mismatch");
+    assert_eq!(issues[0]["field"], "Hash Cond");
+    assert_eq!(issues[0]["relation"], "orders");
+    assert_eq!(issues[0]["column"], "account_id");
+    assert_eq!(issues[0]["type_name"], "bigint");
+    assert_eq!(issues[2]["kind"], "function_call");
+    assert_eq!(issues[2]["function"], "lower");
+    assert_eq!(issues[2]["node_path"], "Plan.Plans[1].Plans[0]");
+}
+
+#[test]
+fn check_casts_without_findings_says_none() {
+    let payload = r#"[{"Plan":{"Node Type":"Seq Scan","Relation Name":"orders","Alias":"orders","Total Cost":10.0,"Actual Total Time":1.0,"Actual Rows":1,"Actual Loops":1,"Filter":"(account_id = 123)"},"Execution Time":1.0}]"#;
+    let output = run(&["--color", "never", "--check-casts"], payload);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("○ Predicates that defeat indexes:\n  none"));
+}
+
+#[test]
+fn check_casts_is_rejected_in_compat_mode() {
+    let output = run(&["--compat", "--check-casts"], PAYLOAD);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--check-casts"));
+}
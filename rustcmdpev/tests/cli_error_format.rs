@@ -0,0 +1,78 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run_with_args_and_stdin(args: &[&str], stdin_payload: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+
+    {
+        let stdin = child.stdin.as_mut().expect("failed to open stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
+    }
+
+    child.wait_with_output().expect("failed to wait on child")
+}
+
+const NEGATIVE_COST: &str =
+    "[\n  {\"Plan\": {\"Node Type\": \"Seq Scan\",\n    \"Total Cost\": -4}}\n]";
+
+#[test]
+fn text_errors_quote_the_offending_line_with_a_caret() {
+    let output = run_with_args_and_stdin(&[], NEGATIVE_COST);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+
+    assert_eq!(lines[0], "error: $[0].Plan.Total Cost must be non-negative");
+    assert_eq!(lines[1], " --> <stdin>:3:19");
+    assert_eq!(lines[3], "3 |     \"Total Cost\": -4}}");
+    assert_eq!(lines[4], format!("  | {}^", " ".repeat(18)));
+}
+
+#[test]
+fn json_errors_carry_kind_path_position_and_exit_code() {
+    let output = run_with_args_and_stdin(&["--error-format", "json"], NEGATIVE_COST);
+    assert_eq!(output.status.code(), Some(3));
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stderr).expect("stderr is one JSON object");
+
+    assert_eq!(report["kind"], "contract_violation");
+    assert_eq!(report["path"], "$[0].Plan.Total Cost");
+    assert_eq!(report["position"]["line"], 3);
+    assert_eq!(report["position"]["column"], 19);
+    assert_eq!(report["exit_code"], 3);
+}
+
+#[test]
+fn json_errors_report_invalid_json_with_hint() {
+    let output = run_with_args_and_stdin(&["--error-format", "json"], "[{\"Plan\": }]");
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stderr).expect("stderr is one JSON object");
+
+    assert_eq!(report["kind"], "invalid_json");
+    assert_eq!(report["position"]["byte"], 10);
+    assert!(report["hint"]
+        .as_str()
+        .is_some_and(|hint| hint.contains("EXPLAIN FORMAT JSON")));
+}
+
+#[test]
+fn json_errors_cover_core_invariant_failures() {
+    let output = run_with_args_and_stdin(
+        &["--error-format", "json"],
+        "[{\"Plan\":{\"Startup Cost\":1}}]",
+    );
+    assert_eq!(output.status.code(), Some(6));
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stderr).expect("stderr is one JSON object");
+
+    assert_eq!(report["kind"], "invalid_plan");
+    assert_eq!(report["path"], "$[0].Plan.Node Type");
+    assert_eq!(report["position"]["byte"], 9);
+}
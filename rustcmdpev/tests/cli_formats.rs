@@ -1,6 +1,8 @@
 use std::io::Write;
 use std::process::{Command, Output, Stdio};
 
+use rustcmdpev_core::constants::JSON_OUTPUT_SCHEMA_VERSION;
+
 fn run_with_args_and_stdin(args: &[&str], stdin_payload: &str) -> Output {
     let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
         .args(args)
@@ -12,9 +14,8 @@ fn run_with_args_and_stdin(args: &[&str], stdin_payload: &str) -> Output {
 
     {
         let stdin = child.stdin.as_mut().expect("failed to open stdin");
-        stdin
-            .write_all(stdin_payload.as_bytes())
-            .expect("failed to write stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
     }
 
     child.wait_with_output().expect("failed to wait on child")
@@ -43,3 +44,68 @@ fn format_table_succeeds_and_contains_header_and_node() {
     assert!(stdout.contains("NODE | DURATION_MS | COST | ROWS | TAGS"));
     assert!(stdout.contains("Seq Scan"));
 }
+
+const WITH_EXTRAS: &str = r#"[{"Plan":{"Node Type":"Custom Scan","Custom Plan Provider":"columnar","Total Cost":4.0,"Plan Rows":3,"Actual Total Time":1.0,"Actual Rows":3,"Actual Loops":1,"Rows Removed By Filter":2},"Triggers":[],"Execution Time":1.5}]"#;
+
+#[test]
+fn format_json_keeps_unrecognized_fields_as_extras() {
+    let output = run_with_args_and_stdin(&["--format", "json"], WITH_EXTRAS);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert_eq!(json["plan"]["extras"]["Custom Plan Provider"], "columnar");
+    assert_eq!(json["extras"]["Triggers"], serde_json::json!([]));
+}
+
+#[test]
+fn format_postgres_round_trips_under_original_key_names() {
+    let output = run_with_args_and_stdin(&["--format", "postgres"], WITH_EXTRAS);
+    assert!(output.status.success());
+    let written: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    let source: serde_json::Value = serde_json::from_str(WITH_EXTRAS).unwrap();
+
+    assert_eq!(
+        rustcmdpev_core::lossless::normalize(&written),
+        rustcmdpev_core::lossless::normalize(&source)
+    );
+    let plan = &written[0]["Plan"];
+    assert_eq!(plan["Custom Plan Provider"], "columnar");
+    assert_eq!(plan["Rows Removed by Filter"], 2);
+    assert!(plan.get("Actual Duration").is_none());
+}
+
+#[test]
+fn verbose_mode_lists_unrecognized_fields() {
+    let output = run_with_args_and_stdin(
+        &["--color", "never", "--render-mode", "verbose"],
+        WITH_EXTRAS,
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("○ Custom Plan Provider: columnar"));
+}
+
+#[test]
+fn schema_command_prints_versioned_json_schema() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .arg("schema")
+        .output()
+        .expect("failed to run rustcmdpev schema");
+    assert!(output.status.success());
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert_eq!(
+        schema["properties"]["schema_version"]["const"],
+        JSON_OUTPUT_SCHEMA_VERSION
+    );
+    assert!(schema["$defs"]["Plan"]["properties"]["costliest"].is_object());
+}
+
+#[test]
+fn format_json_emits_schema_version() {
+    let output = run_with_args_and_stdin(
+        &["--format", "json"],
+        "[{\"Plan\":{\"Node Type\":\"Seq Scan\"}}]",
+    );
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert_eq!(json["schema_version"], JSON_OUTPUT_SCHEMA_VERSION);
+}
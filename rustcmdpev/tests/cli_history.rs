@@ -0,0 +1,151 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const INDEX_SCAN_PLAN: &str = r#"[{"Plan":{"Node Type":"Index Scan","Relation Name":"orders","Index Name":"orders_status_idx","Total Cost":2.0,"Actual Total Time":0.3,"Actual Rows":50,"Actual Loops":1,"Shared Hit Blocks":40},"Execution Time":EXECUTION_TIME,"Query Identifier":-7215}]"#;
+const SEQ_SCAN_PLAN: &str = r#"[{"Plan":{"Node Type":"Seq Scan","Relation Name":"orders","Total Cost":20.0,"Actual Total Time":40.0,"Actual Rows":50,"Actual Loops":1,"Shared Hit Blocks":900},"Execution Time":41.5,"Query Identifier":-7215}]"#;
+
+fn unique_store() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock went backwards")
+        .as_nanos();
+    let pid = std::process::id();
+    env::temp_dir().join(format!("rustcmdpev_{pid}_{nanos}_history"))
+}
+
+fn run(args: &[&str], stdin_payload: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+    {
+        let stdin = child.stdin.as_mut().expect("failed to open stdin");
+        // A command that fails before reading stdin closes it early.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
+    }
+    child.wait_with_output().expect("failed to wait on child")
+}
+
+fn arg(path: &Path) -> &str {
+    path.to_str().expect("utf-8 temp path")
+}
+
+#[test]
+fn record_appends_runs_and_history_flags_the_regression() {
+    let store = unique_store();
+    for time in ["10.2", "11.0", "10.6", "10.9"] {
+        let plan = INDEX_SCAN_PLAN.replace("EXECUTION_TIME", time);
+        let output = run(&["record", "--store", arg(&store)], &plan);
+        assert!(
+            output.status.success(),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let recorded = run(&["record", "--store", arg(&store)], SEQ_SCAN_PLAN);
+    let history = run(&["history", "-7215", "--store", arg(&store)], "");
+    let lines = fs::read_to_string(store.join("-7215.jsonl")).expect("history file");
+    fs::remove_dir_all(&store).expect("remove store");
+
+    let recorded = String::from_utf8_lossy(&recorded.stdout);
+    assert!(recorded.starts_with("Recorded run 5 of query -7215 (plan fingerprint "));
+    assert_eq!(lines.lines().count(), 5);
+    let entry: serde_json::Value =
+        serde_json::from_str(lines.lines().last().expect("entry")).expect("json entry");
+    assert_eq!(entry["summary"]["execution_time"], 41.5);
+    assert_eq!(entry["explain"]["plan"]["node_type"], "Seq Scan");
+
+    assert!(history.status.success());
+    let history = String::from_utf8_lossy(&history.stdout);
+    assert!(history.starts_with("Query -7215: 5 runs"));
+    assert!(history.contains("▲ time, ▲ buffers, ◆ shape"));
+    assert!(history.contains("Latest run regressed:"));
+    assert!(history.contains("execution time 41.50 ms vs baseline median 10.75 ms"));
+}
+
+#[test]
+fn record_files_plans_without_a_query_identifier_by_query_text() {
+    let store = unique_store();
+    let with_text = |plan: &str, text: &str| {
+        plan.replace("EXECUTION_TIME", "3.0").replace(
+            r#""Query Identifier":-7215"#,
+            &format!(r#""Query Text":"{text}""#),
+        )
+    };
+    let index_scan = with_text(
+        INDEX_SCAN_PLAN,
+        "SELECT * FROM orders WHERE status = 'open'",
+    );
+    let seq_scan = with_text(
+        SEQ_SCAN_PLAN,
+        "SELECT * FROM orders WHERE status = 'closed'",
+    );
+    let first = run(&["record", "--store", arg(&store)], &index_scan);
+    let flipped = run(&["record", "--store", arg(&store)], &seq_scan);
+    let anonymous = INDEX_SCAN_PLAN
+        .replace("EXECUTION_TIME", "3.0")
+        .replace(r#","Query Identifier":-7215"#, "");
+    let unkeyed = run(&["record", "--store", arg(&store)], &anonymous);
+    let named = run(
+        &[
+            "record",
+            "--store",
+            arg(&store),
+            "--query",
+            "orders_by_status",
+        ],
+        &anonymous,
+    );
+    let keys: Vec<String> = fs::read_dir(&store)
+        .expect("store")
+        .map(|entry| {
+            entry
+                .expect("entry")
+                .file_name()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    let missing = run(&["history", "unknown", "--store", arg(&store)], "");
+    fs::remove_dir_all(&store).expect("remove store");
+
+    let first = String::from_utf8_lossy(&first.stdout);
+    let key = first
+        .strip_prefix("Recorded run 1 of query ")
+        .and_then(|rest| rest.split(' ').next())
+        .expect("query key");
+    let flipped = String::from_utf8_lossy(&flipped.stdout);
+    assert!(flipped.starts_with(&format!("Recorded run 2 of query {key} ")));
+
+    assert_eq!(unkeyed.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&unkeyed.stderr).contains("pass --query KEY"));
+    assert!(String::from_utf8_lossy(&named.stdout).contains("query orders_by_status "));
+    assert_eq!(keys.len(), 2);
+
+    assert_eq!(missing.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&missing.stderr);
+    let mut recorded = [key, "orders_by_status"];
+    recorded.sort_unstable();
+    assert!(stderr.contains(&format!("recorded queries: {}", recorded.join(", "))));
+}
+
+#[test]
+fn record_rejects_keys_that_are_not_file_names() {
+    let store = unique_store();
+    let plan = INDEX_SCAN_PLAN.replace("EXECUTION_TIME", "3.0");
+    let output = run(
+        &["record", "--store", arg(&store), "--query", "../escape"],
+        &plan,
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("may only hold"));
+    assert!(!store.exists());
+}
@@ -0,0 +1,46 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin_payload: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+    {
+        let stdin = child.stdin.as_mut().expect("failed to open stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
+    }
+    child.wait_with_output().expect("failed to wait on child")
+}
+
+const PAYLOAD: &str = r#"[{"Plan":{"Node Type":"Seq Scan","Schema":"public","Relation Name":"orders","Alias":"orders","Total Cost":1500.0,"Actual Total Time":40.0,"Actual Rows":12,"Actual Loops":1,"Filter":"((customer_id = 42) AND (created_at >= '2024-01-01'::date))","Rows Removed by Filter":99988},"Execution Time":41.0}]"#;
+
+#[test]
+fn suggest_indexes_lists_heuristic_create_index_statements() {
+    let output = run(&["--color", "never", "--suggest-indexes"], PAYLOAD);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("heuristic"));
+    assert!(stdout.contains("CREATE INDEX ON public.orders (customer_id, created_at);"));
+    assert!(stdout.contains("at Plan:"));
+}
+
+#[test]
+fn suggest_indexes_adds_json_block_linked_to_node_path() {
+    let output = run(&["--format", "json", "--suggest-indexes"], PAYLOAD);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    let suggestion = &json["index_suggestions"][0];
+    assert_eq!(suggestion["heuristic"], true);
+    assert_eq!(suggestion["node_path"], "Plan");
+    assert_eq!(suggestion["source"], "filter");
+    assert_eq!(suggestion["rows_removed"], 99988);
+}
@@ -13,9 +13,8 @@ fn run_with_args_and_stdin(args: &[&str], stdin_payload: &str) -> Output {
 
     {
         let stdin = child.stdin.as_mut().expect("failed to open stdin");
-        stdin
-            .write_all(stdin_payload.as_bytes())
-            .expect("failed to write stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
     }
 
     child.wait_with_output().expect("failed to wait on child")
@@ -0,0 +1,119 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin_payload: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+    {
+        let stdin = child.stdin.as_mut().expect("failed to open stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
+    }
+    child.wait_with_output().expect("failed to wait on child")
+}
+
+const PAYLOAD: &str = r#"[{"Plan":{"Node Type":"Hash Join","Total Cost":20.0,"Actual Total Time":3.0,"Actual Rows":5,"Actual Loops":1,"Plans":[{"Node Type":"Seq Scan","Relation Name":"orders","Total Cost":10.0,"Actual Total Time":1.0,"Actual Rows":50,"Actual Loops":1},{"Node Type":"Hash","Total Cost":5.0,"Actual Total Time":1.0,"Actual Rows":5,"Actual Loops":1,"Plans":[{"Node Type":"Seq Scan","Relation Name":"customers","Total Cost":4.0,"Actual Total Time":0.8,"Actual Rows":5,"Actual Loops":1}]}]},"Execution Time":3.2}]"#;
+
+#[test]
+fn pretty_output_numbers_nodes_and_verbose_shows_paths() {
+    let output = run(&["--color", "never", "--render-mode", "verbose"], PAYLOAD);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("#1 Hash Join"));
+    assert!(stdout.contains("#4 Seq Scan"));
+    assert!(stdout.contains("○ Path: Plan.Plans[1].Plans[0]"));
+}
+
+#[test]
+fn node_flag_focuses_every_format_on_the_subtree() {
+    let pretty = run(&["--color", "never", "--node", "3"], PAYLOAD);
+    assert!(pretty.status.success());
+    let stdout = String::from_utf8_lossy(&pretty.stdout);
+    assert!(stdout.contains("#3 Hash"));
+    assert!(stdout.contains("#4 Seq Scan"));
+    assert!(!stdout.contains("#1 "));
+
+    let json = run(&["--format", "json", "--node", "Plan.Plans[1]"], PAYLOAD);
+    assert!(json.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&json.stdout).expect("valid json");
+    assert_eq!(json["plan"]["node_id"], 3);
+    assert_eq!(json["plan"]["node_path"], "Plan.Plans[1]");
+    assert_eq!(json["plan"]["plans"][0]["node_id"], 4);
+    assert_eq!(
+        json["plan"]["plans"][0]["node_path"],
+        "Plan.Plans[1].Plans[0]"
+    );
+
+    let table = run(&["--format", "table", "--node", "#4"], PAYLOAD);
+    assert!(table.status.success());
+    let stdout = String::from_utf8_lossy(&table.stdout);
+    assert!(stdout.contains("ID | NODE |"));
+    assert!(stdout.contains("4 | Seq Scan |"));
+    assert!(stdout.contains("| Plan.Plans[1].Plans[0]"));
+    assert!(!stdout.contains("orders"));
+}
+
+#[test]
+fn unknown_or_malformed_node_is_rejected() {
+    let missing = run(&["--node", "9"], PAYLOAD);
+    assert_eq!(missing.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("--node #9"));
+
+    let malformed = run(&["--node", "Plans[0]"], PAYLOAD);
+    assert_eq!(malformed.status.code(), Some(2));
+
+    let compat = run(&["--compat", "--node", "1"], PAYLOAD);
+    assert_eq!(compat.status.code(), Some(4));
+}
+
+#[test]
+fn json_fingerprints_identify_the_focused_subtree() {
+    let whole = run(&["--format", "json"], PAYLOAD);
+    let focused = run(&["--format", "json", "--node", "#3"], PAYLOAD);
+    let whole: serde_json::Value = serde_json::from_slice(&whole.stdout).expect("valid json");
+    let focused: serde_json::Value = serde_json::from_slice(&focused.stdout).expect("valid json");
+
+    let fingerprint = &whole["plan"]["plans"][1]["fingerprint"];
+    assert_eq!(fingerprint.as_str().map(str::len), Some(16));
+    assert_eq!(&focused["plan"]["fingerprint"], fingerprint);
+    assert_ne!(&whole["plan"]["fingerprint"], fingerprint);
+}
+
+const REPORTS_PAYLOAD: &str = r#"[{"Plan":{"Node Type":"Hash Join","Total Cost":900.0,"Actual Total Time":80.0,"Actual Rows":5,"Actual Loops":1,"Plans":[{"Node Type":"Sort","Sort Method":"external merge","Sort Space Used":10240,"Sort Space Type":"Disk","Total Cost":800.0,"Actual Total Time":70.0,"Actual Rows":10,"Actual Loops":1,"Plans":[{"Node Type":"Seq Scan","Relation Name":"orders","Schema":"public","Alias":"o","Filter":"(((account_id)::text = '123'::text) AND (status = 'open'::text))","Rows Removed by Filter":99990,"Total Cost":700.0,"Actual Total Time":60.0,"Actual Rows":10,"Actual Loops":1}]},{"Node Type":"Hash","Total Cost":5.0,"Actual Total Time":1.0,"Actual Rows":5,"Actual Loops":1,"Plans":[{"Node Type":"Seq Scan","Relation Name":"customers","Total Cost":4.0,"Actual Total Time":0.8,"Actual Rows":5,"Actual Loops":1}]}]},"Execution Time":81.0}]"#;
+
+#[test]
+fn reports_on_a_focused_subtree_use_original_paths() {
+    let args = ["--node", "2", "--suggest-indexes", "--check-casts"];
+    let json = run(
+        &[&args[..], &["--format", "json"]].concat(),
+        REPORTS_PAYLOAD,
+    );
+    assert!(json.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&json.stdout).expect("valid json");
+    assert_eq!(
+        json["index_suggestions"][0]["node_path"],
+        "Plan.Plans[0].Plans[0]"
+    );
+    assert_eq!(
+        json["cast_issues"][0]["node_path"],
+        "Plan.Plans[0].Plans[0]"
+    );
+    assert_eq!(
+        json["work_mem"]["operations"][0]["node_path"],
+        "Plan.Plans[0]"
+    );
+
+    let pretty = run(
+        &[&args[..], &["--color", "never"]].concat(),
+        REPORTS_PAYLOAD,
+    );
+    assert!(pretty.status.success());
+    let stdout = String::from_utf8_lossy(&pretty.stdout);
+    assert!(stdout.contains("at Plan.Plans[0].Plans[0]"), "{stdout}");
+    assert!(!stdout.contains("at Plan\n"), "{stdout}");
+}
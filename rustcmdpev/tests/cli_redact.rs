@@ -0,0 +1,121 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const PLAN: &str = r#"[{"Plan":{"Node Type":"Hash Join","Join Type":"Inner","Hash Cond":"(o.customer_id = c.id)","Total Cost":10.0,"Actual Total Time":5.0,"Actual Rows":1,"Actual Loops":1,"Plans":[{"Node Type":"Seq Scan","Relation Name":"orders","Schema":"public","Alias":"o","Filter":"((status = 'shipped'::text) AND (total > 100.50))","Rows Removed by Filter":9000,"Total Cost":4.0,"Plan Rows":10,"Actual Total Time":4.0,"Actual Rows":10,"Actual Loops":1},{"Node Type":"Index Scan","Relation Name":"customers","Alias":"c","Index Name":"customers_pkey","Index Cond":"(id = o.customer_id)","Total Cost":1.0,"Actual Total Time":0.5,"Actual Rows":1,"Actual Loops":10}]},"Execution Time":5.0}]"#;
+
+fn run_with_args_and_stdin(args: &[&str], stdin_payload: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+    {
+        let stdin = child.stdin.as_mut().expect("failed to open stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
+    }
+    child.wait_with_output().expect("failed to wait on child")
+}
+
+#[test]
+fn redact_writes_postgres_json_without_literals_that_parses_again() {
+    let output = run_with_args_and_stdin(&["--redact", "--format", "postgres"], PLAN);
+    assert!(output.status.success());
+    let redacted = String::from_utf8(output.stdout).expect("utf-8");
+    assert!(!redacted.contains("shipped"));
+    assert!(!redacted.contains("100.50"));
+    assert!(redacted.contains(r#""Filter": "((status = $1::text) AND (total > $2))""#));
+    assert!(redacted.contains(r#""Relation Name": "orders""#));
+
+    let rendered = run_with_args_and_stdin(&["--width", "80"], &redacted);
+    assert!(rendered.status.success());
+    assert!(String::from_utf8_lossy(&rendered.stdout).contains("Seq Scan"));
+}
+
+#[test]
+fn redact_identifiers_hashes_names_consistently() {
+    let output = run_with_args_and_stdin(&["--redact-identifiers", "--format", "json"], PLAN);
+    assert!(output.status.success());
+    let text = String::from_utf8_lossy(&output.stdout);
+    let json: serde_json::Value = serde_json::from_str(&text).expect("json");
+    let plan = &json["plan"];
+    let orders = &plan["plans"][0];
+    let customers = &plan["plans"][1];
+    for name in [
+        "orders",
+        "customers",
+        "public",
+        "customers_pkey",
+        "status",
+        "customer_id",
+    ] {
+        assert!(!text.contains(name), "{name} leaked");
+    }
+
+    let alias = orders["alias"].as_str().expect("alias");
+    assert!(alias.starts_with("n_"));
+    let hash_cond = plan["hash_condition"].as_str().expect("hash condition");
+    let index_cond = customers["index_condition"]
+        .as_str()
+        .expect("index condition");
+    let outer_column = hash_cond
+        .trim_start_matches('(')
+        .split(' ')
+        .next()
+        .expect("outer column");
+    assert!(outer_column.starts_with(&format!("{alias}.")));
+    assert!(index_cond.ends_with(&format!("= {outer_column})")));
+}
+
+#[test]
+fn redact_salt_changes_hashes_and_requires_identifiers() {
+    let unsalted = run_with_args_and_stdin(&["--redact-identifiers", "--format", "postgres"], PLAN);
+    let salted = run_with_args_and_stdin(
+        &[
+            "--redact-identifiers",
+            "--redact-salt",
+            "k3y",
+            "--format",
+            "postgres",
+        ],
+        PLAN,
+    );
+    assert!(salted.status.success());
+    assert_ne!(unsalted.stdout, salted.stdout);
+
+    let output = run_with_args_and_stdin(&["--redact", "--redact-salt", "k3y"], PLAN);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--redact-identifiers"));
+}
+
+#[test]
+fn redact_identifiers_covers_conflict_and_knn_fields() {
+    let plan = r#"[{"Plan":{"Node Type":"ModifyTable","Operation":"Insert","Relation Name":"shops","Alias":"shops","Conflict Resolution":"UPDATE","Conflict Arbiter Indexes":["shops_owner_key"],"Conflict Filter":"(shops.owner <> 'acme'::text)","Total Cost":2.0,"Plans":[{"Node Type":"Index Scan","Relation Name":"shops","Alias":"shops","Index Name":"shops_location_idx","Order By":"(shops.location <-> '(40.7128,-74.0060)'::point)","Total Cost":1.0}]}}]"#;
+    let output = run_with_args_and_stdin(&["--redact-identifiers", "--format", "postgres"], plan);
+    assert!(output.status.success());
+    let redacted = String::from_utf8_lossy(&output.stdout);
+    for leaked in ["acme", "40.7128", "shops", "owner", "location"] {
+        assert!(!redacted.contains(leaked), "{leaked} leaked");
+    }
+    assert!(redacted.contains(r#""Conflict Resolution": "UPDATE""#));
+}
+
+#[test]
+fn redact_identifiers_hashes_schemas_in_search_path() {
+    let plan = r#"[{"Plan":{"Node Type":"Seq Scan","Relation Name":"orders","Schema":"acme_corp","Alias":"orders","Total Cost":1.0},"Settings":{"search_path":"\"acme_corp\", public","work_mem":"64MB"}}]"#;
+    let output = run_with_args_and_stdin(&["--redact-identifiers", "--format", "postgres"], plan);
+    assert!(output.status.success());
+    let redacted: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("acme"));
+    let schema = redacted[0]["Plan"]["Schema"].as_str().expect("schema");
+    let search_path = redacted[0]["Settings"]["search_path"]
+        .as_str()
+        .expect("search_path");
+    assert!(
+        search_path.starts_with(&format!("{schema}, n_")),
+        "{search_path}"
+    );
+    assert_eq!(redacted[0]["Settings"]["work_mem"], "64MB");
+}
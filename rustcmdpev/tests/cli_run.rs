@@ -0,0 +1,233 @@
+//! `rustcmdpev run` against a mock server that speaks just enough of the
+//! PostgreSQL wire protocol to replay a recorded EXPLAIN response.
+#![cfg(feature = "connect")]
+
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Output};
+use std::thread::{self, JoinHandle};
+
+const PLAN: &str = include_str!("fixtures/connect/update_orders.json");
+const STATEMENT_FILE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/connect/update_orders.sql"
+);
+
+/// How the mock answers the EXPLAIN statement.
+#[derive(Clone, Copy)]
+enum Reply {
+    Plan,
+    /// An `ErrorResponse` at this 1-based character position.
+    SyntaxError(usize),
+}
+
+/// Accept one connection and return every query text the client sent.
+fn serve(reply: Reply) -> (u16, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+    let port = listener.local_addr().expect("local addr").port();
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("accept");
+        session(stream, reply)
+    });
+    (port, handle)
+}
+
+fn session(stream: TcpStream, reply: Reply) -> Vec<String> {
+    let mut writer = stream.try_clone().expect("clone stream");
+    let mut reader = BufReader::new(stream);
+    let mut queries = Vec::new();
+
+    let _startup = read_body(&mut reader);
+    let mut out = Vec::new();
+    message(&mut out, b'R', &0_i32.to_be_bytes());
+    message(&mut out, b'S', b"server_version\x0016.4\x00");
+    message(&mut out, b'K', &[0, 0, 0, 1, 0, 0, 0, 2]);
+    message(&mut out, b'Z', b"I");
+    writer.write_all(&out).expect("write startup");
+
+    let mut status = b'I';
+    let mut failed = false;
+    loop {
+        let mut tag = [0_u8];
+        if reader.read_exact(&mut tag).is_err() {
+            break;
+        }
+        let body = read_body(&mut reader);
+        let mut out = Vec::new();
+        match tag[0] {
+            b'Q' => {
+                let query = cstr(&body);
+                let command = match query.split_whitespace().next().unwrap_or_default() {
+                    "START" => "BEGIN",
+                    command => command,
+                };
+                status = if command == "BEGIN" { b'T' } else { b'I' };
+                message(&mut out, b'C', format!("{command}\0").as_bytes());
+                message(&mut out, b'Z', &[status]);
+                queries.push(query);
+            }
+            b'P' => {
+                let name_end = body.iter().position(|b| *b == 0).expect("statement name");
+                queries.push(cstr(&body[name_end + 1..]));
+                match reply {
+                    Reply::Plan => message(&mut out, b'1', &[]),
+                    Reply::SyntaxError(position) => {
+                        let fields = format!(
+                            "SERROR\0C42601\0Msyntax error at or near \"WHERE\"\0P{position}\0\0"
+                        );
+                        message(&mut out, b'E', fields.as_bytes());
+                        failed = true;
+                        status = b'E';
+                    }
+                }
+            }
+            b'D' if !failed => {
+                message(&mut out, b't', &0_i16.to_be_bytes());
+                let mut field = b"QUERY PLAN\0".to_vec();
+                field.extend(0_i32.to_be_bytes()); // table oid
+                field.extend(0_i16.to_be_bytes()); // column number
+                field.extend(114_i32.to_be_bytes()); // json
+                field.extend((-1_i16).to_be_bytes());
+                field.extend((-1_i32).to_be_bytes());
+                field.extend(0_i16.to_be_bytes());
+                let mut row_description = 1_i16.to_be_bytes().to_vec();
+                row_description.extend(field);
+                message(&mut out, b'T', &row_description);
+            }
+            b'B' if !failed => message(&mut out, b'2', &[]),
+            b'E' if !failed => {
+                let mut row = 1_i16.to_be_bytes().to_vec();
+                row.extend((PLAN.len() as i32).to_be_bytes());
+                row.extend(PLAN.as_bytes());
+                message(&mut out, b'D', &row);
+                message(&mut out, b'C', b"EXPLAIN\0");
+            }
+            b'C' => message(&mut out, b'3', &[]),
+            b'S' => {
+                failed = false;
+                message(&mut out, b'Z', &[status]);
+            }
+            b'X' => break,
+            _ => {}
+        }
+        writer.write_all(&out).expect("write reply");
+    }
+    queries
+}
+
+fn read_body(reader: &mut impl Read) -> Vec<u8> {
+    let mut len = [0_u8; 4];
+    reader.read_exact(&mut len).expect("message length");
+    let mut body = vec![0_u8; i32::from_be_bytes(len) as usize - 4];
+    reader.read_exact(&mut body).expect("message body");
+    body
+}
+
+fn message(out: &mut Vec<u8>, tag: u8, body: &[u8]) {
+    out.push(tag);
+    out.extend(((body.len() + 4) as i32).to_be_bytes());
+    out.extend(body);
+}
+
+fn cstr(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn dsn(port: u16) -> String {
+    format!("host=127.0.0.1 port={port} user=tester dbname=shop")
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .env_remove("DATABASE_URL")
+        .output()
+        .expect("failed to run rustcmdpev")
+}
+
+#[test]
+fn run_explains_the_statement_and_rolls_back() {
+    let (port, server) = serve(Reply::Plan);
+    let output = run(&[
+        "--color",
+        "never",
+        "run",
+        "--dsn",
+        &dsn(port),
+        "-f",
+        STATEMENT_FILE,
+    ]);
+    let queries = server.join().expect("mock server");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("#1 ModifyTable"));
+    assert!(stdout.contains("#2 Seq Scan"));
+    assert_eq!(
+        queries,
+        [
+            "START TRANSACTION",
+            "EXPLAIN (ANALYZE, BUFFERS, VERBOSE, SETTINGS, FORMAT JSON) UPDATE orders\n   \
+             SET status = 'shipped'\n WHERE status = 'pending'",
+            "ROLLBACK",
+        ]
+    );
+}
+
+#[test]
+fn run_takes_explain_options_and_can_commit() {
+    let (port, server) = serve(Reply::Plan);
+    let output = run(&[
+        "--format",
+        "json",
+        "run",
+        "--dsn",
+        &dsn(port),
+        "-c",
+        "SELECT 1;",
+        "--explain",
+        "analyze,wal",
+        "--commit",
+    ]);
+    let queries = server.join().expect("mock server");
+
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json output");
+    assert_eq!(json["plan"]["node_type"], "ModifyTable");
+    assert_eq!(
+        queries,
+        [
+            "START TRANSACTION",
+            "EXPLAIN (ANALYZE, WAL, FORMAT JSON) SELECT 1",
+            "COMMIT"
+        ]
+    );
+}
+
+#[test]
+fn run_points_server_errors_into_the_statement() {
+    let prefix = "EXPLAIN (ANALYZE, BUFFERS, VERBOSE, SETTINGS, FORMAT JSON) ";
+    let statement = "UPDATE orders\n   SET status = 'shipped'\n ";
+    let (port, server) = serve(Reply::SyntaxError(prefix.len() + statement.len() + 1));
+    let output = run(&["run", "--dsn", &dsn(port), "-f", STATEMENT_FILE]);
+    let queries = server.join().expect("mock server");
+
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("error: syntax error at or near \"WHERE\""));
+    assert!(stderr.contains("update_orders.sql:3:2"), "{stderr}");
+    assert!(stderr.contains("3 |  WHERE status = 'pending';"));
+    assert_eq!(queries.last().map(String::as_str), Some("ROLLBACK"));
+}
+
+#[test]
+fn run_without_a_connection_string_is_an_input_error() {
+    let output = run(&["run", "-c", "SELECT 1"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("pass --dsn or set DATABASE_URL"));
+}
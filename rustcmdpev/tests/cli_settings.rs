@@ -0,0 +1,61 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin_payload: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+    {
+        let stdin = child.stdin.as_mut().expect("failed to open stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
+    }
+    child.wait_with_output().expect("failed to wait on child")
+}
+
+const PAYLOAD: &str = r#"[{"Plan":{"Node Type":"Index Scan","Relation Name":"orders","Alias":"orders","Index Name":"orders_pkey","Total Cost":8.0,"Actual Total Time":0.2,"Actual Rows":1,"Actual Loops":1},"Settings":{"enable_seqscan":"off","work_mem":"64MB"},"Planning Time":0.1,"Execution Time":0.3}]"#;
+
+#[test]
+fn detailed_summary_lists_settings_and_flags_distorting_ones() {
+    let output = run(&["--color", "never", "--summary", "detailed"], PAYLOAD);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("○ Settings: enable_seqscan=off, work_mem=64MB"));
+    assert!(stdout.contains("  enable_seqscan = off: the planner avoids this plan type"));
+}
+
+#[test]
+fn json_output_carries_settings_and_warnings() {
+    let output = run(&["--format", "json"], PAYLOAD);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert_eq!(json["settings"]["work_mem"], "64MB");
+    assert_eq!(json["setting_warnings"][0]["name"], "enable_seqscan");
+    assert_eq!(json["setting_warnings"][0]["value"], "off");
+    assert_eq!(json["setting_warnings"].as_array().map(Vec::len), Some(1));
+}
+
+#[test]
+fn postgres_output_keeps_the_settings_block() {
+    let output = run(&["--format", "postgres"], PAYLOAD);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert_eq!(json[0]["Settings"]["enable_seqscan"], "off");
+}
+
+#[test]
+fn non_object_settings_break_the_input_contract() {
+    let payload = r#"[{"Plan":{"Node Type":"Seq Scan"},"Settings":["work_mem"]}]"#;
+    let output = run(&[], payload);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("$[0].Settings must be an object if present"));
+}
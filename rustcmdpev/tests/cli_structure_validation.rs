@@ -11,9 +11,8 @@ fn run_with_stdin(stdin_payload: &str) -> Output {
 
     {
         let stdin = child.stdin.as_mut().expect("failed to open stdin");
-        stdin
-            .write_all(stdin_payload.as_bytes())
-            .expect("failed to write stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
     }
 
     child.wait_with_output().expect("failed to wait on child")
@@ -88,3 +87,99 @@ fn rejects_excessive_plan_node_count() {
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(stderr.contains("maximum supported node count"));
 }
+
+fn run_with_args_and_stdin(args: &[&str], stdin_payload: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+
+    {
+        let stdin = child.stdin.as_mut().expect("failed to open stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
+    }
+
+    child.wait_with_output().expect("failed to wait on child")
+}
+
+#[test]
+fn reports_depth_limit_for_plans_nested_past_the_json_recursion_limit() {
+    let output = run_with_stdin(&nested_plan_json(200));
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("maximum supported plan depth of 32"));
+    assert!(!stderr.contains("recursion limit"));
+}
+
+#[test]
+fn max_depth_flag_raises_the_depth_limit() {
+    let output = run_with_args_and_stdin(&["--max-depth", "40"], &nested_plan_json(33));
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = run_with_args_and_stdin(&["--max-depth", "2000"], &nested_plan_json(1_500));
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("#1501 Seq Scan"));
+}
+
+#[test]
+fn every_format_renders_plans_at_the_depth_ceiling() {
+    let payload = nested_plan_json(2_000);
+    for format in ["pretty", "json", "table", "postgres"] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+            .args(["--max-depth", "2000", "--format", format])
+            .stdin(Stdio::piped())
+            // Pretty and JSON output grow with the square of the depth.
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("failed to spawn rustcmdpev");
+        child
+            .stdin
+            .take()
+            .expect("failed to open stdin")
+            .write_all(payload.as_bytes())
+            .expect("failed to write stdin");
+        let output = child.wait_with_output().expect("failed to wait on child");
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "--format {format}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn max_depth_flag_is_capped() {
+    let output = run_with_args_and_stdin(&["--max-depth", "2001"], &nested_plan_json(1));
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("plan depth must be at most 2000"));
+
+    let output = run_with_args_and_stdin(&["--max-depth", "2000"], &nested_plan_json(2_001));
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("maximum supported plan depth of 2000"));
+}
+
+#[test]
+fn max_nodes_flag_lowers_the_node_limit() {
+    let output = run_with_args_and_stdin(&["--max-nodes", "3"], &nested_plan_json(3));
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("maximum supported node count of 3"));
+}
+
+#[test]
+fn stream_flag_renders_the_same_output() {
+    let payload = nested_plan_json(4);
+    let recursive = run_with_args_and_stdin(&["--format", "json"], &payload);
+    let streamed = run_with_args_and_stdin(&["--format", "json", "--stream"], &payload);
+    assert_eq!(streamed.status.code(), Some(0));
+    assert_eq!(streamed.stdout, recursive.stdout);
+}
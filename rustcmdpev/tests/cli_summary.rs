@@ -11,9 +11,8 @@ fn run(args: &[&str], stdin_payload: &str) -> Output {
         .expect("failed to spawn rustcmdpev");
     {
         let stdin = child.stdin.as_mut().expect("failed to open stdin");
-        stdin
-            .write_all(stdin_payload.as_bytes())
-            .expect("failed to write stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
     }
     child.wait_with_output().expect("failed to wait on child")
 }
@@ -61,3 +60,35 @@ fn compat_rejects_detailed_summary() {
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(stderr.contains("--compat"));
 }
+
+const IO_PAYLOAD: &str = r#"[{"Plan":{"Node Type":"Seq Scan","Total Cost":1.0,"Actual Total Time":2.0,"Actual Rows":2,"Actual Loops":1,"Shared Hit Blocks":0,"Shared Read Blocks":128,"I/O Read Time":1000.0}}]"#;
+
+#[test]
+fn detailed_summary_reports_cache_profile_with_configurable_block_size() {
+    let default = run(&["--color", "never", "--summary", "detailed"], IO_PAYLOAD);
+    let large = run(
+        &[
+            "--color",
+            "never",
+            "--summary",
+            "detailed",
+            "--block-size",
+            "16384",
+        ],
+        IO_PAYLOAD,
+    );
+    assert!(default.status.success());
+    assert!(large.status.success());
+    let default = String::from_utf8_lossy(&default.stdout);
+    let large = String::from_utf8_lossy(&large.stdout);
+    assert!(default.contains("○ Cache: hit=0.0% read=1.00 MiB/s io=100.0% (disk-bound)"));
+    assert!(large.contains("read=2.00 MiB/s"));
+}
+
+#[test]
+fn invalid_block_size_is_rejected() {
+    let output = run(&["--block-size", "3000"], PAYLOAD);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("power of two"));
+}
@@ -11,9 +11,8 @@ fn run(args: &[&str], stdin_payload: &str) -> Output {
         .expect("failed to spawn rustcmdpev");
     {
         let stdin = child.stdin.as_mut().expect("failed to open stdin");
-        stdin
-            .write_all(stdin_payload.as_bytes())
-            .expect("failed to write stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
     }
     child.wait_with_output().expect("failed to wait on child")
 }
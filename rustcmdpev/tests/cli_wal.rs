@@ -0,0 +1,76 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin_payload: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+    {
+        let stdin = child.stdin.as_mut().expect("failed to open stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
+    }
+    child.wait_with_output().expect("failed to wait on child")
+}
+
+const PAYLOAD: &str = r#"[{"Plan":{"Node Type":"ModifyTable","Operation":"Update","Relation Name":"orders","Alias":"orders","Total Cost":2100.0,"Actual Total Time":950.0,"Actual Rows":0,"Actual Loops":1,"Shared Hit Blocks":52000,"Shared Dirtied Blocks":1800,"WAL Records":100000,"WAL FPI":1750,"WAL Bytes":21495808,"Plans":[{"Node Type":"Seq Scan","Parent Relationship":"Outer","Relation Name":"orders","Alias":"orders","Total Cost":2100.0,"Actual Total Time":120.0,"Actual Rows":100000,"Actual Loops":1,"Shared Hit Blocks":1800,"WAL Records":12,"WAL FPI":0,"WAL Bytes":720}]},"Planning":{"Shared Hit Blocks":40,"Shared Read Blocks":3},"Planning Time":0.4,"Execution Time":951.0}]"#;
+
+#[test]
+fn detailed_summary_reports_wal_and_planning_buffers_apart() {
+    let output = run(&["--color", "never", "--summary", "detailed"], PAYLOAD);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("○ Planning Buffers: shared hit=40 read=3 written=0 dirtied=0"));
+    assert!(stdout.contains("○ WAL: records=100000 fpi=1750 bytes=20.50 MiB"));
+}
+
+#[test]
+fn verbose_nodes_show_their_wal() {
+    let output = run(&["--color", "never", "--render-mode", "verbose"], PAYLOAD);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("○ WAL: records=12 fpi=0 bytes=720 B"));
+    assert!(!stdout.contains("○ WAL Records:"));
+}
+
+#[test]
+fn json_output_carries_wal_and_planning_buffers() {
+    let output = run(&["--format", "json"], PAYLOAD);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert_eq!(json["plan"]["wal_records"], 100_000);
+    assert_eq!(json["plan"]["wal_fpi"], 1750);
+    assert_eq!(json["plan"]["wal_bytes"], 21_495_808);
+    assert_eq!(json["planning"]["shared_read_blocks"], 3);
+}
+
+#[test]
+fn postgres_output_keeps_wal_and_planning_keys() {
+    let output = run(&["--format", "postgres"], PAYLOAD);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert_eq!(json[0]["Plan"]["WAL Bytes"], 21_495_808);
+    assert_eq!(json[0]["Planning"]["Shared Hit Blocks"], 40);
+}
+
+#[test]
+fn postgres_output_round_trips_zero_wal_and_planning_counters() {
+    let payload = r#"[{"Plan":{"Node Type":"Seq Scan","Relation Name":"orders","Alias":"orders","Total Cost":10.0,"Actual Total Time":0.5,"Actual Rows":3,"Actual Loops":1,"Shared Hit Blocks":1,"WAL Records":0,"WAL FPI":0,"WAL Bytes":0},"Planning":{"Shared Hit Blocks":0,"Shared Read Blocks":0,"Shared Dirtied Blocks":0,"Shared Written Blocks":0},"Planning Time":0.1,"Execution Time":0.6}]"#;
+    let output = run(&["--format", "postgres"], payload);
+    assert!(output.status.success());
+    let written: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    let source: serde_json::Value = serde_json::from_str(payload).unwrap();
+
+    assert_eq!(
+        rustcmdpev_core::lossless::normalize(&written),
+        rustcmdpev_core::lossless::normalize(&source)
+    );
+}
@@ -0,0 +1,100 @@
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const SEQ_SCAN_PLAN: &str = r#"[{"Plan":{"Node Type":"Hash Join","Total Cost":20.0,"Actual Total Time":9.0,"Actual Rows":5,"Actual Loops":1,"Plans":[{"Node Type":"Seq Scan","Relation Name":"orders","Total Cost":10.0,"Actual Total Time":8.0,"Actual Rows":50,"Actual Loops":1},{"Node Type":"Hash","Total Cost":5.0,"Actual Total Time":0.5,"Actual Rows":5,"Actual Loops":1}]},"Execution Time":9.5}]"#;
+const INDEX_SCAN_PLAN: &str = r#"[{"Plan":{"Node Type":"Hash Join","Total Cost":12.0,"Actual Total Time":2.0,"Actual Rows":5,"Actual Loops":1,"Plans":[{"Node Type":"Index Scan","Relation Name":"orders","Index Name":"orders_status_idx","Total Cost":2.0,"Actual Total Time":0.3,"Actual Rows":50,"Actual Loops":1},{"Node Type":"Hash","Total Cost":5.0,"Actual Total Time":0.5,"Actual Rows":5,"Actual Loops":1}]},"Execution Time":2.5}]"#;
+
+fn unique_temp_file_path(suffix: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock went backwards")
+        .as_nanos();
+    let pid = std::process::id();
+    env::temp_dir().join(format!("rustcmdpev_{pid}_{nanos}_{suffix}.json"))
+}
+
+/// Collect everything `stream` produces in the background.
+fn capture(mut stream: impl Read + Send + 'static) -> Arc<Mutex<String>> {
+    let captured = Arc::new(Mutex::new(String::new()));
+    let sink = Arc::clone(&captured);
+    thread::spawn(move || {
+        let mut chunk = [0_u8; 4096];
+        while let Ok(read) = stream.read(&mut chunk) {
+            if read == 0 {
+                break;
+            }
+            sink.lock()
+                .expect("capture lock")
+                .push_str(&String::from_utf8_lossy(&chunk[..read]));
+        }
+    });
+    captured
+}
+
+/// Wait until `captured` holds `needle`.
+fn wait_for(child: &mut Child, captured: &Arc<Mutex<String>>, needle: &str) {
+    let deadline = Instant::now() + Duration::from_secs(20);
+    while !captured.lock().expect("capture lock").contains(needle) {
+        if Instant::now() > deadline {
+            let _ = child.kill();
+            panic!(
+                "timed out waiting for {needle:?}; got:\n{}",
+                captured.lock().expect("capture lock")
+            );
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn watch_redraws_on_change_survives_bad_writes_and_reports_deltas() {
+    let path = unique_temp_file_path("watch");
+    fs::write(&path, SEQ_SCAN_PLAN).expect("write initial plan");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .arg("--input")
+        .arg(&path)
+        .args(["--watch", "--delta", "--color", "never"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+    let stdout = capture(child.stdout.take().expect("stdout"));
+    let stderr = capture(child.stderr.take().expect("stderr"));
+
+    wait_for(&mut child, &stdout, "#2 Seq Scan");
+
+    fs::write(&path, r#"[{"Plan": {"Node Type": "#).expect("write partial plan");
+    wait_for(&mut child, &stderr, "is not a usable plan");
+    wait_for(&mut child, &stderr, "error:");
+
+    fs::write(&path, INDEX_SCAN_PLAN).expect("write changed plan");
+    wait_for(&mut child, &stdout, "#2 Index Scan");
+    // The delta arrives in pieces; settings is its last line.
+    wait_for(&mut child, &stderr, "Δ settings");
+
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = fs::remove_file(&path);
+
+    let stderr = stderr.lock().expect("capture lock").clone();
+    assert!(stderr.contains("Δ execution: 9.50 ms → 2.50 ms (-73.7%)"));
+    assert!(stderr.contains("Δ hotspot: #2 Seq Scan on orders → #1 Hash Join"));
+    assert!(stderr.contains("Δ node types: Plan.Plans[0]: Seq Scan → Index Scan"));
+}
+
+#[test]
+fn watch_requires_an_input_file() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .arg("--watch")
+        .output()
+        .expect("failed to run rustcmdpev");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--input"));
+}
@@ -0,0 +1,56 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin_payload: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustcmdpev"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn rustcmdpev");
+    {
+        let stdin = child.stdin.as_mut().expect("failed to open stdin");
+        // Argument errors exit before stdin is read.
+        let _ = stdin.write_all(stdin_payload.as_bytes());
+    }
+    child.wait_with_output().expect("failed to wait on child")
+}
+
+const SPILLING: &str = r#"[{"Plan":{"Node Type":"Gather Merge","Total Cost":900.0,"Actual Total Time":30.0,"Actual Rows":100,"Actual Loops":1,"Workers Planned":2,"Workers Launched":2,"Plans":[{"Node Type":"Sort","Parent Relationship":"Outer","Total Cost":800.0,"Actual Total Time":25.0,"Actual Rows":34,"Actual Loops":3,"Sort Method":"external merge","Sort Space Used":5120,"Sort Space Type":"Disk"}]},"Execution Time":31.0}]"#;
+
+const SERIAL_SPILLING: &str = r#"[{"Plan":{"Node Type":"Sort","Total Cost":800.0,"Actual Total Time":25.0,"Actual Rows":100,"Actual Loops":1,"Sort Method":"external merge","Sort Space Used":5120,"Sort Space Type":"Disk"},"Execution Time":26.0}]"#;
+
+const IN_MEMORY: &str = r#"[{"Plan":{"Node Type":"Sort","Total Cost":10.0,"Actual Total Time":1.0,"Actual Rows":10,"Actual Loops":1,"Sort Method":"quicksort","Sort Space Used":25,"Sort Space Type":"Memory"},"Execution Time":1.0}]"#;
+
+#[test]
+fn detailed_summary_recommends_work_mem_for_external_sort() {
+    let output = run(
+        &["--color", "never", "--summary", "detailed"],
+        SERIAL_SPILLING,
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("○ work_mem: ≥ 10MB per operation (1 spilling, up to 10MB"));
+    assert!(stdout.contains("Sort at Plan: external sort needs 10MB × 1 process(es)"));
+}
+
+#[test]
+fn json_output_includes_work_mem_block_only_when_spilling() {
+    let output = run(&["--format", "json"], SPILLING);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert_eq!(json["work_mem"]["per_operation_kb"], 10 * 1024);
+    assert_eq!(json["work_mem"]["operations"][0]["kind"], "external_sort");
+    assert_eq!(json["work_mem"]["operations"][0]["processes"], 3);
+    assert_eq!(json["work_mem"]["query_total_kb"], 30 * 1024);
+
+    let output = run(&["--format", "json"], IN_MEMORY);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+    assert!(json.get("work_mem").is_none());
+}
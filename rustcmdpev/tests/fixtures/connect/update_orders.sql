@@ -0,0 +1,3 @@
+UPDATE orders
+   SET status = 'shipped'
+ WHERE status = 'pending';